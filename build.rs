@@ -0,0 +1,6 @@
+fn main() {
+    // protoc is vendored, so it's not required to be installed
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
+
+    tonic_build::compile_protos("proto/index_daemon.proto").unwrap();
+}
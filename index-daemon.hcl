@@ -4,11 +4,38 @@ job "quazar-idx-daemon" {
       port "ws" {
         to = 8000
       }
+      port "http" {
+        to = 8081
+      }
+    }
+
+    update {
+      health_check     = "checks"
+      min_healthy_time = "30s"
+      healthy_deadline = "10m"
     }
 
     service {
       name = "quazar-idx-daemon"
       port = "ws"
+
+      check {
+        name     = "alive"
+        type     = "http"
+        port     = "http"
+        path     = "/healthz"
+        interval = "10s"
+        timeout  = "2s"
+      }
+
+      check {
+        name     = "ready"
+        type     = "http"
+        port     = "http"
+        path     = "/readyz"
+        interval = "10s"
+        timeout  = "2s"
+      }
     }
 
     task "quazar-idx-daemon" {
@@ -17,7 +44,7 @@ job "quazar-idx-daemon" {
 
       config {
         image = "andskur/index-daemon:latest" // must be fulfilled!
-        ports = ["ws"]
+        ports = ["ws", "http"]
       }
 
       resources {
@@ -26,10 +53,13 @@ job "quazar-idx-daemon" {
       }
 
       env {
-        APP__SERVICE_CONFIG__WS       = "1"
-        APP__SERVICE_CONFIG__WS_PORT  = "8000"
+        APP__SERVICE_CONFIG__WS                  = "1"
+        APP__SERVICE_CONFIG__WS_PORT             = "8000"
+        APP__SERVICE_CONFIG__HTTP                = "1"
+        APP__SERVICE_CONFIG__HTTP_HOST           = "0.0.0.0"
+        APP__SERVICE_CONFIG__HTTP_PORT           = "8081"
+        APP__SERVICE_CONFIG__READY_MIN_EXCHANGES = "2"
       }
     }
   }
 }
-
@@ -0,0 +1,20 @@
+use async_std::task;
+use index_daemon::client::icex_command::IcexCommand;
+use index_daemon::client::icex_output::IcexOutput;
+use std::process;
+
+fn main() {
+    let matches = IcexCommand::make_app().get_matches();
+    let url = matches.value_of("url").unwrap().to_string();
+    let mut output = IcexOutput::new(matches.is_present("json"));
+
+    let command = IcexCommand::new(&matches).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+
+    if let Err(e) = task::block_on(command.run(&url, &mut output)) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
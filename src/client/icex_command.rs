@@ -0,0 +1,306 @@
+use crate::client::icex_output::IcexOutput;
+use crate::client::ws_client::WsClient;
+use crate::client::ws_client_error::WsClientError;
+use crate::worker::network_helpers::ws_server::channels::ws_channel_action::WsChannelAction;
+use crate::worker::network_helpers::ws_server::channels::ws_channel_subscription_request::WsChannelSubscriptionRequest;
+use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcRequest;
+use crate::worker::network_helpers::ws_server::requests::ws_method_request::WsMethodRequest;
+use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use crate::worker::network_helpers::ws_server::ws_request::WsRequest;
+use clap::{App, AppSettings, Arg, ArgMatches};
+use futures::StreamExt;
+use serde_json::json;
+
+/// Command of `icex` CLI. Params are parsed the same way as by the server.
+#[derive(Debug)]
+pub enum IcexCommand {
+    Subscribe(WsChannelSubscriptionRequest),
+    Request(WsMethodRequest),
+    /// Discovery request (`list_*`)
+    List(WsChannelName),
+}
+
+impl IcexCommand {
+    pub fn make_app() -> App<'static> {
+        let interval = Arg::new("interval")
+            .long("interval")
+            .value_name("INTERVAL")
+            .possible_values(["second", "minute", "hour", "day", "week", "month"]);
+        let coin = Arg::new("coin").value_name("COIN").required(true);
+        let from = Arg::new("from")
+            .long("from")
+            .value_name("TIMESTAMP")
+            .required(true)
+            .help("Unix timestamp (seconds)");
+        let to = Arg::new("to")
+            .long("to")
+            .value_name("TIMESTAMP")
+            .help("Unix timestamp (seconds). Default: now");
+
+        App::new("icex")
+            .about("Client of index-daemon websocket server")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .arg(
+                Arg::new("url")
+                    .long("url")
+                    .value_name("URL")
+                    .default_value("ws://127.0.0.1:8080")
+                    .global(true)
+                    .help("Websocket server url (API key can be passed via `token` query param)"),
+            )
+            .arg(
+                Arg::new("json")
+                    .long("json")
+                    .global(true)
+                    .help("Print JSON lines instead of a table"),
+            )
+            .subcommand(
+                App::new("subscribe")
+                    .about("Subscribe to a channel and print its messages until interrupted")
+                    .arg(
+                        Arg::new("channel")
+                            .value_name("CHANNEL")
+                            .required(true)
+                            .possible_values([
+                                "coin_average_price",
+                                "coin_average_price_candles",
+                                "coin_exchange_price",
+                                "coin_exchange_volume",
+                            ]),
+                    )
+                    .arg(
+                        Arg::new("coins")
+                            .value_name("COINS")
+                            .required(true)
+                            .multiple_values(true),
+                    )
+                    .arg(
+                        Arg::new("exchanges")
+                            .long("exchanges")
+                            .value_name("EXCHANGES")
+                            .use_delimiter(true)
+                            .help("Comma-separated exchanges (for coin_exchange_* channels)"),
+                    )
+                    .arg(
+                        Arg::new("frequency_ms")
+                            .long("frequency_ms")
+                            .value_name("MS"),
+                    )
+                    .arg(
+                        interval
+                            .clone()
+                            .help("For coin_average_price_candles channel"),
+                    ),
+            )
+            .subcommand(
+                App::new("history")
+                    .about("Request historical average price of a coin")
+                    .arg(coin.clone())
+                    .arg(interval.clone().required(true))
+                    .arg(from.clone())
+                    .arg(to.clone()),
+            )
+            .subcommand(
+                App::new("candles")
+                    .about("Request historical candles of average price of a coin")
+                    .arg(coin)
+                    .arg(interval.required(true))
+                    .arg(from)
+                    .arg(to),
+            )
+            .subcommand(
+                App::new("list")
+                    .about("List coins, exchanges, methods or intervals")
+                    .arg(
+                        Arg::new("what")
+                            .value_name("WHAT")
+                            .required(true)
+                            .possible_values(["coins", "exchanges", "methods", "intervals"]),
+                    ),
+            )
+    }
+
+    pub fn new(matches: &ArgMatches) -> Result<Self, String> {
+        let (command, matches) = matches.subcommand().ok_or("Command is required.")?;
+
+        let (method, params) = match command {
+            "subscribe" => {
+                let method = matches.value_of("channel").unwrap().parse().unwrap();
+                let coins: Vec<&str> = matches.values_of("coins").unwrap().collect();
+
+                let mut params = json!({ "coins": coins });
+                if let Some(exchanges) = matches.values_of("exchanges") {
+                    params["exchanges"] = json!(exchanges.collect::<Vec<&str>>());
+                }
+                if let Some(frequency_ms) = Self::parse_u64(matches, "frequency_ms")? {
+                    params["frequency_ms"] = json!(frequency_ms);
+                }
+                if let Some(interval) = matches.value_of("interval") {
+                    params["interval"] = json!(interval);
+                }
+
+                (method, params)
+            }
+            "history" | "candles" => {
+                let method = match command {
+                    "history" => WsChannelName::CoinAveragePriceHistorical,
+                    _ => WsChannelName::CoinAveragePriceCandlesHistorical,
+                };
+
+                let mut params = json!({
+                    "coin": matches.value_of("coin").unwrap(),
+                    "interval": matches.value_of("interval").unwrap(),
+                    "from": Self::parse_u64(matches, "from")?.unwrap(),
+                });
+                if let Some(to) = Self::parse_u64(matches, "to")? {
+                    params["to"] = json!(to);
+                }
+
+                (method, params)
+            }
+            "list" => {
+                let method = format!("list_{}", matches.value_of("what").unwrap());
+                let method = serde_json::from_value(json!(method)).unwrap();
+
+                return Ok(Self::List(method));
+            }
+            _ => unreachable!(),
+        };
+
+        let request = JsonRpcRequest {
+            id: None,
+            method,
+            params,
+        };
+
+        match WsRequest::try_from(request)? {
+            WsRequest::Channel(WsChannelAction::Subscribe(request)) => Ok(Self::Subscribe(request)),
+            WsRequest::Method(request) => Ok(Self::Request(request)),
+            WsRequest::Channel(WsChannelAction::Unsubscribe(..))
+            | WsRequest::Configure(..)
+            | WsRequest::Auth(..)
+            | WsRequest::Discovery(..)
+            | WsRequest::Alert(..) => unreachable!(),
+        }
+    }
+
+    fn parse_u64(matches: &ArgMatches, key: &str) -> Result<Option<u64>, String> {
+        match matches.value_of(key) {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("Wrong value of {}: {}.", key, value)),
+            None => Ok(None),
+        }
+    }
+
+    /// Subscription is executing until the process is interrupted (or the server removes it)
+    pub async fn run(self, url: &str, output: &mut IcexOutput) -> Result<(), WsClientError> {
+        let client = WsClient::connect(url).await?;
+
+        match self {
+            Self::Subscribe(request) => {
+                let mut subscription = client.subscribe(request).await?;
+
+                while let Some(notification) = subscription.next().await {
+                    output.print(&notification.payload, notification.snapshot);
+                }
+            }
+            Self::Request(request) => {
+                output.print(&client.request(request).await?, false);
+            }
+            Self::List(method) => {
+                let payload = client.call(method, serde_json::Value::Null).await?;
+                output.print(&payload, false);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::client::icex_command::IcexCommand;
+    use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+
+    fn make_command(args: &[&str]) -> Result<IcexCommand, String> {
+        let matches = IcexCommand::make_app()
+            .try_get_matches_from(args)
+            .map_err(|e| e.to_string())?;
+
+        IcexCommand::new(&matches)
+    }
+
+    #[test]
+    fn test_new() {
+        let command = make_command(&["icex", "subscribe", "coin_average_price", "BTC", "ETH"]);
+        match command {
+            Ok(IcexCommand::Subscribe(request)) => {
+                assert_eq!(request.get_method(), WsChannelName::CoinAveragePrice);
+                assert_eq!(request.get_coins(), ["BTC", "ETH"]);
+                assert_eq!(request.get_frequency_ms(), None);
+            }
+            _ => panic!("Wrong command."),
+        }
+
+        let command = make_command(&[
+            "icex",
+            "subscribe",
+            "coin_exchange_price",
+            "BTC",
+            "--exchanges",
+            "binance,coinbase",
+            "--frequency_ms",
+            "500",
+        ]);
+        match command {
+            Ok(IcexCommand::Subscribe(request)) => {
+                assert_eq!(request.get_method(), WsChannelName::CoinExchangePrice);
+                assert_eq!(request.get_frequency_ms(), Some(500));
+                assert_eq!(
+                    request.get_params()["exchanges"],
+                    serde_json::json!(["binance", "coinbase"])
+                );
+            }
+            _ => panic!("Wrong command."),
+        }
+
+        let command = make_command(&[
+            "icex",
+            "candles",
+            "BTC",
+            "--interval",
+            "hour",
+            "--from",
+            "1643835600",
+            "--to",
+            "1644440400",
+        ]);
+        match command {
+            Ok(IcexCommand::Request(request)) => {
+                assert_eq!(
+                    request.get_method(),
+                    WsChannelName::CoinAveragePriceCandlesHistorical
+                );
+                assert_eq!(request.get_params()["to"], 1644440400);
+            }
+            _ => panic!("Wrong command."),
+        }
+
+        let command = make_command(&["icex", "list", "exchanges", "--json"]);
+        assert!(matches!(
+            command,
+            Ok(IcexCommand::List(WsChannelName::ListExchanges))
+        ));
+
+        // Exchanges are required by coin_exchange_* channels
+        let command = make_command(&["icex", "subscribe", "coin_exchange_price", "BTC"]);
+        assert_eq!(command.unwrap_err(), "Wrong params.");
+
+        let command = make_command(&["icex", "history", "BTC", "--interval", "day", "--from", "x"]);
+        assert_eq!(command.unwrap_err(), "Wrong value of from: x.");
+
+        assert!(make_command(&["icex", "history", "BTC", "--from", "1"]).is_err());
+    }
+}
@@ -0,0 +1,309 @@
+use crate::worker::network_helpers::alerts::alert_rule::AlertRule;
+use crate::worker::network_helpers::ws_server::candles::Candle;
+use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+use chrono::{DateTime, Utc};
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+/// Minimal width of a table column
+const COLUMN_WIDTH: usize = 12;
+
+/// Prints payloads of `icex` responses as table rows or JSON lines
+pub struct IcexOutput {
+    json: bool,
+    /// Header of the last printed table. It's printed again when the payload kind changes.
+    header: Option<Vec<&'static str>>,
+}
+
+impl IcexOutput {
+    pub fn new(json: bool) -> Self {
+        Self { json, header: None }
+    }
+
+    pub fn print(&mut self, payload: &WsChannelResponsePayload, snapshot: bool) {
+        for line in self.format(payload, snapshot) {
+            println!("{}", line);
+        }
+    }
+
+    /// Returns lines to print
+    pub fn format(&mut self, payload: &WsChannelResponsePayload, snapshot: bool) -> Vec<String> {
+        if self.json {
+            let mut value = serde_json::to_value(payload).unwrap();
+            if snapshot {
+                value["snapshot"] = true.into();
+            }
+
+            return vec![value.to_string()];
+        }
+
+        if let WsChannelResponsePayload::Schema(schema) = payload {
+            self.header = None;
+            return vec![serde_json::to_string_pretty(schema).unwrap()];
+        }
+
+        let (header, rows) = Self::make_table(payload);
+
+        let mut lines = Vec::new();
+        if self.header.as_ref() != Some(&header) {
+            let header_row: Vec<String> = header.iter().map(|v| v.to_string()).collect();
+            lines.push(Self::format_row(&header_row));
+            self.header = Some(header);
+        }
+        lines.extend(rows.iter().map(|row| Self::format_row(row)));
+
+        lines
+    }
+
+    fn make_table(payload: &WsChannelResponsePayload) -> (Vec<&'static str>, Vec<Vec<String>>) {
+        match payload {
+            WsChannelResponsePayload::SuccSub { method, message } => (
+                vec!["METHOD", "MESSAGE"],
+                vec![vec![Self::format_json(method), message.clone()]],
+            ),
+            WsChannelResponsePayload::Err {
+                method,
+                code,
+                message,
+            } => (
+                vec!["METHOD", "CODE", "MESSAGE"],
+                vec![vec![
+                    method.map(|v| Self::format_json(&v)).unwrap_or_default(),
+                    code.to_string(),
+                    message.clone(),
+                ]],
+            ),
+            WsChannelResponsePayload::CoinAveragePrice {
+                coin,
+                value,
+                timestamp,
+            } => (
+                vec!["TIME", "COIN", "VALUE"],
+                vec![vec![
+                    Self::format_time(timestamp),
+                    coin.clone(),
+                    value.to_string(),
+                ]],
+            ),
+            WsChannelResponsePayload::CoinExchangePrice {
+                coin,
+                exchange,
+                value,
+                timestamp,
+            }
+            | WsChannelResponsePayload::CoinExchangeVolume {
+                coin,
+                exchange,
+                value,
+                timestamp,
+            } => (
+                vec!["TIME", "COIN", "EXCHANGE", "VALUE"],
+                vec![vec![
+                    Self::format_time(timestamp),
+                    coin.clone(),
+                    exchange.clone(),
+                    value.to_string(),
+                ]],
+            ),
+            WsChannelResponsePayload::CoinAveragePriceHistorical { values, .. } => (
+                vec!["TIME", "VALUE"],
+                values
+                    .0
+                    .iter()
+                    .map(|v| vec![Self::format_time(&v.timestamp), v.value.to_string()])
+                    .collect(),
+            ),
+            WsChannelResponsePayload::CoinAveragePriceCandles { coin, value } => (
+                vec!["TIME", "COIN", "OPEN", "CLOSE", "MIN", "MAX", "AVG"],
+                vec![Self::format_candle(Some(coin), value)],
+            ),
+            WsChannelResponsePayload::CoinAveragePriceCandlesHistorical { values, .. } => (
+                vec!["TIME", "OPEN", "CLOSE", "MIN", "MAX", "AVG"],
+                values
+                    .0
+                    .iter()
+                    .map(|v| Self::format_candle(None, v))
+                    .collect(),
+            ),
+            WsChannelResponsePayload::Coins { coins } => (
+                vec!["COIN"],
+                coins.iter().map(|v| vec![v.clone()]).collect(),
+            ),
+            WsChannelResponsePayload::Exchanges { exchanges } => (
+                vec!["EXCHANGE", "COIN", "LIVE"],
+                exchanges
+                    .iter()
+                    .flat_map(|exchange| {
+                        exchange.coins.iter().map(|coin| {
+                            vec![
+                                exchange.exchange.clone(),
+                                coin.coin.clone(),
+                                coin.live.to_string(),
+                            ]
+                        })
+                    })
+                    .collect(),
+            ),
+            WsChannelResponsePayload::Methods { methods } => (
+                vec!["METHOD", "KIND"],
+                methods
+                    .iter()
+                    .map(|v| vec![Self::format_json(&v.method), Self::format_json(&v.kind)])
+                    .collect(),
+            ),
+            WsChannelResponsePayload::Intervals { intervals } => (
+                vec!["INTERVAL", "SECONDS"],
+                intervals
+                    .iter()
+                    .map(|v| vec![Self::format_json(&v.interval), v.seconds.to_string()])
+                    .collect(),
+            ),
+            WsChannelResponsePayload::Alert(notification) => (
+                vec!["TIME", "ALERT", "COIN", "KIND", "EXCHANGE", "PRICE"],
+                vec![vec![
+                    Self::format_time(&notification.timestamp),
+                    notification.alert_id.clone(),
+                    notification.coin.clone(),
+                    notification.condition.get_kind().to_string(),
+                    notification.exchange.clone().unwrap_or_default(),
+                    notification.price.to_string(),
+                ]],
+            ),
+            WsChannelResponsePayload::AlertAdded { alert } => (
+                vec!["ID", "COIN", "CONDITION"],
+                vec![Self::format_alert(alert)],
+            ),
+            WsChannelResponsePayload::Alerts { alerts } => (
+                vec!["ID", "COIN", "CONDITION"],
+                alerts.iter().map(Self::format_alert).collect(),
+            ),
+            WsChannelResponsePayload::Schema(..) => unreachable!(),
+        }
+    }
+
+    fn format_row(row: &[String]) -> String {
+        let (last, cells) = row.split_last().unwrap();
+        let mut res: String = cells
+            .iter()
+            .map(|v| format!("{:<width$} ", v, width = COLUMN_WIDTH))
+            .collect();
+        res.push_str(last);
+
+        res
+    }
+
+    fn format_candle(coin: Option<&String>, candle: &Candle) -> Vec<String> {
+        let mut res = vec![Self::format_time(&candle.timestamp)];
+        res.extend(coin.cloned());
+        res.extend(
+            [
+                candle.open,
+                candle.close,
+                candle.min,
+                candle.max,
+                candle.avg,
+            ]
+            .iter()
+            .map(|v| v.to_string()),
+        );
+
+        res
+    }
+
+    fn format_alert(alert: &AlertRule) -> Vec<String> {
+        vec![
+            alert.id.clone(),
+            alert.coin.clone(),
+            Self::format_json(&alert.condition),
+        ]
+    }
+
+    fn format_time(timestamp: &DateTime<Utc>) -> String {
+        timestamp.format(TIME_FORMAT).to_string()
+    }
+
+    /// Formats enums the same way they are named in the API
+    fn format_json<T: serde::Serialize>(value: &T) -> String {
+        match serde_json::to_value(value).unwrap() {
+            serde_json::Value::String(value) => value,
+            value => value.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::client::icex_output::IcexOutput;
+    use crate::worker::helper_functions::date_time_from_timestamp_sec;
+    use crate::worker::network_helpers::ws_server::candles::{Candle, Candles};
+    use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+
+    fn make_price(value: f64) -> WsChannelResponsePayload {
+        WsChannelResponsePayload::CoinAveragePrice {
+            coin: "BTC".to_string(),
+            value,
+            timestamp: date_time_from_timestamp_sec(1643835600),
+        }
+    }
+
+    #[test]
+    fn test_format_table() {
+        let mut output = IcexOutput::new(false);
+
+        assert_eq!(
+            output.format(&make_price(100.5), true),
+            [
+                "TIME         COIN         VALUE",
+                "2022-02-02 21:00:00 BTC          100.5",
+            ]
+        );
+        // Header isn't repeated for the same kind of payload
+        assert_eq!(
+            output.format(&make_price(101.0), false),
+            ["2022-02-02 21:00:00 BTC          101"]
+        );
+
+        let candles = WsChannelResponsePayload::CoinAveragePriceCandlesHistorical {
+            coin: "BTC".to_string(),
+            values: Candles(vec![Candle {
+                open: 1.0,
+                close: 2.0,
+                min: 0.5,
+                max: 3.0,
+                avg: 1.5,
+                timestamp: date_time_from_timestamp_sec(1643835600),
+            }]),
+        };
+        assert_eq!(
+            output.format(&candles, false),
+            [
+                "TIME         OPEN         CLOSE        MIN          MAX          AVG",
+                "2022-02-02 21:00:00 1            2            0.5          3            1.5",
+            ]
+        );
+
+        let coins = WsChannelResponsePayload::Coins {
+            coins: vec!["BTC".to_string(), "ETH".to_string()],
+        };
+        assert_eq!(output.format(&coins, false), ["COIN", "BTC", "ETH"]);
+    }
+
+    #[test]
+    fn test_format_json() {
+        let mut output = IcexOutput::new(true);
+
+        let lines = output.format(&make_price(100.5), true);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&lines[0]).unwrap(),
+            serde_json::json!({
+                "coin": "BTC",
+                "value": 100.5,
+                "timestamp": 1643835600,
+                "snapshot": true,
+            })
+        );
+
+        let lines = output.format(&make_price(101.0), false);
+        assert!(!lines[0].contains("snapshot"));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod icex_command;
+pub mod icex_output;
+pub mod ws_client;
+pub mod ws_client_error;
+pub mod ws_subscription;
@@ -0,0 +1,433 @@
+use crate::client::ws_client_error::WsClientError;
+use crate::client::ws_subscription::{WsNotification, WsSubscription};
+use crate::worker::network_helpers::ws_server::channels::ws_channel_subscription_request::WsChannelSubscriptionRequest;
+use crate::worker::network_helpers::ws_server::requests::ws_method_request::WsMethodRequest;
+use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+use async_std::task;
+use async_tungstenite::async_std::{connect_async, ConnectStream};
+use async_tungstenite::tungstenite::protocol::Message;
+use async_tungstenite::WebSocketStream;
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, Either};
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time;
+
+type WsStream = WebSocketStream<ConnectStream>;
+type Responder<T> = oneshot::Sender<Result<T, WsClientError>>;
+
+/// Delay before the first reconnect attempt. Is doubled after every failed attempt.
+const RECONNECT_DELAY_MS: u64 = 100;
+const MAX_RECONNECT_DELAY_MS: u64 = 10000;
+
+enum Command {
+    Subscribe {
+        request: WsChannelSubscriptionRequest,
+        notifications: mpsc::UnboundedSender<WsNotification>,
+        subscribed: Responder<()>,
+    },
+    Call {
+        method: WsChannelName,
+        params: Value,
+        response: Responder<WsChannelResponsePayload>,
+    },
+}
+
+/// Client of the websocket server (expects JSON-RPC 2.0 responses in json encoding).
+/// Reconnects automatically and restores subscriptions.
+/// Connection is closed when all clones of the client are dropped.
+#[derive(Clone)]
+pub struct WsClient {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl WsClient {
+    /// `url` - e.g. "ws://127.0.0.1:8080" (API key can be passed via `token` query param).
+    /// Fails if the first connection attempt fails.
+    pub async fn connect(url: &str) -> Result<Self, WsClientError> {
+        let ws_stream = connect(url).await?;
+
+        let (tx, rx) = mpsc::unbounded();
+        task::spawn(WsClientConnection::new(url.to_string(), rx).run(ws_stream));
+
+        Ok(Self { commands: tx })
+    }
+
+    /// Returns after the server confirmed the subscription
+    pub async fn subscribe(
+        &self,
+        request: WsChannelSubscriptionRequest,
+    ) -> Result<WsSubscription, WsClientError> {
+        let (notifications, notifications_rx) = mpsc::unbounded();
+        let (subscribed, subscribed_rx) = oneshot::channel();
+
+        self.send_command(Command::Subscribe {
+            request,
+            notifications,
+            subscribed,
+        })?;
+        subscribed_rx.await.map_err(|_| WsClientError::Closed)??;
+
+        Ok(WsSubscription::new(notifications_rx))
+    }
+
+    /// Historical data request
+    pub async fn request(
+        &self,
+        request: WsMethodRequest,
+    ) -> Result<WsChannelResponsePayload, WsClientError> {
+        self.call(request.get_method(), request.get_params()).await
+    }
+
+    /// Any request, which is not a subscription (e.g. `list_coins`).
+    /// Request, sent while the client is reconnecting, fails with `WsClientError::Disconnected`.
+    pub async fn call(
+        &self,
+        method: WsChannelName,
+        params: Value,
+    ) -> Result<WsChannelResponsePayload, WsClientError> {
+        let (response, response_rx) = oneshot::channel();
+
+        self.send_command(Command::Call {
+            method,
+            params,
+            response,
+        })?;
+
+        response_rx.await.map_err(|_| WsClientError::Closed)?
+    }
+
+    fn send_command(&self, command: Command) -> Result<(), WsClientError> {
+        self.commands
+            .unbounded_send(command)
+            .map_err(|_| WsClientError::Closed)
+    }
+}
+
+async fn connect(url: &str) -> Result<WsStream, WsClientError> {
+    connect_async(url)
+        .await
+        .map(|(ws_stream, _)| ws_stream)
+        .map_err(|e| WsClientError::Connection(e.to_string()))
+}
+
+fn make_request(id: u64, method: WsChannelName, params: Value) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+        "params": params,
+    })
+    .to_string()
+}
+
+enum Pending {
+    Call(WsChannelName, Responder<WsChannelResponsePayload>),
+    /// Value - subscription key
+    Subscribe(u64),
+    /// Response is ignored
+    Unsubscribe,
+}
+
+struct Subscription {
+    request: WsChannelSubscriptionRequest,
+    notifications: mpsc::UnboundedSender<WsNotification>,
+    /// Is `Some` until the subscription is confirmed for the first time
+    subscribed: Option<Responder<()>>,
+}
+
+/// Owns the connection: sends requests, routes responses and notifications, reconnects
+struct WsClientConnection {
+    url: String,
+    commands: mpsc::UnboundedReceiver<Command>,
+    /// Is used both for request ids and subscription keys
+    next_id: u64,
+    /// Requests waiting for responses, by request id
+    pending: HashMap<u64, Pending>,
+    /// Subscriptions by key. They are restored after reconnect.
+    subscriptions: HashMap<u64, Subscription>,
+    /// Key - `subscription_id` (is changed after reconnect), value - subscription key
+    subscription_keys: HashMap<String, u64>,
+}
+
+impl WsClientConnection {
+    fn new(url: String, commands: mpsc::UnboundedReceiver<Command>) -> Self {
+        Self {
+            url,
+            commands,
+            next_id: 0,
+            pending: HashMap::new(),
+            subscriptions: HashMap::new(),
+            subscription_keys: HashMap::new(),
+        }
+    }
+
+    async fn run(mut self, mut ws_stream: WsStream) {
+        while self.handle_connection(ws_stream).await {
+            self.reset();
+
+            ws_stream = match self.reconnect().await {
+                Some(ws_stream) => ws_stream,
+                None => return,
+            };
+        }
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Function is executing until the connection is lost (returns `true`) or the client is closed (returns `false`)
+    async fn handle_connection(&mut self, ws_stream: WsStream) -> bool {
+        let (mut write, mut read) = ws_stream.split();
+
+        let keys: Vec<u64> = self.subscriptions.keys().copied().collect();
+        let mut outgoing: Vec<String> = keys
+            .into_iter()
+            .map(|key| self.make_subscribe_request(key))
+            .collect();
+
+        loop {
+            for message in outgoing.drain(..) {
+                if write.send(Message::text(message)).await.is_err() {
+                    return true;
+                }
+            }
+
+            let event = match future::select(read.next(), self.commands.next()).await {
+                Either::Left((message, _)) => Either::Left(message),
+                Either::Right((command, _)) => Either::Right(command),
+            };
+
+            match event {
+                Either::Left(Some(Ok(Message::Text(message)))) => {
+                    outgoing = self.process_message(&message);
+                }
+                Either::Left(Some(Ok(Message::Close(_)))) | Either::Left(Some(Err(_))) => {
+                    return true
+                }
+                Either::Left(None) => return true,
+                Either::Left(Some(Ok(_))) => {}
+                Either::Right(Some(command)) => {
+                    outgoing.extend(self.process_command(command, true));
+                }
+                Either::Right(None) => {
+                    let _ = write.close().await;
+                    return false;
+                }
+            }
+        }
+    }
+
+    /// Fails requests, which wait for responses. Subscriptions are restored after reconnect.
+    fn reset(&mut self) {
+        for (_, pending) in self.pending.drain() {
+            if let Pending::Call(_, response) = pending {
+                let _ = response.send(Err(WsClientError::Disconnected));
+            }
+        }
+
+        self.subscription_keys.clear();
+        self.subscriptions
+            .retain(|_, subscription| !subscription.notifications.is_closed());
+    }
+
+    /// Returns `None` if the client is closed
+    async fn reconnect(&mut self) -> Option<WsStream> {
+        let mut delay_ms = RECONNECT_DELAY_MS;
+
+        loop {
+            task::sleep(time::Duration::from_millis(delay_ms)).await;
+
+            // Commands, received while disconnected
+            loop {
+                match self.commands.try_next() {
+                    Ok(Some(command)) => {
+                        self.process_command(command, false);
+                    }
+                    Ok(None) => return None,
+                    Err(_) => break,
+                }
+            }
+
+            match connect(&self.url).await {
+                Ok(ws_stream) => {
+                    info!("WsClient. Reconnected. Url: {}", self.url);
+
+                    return Some(ws_stream);
+                }
+                Err(e) => {
+                    error!(
+                        "WsClient. Failed to reconnect. Url: {}, error: {:?}",
+                        self.url, e
+                    );
+
+                    delay_ms = (delay_ms * 2).min(MAX_RECONNECT_DELAY_MS);
+                }
+            }
+        }
+    }
+
+    fn make_subscribe_request(&mut self, key: u64) -> String {
+        let id = self.next_id();
+        self.pending.insert(id, Pending::Subscribe(key));
+
+        let request = &self.subscriptions[&key].request;
+
+        make_request(id, request.get_method(), request.get_params())
+    }
+
+    /// Returns message, which must be sent
+    fn process_command(&mut self, command: Command, connected: bool) -> Option<String> {
+        match command {
+            Command::Subscribe {
+                request,
+                notifications,
+                subscribed,
+            } => {
+                let key = self.next_id();
+                self.subscriptions.insert(
+                    key,
+                    Subscription {
+                        request,
+                        notifications,
+                        subscribed: Some(subscribed),
+                    },
+                );
+
+                // Subscription is sent after reconnect
+                connected.then(|| self.make_subscribe_request(key))
+            }
+            Command::Call {
+                method,
+                params,
+                response,
+            } => {
+                if !connected {
+                    let _ = response.send(Err(WsClientError::Disconnected));
+                    return None;
+                }
+
+                let id = self.next_id();
+                self.pending.insert(id, Pending::Call(method, response));
+
+                Some(make_request(id, method, params))
+            }
+        }
+    }
+
+    /// Processes one message or a batch. Returns messages, which must be sent.
+    fn process_message(&mut self, message: &str) -> Vec<String> {
+        let messages = match serde_json::from_str(message) {
+            Ok(Value::Array(messages)) => messages,
+            Ok(message) => vec![message],
+            Err(e) => {
+                error!("WsClient. Wrong message: {}, error: {:?}", message, e);
+                return Vec::new();
+            }
+        };
+
+        let mut outgoing = Vec::new();
+        for message in messages {
+            match message["id"].as_u64() {
+                Some(id) => self.process_response(id, message),
+                None => outgoing.extend(self.process_notification(message)),
+            }
+        }
+
+        outgoing
+    }
+
+    fn process_response(&mut self, id: u64, mut response: Value) {
+        let pending = match self.pending.remove(&id) {
+            Some(pending) => pending,
+            None => return,
+        };
+
+        let result = match response.get("error") {
+            Some(error) => Err(WsClientError::Rpc {
+                code: error["code"].as_i64().unwrap_or_default(),
+                message: error["message"].as_str().unwrap_or_default().to_string(),
+            }),
+            None => Ok(response["result"].take()),
+        };
+
+        match pending {
+            Pending::Call(method, response) => {
+                let result = result.and_then(|result| {
+                    WsChannelResponsePayload::from_value(method, result)
+                        .map_err(WsClientError::Parse)
+                });
+
+                let _ = response.send(result);
+            }
+            Pending::Subscribe(key) => match result {
+                Ok(result) => {
+                    if let Some(subscription_id) = result["subscription_id"].as_str() {
+                        self.subscription_keys
+                            .insert(subscription_id.to_string(), key);
+                    }
+
+                    if let Some(subscription) = self.subscriptions.get_mut(&key) {
+                        if let Some(subscribed) = subscription.subscribed.take() {
+                            let _ = subscribed.send(Ok(()));
+                        }
+                    }
+                }
+                Err(e) => {
+                    // Subscription stream ends if the subscription is not restored after reconnect
+                    if let Some(subscription) = self.subscriptions.remove(&key) {
+                        if let Some(subscribed) = subscription.subscribed {
+                            let _ = subscribed.send(Err(e));
+                        }
+                    }
+                }
+            },
+            Pending::Unsubscribe => {}
+        }
+    }
+
+    /// Returns unsubscribe request if the subscription stream is dropped
+    fn process_notification(&mut self, mut notification: Value) -> Option<String> {
+        let method: WsChannelName = serde_json::from_value(notification["method"].take()).ok()?;
+        let params = notification["params"].take();
+        let subscription_id = params["subscription_id"].as_str()?.to_string();
+        let snapshot = params["snapshot"].as_bool().unwrap_or(false);
+        let key = *self.subscription_keys.get(&subscription_id)?;
+
+        let payload = match WsChannelResponsePayload::from_value(method, params) {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!("WsClient. Wrong notification. Error: {}", e);
+                return None;
+            }
+        };
+
+        let notification = WsNotification { payload, snapshot };
+        let is_sent = self
+            .subscriptions
+            .get(&key)?
+            .notifications
+            .unbounded_send(notification)
+            .is_ok();
+
+        if is_sent {
+            None
+        } else {
+            self.subscriptions.remove(&key);
+            self.subscription_keys.remove(&subscription_id);
+
+            let id = self.next_id();
+            self.pending.insert(id, Pending::Unsubscribe);
+
+            Some(make_request(
+                id,
+                WsChannelName::Unsubscribe,
+                json!({ "subscription_id": subscription_id }),
+            ))
+        }
+    }
+}
@@ -0,0 +1,25 @@
+#[derive(Debug, Clone, PartialEq)]
+pub enum WsClientError {
+    /// Failed to connect to the server
+    Connection(String),
+    /// Connection was lost before the response was received
+    Disconnected,
+    /// Client is closed
+    Closed,
+    /// Jsonrpc error response
+    Rpc { code: i64, message: String },
+    /// Server sent a message, which can't be parsed
+    Parse(String),
+}
+
+impl std::fmt::Display for WsClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Connection(e) => write!(f, "Connection error: {}", e),
+            Self::Disconnected => write!(f, "Connection was lost."),
+            Self::Closed => write!(f, "Client is closed."),
+            Self::Rpc { code, message } => write!(f, "Error {}: {}", code, message),
+            Self::Parse(e) => write!(f, "Wrong message from the server: {}", e),
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+use futures::channel::mpsc;
+use futures::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pub struct WsNotification {
+    pub payload: WsChannelResponsePayload,
+    /// Current value, sent right after (re)subscription
+    pub snapshot: bool,
+}
+
+/// Stream of notifications of one subscription. Subscription is kept after reconnect.
+/// Server subscription is removed after the stream is dropped.
+pub struct WsSubscription(mpsc::UnboundedReceiver<WsNotification>);
+
+impl WsSubscription {
+    pub fn new(notifications: mpsc::UnboundedReceiver<WsNotification>) -> Self {
+        Self(notifications)
+    }
+}
+
+impl Stream for WsSubscription {
+    type Item = WsNotification;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.0).poll_next(cx)
+    }
+}
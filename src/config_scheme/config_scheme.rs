@@ -10,9 +10,7 @@ pub struct ConfigScheme {
 }
 
 impl ConfigScheme {
-    pub fn new() -> Self {
-        let matches = Self::make_matches();
-
+    pub fn new(matches: ArgMatches) -> Self {
         Self {
             market: MarketConfig::new(&matches),
             service: ServiceConfig::new(&matches),
@@ -21,7 +19,7 @@ impl ConfigScheme {
     }
 
     /// Call only once
-    fn make_matches() -> ArgMatches {
+    pub fn make_matches() -> ArgMatches {
         App::new("ICEX")
             .version("1.0")
             .arg(
@@ -44,6 +42,18 @@ impl ConfigScheme {
                     .value_names(&["TIMESTAMP", "COINS"])
                     .help("Fill historical data. Params: timestamp (contains comma-separated \"from\" and \"to\", \"to\" is optional), coins (uppercase comma-separated)."),
             )
+            .arg(
+                Arg::new("healthcheck")
+                    .long("healthcheck")
+                    .value_name("URL")
+                    .help("Request health endpoint of a running daemon and exit. Exit code is 0 if endpoint answered with success status, else 1.")
+                    .value_hint(ValueHint::Url),
+            )
+            .arg(
+                Arg::new("openrpc")
+                    .long("openrpc")
+                    .help("Print OpenRPC document of the websocket API and exit."),
+            )
             .get_matches()
     }
 }
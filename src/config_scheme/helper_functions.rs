@@ -71,6 +71,14 @@ pub fn get_default_port() -> String {
     "8080".to_string()
 }
 
+pub fn get_default_http_port() -> String {
+    "8081".to_string()
+}
+
+pub fn get_default_grpc_port() -> String {
+    "8082".to_string()
+}
+
 pub fn get_default_historical() -> bool {
     false
 }
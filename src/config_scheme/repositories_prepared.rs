@@ -5,6 +5,8 @@ use crate::repository::repositories::{
 use crate::worker::market_helpers::pair_average_price::{
     make_pair_average_price, PairAveragePriceType,
 };
+use crate::worker::network_helpers::alerts::alert_registry::AlertRegistry;
+use crate::worker::network_helpers::output_sink::output_sink::OutputSinks;
 use crate::worker::network_helpers::ws_server::ws_channels_holder::{
     WsChannelsHolder, WsChannelsHolderHashMap,
 };
@@ -14,6 +16,10 @@ pub struct RepositoriesPrepared {
     pub market_repositories: Option<MarketRepositoriesByMarketName>,
     pub ws_channels_holder: WsChannelsHolderHashMap,
     pub pair_average_price: PairAveragePriceType,
+    /// Started output sinks. Markets publish their values and trades to them.
+    pub output_sinks: OutputSinks,
+    /// Registered alerts (None if alerts are turned off)
+    pub alerts: Option<AlertRegistry>,
 }
 
 impl RepositoriesPrepared {
@@ -23,10 +29,31 @@ impl RepositoriesPrepared {
 
         let ws_channels_holder = WsChannelsHolder::make_hashmap(&config.market);
 
+        let output_sink_queue_size = config.service.output_sink_queue_size;
+        let mut output_sinks: OutputSinks = config
+            .service
+            .output_sinks
+            .iter()
+            .map(|v| v.start(output_sink_queue_size))
+            .collect();
+        if let Some(kafka_sink) = &config.service.kafka_sink {
+            output_sinks.push(kafka_sink.start(output_sink_queue_size));
+        }
+        if let Some(file_sink) = &config.service.file_sink {
+            output_sinks.push(file_sink.start(output_sink_queue_size));
+        }
+        let alerts = config.service.alerts.as_ref().map(|alerts_config| {
+            let (alerts, alert_sink) =
+                alerts_config.start(output_sink_queue_size, &ws_channels_holder);
+            output_sinks.push(alert_sink);
+            alerts
+        });
+
         let pair_average_price = make_pair_average_price(
             &config.market,
             pair_average_price_repository.clone(),
             &ws_channels_holder,
+            &output_sinks,
         );
 
         Self {
@@ -34,6 +61,8 @@ impl RepositoriesPrepared {
             market_repositories,
             ws_channels_holder,
             pair_average_price,
+            output_sinks,
+            alerts,
         }
     }
 }
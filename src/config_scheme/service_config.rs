@@ -1,15 +1,63 @@
 use crate::config_scheme::helper_functions::{
-    get_config_from_config_files, get_default_historical, get_default_host, get_default_port,
-    get_default_storage, set_log_level,
+    get_config_from_config_files, get_default_grpc_port, get_default_historical, get_default_host,
+    get_default_http_port, get_default_port, get_default_storage, set_log_level,
 };
 use crate::config_scheme::storage::Storage;
+use crate::worker::network_helpers::alerts::alert_rule::AlertRule;
+use crate::worker::network_helpers::alerts::alert_sink::AlertsConfig;
+use crate::worker::network_helpers::alerts::webhook::WebhookConfig;
+use crate::worker::network_helpers::output_sink::file_sink::{FileSinkConfig, FileSinkFormat};
+use crate::worker::network_helpers::output_sink::kafka_sink::KafkaSinkConfig;
+use crate::worker::network_helpers::output_sink::output_sink::{OutputSinkConfig, OutputSinkKind};
+use crate::worker::network_helpers::ws_server::outbound_queue::OutboundQueuePolicy;
+use crate::worker::network_helpers::ws_server::permessage_deflate::PermessageDeflateConfig;
+use crate::worker::network_helpers::ws_server::unix_socket::{
+    UnixSocketConfig, UnixSocketProtocol,
+};
+use crate::worker::network_helpers::ws_server::ws_auth::ApiKeys;
+use crate::worker::network_helpers::ws_server::ws_limits::WsLimitsConfig;
+use crate::worker::network_helpers::ws_server::ws_tls::WsTlsConfig;
 use clap::ArgMatches;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
 
 pub struct ServiceConfig {
     pub rest_timeout_sec: u64,
     pub ws: bool,
+    /// Whether websocket server listens on `ws_addr` (TCP)
+    pub ws_tcp: bool,
     pub ws_addr: String,
+    /// Unix domain socket config (`None` if Unix domain socket is turned off)
+    pub ws_unix_socket: Option<UnixSocketConfig>,
     pub ws_answer_timeout_ms: u64,
+    pub ws_outbound_queue_size: usize,
+    pub ws_outbound_queue_policy: OutboundQueuePolicy,
+    pub ws_compression: Option<PermessageDeflateConfig>,
+    /// API keys (`None` if authentication is turned off)
+    pub ws_api_keys: Option<Arc<ApiKeys>>,
+    /// TLS config (`None` if TLS is turned off)
+    pub ws_tls: Option<WsTlsConfig>,
+    pub ws_limits: WsLimitsConfig,
+    pub ws_workers: usize,
+    pub ws_workers_queue_size: usize,
+    /// Whether responses have the legacy shape (errors and subscription messages inside `result`)
+    pub ws_legacy_responses: bool,
+    pub http: bool,
+    pub http_addr: String,
+    pub metrics: bool,
+    pub ready_min_exchanges: usize,
+    pub grpc: bool,
+    pub grpc_addr: String,
+    /// Brokers, which index updates are published to
+    pub output_sinks: Vec<OutputSinkConfig>,
+    pub output_sink_queue_size: usize,
+    /// Kafka producer config (`None` if Kafka sink is turned off)
+    pub kafka_sink: Option<KafkaSinkConfig>,
+    /// File sink config (`None` if file sink is turned off)
+    pub file_sink: Option<FileSinkConfig>,
+    /// Alerts config (`None` if alerts are turned off)
+    pub alerts: Option<AlertsConfig>,
     pub storage: Option<Storage>,
     pub historical_storage_frequency_ms: u64,
 }
@@ -44,7 +92,22 @@ impl ServiceConfig {
         if !ws
             && (service_config.get_str("ws_host").is_ok()
                 || service_config.get_str("ws_port").is_ok()
-                || service_config.get_str("ws_answer_timeout_ms").is_ok())
+                || service_config.get_str("ws_answer_timeout_ms").is_ok()
+                || service_config.get_str("ws_outbound_queue_size").is_ok()
+                || service_config.get_str("ws_outbound_queue_policy").is_ok()
+                || service_config.get_str("ws_compression").is_ok()
+                || service_config.get_str("ws_auth_keys_file").is_ok()
+                || service_config.get_str("ws_tls_cert").is_ok()
+                || service_config.get_str("ws_unix_socket").is_ok()
+                || service_config.get_str("ws_tls_key").is_ok()
+                || service_config.get_str("ws_max_connections").is_ok()
+                || service_config.get_str("ws_max_connections_per_ip").is_ok()
+                || service_config.get_str("ws_max_subscriptions").is_ok()
+                || service_config.get_str("ws_requests_per_sec").is_ok()
+                || service_config.get_str("ws_requests_per_sec_per_ip").is_ok()
+                || service_config.get_str("ws_workers").is_ok()
+                || service_config.get_str("ws_workers_queue_size").is_ok()
+                || service_config.get_str("ws_legacy_responses").is_ok())
         {
             panic!(
                 "Got unexpected config. service_config: ws_*. That config is allowed only if ws=1"
@@ -58,6 +121,44 @@ impl ServiceConfig {
             .get_str("ws_port")
             .unwrap_or(get_default_port());
         let ws_addr = ws_host + ":" + &ws_port;
+
+        let ws_unix_socket_path = service_config.get_str("ws_unix_socket").ok();
+        if ws_unix_socket_path.is_none()
+            && (service_config.get_str("ws_unix_socket_protocol").is_ok()
+                || service_config.get_str("ws_unix_socket_only").is_ok())
+        {
+            panic!(
+                "Got unexpected config. service_config: ws_unix_socket_*. These configs are allowed only if ws_unix_socket is set"
+            );
+        }
+        let ws_unix_socket = ws_unix_socket_path.map(|path| {
+            let protocol = service_config
+                .get_str("ws_unix_socket_protocol")
+                .map(|v| {
+                    UnixSocketProtocol::from_str(&v).unwrap_or_else(|_| {
+                        panic!(
+                            "Got wrong config value. service_config: ws_unix_socket_protocol={}",
+                            v
+                        )
+                    })
+                })
+                .unwrap_or(UnixSocketProtocol::Ws);
+
+            UnixSocketConfig { path, protocol }
+        });
+        let ws_tcp = if let Ok(ws_unix_socket_only) = service_config.get_str("ws_unix_socket_only")
+        {
+            if ws_unix_socket_only == "1" {
+                false
+            } else {
+                panic!(
+                    "Got wrong config value. service_config: ws_unix_socket_only={}",
+                    ws_unix_socket_only
+                );
+            }
+        } else {
+            default.ws_tcp
+        };
         let ws_answer_timeout_ms = service_config
             .get_str("ws_answer_timeout_ms")
             .map(|v| v.parse().unwrap())
@@ -69,6 +170,430 @@ impl ServiceConfig {
             );
         }
 
+        let ws_outbound_queue_size = service_config
+            .get_str("ws_outbound_queue_size")
+            .map(|v| v.parse().unwrap())
+            .unwrap_or(default.ws_outbound_queue_size);
+        if ws_outbound_queue_size < 1 {
+            panic!(
+                "Got wrong config value. Value is less than allowed min. service_config: ws_outbound_queue_size={}",
+                ws_outbound_queue_size
+            );
+        }
+        let ws_outbound_queue_policy = service_config
+            .get_str("ws_outbound_queue_policy")
+            .map(|v| {
+                OutboundQueuePolicy::from_str(&v).unwrap_or_else(|_| {
+                    panic!(
+                        "Got wrong config value. service_config: ws_outbound_queue_policy={}",
+                        v
+                    )
+                })
+            })
+            .unwrap_or(default.ws_outbound_queue_policy);
+
+        let ws_compression = if let Ok(ws_compression) = service_config.get_str("ws_compression") {
+            if ws_compression == "1" {
+                true
+            } else {
+                panic!(
+                    "Got wrong config value. service_config: ws_compression={}",
+                    ws_compression
+                );
+            }
+        } else {
+            false
+        };
+        if !ws_compression
+            && (service_config.get_str("ws_compression_level").is_ok()
+                || service_config.get_str("ws_compression_min_size").is_ok())
+        {
+            panic!(
+                "Got unexpected config. service_config: ws_compression_*. These configs are allowed only if ws_compression=1"
+            );
+        }
+        let ws_compression = if ws_compression {
+            let level = service_config
+                .get_str("ws_compression_level")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(6);
+            if level > 9 {
+                panic!(
+                    "Got wrong config value. Value is greater than allowed max. service_config: ws_compression_level={}",
+                    level
+                );
+            }
+            let min_size = service_config
+                .get_str("ws_compression_min_size")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(1024);
+
+            Some(PermessageDeflateConfig { level, min_size })
+        } else {
+            default.ws_compression
+        };
+
+        let ws_api_keys = service_config
+            .get_str("ws_auth_keys_file")
+            .map(|v| Some(Arc::new(ApiKeys::load(&v))))
+            .unwrap_or(default.ws_api_keys);
+
+        let ws_tls = match (
+            service_config.get_str("ws_tls_cert"),
+            service_config.get_str("ws_tls_key"),
+        ) {
+            (Ok(cert_path), Ok(key_path)) => Some(WsTlsConfig {
+                cert_path,
+                key_path,
+            }),
+            (Err(_), Err(_)) => default.ws_tls,
+            _ => panic!(
+                "Got unexpected config. service_config: ws_tls_*. ws_tls_cert and ws_tls_key must be set together"
+            ),
+        };
+
+        let ws_limits = WsLimitsConfig {
+            max_connections: Self::get_value_with_min(
+                &service_config,
+                "ws_max_connections",
+                default.ws_limits.max_connections,
+                1,
+            ),
+            max_connections_per_ip: Self::get_value_with_min(
+                &service_config,
+                "ws_max_connections_per_ip",
+                default.ws_limits.max_connections_per_ip,
+                1,
+            ),
+            max_subscriptions: Self::get_value_with_min(
+                &service_config,
+                "ws_max_subscriptions",
+                default.ws_limits.max_subscriptions,
+                1,
+            ),
+            requests_per_sec: Self::get_value_with_min(
+                &service_config,
+                "ws_requests_per_sec",
+                default.ws_limits.requests_per_sec,
+                1,
+            ),
+            requests_per_sec_per_ip: Self::get_value_with_min(
+                &service_config,
+                "ws_requests_per_sec_per_ip",
+                default.ws_limits.requests_per_sec_per_ip,
+                1,
+            ),
+        };
+        let ws_workers =
+            Self::get_value_with_min(&service_config, "ws_workers", default.ws_workers, 1);
+        let ws_workers_queue_size = Self::get_value_with_min(
+            &service_config,
+            "ws_workers_queue_size",
+            default.ws_workers_queue_size,
+            1,
+        );
+
+        let ws_legacy_responses =
+            if let Ok(ws_legacy_responses) = service_config.get_str("ws_legacy_responses") {
+                if ws_legacy_responses == "1" {
+                    true
+                } else if ws_legacy_responses == "0" {
+                    false
+                } else {
+                    panic!(
+                        "Got wrong config value. service_config: ws_legacy_responses={}",
+                        ws_legacy_responses
+                    );
+                }
+            } else {
+                default.ws_legacy_responses
+            };
+
+        let http = if let Ok(http) = service_config.get_str("http") {
+            if http == "1" {
+                true
+            } else {
+                panic!("Got wrong config value. service_config: http={}", http);
+            }
+        } else {
+            default.http
+        };
+        if !http
+            && (service_config.get_str("http_host").is_ok()
+                || service_config.get_str("http_port").is_ok()
+                || service_config.get_str("metrics").is_ok()
+                || service_config.get_str("ready_min_exchanges").is_ok())
+        {
+            panic!(
+                "Got unexpected config. service_config: http_*, metrics, ready_min_exchanges. These configs are allowed only if http=1"
+            );
+        }
+
+        let http_host = service_config
+            .get_str("http_host")
+            .unwrap_or(get_default_host());
+        let http_port = service_config
+            .get_str("http_port")
+            .unwrap_or(get_default_http_port());
+        let http_addr = http_host + ":" + &http_port;
+        let metrics = if let Ok(metrics) = service_config.get_str("metrics") {
+            if metrics == "1" {
+                true
+            } else {
+                panic!(
+                    "Got wrong config value. service_config: metrics={}",
+                    metrics
+                );
+            }
+        } else {
+            default.metrics
+        };
+        let ready_min_exchanges = service_config
+            .get_str("ready_min_exchanges")
+            .map(|v| v.parse().unwrap())
+            .unwrap_or(default.ready_min_exchanges);
+
+        let grpc = if let Ok(grpc) = service_config.get_str("grpc") {
+            if grpc == "1" {
+                true
+            } else {
+                panic!("Got wrong config value. service_config: grpc={}", grpc);
+            }
+        } else {
+            default.grpc
+        };
+        if !grpc
+            && (service_config.get_str("grpc_host").is_ok()
+                || service_config.get_str("grpc_port").is_ok())
+        {
+            panic!(
+                "Got unexpected config. service_config: grpc_*. These configs are allowed only if grpc=1"
+            );
+        }
+
+        let grpc_host = service_config
+            .get_str("grpc_host")
+            .unwrap_or(get_default_host());
+        let grpc_port = service_config
+            .get_str("grpc_port")
+            .unwrap_or(get_default_grpc_port());
+        let grpc_addr = grpc_host + ":" + &grpc_port;
+
+        let output_sinks: Vec<OutputSinkConfig> = [
+            (OutputSinkKind::Nats, "nats_addr", "nats_subject"),
+            (OutputSinkKind::Mqtt, "mqtt_addr", "mqtt_topic"),
+            (OutputSinkKind::RedisStream, "redis_addr", "redis_stream"),
+        ]
+        .into_iter()
+        .filter_map(|(kind, addr_key, topic_key)| {
+            let topic_template = service_config.get_str(topic_key).ok();
+
+            match service_config.get_str(addr_key) {
+                Ok(addr) => Some(OutputSinkConfig {
+                    kind,
+                    addr,
+                    topic_template: topic_template
+                        .unwrap_or_else(|| kind.get_default_topic_template().to_string()),
+                }),
+                Err(_) if topic_template.is_some() => panic!(
+                    "Got unexpected config. service_config: {}. That config is allowed only if {} is set",
+                    topic_key, addr_key
+                ),
+                Err(_) => None,
+            }
+        })
+        .collect();
+
+        let kafka_brokers = service_config.get_str("kafka_brokers").ok();
+        if kafka_brokers.is_none()
+            && (service_config.get_str("kafka_topic").is_ok()
+                || service_config.get_str("kafka_message_timeout_ms").is_ok()
+                || service_config.get_str("kafka_buffer_path").is_ok()
+                || service_config.get_str("kafka_buffer_max_messages").is_ok())
+        {
+            panic!(
+                "Got unexpected config. service_config: kafka_*. These configs are allowed only if kafka_brokers is set"
+            );
+        }
+        let kafka_sink = kafka_brokers.map(|brokers| {
+            let default = KafkaSinkConfig::new(brokers);
+
+            KafkaSinkConfig {
+                topic_template: service_config
+                    .get_str("kafka_topic")
+                    .unwrap_or(default.topic_template),
+                message_timeout_ms: Self::get_value_with_min(
+                    &service_config,
+                    "kafka_message_timeout_ms",
+                    default.message_timeout_ms,
+                    100,
+                ),
+                buffer_path: service_config
+                    .get_str("kafka_buffer_path")
+                    .unwrap_or(default.buffer_path),
+                buffer_max_messages: Self::get_value_with_min(
+                    &service_config,
+                    "kafka_buffer_max_messages",
+                    default.buffer_max_messages,
+                    1,
+                ),
+                brokers: default.brokers,
+            }
+        });
+
+        let file_sink_path = service_config.get_str("file_sink_path").ok();
+        if file_sink_path.is_none()
+            && (service_config.get_str("file_sink_format").is_ok()
+                || service_config.get_str("file_sink_rotate_size_mb").is_ok()
+                || service_config
+                    .get_str("file_sink_rotate_interval_sec")
+                    .is_ok()
+                || service_config.get_str("file_sink_gzip").is_ok())
+        {
+            panic!(
+                "Got unexpected config. service_config: file_sink_*. These configs are allowed only if file_sink_path is set"
+            );
+        }
+        let file_sink = file_sink_path.map(|path| {
+            let default = FileSinkConfig::new(path);
+
+            let format = service_config
+                .get_str("file_sink_format")
+                .map(|v| {
+                    FileSinkFormat::from_str(&v).unwrap_or_else(|_| {
+                        panic!(
+                            "Got wrong config value. service_config: file_sink_format={}",
+                            v
+                        )
+                    })
+                })
+                .unwrap_or(default.format);
+            let rotate_size_mb = Self::get_value_with_min(
+                &service_config,
+                "file_sink_rotate_size_mb",
+                default.rotate_size_bytes / 1024 / 1024,
+                1,
+            );
+            let rotate_interval_sec = service_config
+                .get_str("file_sink_rotate_interval_sec")
+                .ok()
+                .map(|_| {
+                    Self::get_value_with_min(&service_config, "file_sink_rotate_interval_sec", 0, 1)
+                });
+            let gzip = if let Ok(gzip) = service_config.get_str("file_sink_gzip") {
+                if gzip == "1" {
+                    true
+                } else {
+                    panic!(
+                        "Got wrong config value. service_config: file_sink_gzip={}",
+                        gzip
+                    );
+                }
+            } else {
+                default.gzip
+            };
+
+            FileSinkConfig {
+                path: default.path,
+                format,
+                rotate_size_bytes: rotate_size_mb * 1024 * 1024,
+                rotate_interval_sec,
+                gzip,
+            }
+        });
+
+        let alerts = if let Ok(alerts) = service_config.get_str("alerts") {
+            if alerts == "1" {
+                true
+            } else {
+                panic!("Got wrong config value. service_config: alerts={}", alerts);
+            }
+        } else {
+            false
+        };
+        let alerts_webhook_url = service_config.get_str("alerts_webhook_url").ok();
+        if !alerts
+            && (service_config.get_str("alerts_file").is_ok()
+                || service_config.get_str("alerts_max").is_ok()
+                || alerts_webhook_url.is_some())
+        {
+            panic!(
+                "Got unexpected config. service_config: alerts_*. These configs are allowed only if alerts=1"
+            );
+        }
+        if alerts_webhook_url.is_none()
+            && (service_config.get_str("alerts_webhook_secret").is_ok()
+                || service_config.get_str("alerts_webhook_retries").is_ok()
+                || service_config.get_str("alerts_webhook_timeout_ms").is_ok())
+        {
+            panic!(
+                "Got unexpected config. service_config: alerts_webhook_*. These configs are allowed only if alerts_webhook_url is set"
+            );
+        }
+        let alerts = alerts.then(|| {
+            let webhook = alerts_webhook_url.map(|url| {
+                let default = WebhookConfig::new(url);
+
+                WebhookConfig {
+                    secret: service_config.get_str("alerts_webhook_secret").ok(),
+                    retries: Self::get_value_with_min(
+                        &service_config,
+                        "alerts_webhook_retries",
+                        default.retries,
+                        0,
+                    ),
+                    timeout_ms: Self::get_value_with_min(
+                        &service_config,
+                        "alerts_webhook_timeout_ms",
+                        default.timeout_ms,
+                        100,
+                    ),
+                    url: default.url,
+                }
+            });
+
+            let default = AlertsConfig::new(
+                service_config
+                    .get_str("alerts_file")
+                    .map(|path| AlertRule::load(&path))
+                    .unwrap_or_default(),
+            );
+
+            AlertsConfig {
+                webhook,
+                max_alerts: Self::get_value_with_min(
+                    &service_config,
+                    "alerts_max",
+                    default.max_alerts,
+                    1,
+                ),
+                max_alerts_per_owner: Self::get_value_with_min(
+                    &service_config,
+                    "alerts_max_per_owner",
+                    default.max_alerts_per_owner,
+                    1,
+                ),
+                ..default
+            }
+        });
+
+        if output_sinks.is_empty()
+            && kafka_sink.is_none()
+            && file_sink.is_none()
+            && alerts.is_none()
+            && service_config.get_str("output_sink_queue_size").is_ok()
+        {
+            panic!(
+                "Got unexpected config. service_config: output_sink_queue_size. That config is allowed only if any of nats_addr, mqtt_addr, redis_addr, kafka_brokers, file_sink_path is set or alerts=1"
+            );
+        }
+        let output_sink_queue_size = Self::get_value_with_min(
+            &service_config,
+            "output_sink_queue_size",
+            default.output_sink_queue_size,
+            1,
+        );
+
         let historical = if let Ok(historical) = service_config.get_str("historical") {
             if historical == "1" {
                 true
@@ -95,7 +620,7 @@ impl ServiceConfig {
             Some(
                 service_config
                     .get_str("storage")
-                    .map(|v| Storage::from_str(&v))
+                    .map(|v| Storage::from_name(&v))
                     .unwrap_or_default(),
             )
         } else {
@@ -114,12 +639,58 @@ impl ServiceConfig {
         Self {
             rest_timeout_sec,
             ws,
+            ws_tcp,
             ws_addr,
+            ws_unix_socket,
             ws_answer_timeout_ms,
+            ws_outbound_queue_size,
+            ws_outbound_queue_policy,
+            ws_compression,
+            ws_api_keys,
+            ws_tls,
+            ws_limits,
+            ws_workers,
+            ws_workers_queue_size,
+            ws_legacy_responses,
+            http,
+            http_addr,
+            metrics,
+            ready_min_exchanges,
+            grpc,
+            grpc_addr,
+            output_sinks,
+            output_sink_queue_size,
+            kafka_sink,
+            file_sink,
+            alerts,
             storage,
             historical_storage_frequency_ms,
         }
     }
+
+    fn get_value_with_min<T: FromStr + PartialOrd + Display>(
+        service_config: &config::Config,
+        key: &str,
+        default: T,
+        min: T,
+    ) -> T {
+        let value = service_config
+            .get_str(key)
+            .map(|v| {
+                v.parse().unwrap_or_else(|_| {
+                    panic!("Got wrong config value. service_config: {}={}", key, v)
+                })
+            })
+            .unwrap_or(default);
+        if value < min {
+            panic!(
+                "Got wrong config value. Value is less than allowed min. service_config: {}={}",
+                key, value
+            );
+        }
+
+        value
+    }
 }
 
 impl Default for ServiceConfig {
@@ -127,8 +698,36 @@ impl Default for ServiceConfig {
         Self {
             rest_timeout_sec: 1,
             ws: false,
+            ws_tcp: true,
             ws_addr: get_default_host() + ":" + &get_default_port(),
+            ws_unix_socket: None,
             ws_answer_timeout_ms: 100,
+            ws_outbound_queue_size: 1000,
+            ws_outbound_queue_policy: OutboundQueuePolicy::DropOldest,
+            ws_compression: None,
+            ws_api_keys: None,
+            ws_tls: None,
+            ws_limits: WsLimitsConfig {
+                max_connections: 10000,
+                max_connections_per_ip: 100,
+                max_subscriptions: 100,
+                requests_per_sec: 20,
+                requests_per_sec_per_ip: 100,
+            },
+            ws_workers: 8,
+            ws_workers_queue_size: 1000,
+            ws_legacy_responses: false,
+            http: false,
+            http_addr: get_default_host() + ":" + &get_default_http_port(),
+            metrics: false,
+            ready_min_exchanges: 1,
+            grpc: false,
+            grpc_addr: get_default_host() + ":" + &get_default_grpc_port(),
+            output_sinks: Vec::new(),
+            output_sink_queue_size: 1000,
+            kafka_sink: None,
+            file_sink: None,
+            alerts: None,
             storage: get_default_storage(get_default_historical()),
             historical_storage_frequency_ms: 20,
         }
@@ -1,5 +1,6 @@
 use std::sync::{Arc, Mutex};
 
+#[derive(Clone)]
 pub enum Storage {
     Sled(Arc<Mutex<vsdbsled::Db>>),
 }
@@ -11,7 +12,17 @@ impl Storage {
         Self::Sled(tree)
     }
 
-    pub fn from_str(name: &str) -> Self {
+    /// Checks whether storage can be read
+    pub fn is_open(&self) -> bool {
+        match self {
+            Self::Sled(tree) => tree
+                .lock()
+                .map(|tree| tree.get("keys").is_ok())
+                .unwrap_or(false),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Self {
         match name {
             "sled" => Self::make_sled(),
             other_storage => panic!("Got wrong storage name: {}", other_storage),
@@ -98,6 +98,19 @@ fn fill_storage(
     info!("Fill historical data for {} end.", coin);
 }
 
+/// Requests health endpoint of a running daemon. Returns process exit code.
+pub fn healthcheck(url: &str) -> i32 {
+    let response = Client::new()
+        .get(url)
+        .timeout(time::Duration::from_secs(5))
+        .send();
+
+    match response {
+        Ok(response) if response.status().is_success() => 0,
+        _ => 1,
+    }
+}
+
 pub fn fill_historical_data(config: &ConfigScheme) {
     info!("Fill historical data begin.");
 
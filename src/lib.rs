@@ -0,0 +1,14 @@
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+
+pub mod client;
+pub mod config_scheme;
+pub mod graceful_shutdown;
+pub mod helper_functions;
+pub mod metrics;
+pub mod repository;
+#[cfg(test)]
+mod test;
+pub mod worker;
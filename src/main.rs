@@ -1,25 +1,26 @@
-use crate::config_scheme::config_scheme::ConfigScheme;
-use crate::graceful_shutdown::start_graceful_shutdown_listener;
-use crate::helper_functions::fill_historical_data;
-use crate::worker::worker::Worker;
+use index_daemon::config_scheme::config_scheme::ConfigScheme;
+use index_daemon::graceful_shutdown::start_graceful_shutdown_listener;
+use index_daemon::helper_functions::{fill_historical_data, healthcheck};
+use index_daemon::worker::network_helpers::ws_server::openrpc::make_openrpc_document;
+use index_daemon::worker::worker::Worker;
+use std::process;
 use std::sync::mpsc;
 
-#[macro_use]
-extern crate log;
-#[macro_use]
-extern crate serde_derive;
-
-mod config_scheme;
-mod graceful_shutdown;
-mod helper_functions;
-mod repository;
-#[cfg(test)]
-mod test;
-mod worker;
-
 fn main() {
+    let matches = ConfigScheme::make_matches();
+    if let Some(url) = matches.value_of("healthcheck") {
+        process::exit(healthcheck(url));
+    }
+    if matches.is_present("openrpc") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&make_openrpc_document()).unwrap()
+        );
+        return;
+    }
+
     let graceful_shutdown = start_graceful_shutdown_listener();
-    let config = ConfigScheme::new();
+    let config = ConfigScheme::new(matches);
 
     fill_historical_data(&config);
 
@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub static METRICS: Metrics = Metrics::new();
+
+pub const WS_CLIENT_MESSAGES_RECEIVED: &str = "index_daemon_ws_client_messages_received_total";
+pub const WS_CLIENT_PARSE_ERRORS: &str = "index_daemon_ws_client_parse_errors_total";
+pub const WS_CLIENT_RECONNECTS: &str = "index_daemon_ws_client_reconnects_total";
+pub const INDEX_UPDATES: &str = "index_daemon_index_updates_total";
+pub const REJECTED_OUTLIER_TICKS: &str = "index_daemon_rejected_outlier_ticks_total";
+pub const STORAGE_WRITE_DURATION: &str = "index_daemon_storage_write_duration_seconds";
+pub const WS_SERVER_CONNECTIONS: &str = "index_daemon_ws_server_connections";
+pub const WS_SERVER_SUBSCRIPTIONS: &str = "index_daemon_ws_server_subscriptions";
+pub const WS_SERVER_MESSAGES_SENT: &str = "index_daemon_ws_server_messages_sent_total";
+pub const WS_SERVER_MESSAGES_DROPPED: &str = "index_daemon_ws_server_messages_dropped_total";
+pub const WS_SERVER_OUTBOUND_QUEUE_DROPPED: &str =
+    "index_daemon_ws_server_outbound_queue_dropped_total";
+pub const WS_SERVER_SLOW_CONSUMER_DISCONNECTS: &str =
+    "index_daemon_ws_server_slow_consumer_disconnects_total";
+pub const WS_SERVER_REJECTED: &str = "index_daemon_ws_server_rejected_total";
+pub const OUTPUT_SINK_MESSAGES_PUBLISHED: &str =
+    "index_daemon_output_sink_messages_published_total";
+pub const OUTPUT_SINK_MESSAGES_DROPPED: &str = "index_daemon_output_sink_messages_dropped_total";
+pub const OUTPUT_SINK_BUFFERED_MESSAGES: &str = "index_daemon_output_sink_buffered_messages";
+pub const ALERTS_TRIGGERED: &str = "index_daemon_alerts_triggered_total";
+pub const ALERT_WEBHOOK_REQUESTS: &str = "index_daemon_alert_webhook_requests_total";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MetricKind {
+    Counter,
+    Gauge,
+    Summary,
+}
+
+impl MetricKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Counter => "counter",
+            Self::Gauge => "gauge",
+            Self::Summary => "summary",
+        }
+    }
+}
+
+const DESCRIPTIONS: [(&str, MetricKind, &str); 18] = [
+    (
+        WS_CLIENT_MESSAGES_RECEIVED,
+        MetricKind::Counter,
+        "Messages received from exchanges.",
+    ),
+    (
+        WS_CLIENT_PARSE_ERRORS,
+        MetricKind::Counter,
+        "Messages received from exchanges which are not valid JSON.",
+    ),
+    (
+        WS_CLIENT_RECONNECTS,
+        MetricKind::Counter,
+        "Reconnects to exchange websockets.",
+    ),
+    (
+        INDEX_UPDATES,
+        MetricKind::Counter,
+        "Updates of coin average price.",
+    ),
+    (
+        REJECTED_OUTLIER_TICKS,
+        MetricKind::Counter,
+        "Trade prices rejected because they are too far from the previous price.",
+    ),
+    (
+        STORAGE_WRITE_DURATION,
+        MetricKind::Summary,
+        "Time spent writing values into the storage.",
+    ),
+    (
+        WS_SERVER_CONNECTIONS,
+        MetricKind::Gauge,
+        "Open websocket server connections.",
+    ),
+    (
+        WS_SERVER_SUBSCRIPTIONS,
+        MetricKind::Gauge,
+        "Active websocket server subscriptions.",
+    ),
+    (
+        WS_SERVER_MESSAGES_SENT,
+        MetricKind::Counter,
+        "Channel messages sent by the websocket server.",
+    ),
+    (
+        WS_SERVER_MESSAGES_DROPPED,
+        MetricKind::Counter,
+        "Channel messages not sent by the websocket server.",
+    ),
+    (
+        WS_SERVER_OUTBOUND_QUEUE_DROPPED,
+        MetricKind::Counter,
+        "Messages dropped (or replaced) because connection's outbound queue was full.",
+    ),
+    (
+        WS_SERVER_SLOW_CONSUMER_DISCONNECTS,
+        MetricKind::Counter,
+        "Connections closed because their outbound queue was full.",
+    ),
+    (
+        WS_SERVER_REJECTED,
+        MetricKind::Counter,
+        "Connections and requests rejected because of websocket server limits.",
+    ),
+    (
+        OUTPUT_SINK_MESSAGES_PUBLISHED,
+        MetricKind::Counter,
+        "Events published to output sinks.",
+    ),
+    (
+        OUTPUT_SINK_MESSAGES_DROPPED,
+        MetricKind::Counter,
+        "Events not published to output sinks.",
+    ),
+    (
+        OUTPUT_SINK_BUFFERED_MESSAGES,
+        MetricKind::Gauge,
+        "Events kept in disk buffers of output sinks until the broker is available.",
+    ),
+    (ALERTS_TRIGGERED, MetricKind::Counter, "Triggered alerts."),
+    (
+        ALERT_WEBHOOK_REQUESTS,
+        MetricKind::Counter,
+        "Triggered alerts posted (or not) to the webhook.",
+    ),
+];
+
+type Labels = Vec<(&'static str, String)>;
+type MetricValues<T> = Mutex<BTreeMap<(&'static str, Labels), T>>;
+
+/// Process-wide registry of counters, gauges and summaries.
+/// Rendered in the Prometheus text exposition format.
+pub struct Metrics {
+    values: MetricValues<f64>,
+    summaries: MetricValues<(f64, u64)>,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            values: Mutex::new(BTreeMap::new()),
+            summaries: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn make_labels(labels: &[(&'static str, &str)]) -> Labels {
+        labels.iter().map(|(k, v)| (*k, v.to_string())).collect()
+    }
+
+    pub fn inc(&self, name: &'static str, labels: &[(&'static str, &str)]) {
+        self.add(name, labels, 1.0);
+    }
+
+    pub fn dec(&self, name: &'static str, labels: &[(&'static str, &str)]) {
+        self.add(name, labels, -1.0);
+    }
+
+    pub fn add(&self, name: &'static str, labels: &[(&'static str, &str)], value: f64) {
+        *self
+            .values
+            .lock()
+            .unwrap()
+            .entry((name, Self::make_labels(labels)))
+            .or_insert(0.0) += value;
+    }
+
+    /// Adds an observation to a summary (i.e. increments both `_sum` and `_count`)
+    pub fn observe(&self, name: &'static str, labels: &[(&'static str, &str)], value: Duration) {
+        let mut summaries = self.summaries.lock().unwrap();
+        let (sum, count) = summaries
+            .entry((name, Self::make_labels(labels)))
+            .or_insert((0.0, 0));
+
+        *sum += value.as_secs_f64();
+        *count += 1;
+    }
+
+    fn render_labels(labels: &[(&'static str, String)]) -> String {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(k, v)| {
+                let v = v
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n");
+
+                format!("{}=\"{}\"", k, v)
+            })
+            .collect();
+
+        if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", labels.join(","))
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let values = self.values.lock().unwrap();
+        let summaries = self.summaries.lock().unwrap();
+        let mut res = String::new();
+
+        for (name, kind, help) in DESCRIPTIONS {
+            let _ = writeln!(res, "# HELP {} {}", name, help);
+            let _ = writeln!(res, "# TYPE {} {}", name, kind.as_str());
+
+            if kind == MetricKind::Summary {
+                for ((_, labels), (sum, count)) in summaries.iter().filter(|(k, _)| k.0 == name) {
+                    let labels = Self::render_labels(labels);
+
+                    let _ = writeln!(res, "{}_sum{} {}", name, labels, sum);
+                    let _ = writeln!(res, "{}_count{} {}", name, labels, count);
+                }
+            } else {
+                for ((_, labels), value) in values.iter().filter(|(k, _)| k.0 == name) {
+                    let _ = writeln!(res, "{}{} {}", name, Self::render_labels(labels), value);
+                }
+            }
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::metrics::metrics::{
+        Metrics, INDEX_UPDATES, STORAGE_WRITE_DURATION, WS_SERVER_CONNECTIONS,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::new();
+
+        metrics.inc(INDEX_UPDATES, &[("coin", "BTC")]);
+        metrics.inc(INDEX_UPDATES, &[("coin", "BTC")]);
+        metrics.inc(INDEX_UPDATES, &[("coin", "ETH")]);
+        metrics.inc(WS_SERVER_CONNECTIONS, &[]);
+        metrics.inc(WS_SERVER_CONNECTIONS, &[]);
+        metrics.dec(WS_SERVER_CONNECTIONS, &[]);
+        metrics.observe(STORAGE_WRITE_DURATION, &[], Duration::from_millis(500));
+        metrics.observe(STORAGE_WRITE_DURATION, &[], Duration::from_millis(250));
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("# TYPE index_daemon_index_updates_total counter\n"));
+        assert!(rendered.contains("index_daemon_index_updates_total{coin=\"BTC\"} 2\n"));
+        assert!(rendered.contains("index_daemon_index_updates_total{coin=\"ETH\"} 1\n"));
+        assert!(rendered.contains("index_daemon_ws_server_connections 1\n"));
+        assert!(rendered.contains("index_daemon_storage_write_duration_seconds_sum 0.75\n"));
+        assert!(rendered.contains("index_daemon_storage_write_duration_seconds_count 2\n"));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::metrics::metrics::{METRICS, STORAGE_WRITE_DURATION};
 use crate::repository::hepler_functions::get_all_keys_sled;
 use crate::repository::repository::Repository;
 use crate::worker::helper_functions::date_time_from_timestamp_sec;
@@ -5,6 +6,7 @@ use chrono::{DateTime, Utc, MIN_DATETIME};
 use std::collections::{HashMap, HashSet};
 use std::str;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 #[derive(Clone)]
 pub struct F64ByTimestampSled {
@@ -134,6 +136,7 @@ impl Repository<DateTime<Utc>, f64> for F64ByTimestampSled {
         if (primary - self.last_insert_timestamp).num_milliseconds() as u64 > self.frequency_ms {
             // Enough time passed
             self.last_insert_timestamp = primary;
+            let start = Instant::now();
 
             let key = self.stringify_primary(primary);
 
@@ -148,6 +151,8 @@ impl Repository<DateTime<Utc>, f64> for F64ByTimestampSled {
                 .map_err(|e| e.to_string());
             let _ = self.repository.lock().unwrap().flush();
 
+            METRICS.observe(STORAGE_WRITE_DURATION, &[], start.elapsed());
+
             Some(res)
         } else {
             // Too early
@@ -0,0 +1,138 @@
+use crate::config_scheme::market_config::MarketConfig;
+use crate::config_scheme::service_config::ServiceConfig;
+use crate::worker::market_helpers::market_value::MarketValue;
+use crate::worker::market_helpers::stored_and_ws_transmissible_f64::StoredAndWsTransmissibleF64;
+use crate::worker::network_helpers::http_server::http_server::HttpServer;
+use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use crate::worker::network_helpers::ws_server::ws_channels_holder::WsChannelsHolder;
+use crate::worker::network_helpers::ws_server::ws_limits::WsLimits;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time;
+
+/// Reads the next event. Returns its type and data.
+fn read_event(reader: &mut BufReader<TcpStream>) -> (String, serde_json::Value) {
+    let mut event = String::new();
+    let mut data = String::new();
+
+    for line in reader.lines() {
+        let line = line.unwrap();
+
+        if let Some(value) = line.strip_prefix("event: ") {
+            event = value.to_string();
+        } else if let Some(value) = line.strip_prefix("data: ") {
+            data = value.to_string();
+        } else if line.is_empty() && !data.is_empty() {
+            break;
+        }
+    }
+
+    (event, serde_json::from_str(&data).unwrap())
+}
+
+#[test]
+fn test_event_stream() {
+    let http_addr = "127.0.0.1:8103";
+    let config = ServiceConfig::default();
+    let graceful_shutdown = Arc::new(Mutex::new(false));
+
+    let pair = ("BTC".to_string(), "USD".to_string());
+    let ws_channels = WsChannelsHolder::make_hashmap(&MarketConfig::default());
+    let holder_key = (
+        "worker".to_string(),
+        MarketValue::PairAveragePrice,
+        pair.clone(),
+    );
+    let ws_channels_btc = Arc::clone(&ws_channels[&holder_key]);
+    let pair_average_price = Arc::new(Mutex::new(StoredAndWsTransmissibleF64::new(
+        None,
+        vec![WsChannelName::CoinAveragePrice],
+        None,
+        pair.clone(),
+        Arc::clone(&ws_channels[&holder_key]),
+        Vec::new(),
+    )));
+    pair_average_price.lock().unwrap().set_new_value(100.0);
+    let ws_channels_holder = WsChannelsHolder::new(
+        ws_channels,
+        HashMap::new(),
+        HashMap::from([(pair, Arc::clone(&pair_average_price))]),
+    );
+
+    let http_server = HttpServer {
+        http_addr: http_addr.to_string(),
+        metrics: false,
+        ws: true,
+        ws_listener_bound: Arc::new(Mutex::new(true)),
+        storage: None,
+        markets: HashMap::new(),
+        exchange_pairs: Vec::new(),
+        ready_min_exchanges: 0,
+        pair_average_price: HashMap::new(),
+        pair_average_price_repositories: None,
+        ws_channels_holder,
+        ws_api_keys: None,
+        ws_limits: WsLimits::new(config.ws_limits),
+        ws_answer_timeout_ms: config.ws_answer_timeout_ms,
+        ws_outbound_queue_size: config.ws_outbound_queue_size,
+        ws_outbound_queue_policy: config.ws_outbound_queue_policy,
+        graceful_shutdown: Arc::clone(&graceful_shutdown),
+    };
+    let _ = thread::spawn(move || http_server.start());
+
+    let mut stream = loop {
+        match TcpStream::connect(http_addr) {
+            Ok(stream) => break stream,
+            Err(_) => thread::sleep(time::Duration::from_millis(10)),
+        }
+    };
+    stream
+        .set_read_timeout(Some(time::Duration::from_secs(5)))
+        .unwrap();
+    stream
+        .write_all(b"GET /v1/stream?method=coin_average_price&coins=BTC HTTP/1.1\r\n\r\n")
+        .unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line).unwrap();
+    assert_eq!(status_line, "HTTP/1.1 200 OK\r\n");
+
+    // Snapshot is sent right after subscription
+    let (event, data) = read_event(&mut reader);
+    assert_eq!(event, "coin_average_price");
+    assert_eq!(data["method"], "coin_average_price");
+    assert_eq!(data["params"]["snapshot"], true);
+    assert_eq!(data["params"]["coin"], "BTC");
+    assert_eq!(data["params"]["value"], 100.0);
+
+    // New values are sent the same way as to websocket subscribers
+    pair_average_price.lock().unwrap().set_new_value(200.0);
+    let (_, data) = read_event(&mut reader);
+    assert!(data["params"]["snapshot"].is_null());
+    assert_eq!(data["params"]["value"], 200.0);
+
+    // Subscription is removed after the client is disconnected
+    drop(reader);
+    stream.shutdown(std::net::Shutdown::Both).unwrap();
+    let mut subscriptions = 1;
+    for _ in 0..100 {
+        thread::sleep(time::Duration::from_millis(50));
+        pair_average_price.lock().unwrap().set_new_value(300.0);
+
+        subscriptions = ws_channels_btc
+            .lock()
+            .unwrap()
+            .get_channels_by_method(WsChannelName::CoinAveragePrice)
+            .len();
+        if subscriptions == 0 {
+            break;
+        }
+    }
+    assert_eq!(subscriptions, 0);
+
+    *graceful_shutdown.lock().unwrap() = true;
+}
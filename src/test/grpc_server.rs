@@ -0,0 +1,170 @@
+use crate::config_scheme::market_config::MarketConfig;
+use crate::config_scheme::service_config::ServiceConfig;
+use crate::worker::market_helpers::market_value::MarketValue;
+use crate::worker::market_helpers::stored_and_ws_transmissible_f64::StoredAndWsTransmissibleF64;
+use crate::worker::network_helpers::grpc_server::grpc_server::GrpcServer;
+use crate::worker::network_helpers::grpc_server::proto::index_daemon_client::IndexDaemonClient;
+use crate::worker::network_helpers::grpc_server::proto::{
+    CoinRequest, HistoricalRequest, SubscribeRequest,
+};
+use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use crate::worker::network_helpers::ws_server::ws_channels_holder::WsChannelsHolder;
+use crate::worker::network_helpers::ws_server::ws_limits::WsLimits;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time;
+use tokio_stream::StreamExt;
+use tonic::transport::Channel;
+use tonic::Code;
+
+async fn connect(grpc_addr: &str) -> IndexDaemonClient<Channel> {
+    loop {
+        match IndexDaemonClient::connect(format!("http://{}", grpc_addr)).await {
+            Ok(client) => return client,
+            Err(_) => tokio::time::sleep(time::Duration::from_millis(10)).await,
+        }
+    }
+}
+
+#[test]
+fn test_grpc_server() {
+    let grpc_addr = "127.0.0.1:8104";
+    let mut config = ServiceConfig::default();
+    // Stream is counted as a websocket connection
+    config.ws_limits.max_connections_per_ip = 1;
+    let graceful_shutdown = Arc::new(Mutex::new(false));
+
+    let pair = ("BTC".to_string(), "USD".to_string());
+    let ws_channels = WsChannelsHolder::make_hashmap(&MarketConfig::default());
+    let holder_key = (
+        "worker".to_string(),
+        MarketValue::PairAveragePrice,
+        pair.clone(),
+    );
+    let pair_average_price = Arc::new(Mutex::new(StoredAndWsTransmissibleF64::new(
+        None,
+        vec![WsChannelName::CoinAveragePrice],
+        None,
+        pair.clone(),
+        Arc::clone(&ws_channels[&holder_key]),
+        Vec::new(),
+    )));
+    pair_average_price.lock().unwrap().set_new_value(100.0);
+    let pair_average_price_hashmap = HashMap::from([(pair, Arc::clone(&pair_average_price))]);
+    let ws_channels_holder = WsChannelsHolder::new(
+        ws_channels,
+        HashMap::new(),
+        pair_average_price_hashmap.clone(),
+    );
+
+    let grpc_server = GrpcServer {
+        grpc_addr: grpc_addr.to_string(),
+        ws: false,
+        markets: HashMap::new(),
+        pair_average_price: pair_average_price_hashmap,
+        pair_average_price_repositories: Mutex::new(None),
+        ws_channels_holder,
+        ws_api_keys: None,
+        ws_limits: WsLimits::new(config.ws_limits),
+        ws_answer_timeout_ms: config.ws_answer_timeout_ms,
+        ws_outbound_queue_size: config.ws_outbound_queue_size,
+        ws_outbound_queue_policy: config.ws_outbound_queue_policy,
+        graceful_shutdown: Arc::clone(&graceful_shutdown),
+    };
+    let _ = thread::spawn(move || grpc_server.start());
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let mut client = connect(grpc_addr).await;
+
+        let value = client
+            .get_coin_average_price(CoinRequest {
+                coin: "BTC".to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(value.coin, "BTC");
+        assert_eq!(value.value, 100.0);
+
+        let status = client
+            .get_coin_average_price(CoinRequest {
+                coin: "ABC".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(status.message(), "Coin ABC not supported.");
+
+        // Requests are validated the same way as websocket requests
+
+        let status = client
+            .get_coin_average_price_historical(HistoricalRequest {
+                coin: "BTC".to_string(),
+                interval: 0,
+                from: 0,
+                to: None,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let status = client
+            .subscribe_coin_average_price(SubscribeRequest {
+                coins: vec!["ABC".to_string()],
+                frequency_ms: None,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let mut stream = client
+            .subscribe_coin_average_price(SubscribeRequest {
+                coins: vec!["BTC".to_string()],
+                frequency_ms: None,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+
+        // Snapshot is sent right after subscription
+        let value = stream.next().await.unwrap().unwrap();
+        assert_eq!(value.coin, "BTC");
+        assert_eq!(value.value, 100.0);
+        assert!(value.snapshot);
+
+        // New values are sent the same way as to websocket subscribers
+        pair_average_price.lock().unwrap().set_new_value(200.0);
+        let value = stream.next().await.unwrap().unwrap();
+        assert_eq!(value.value, 200.0);
+        assert!(!value.snapshot);
+
+        let subscribe_request = SubscribeRequest {
+            coins: vec!["BTC".to_string()],
+            frequency_ms: None,
+        };
+        let status = client
+            .subscribe_coin_average_price(subscribe_request.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+
+        // Connection is released after the stream is dropped
+        drop(stream);
+        let deadline = time::Instant::now() + time::Duration::from_secs(5);
+        while client
+            .subscribe_coin_average_price(subscribe_request.clone())
+            .await
+            .is_err()
+        {
+            assert!(time::Instant::now() < deadline);
+            tokio::time::sleep(time::Duration::from_millis(10)).await;
+        }
+    });
+
+    *graceful_shutdown.lock().unwrap() = true;
+}
@@ -1 +1,5 @@
+pub mod event_stream;
+pub mod grpc_server;
+pub mod output_sink;
+pub mod ws_client;
 pub mod ws_server;
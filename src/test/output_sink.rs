@@ -0,0 +1,259 @@
+use crate::config_scheme::config_scheme::ConfigScheme;
+use crate::config_scheme::helper_functions::make_exchange_pairs;
+use crate::config_scheme::repositories_prepared::RepositoriesPrepared;
+use crate::worker::helper_functions::get_pair_ref;
+use crate::worker::market_helpers::market::market_factory;
+use crate::worker::market_helpers::market_spine::MarketSpine;
+use crate::worker::market_helpers::stored_and_ws_transmissible_f64::StoredAndWsTransmissibleF64;
+use crate::worker::network_helpers::output_sink::file_sink::{FileSinkConfig, FileSinkFormat};
+use crate::worker::network_helpers::output_sink::output_sink::{
+    OutputSinkConfig, OutputSinkKind, OutputSinks,
+};
+use crate::worker::network_helpers::ws_server::ws_channels::WsChannels;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Published topic and payload (fields of Redis stream entry are joined by spaces)
+type Published = (String, String);
+
+fn accept(listener: &TcpListener) -> TcpStream {
+    let (stream, _) = listener.accept().unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    stream
+}
+
+fn read_line(reader: &mut BufReader<TcpStream>) -> String {
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+
+    line.trim_end().to_string()
+}
+
+/// NATS server stand-in: answers the handshake and receives one message
+fn start_nats(addr: &str, tx: Sender<Published>) {
+    let listener = TcpListener::bind(addr).unwrap();
+
+    thread::spawn(move || {
+        let mut stream = accept(&listener);
+        stream.write_all(b"INFO {}\r\n").unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        assert!(read_line(&mut reader).starts_with("CONNECT {"));
+        assert_eq!(read_line(&mut reader), "PING");
+        stream.write_all(b"PONG\r\n").unwrap();
+
+        let command = read_line(&mut reader);
+        let command: Vec<&str> = command.split(' ').collect();
+        assert_eq!(command[0], "PUB");
+        let mut payload = vec![0; command[2].parse::<usize>().unwrap() + 2];
+        reader.read_exact(&mut payload).unwrap();
+
+        let payload = String::from_utf8(payload).unwrap();
+        tx.send((command[1].to_string(), payload.trim_end().to_string()))
+            .unwrap();
+    });
+}
+
+/// MQTT broker stand-in: answers the handshake and receives one message
+fn start_mqtt(addr: &str, tx: Sender<Published>) {
+    let listener = TcpListener::bind(addr).unwrap();
+
+    thread::spawn(move || {
+        let mut stream = accept(&listener);
+
+        // Packets are short, so remaining length is one byte
+        let mut header = [0; 2];
+        stream.read_exact(&mut header).unwrap();
+        assert_eq!(header[0], 0x10);
+        let mut connect = vec![0; header[1] as usize];
+        stream.read_exact(&mut connect).unwrap();
+        assert_eq!(&connect[..6], b"\x00\x04MQTT");
+        stream.write_all(&[0x20, 0x02, 0x00, 0x00]).unwrap();
+
+        stream.read_exact(&mut header).unwrap();
+        assert_eq!(header[0], 0x30);
+        let mut publish = vec![0; header[1] as usize];
+        stream.read_exact(&mut publish).unwrap();
+
+        let topic_len = u16::from_be_bytes([publish[0], publish[1]]) as usize;
+        let topic = String::from_utf8(publish[2..2 + topic_len].to_vec()).unwrap();
+        let payload = String::from_utf8(publish[2 + topic_len..].to_vec()).unwrap();
+        tx.send((topic, payload)).unwrap();
+    });
+}
+
+/// Reads RESP array of bulk strings
+fn read_redis_command(reader: &mut BufReader<TcpStream>) -> Vec<String> {
+    let len: usize = read_line(reader)[1..].parse().unwrap();
+
+    (0..len)
+        .map(|_| {
+            read_line(reader);
+            read_line(reader)
+        })
+        .collect()
+}
+
+/// Redis stand-in: answers `PING` and receives one `XADD`
+fn start_redis(addr: &str, tx: Sender<Published>) {
+    let listener = TcpListener::bind(addr).unwrap();
+
+    thread::spawn(move || {
+        let mut stream = accept(&listener);
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        assert_eq!(read_redis_command(&mut reader), vec!["PING"]);
+        stream.write_all(b"+PONG\r\n").unwrap();
+
+        let command = read_redis_command(&mut reader);
+        stream.write_all(b"$15\r\n1526919030474-0\r\n").unwrap();
+
+        assert_eq!(command[0], "XADD");
+        assert_eq!(command[2..6], ["MAXLEN", "~", "100000", "*"]);
+        tx.send((command[1].clone(), command[6..].join(" ")))
+            .unwrap();
+    });
+}
+
+#[test]
+fn test_output_sinks() {
+    let (tx, rx) = mpsc::channel();
+    start_nats("127.0.0.1:8105", tx.clone());
+    start_mqtt("127.0.0.1:8106", tx.clone());
+    start_redis("127.0.0.1:8107", tx);
+
+    let output_sinks: OutputSinks = [
+        (OutputSinkKind::Nats, "127.0.0.1:8105"),
+        (OutputSinkKind::Mqtt, "127.0.0.1:8106"),
+        (OutputSinkKind::RedisStream, "127.0.0.1:8107"),
+    ]
+    .into_iter()
+    .map(|(kind, addr)| {
+        OutputSinkConfig {
+            kind,
+            addr: addr.to_string(),
+            topic_template: kind.get_default_topic_template().to_string(),
+        }
+        .start(10)
+    })
+    .collect();
+
+    let mut pair_average_price = StoredAndWsTransmissibleF64::new(
+        None,
+        Vec::new(),
+        None,
+        ("BTC".to_string(), "USD".to_string()),
+        Arc::new(Mutex::new(WsChannels::new())),
+        output_sinks,
+    );
+    pair_average_price.set_new_value(100.0);
+    let timestamp = pair_average_price.get_timestamp().timestamp();
+
+    let mut published: Vec<Published> = (0..3)
+        .map(|_| rx.recv_timeout(Duration::from_secs(5)).unwrap())
+        .collect();
+    published.sort();
+
+    let json = format!(
+        r#"{{"coin":"BTC","quote":"USD","value":100.0,"timestamp":{}}}"#,
+        timestamp
+    );
+    assert_eq!(
+        published,
+        vec![
+            (
+                "index.BTC.USD".to_string(),
+                format!("coin BTC quote USD value 100 timestamp {}", timestamp)
+            ),
+            ("index.BTC.USD".to_string(), json.clone()),
+            ("index/BTC/USD".to_string(), json),
+        ]
+    );
+}
+
+#[test]
+fn test_file_sink() {
+    let path = std::env::temp_dir().join("index_daemon_test_file_sink_market");
+    let _ = fs::remove_dir_all(&path);
+
+    let mut config = ConfigScheme::default();
+    config.market.markets = vec!["binance".to_string()];
+    config.market.exchange_pairs = make_exchange_pairs(vec!["BTC".to_string()], None);
+    config.service.file_sink = Some(FileSinkConfig {
+        format: FileSinkFormat::Csv,
+        ..FileSinkConfig::new(path.to_str().unwrap().to_string())
+    });
+
+    let RepositoriesPrepared {
+        pair_average_price_repository: _,
+        market_repositories: _,
+        ws_channels_holder,
+        pair_average_price,
+        output_sinks,
+        alerts: _,
+    } = RepositoriesPrepared::make(&config);
+
+    let (tx, rx) = mpsc::channel();
+    let spine = MarketSpine::new(
+        pair_average_price,
+        tx,
+        1,
+        "binance".to_string(),
+        config.market.channels,
+        Arc::new(Mutex::new(false)),
+        output_sinks,
+    );
+    let market = market_factory(
+        spine,
+        config.market.exchange_pairs,
+        None,
+        &ws_channels_holder,
+    );
+
+    {
+        let mut market = market.lock().unwrap();
+        let pair = market.make_pair(get_pair_ref(&("BTC".to_string(), "USD".to_string())));
+
+        market.parse_ticker_json_inner(pair.clone(), 1000.0);
+        market.parse_last_trade_json_inner(pair.clone(), 0.5, 100.0);
+        market.parse_depth_json_inner(pair, vec![(101.0, 2.0)], vec![(99.0, 1.0)]);
+    }
+    // Average price is recalculated in a separate thread
+    rx.recv().unwrap().join().unwrap();
+    // Events are flushed to the file once per second
+    thread::sleep(Duration::from_millis(1500));
+
+    let file = fs::read_to_string(path.join("events.csv")).unwrap();
+    let mut lines: Vec<String> = file
+        .lines()
+        .skip(1)
+        .map(|line| {
+            // Timestamp is skipped
+            line.split_once(',').unwrap().1.to_string()
+        })
+        .collect();
+    lines.sort();
+
+    assert!(file.starts_with("timestamp,event,"));
+    assert_eq!(
+        lines,
+        vec![
+            "book,binance,BTC,USD,,,2,99",
+            "coin_average_price,,BTC,USD,100,,,",
+            "coin_exchange_price,binance,BTC,USD,100,,,",
+            "coin_exchange_volume,binance,BTC,USD,,1000,,",
+            "ticker,binance,BTC,USD,,1000,,",
+            "trade,binance,BTC,USD,100,0.5,,",
+        ]
+    );
+
+    let _ = fs::remove_dir_all(path);
+}
@@ -0,0 +1,179 @@
+use crate::client::ws_client::WsClient;
+use crate::client::ws_client_error::WsClientError;
+use crate::config_scheme::market_config::MarketConfig;
+use crate::config_scheme::service_config::ServiceConfig;
+use crate::worker::market_helpers::market_value::MarketValue;
+use crate::worker::market_helpers::stored_and_ws_transmissible_f64::StoredAndWsTransmissibleF64;
+use crate::worker::network_helpers::ws_server::channels::worker_channels::WorkerChannels;
+use crate::worker::network_helpers::ws_server::channels::ws_channel_subscription_request::WsChannelSubscriptionRequest;
+use crate::worker::network_helpers::ws_server::interval::Interval;
+use crate::worker::network_helpers::ws_server::requests::ws_method_request::WsMethodRequest;
+use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+use crate::worker::network_helpers::ws_server::ws_channels_holder::WsChannelsHolder;
+use crate::worker::network_helpers::ws_server::ws_limits::WsLimits;
+use crate::worker::network_helpers::ws_server::ws_server::WsServer;
+use async_std::{future, task};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::io;
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time;
+
+/// Starts websocket server. Average price of BTC is set, so subscription gets a snapshot.
+fn start_ws_server(ws_addr: &str) -> Arc<Mutex<bool>> {
+    let config = ServiceConfig::default();
+    let ws_listener_bound = Arc::new(Mutex::new(false));
+    let graceful_shutdown = Arc::new(Mutex::new(false));
+
+    let pair = ("BTC".to_string(), "USD".to_string());
+    let ws_channels = WsChannelsHolder::make_hashmap(&MarketConfig::default());
+    let holder_key = (
+        "worker".to_string(),
+        MarketValue::PairAveragePrice,
+        pair.clone(),
+    );
+    let mut pair_average_price = StoredAndWsTransmissibleF64::new(
+        None,
+        vec![WsChannelName::CoinAveragePrice],
+        None,
+        pair.clone(),
+        Arc::clone(&ws_channels[&holder_key]),
+        Vec::new(),
+    );
+    pair_average_price.set_new_value(100.0);
+
+    let ws_server = WsServer {
+        ws_channels_holder: WsChannelsHolder::new(
+            ws_channels,
+            HashMap::new(),
+            HashMap::from([(pair, Arc::new(Mutex::new(pair_average_price)))]),
+        ),
+        ws_tcp: true,
+        ws_addr: ws_addr.to_string(),
+        ws_unix_socket: None,
+        ws_answer_timeout_ms: config.ws_answer_timeout_ms,
+        ws_outbound_queue_size: config.ws_outbound_queue_size,
+        ws_outbound_queue_policy: config.ws_outbound_queue_policy,
+        ws_compression: config.ws_compression,
+        ws_api_keys: config.ws_api_keys,
+        ws_tls: config.ws_tls,
+        ws_limits: WsLimits::new(config.ws_limits),
+        ws_workers: config.ws_workers,
+        ws_workers_queue_size: config.ws_workers_queue_size,
+        ws_legacy_responses: false,
+        pair_average_price_repositories: None,
+        alerts: None,
+        ws_listener_bound: Arc::clone(&ws_listener_bound),
+        graceful_shutdown: Arc::clone(&graceful_shutdown),
+    };
+    let _ = thread::spawn(move || ws_server.start());
+
+    while !*ws_listener_bound.lock().unwrap() {
+        thread::sleep(time::Duration::from_millis(10));
+    }
+
+    graceful_shutdown
+}
+
+/// Forwards TCP connections from `addr` to `target`. Returns sockets of the current connections (to break them).
+fn start_proxy(addr: &str, target: &str) -> Arc<Mutex<Vec<TcpStream>>> {
+    let listener = TcpListener::bind(addr).unwrap();
+    let target = target.to_string();
+    let connections = Arc::new(Mutex::new(Vec::new()));
+
+    let connections_2 = Arc::clone(&connections);
+    let _ = thread::spawn(move || {
+        for client in listener.incoming().flatten() {
+            let server = TcpStream::connect(&target).unwrap();
+            connections_2
+                .lock()
+                .unwrap()
+                .push(client.try_clone().unwrap());
+
+            let (mut client_read, mut client_write) = (client.try_clone().unwrap(), client);
+            let (mut server_read, mut server_write) = (server.try_clone().unwrap(), server);
+            let _ = thread::spawn(move || io::copy(&mut client_read, &mut server_write));
+            let _ = thread::spawn(move || io::copy(&mut server_read, &mut client_write));
+        }
+    });
+
+    connections
+}
+
+#[test]
+fn test_ws_client() {
+    let ws_addr = "127.0.0.1:8101";
+    let proxy_addr = "127.0.0.1:8102";
+    let graceful_shutdown = start_ws_server(ws_addr);
+    let connections = start_proxy(proxy_addr, ws_addr);
+    let timeout = time::Duration::from_secs(5);
+
+    task::block_on(async {
+        let client = WsClient::connect(&format!("ws://{}", proxy_addr))
+            .await
+            .unwrap();
+
+        let request =
+            WsChannelSubscriptionRequest::WorkerChannels(WorkerChannels::CoinAveragePrice {
+                id: None,
+                coins: vec!["BTC".to_string()],
+                frequency_ms: None,
+            });
+        let mut subscription = client.subscribe(request).await.unwrap();
+        let notification = future::timeout(timeout, subscription.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(notification.snapshot);
+        assert!(matches!(
+            notification.payload,
+            WsChannelResponsePayload::CoinAveragePrice { coin, value, .. } if coin == "BTC" && value == 100.0
+        ));
+
+        let request =
+            WsChannelSubscriptionRequest::WorkerChannels(WorkerChannels::CoinAveragePrice {
+                id: None,
+                coins: vec!["WRONG".to_string()],
+                frequency_ms: Some(100),
+            });
+        assert!(matches!(
+            client.subscribe(request).await,
+            Err(WsClientError::Rpc { code: -32602, .. })
+        ));
+
+        let request = WsMethodRequest::CoinAveragePriceHistorical {
+            id: None,
+            coin: "BTC".to_string(),
+            interval: Interval::Day,
+            from: 1643835600,
+            to: None,
+        };
+        assert!(matches!(
+            client.request(request).await,
+            Err(WsClientError::Rpc { code: -32603, .. })
+        ));
+
+        let response = client
+            .call(WsChannelName::ListCoins, serde_json::Value::Null)
+            .await;
+        assert!(matches!(
+            response,
+            Ok(WsChannelResponsePayload::Coins { coins }) if coins.contains(&"BTC".to_string())
+        ));
+
+        // Subscription is restored after reconnect (and gets a snapshot again)
+        for connection in connections.lock().unwrap().drain(..) {
+            let _ = connection.shutdown(Shutdown::Both);
+        }
+        let notification = future::timeout(timeout, subscription.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(notification.snapshot);
+    });
+
+    *graceful_shutdown.lock().unwrap() = true;
+}
@@ -1,13 +1,36 @@
 mod ws_client_for_testing;
 
 use crate::config_scheme::config_scheme::ConfigScheme;
+use crate::config_scheme::market_config::MarketConfig;
+use crate::config_scheme::service_config::ServiceConfig;
 use crate::test::ws_server::ws_client_for_testing::WsClientForTesting;
 use crate::worker::market_helpers::market_channels::MarketChannels;
+use crate::worker::market_helpers::market_value::MarketValue;
+use crate::worker::network_helpers::alerts::alert_rule::{AlertCondition, AlertRule};
+use crate::worker::network_helpers::alerts::alert_sink::AlertsConfig;
+use crate::worker::network_helpers::output_sink::output_sink::{
+    ExchangeUpdate, IndexUpdate, OutputEvent, OutputSink,
+};
+use crate::worker::network_helpers::ws_server::outbound_queue::OutboundQueuePolicy;
+use crate::worker::network_helpers::ws_server::unix_socket::{
+    UnixSocketConfig, UnixSocketProtocol,
+};
 use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+use crate::worker::network_helpers::ws_server::ws_channels_holder::WsChannelsHolder;
+use crate::worker::network_helpers::ws_server::ws_limits::WsLimits;
+use crate::worker::network_helpers::ws_server::ws_server::WsServer;
 use crate::worker::worker::Worker;
+use async_std::{future, task};
+use async_tungstenite::async_std::connect_async;
+use async_tungstenite::tungstenite::protocol::Message;
+use chrono::{Duration, TimeZone, Utc};
+use futures::{SinkExt, StreamExt};
 use serde_json::json;
 use serial_test::serial;
 use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
@@ -129,33 +152,43 @@ fn check_incoming_messages(
         }
     }
 
+    // Key - `subscription_id`, value - request id
+    let mut subscription_ids = HashMap::new();
+
     let start = Instant::now();
     while !expected_new.is_empty() {
         if let Ok(incoming_msg) = incoming_msg_rx.try_recv() {
             let incoming_msg: serde_json::Value = serde_json::from_str(&incoming_msg).unwrap();
 
-            let sub_id = incoming_msg.get("id").unwrap().as_str().unwrap();
             let jsonrpc = incoming_msg.get("jsonrpc").unwrap().as_str().unwrap();
             assert_eq!(jsonrpc, "2.0");
 
-            let result = incoming_msg.get("result").unwrap().as_object().unwrap();
-            if let Some(message) = result.get("message") {
-                // SuccSub message (`result` has `message` field)
+            if let Some(result) = incoming_msg.get("result") {
+                // Successful subscription response
 
-                let message = message.as_str().unwrap();
+                let sub_id = incoming_msg.get("id").unwrap().as_str().unwrap();
+                let message = result.get("message").unwrap().as_str().unwrap();
                 assert_eq!(message, "Successfully subscribed.");
+
+                let subscription_id = result.get("subscription_id").unwrap().as_str().unwrap();
+                subscription_ids.insert(subscription_id.to_string(), sub_id.to_string());
             } else {
-                // Message with payload (no `message` field in `result`)
+                // Notification with payload
 
-                let coin = result.get("coin").unwrap().as_str().unwrap().to_string();
-                let exchange = result
+                let params = incoming_msg.get("params").unwrap().as_object().unwrap();
+                let subscription_id = params.get("subscription_id").unwrap().as_str().unwrap();
+                let sub_id = subscription_ids.get(subscription_id).unwrap();
+
+                let coin = params.get("coin").unwrap().as_str().unwrap().to_string();
+                let exchange = params
                     .get("exchange")
                     .map(|v| v.as_str().unwrap().to_string());
-                let _value = result.get("value").unwrap().as_f64().unwrap();
-                let timestamp = result.get("timestamp").unwrap().as_i64().unwrap();
+                let _value = params.get("value").unwrap().as_f64().unwrap();
+                let timestamp = params.get("timestamp").unwrap().as_i64().unwrap();
                 // 1640984400 = 2022-01-01 00:00:00
                 assert!(timestamp > 1640984400);
                 let method = *methods.get(sub_id).unwrap();
+                assert_eq!(incoming_msg["method"], json!(method));
 
                 expected_new.remove(&(sub_id.to_string(), method, coin, exchange));
             }
@@ -207,3 +240,525 @@ fn test_ws_channels_response() {
     ws_connect_and_subscribe(ws_addr, requests, incoming_msg_tx);
     check_incoming_messages(incoming_msg_rx, expected);
 }
+
+/// Websocket server, listening only on Unix domain socket. Server is stopped on drop.
+struct UnixSocketServer {
+    path: String,
+    graceful_shutdown: Arc<Mutex<bool>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for UnixSocketServer {
+    fn drop(&mut self) {
+        *self.graceful_shutdown.lock().unwrap() = true;
+
+        // Listener checks graceful shutdown after a connection is accepted
+        let _ = UnixStream::connect(&self.path);
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap();
+        }
+    }
+}
+
+/// Starts websocket server, listening only on Unix domain socket (newline-delimited JSON).
+/// Returns client's stream, the server and alert sink (if alerts are turned on).
+fn start_unix_socket_server(
+    name: &str,
+    ws_legacy_responses: bool,
+    alerts: Option<AlertsConfig>,
+) -> (UnixStream, UnixSocketServer, Option<Box<dyn OutputSink>>) {
+    let path = std::env::temp_dir().join(format!("index_daemon_test_{}.sock", name));
+    let path = path.to_str().unwrap().to_string();
+    let config = ServiceConfig::default();
+    let ws_listener_bound = Arc::new(Mutex::new(false));
+    let graceful_shutdown = Arc::new(Mutex::new(false));
+    let ws_channels = WsChannelsHolder::make_hashmap(&MarketConfig::default());
+    let (alerts, alert_sink) = match alerts {
+        Some(alerts) => {
+            let (alerts, alert_sink) = alerts.start(config.output_sink_queue_size, &ws_channels);
+            (Some(alerts), Some(alert_sink))
+        }
+        None => (None, None),
+    };
+
+    let ws_server = WsServer {
+        ws_channels_holder: WsChannelsHolder::new(ws_channels, HashMap::new(), HashMap::new()),
+        ws_tcp: false,
+        ws_addr: config.ws_addr,
+        ws_unix_socket: Some(UnixSocketConfig {
+            path: path.clone(),
+            protocol: UnixSocketProtocol::Ndjson,
+        }),
+        ws_answer_timeout_ms: config.ws_answer_timeout_ms,
+        ws_outbound_queue_size: config.ws_outbound_queue_size,
+        ws_outbound_queue_policy: config.ws_outbound_queue_policy,
+        ws_compression: config.ws_compression,
+        ws_api_keys: config.ws_api_keys,
+        ws_tls: config.ws_tls,
+        ws_limits: WsLimits::new(config.ws_limits),
+        ws_workers: config.ws_workers,
+        ws_workers_queue_size: config.ws_workers_queue_size,
+        ws_legacy_responses,
+        pair_average_price_repositories: None,
+        alerts,
+        ws_listener_bound: Arc::clone(&ws_listener_bound),
+        graceful_shutdown: Arc::clone(&graceful_shutdown),
+    };
+    let thread = thread::spawn(move || ws_server.start());
+
+    while !*ws_listener_bound.lock().unwrap() {
+        thread::sleep(time::Duration::from_millis(10));
+    }
+
+    (
+        UnixStream::connect(&path).unwrap(),
+        UnixSocketServer {
+            path,
+            graceful_shutdown,
+            thread: Some(thread),
+        },
+        alert_sink,
+    )
+}
+
+/// Sends request line and returns response line
+fn ndjson_request(
+    stream: &mut UnixStream,
+    lines: &mut std::io::Lines<BufReader<UnixStream>>,
+    request: &str,
+) -> serde_json::Value {
+    writeln!(stream, "{}", request).unwrap();
+
+    serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap()
+}
+
+#[test]
+fn test_unix_socket_ndjson() {
+    let (mut stream, server, _) = start_unix_socket_server("ndjson", false, None);
+    let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+    let mut request = |request: serde_json::Value| -> serde_json::Value {
+        ndjson_request(&mut stream, &mut lines, &request.to_string())
+    };
+
+    // Subscription is added to the same channels as of websocket connections
+    let response = request(json!({
+        "id": 1,
+        "jsonrpc": "2.0",
+        "method": "coin_average_price",
+        "params": {"coins": ["BTC"]}
+    }));
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["result"]["message"], "Successfully subscribed.");
+    assert!(response["result"]["subscription_id"].is_string());
+
+    let response = request(json!({
+        "id": 2,
+        "jsonrpc": "2.0",
+        "method": "coin_average_price_historical",
+        "params": {"coin": "BTC", "interval": "day", "from": 1643835600}
+    }));
+    assert_eq!(response["id"], 2);
+    assert_eq!(response["error"]["code"], -32603);
+    assert_eq!(
+        response["error"]["message"],
+        "Historical data storage is turned off."
+    );
+
+    // Binary encodings are not supported by newline-delimited JSON
+    let response = request(json!({
+        "id": 3,
+        "jsonrpc": "2.0",
+        "method": "configure",
+        "params": {"encoding": "msgpack"}
+    }));
+    assert_eq!(response["id"], 3);
+    assert_eq!(response["error"]["code"], -32602);
+
+    // Batch: notification (request without `id`) gets no response
+    let response = request(json!([
+        {"id": 4, "jsonrpc": "2.0", "method": "unsubscribe", "params": {"method": "coin_average_price"}},
+        {"jsonrpc": "2.0", "method": "configure", "params": {"encoding": "json"}},
+        {"id": 5, "jsonrpc": "2.0", "method": "not_existing_method", "params": {}},
+        {"id": 6, "jsonrpc": "2.0", "method": "coin_average_price", "params": {"coins": ["WRONG"]}},
+        1,
+    ]));
+    let mut response = response.as_array().unwrap().clone();
+    response.sort_by_key(|v| v["id"].as_i64());
+    assert_eq!(
+        response,
+        vec![
+            json!({"jsonrpc": "2.0", "id": null, "error": {"code": -32600, "message": "Invalid request. Request must be an object."}}),
+            json!({"jsonrpc": "2.0", "id": 4, "result": {"method": "unsubscribe", "message": "Successfully unsubscribed."}}),
+            json!({"jsonrpc": "2.0", "id": 5, "error": {"code": -32601, "message": "Method not found: not_existing_method."}}),
+            json!({"jsonrpc": "2.0", "id": 6, "error": {"code": -32602, "message": "Parameter value is wrong: coin."}}),
+        ]
+    );
+
+    // Discovery methods take no params
+    let response = request(json!({"id": 7, "jsonrpc": "2.0", "method": "list_coins"}));
+    assert_eq!(response["id"], 7);
+    assert!(response["result"]["coins"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("BTC")));
+
+    let response = request(json!({"id": 8, "jsonrpc": "2.0", "method": "list_methods"}));
+    assert!(response["result"]["methods"]
+        .as_array()
+        .unwrap()
+        .contains(&json!({"method": "coin_average_price", "kind": "channel"})));
+
+    let response = request(json!({"id": 9, "jsonrpc": "2.0", "method": "rpc.discover"}));
+    assert_eq!(response["result"]["openrpc"], "1.2.6");
+
+    let response = request(json!([]));
+    assert_eq!(response["error"]["code"], -32600);
+
+    let response = ndjson_request(&mut stream, &mut lines, "{");
+    assert_eq!(response["id"], serde_json::Value::Null);
+    assert_eq!(response["error"]["code"], -32700);
+
+    // Alerts are turned off
+    let response = ndjson_request(
+        &mut stream,
+        &mut lines,
+        &json!({"id": 10, "jsonrpc": "2.0", "method": "list_alerts"}).to_string(),
+    );
+    assert_eq!(response["error"]["code"], -32601);
+
+    drop(server);
+}
+
+#[test]
+fn test_unix_socket_ndjson_legacy() {
+    let (mut stream, server, _) = start_unix_socket_server("ndjson_legacy", true, None);
+    let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+
+    let response = ndjson_request(
+        &mut stream,
+        &mut lines,
+        &json!({
+            "id": 1,
+            "jsonrpc": "2.0",
+            "method": "coin_average_price_historical",
+            "params": {"coin": "BTC", "interval": "day", "from": 1643835600}
+        })
+        .to_string(),
+    );
+    assert_eq!(
+        response,
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {
+                "method": "coin_average_price_historical",
+                "code": -32603,
+                "message": "Historical data storage is turned off."
+            }
+        })
+    );
+
+    let response = ndjson_request(&mut stream, &mut lines, "[]");
+    assert_eq!(response["result"]["code"], -32600);
+
+    drop(server);
+}
+
+#[test]
+fn test_alerts() {
+    let alerts = AlertsConfig::new(vec![AlertRule {
+        id: "btc_50k".to_string(),
+        coin: "BTC".to_string(),
+        condition: AlertCondition::Cross { price: 50000.0 },
+    }]);
+    let (mut stream, server, alert_sink) = start_unix_socket_server("alerts", false, Some(alerts));
+    let alert_sink = alert_sink.unwrap();
+    let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+    let mut request = |request: serde_json::Value| -> serde_json::Value {
+        ndjson_request(&mut stream, &mut lines, &request.to_string())
+    };
+
+    let response = request(json!({
+        "id": 1,
+        "jsonrpc": "2.0",
+        "method": "add_alert",
+        "params": {
+            "id": "eth_divergence",
+            "coin": "ETH",
+            "condition": {"kind": "divergence", "percent": 1, "exchange": "binance"}
+        }
+    }));
+    assert_eq!(
+        response["result"]["alert"],
+        json!({
+            "id": "eth_divergence",
+            "coin": "ETH",
+            "condition": {"kind": "divergence", "percent": 1.0, "exchange": "binance"}
+        })
+    );
+
+    let response = request(json!({
+        "id": 2,
+        "jsonrpc": "2.0",
+        "method": "add_alert",
+        "params": {"coin": "WRONG", "condition": {"kind": "cross", "price": 1}}
+    }));
+    assert_eq!(
+        response["error"],
+        json!({"code": -32602, "message": "Coin WRONG not supported."})
+    );
+
+    let response = request(json!({
+        "id": 3,
+        "jsonrpc": "2.0",
+        "method": "add_alert",
+        "params": {"coin": "BTC", "condition": {"kind": "change", "percent": 5, "window_sec": 0}}
+    }));
+    assert_eq!(response["error"]["code"], -32602);
+
+    let response = request(json!({"id": 4, "jsonrpc": "2.0", "method": "list_alerts"}));
+    let ids: Vec<&serde_json::Value> = response["result"]["alerts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| &v["id"])
+        .collect();
+    assert_eq!(ids, vec!["btc_50k", "eth_divergence"]);
+
+    let response = request(json!({
+        "id": 5,
+        "jsonrpc": "2.0",
+        "method": "remove_alert",
+        "params": {"id": "eth_divergence"}
+    }));
+    assert_eq!(response["result"]["message"], "Successfully removed.");
+
+    let response = request(json!({
+        "id": 6,
+        "jsonrpc": "2.0",
+        "method": "remove_alert",
+        "params": {"id": "eth_divergence"}
+    }));
+    assert_eq!(
+        response["error"],
+        json!({"code": -32602, "message": "Alert eth_divergence not found."})
+    );
+
+    // Alerts, loaded from config, are read-only
+    let response = request(json!({
+        "id": 7,
+        "jsonrpc": "2.0",
+        "method": "remove_alert",
+        "params": {"id": "btc_50k"}
+    }));
+    assert_eq!(response["error"]["code"], -32003);
+
+    let response = request(json!({
+        "id": 8,
+        "jsonrpc": "2.0",
+        "method": "alerts",
+        "params": {"coins": ["BTC"]}
+    }));
+    assert_eq!(response["result"]["message"], "Successfully subscribed.");
+    let subscription_id = response["result"]["subscription_id"].clone();
+
+    // Exchange prices don't trigger `cross` alerts
+    let timestamp = Utc.timestamp_opt(1700000000, 0).unwrap();
+    for value in [49000.0, 51000.0] {
+        alert_sink.publish(&OutputEvent::CoinExchangePrice(ExchangeUpdate {
+            exchange: "binance".to_string(),
+            coin: "BTC".to_string(),
+            quote: "USD".to_string(),
+            value,
+            timestamp,
+        }));
+        alert_sink.publish(&OutputEvent::CoinAveragePrice(IndexUpdate {
+            coin: "BTC".to_string(),
+            quote: "USD".to_string(),
+            value,
+            timestamp,
+        }));
+    }
+
+    let notification: serde_json::Value =
+        serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+    assert_eq!(
+        notification,
+        json!({
+            "jsonrpc": "2.0",
+            "method": "alerts",
+            "params": {
+                "subscription_id": subscription_id,
+                "alert_id": "btc_50k",
+                "coin": "BTC",
+                "condition": {"kind": "cross", "price": 50000.0},
+                "exchange": null,
+                "price": 51000.0,
+                "reference_price": 50000.0,
+                "timestamp": 1700000000
+            }
+        })
+    );
+
+    let response = ndjson_request(
+        &mut stream,
+        &mut lines,
+        &json!({
+            "id": 9,
+            "jsonrpc": "2.0",
+            "method": "add_alert",
+            "params": {"id": "btc_cross", "coin": "BTC", "condition": {"kind": "cross", "price": 1}}
+        })
+        .to_string(),
+    );
+    assert_eq!(response["result"]["alert"]["id"], "btc_cross");
+
+    // Alerts of not authenticated client are removed after it's disconnected
+    drop(lines);
+    drop(stream);
+    let mut stream = UnixStream::connect(&server.path).unwrap();
+    let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+    let deadline = time::Instant::now() + time::Duration::from_secs(5);
+    loop {
+        let response = ndjson_request(
+            &mut stream,
+            &mut lines,
+            &json!({"id": 1, "jsonrpc": "2.0", "method": "list_alerts"}).to_string(),
+        );
+        if response["result"]["alerts"].as_array().unwrap().len() == 1 {
+            break;
+        }
+
+        assert!(time::Instant::now() < deadline);
+        thread::sleep(time::Duration::from_millis(10));
+    }
+
+    drop(server);
+}
+
+#[test]
+fn test_slow_consumer() {
+    let ws_addr = "127.0.0.1:8108";
+    let config = ServiceConfig::default();
+    let ws_listener_bound = Arc::new(Mutex::new(false));
+    let graceful_shutdown = Arc::new(Mutex::new(false));
+
+    let ws_channels = WsChannelsHolder::make_hashmap(&MarketConfig::default());
+    let btc_channels = Arc::clone(
+        &ws_channels[&(
+            "worker".to_string(),
+            MarketValue::PairAveragePrice,
+            ("BTC".to_string(), "USD".to_string()),
+        )],
+    );
+
+    let ws_server = WsServer {
+        ws_channels_holder: WsChannelsHolder::new(ws_channels, HashMap::new(), HashMap::new()),
+        ws_tcp: true,
+        ws_addr: ws_addr.to_string(),
+        ws_unix_socket: None,
+        ws_answer_timeout_ms: config.ws_answer_timeout_ms,
+        ws_outbound_queue_size: 10,
+        ws_outbound_queue_policy: OutboundQueuePolicy::DropOldest,
+        ws_compression: config.ws_compression,
+        ws_api_keys: config.ws_api_keys,
+        ws_tls: config.ws_tls,
+        ws_limits: WsLimits::new(config.ws_limits),
+        ws_workers: config.ws_workers,
+        ws_workers_queue_size: config.ws_workers_queue_size,
+        ws_legacy_responses: false,
+        pair_average_price_repositories: None,
+        alerts: None,
+        ws_listener_bound: Arc::clone(&ws_listener_bound),
+        graceful_shutdown: Arc::clone(&graceful_shutdown),
+    };
+    let _ = thread::spawn(move || ws_server.start());
+    while !*ws_listener_bound.lock().unwrap() {
+        thread::sleep(time::Duration::from_millis(10));
+    }
+
+    // Every update is far enough in time from the previous one to be sent right away
+    let start = Utc::now();
+    let send_updates = |values: std::ops::Range<u32>| {
+        for i in values {
+            btc_channels
+                .lock()
+                .unwrap()
+                .send_general(WsChannelResponsePayload::CoinAveragePrice {
+                    coin: "BTC".to_string(),
+                    value: i as f64,
+                    timestamp: start + Duration::seconds(i as i64 + 1),
+                });
+        }
+    };
+    let list_coins = json!({"id": "list_coins", "jsonrpc": "2.0", "method": "list_coins"});
+    let timeout = time::Duration::from_secs(10);
+    let updates_count = 100000;
+
+    task::block_on(async {
+        let (mut slow_client, _) = connect_async(format!("ws://{}", ws_addr)).await.unwrap();
+        let request = json!({
+            "id": "sub",
+            "jsonrpc": "2.0",
+            "method": "coin_average_price",
+            "params": {"coins": ["BTC"]}
+        });
+        slow_client
+            .send(Message::text(request.to_string()))
+            .await
+            .unwrap();
+        let response = future::timeout(timeout, slow_client.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert!(response.to_string().contains("Successfully subscribed."));
+
+        // The client stops reading, while updates and the response to its request are queued
+        send_updates(0..updates_count);
+        slow_client
+            .send(Message::text(list_coins.to_string()))
+            .await
+            .unwrap();
+        thread::sleep(time::Duration::from_millis(500));
+        send_updates(updates_count..2 * updates_count);
+
+        // Server serves other clients meanwhile
+        let (mut client, _) = connect_async(format!("ws://{}", ws_addr)).await.unwrap();
+        client
+            .send(Message::text(list_coins.to_string()))
+            .await
+            .unwrap();
+        let response = future::timeout(timeout, client.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert!(response.to_string().contains("\"coins\""));
+
+        // Old updates are dropped, the response and the latest update are received
+        let mut received_updates = 0;
+        let mut is_response_received = false;
+        loop {
+            let message = future::timeout(timeout, slow_client.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            let message: serde_json::Value = serde_json::from_str(&message.to_string()).unwrap();
+
+            if message["id"] == "list_coins" {
+                is_response_received = true;
+            } else {
+                received_updates += 1;
+                if message["params"]["value"].as_f64() == Some((2 * updates_count - 1) as f64) {
+                    break;
+                }
+            }
+        }
+        assert!(is_response_received);
+        assert!(received_updates < 2 * updates_count);
+    });
+
+    *graceful_shutdown.lock().unwrap() = true;
+}
@@ -1,9 +1,11 @@
 use crate::repository::repositories::MarketRepositoriesByMarketValue;
 use crate::worker::market_helpers::market_value::MarketValue;
 use crate::worker::market_helpers::stored_and_ws_transmissible_f64::StoredAndWsTransmissibleF64;
+use crate::worker::network_helpers::output_sink::output_sink::OutputSinks;
 use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
 use crate::worker::network_helpers::ws_server::ws_channels_holder::WsChannelsHolderHashMap;
 use chrono::{DateTime, Utc, MIN_DATETIME};
+use std::cmp;
 use std::sync::Arc;
 
 pub struct ExchangePairInfo {
@@ -21,6 +23,7 @@ impl ExchangePairInfo {
         ws_channels_holder: &WsChannelsHolderHashMap,
         market_name: String,
         pair: (String, String),
+        output_sinks: OutputSinks,
     ) -> Self {
         let mut repositories = repositories.unwrap_or_default();
 
@@ -39,6 +42,7 @@ impl ExchangePairInfo {
                         ))
                         .unwrap(),
                 ),
+                output_sinks.clone(),
             ),
             last_trade_volume: 0.0,
             total_volume: StoredAndWsTransmissibleF64::new(
@@ -51,6 +55,7 @@ impl ExchangePairInfo {
                         .get(&(market_name, MarketValue::PairExchangeVolume, pair))
                         .unwrap(),
                 ),
+                output_sinks,
             ),
             total_ask: 0.0,
             total_bid: 0.0,
@@ -58,6 +63,17 @@ impl ExchangePairInfo {
         }
     }
 
+    /// Returns time of the latest update of any of exchange pair values
+    pub fn get_last_update(&self) -> DateTime<Utc> {
+        cmp::max(
+            self.timestamp,
+            cmp::max(
+                self.last_trade_price.get_timestamp(),
+                self.total_volume.get_timestamp(),
+            ),
+        )
+    }
+
     pub fn get_total_ask(&self) -> f64 {
         self.total_ask
     }
@@ -1,15 +1,54 @@
 use crate::repository::repositories::RepositoryForF64ByTimestamp;
 use crate::worker::helper_functions::{date_time_from_timestamp_sec, strip_usd};
+use crate::worker::market_helpers::exchange_pair_info::ExchangePairInfo;
+use crate::worker::market_helpers::market::MarketsHashMap;
+use crate::worker::market_helpers::market_spine::MarketSpine;
+use crate::worker::market_helpers::pair_average_price::PairAveragePriceType;
 use crate::worker::network_helpers::ws_server::candles::Candle;
 use crate::worker::network_helpers::ws_server::channels::worker_channels::WorkerChannels;
 use crate::worker::network_helpers::ws_server::channels::ws_channel_subscription_request::WsChannelSubscriptionRequest;
+use crate::worker::network_helpers::ws_server::interval::Interval;
 use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
 use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
 use crate::worker::network_helpers::ws_server::ws_channels::WsChannels;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
+pub fn make_ws_response_payload_1(
+    ws_channel_name: WsChannelName,
+    market_name: &Option<String>,
+    pair: &(String, String),
+    value: f64,
+    timestamp: DateTime<Utc>,
+) -> Option<WsChannelResponsePayload> {
+    let coin = strip_usd(pair)?;
+    let market_name = market_name.as_ref().map(|v| v.to_string());
+
+    let response_payload = match ws_channel_name {
+        WsChannelName::CoinAveragePrice => WsChannelResponsePayload::CoinAveragePrice {
+            coin,
+            value,
+            timestamp,
+        },
+        WsChannelName::CoinExchangePrice => WsChannelResponsePayload::CoinExchangePrice {
+            coin,
+            exchange: market_name.unwrap(),
+            value,
+            timestamp,
+        },
+        WsChannelName::CoinExchangeVolume => WsChannelResponsePayload::CoinExchangeVolume {
+            coin,
+            exchange: market_name.unwrap(),
+            value,
+            timestamp,
+        },
+        _ => unreachable!(),
+    };
+
+    Some(response_payload)
+}
+
 pub fn send_ws_response_1(
     ws_channels: &Arc<Mutex<WsChannels>>,
     ws_channel_name: WsChannelName,
@@ -18,36 +57,31 @@ pub fn send_ws_response_1(
     value: f64,
     timestamp: DateTime<Utc>,
 ) -> Option<()> {
-    if let Some(coin) = strip_usd(pair) {
-        let market_name = market_name.as_ref().map(|v| v.to_string());
-
-        let response_payload = match ws_channel_name {
-            WsChannelName::CoinAveragePrice => WsChannelResponsePayload::CoinAveragePrice {
-                coin,
-                value,
-                timestamp,
-            },
-            WsChannelName::CoinExchangePrice => WsChannelResponsePayload::CoinExchangePrice {
-                coin,
-                exchange: market_name.unwrap(),
-                value,
-                timestamp,
-            },
-            WsChannelName::CoinExchangeVolume => WsChannelResponsePayload::CoinExchangeVolume {
-                coin,
-                exchange: market_name.unwrap(),
-                value,
-                timestamp,
-            },
-            _ => unreachable!(),
-        };
-
-        ws_channels.lock().unwrap().send_general(response_payload);
-
-        Some(())
-    } else {
-        None
-    }
+    let response_payload =
+        make_ws_response_payload_1(ws_channel_name, market_name, pair, value, timestamp)?;
+
+    ws_channels.lock().unwrap().send_general(response_payload);
+
+    Some(())
+}
+
+/// Calculates candle of values within `interval` before `timestamp`.
+/// Returns `Ok(None)` if there are no values.
+pub fn calculate_last_candle(
+    repository: &RepositoryForF64ByTimestamp,
+    interval: Interval,
+    timestamp: DateTime<Utc>,
+) -> Result<Option<Candle>, String> {
+    let interval = interval.into_seconds() as i64;
+    let from = timestamp.timestamp() - interval;
+    let from = date_time_from_timestamp_sec(from as u64);
+
+    let values: Vec<(DateTime<Utc>, f64)> = repository
+        .read_range(from, timestamp)?
+        .into_iter()
+        .collect();
+
+    Ok(Candle::calculate(values, timestamp))
 }
 
 pub fn send_ws_response_2(
@@ -64,34 +98,29 @@ pub fn send_ws_response_2(
                 let mut responses = HashMap::new();
 
                 for (key, request) in channels {
-                    let to = timestamp;
-
                     match request {
                         WsChannelSubscriptionRequest::WorkerChannels(request) => {
                             match request {
                                 WorkerChannels::CoinAveragePriceCandles { interval, .. } => {
-                                    let interval = interval.into_seconds() as i64;
-                                    let from = timestamp.timestamp() - interval;
-                                    let from = date_time_from_timestamp_sec(from as u64);
-
-                                    if let Ok(values) = repository.read_range(from, to) {
-                                        let values: Vec<(DateTime<Utc>, f64)> =
-                                            values.into_iter().map(|(k, v)| (k, v)).collect();
-
-                                        let response_payload = if !values.is_empty() {
-                                            WsChannelResponsePayload::CoinAveragePriceCandles {
-                                                coin: coin.clone(),
-                                                value: Candle::calculate(values, timestamp)
-                                                    .unwrap(),
+                                    if let Ok(candle) =
+                                        calculate_last_candle(repository, *interval, timestamp)
+                                    {
+                                        let response_payload = match candle {
+                                            Some(candle) => {
+                                                WsChannelResponsePayload::CoinAveragePriceCandles {
+                                                    coin: coin.clone(),
+                                                    value: candle,
+                                                }
                                             }
-                                        } else {
-                                            // Send "empty" message
-
-                                            WsChannelResponsePayload::Err {
-                                                method: Some(WsChannelName::CoinAveragePriceCandles),
-                                                code: 0,
-                                                message: "No entries found in the requested time interval."
-                                                    .to_string(),
+                                            None => {
+                                                // Send "empty" message
+
+                                                WsChannelResponsePayload::Err {
+                                                    method: Some(WsChannelName::CoinAveragePriceCandles),
+                                                    code: 0,
+                                                    message: "No entries found in the requested time interval."
+                                                        .to_string(),
+                                                }
                                             }
                                         };
 
@@ -118,3 +147,83 @@ pub fn send_ws_response_2(
         None
     }
 }
+
+/// Market is considered live if it updated coin values within this time
+pub const LIVE_MARKET_MAX_AGE_SEC: i64 = 60;
+
+/// Returns names of markets, which updated any value of a coin within the last `max_age_sec` seconds.
+/// Result is grouped by coin.
+pub fn get_live_markets_by_coin(
+    markets: &MarketsHashMap,
+    max_age_sec: i64,
+) -> HashMap<String, Vec<String>> {
+    let oldest_update = Utc::now() - Duration::seconds(max_age_sec);
+    let mut res: HashMap<String, Vec<String>> = HashMap::new();
+
+    for (market_name, market) in markets {
+        let market = market.lock().unwrap();
+        let spine = market.get_spine();
+
+        for (pair_string, exchange_pair_info) in spine.get_exchange_pairs() {
+            if exchange_pair_info.get_last_update() < oldest_update {
+                continue;
+            }
+
+            if let Some(coin) = spine.get_pairs().get(pair_string).and_then(strip_usd) {
+                res.entry(coin).or_default().push(market_name.to_string());
+            }
+        }
+    }
+
+    for market_names in res.values_mut() {
+        market_names.sort();
+        market_names.dedup();
+    }
+
+    res
+}
+
+/// Returns info of the market's exchange pair, which has the given (unmasked) pair
+pub fn find_exchange_pair_info<'a>(
+    spine: &'a MarketSpine,
+    pair: &(String, String),
+) -> Option<&'a ExchangePairInfo> {
+    spine
+        .get_pairs()
+        .iter()
+        .find(|(_, v)| *v == pair)
+        .and_then(|(pair_string, _)| spine.get_exchange_pairs().get(pair_string))
+}
+
+/// Returns the last trade price of a coin on a market and time of its update
+pub fn get_coin_exchange_price(
+    markets: &MarketsHashMap,
+    market_name: &str,
+    coin: &str,
+) -> Option<(f64, DateTime<Utc>)> {
+    let market = markets.get(market_name)?.lock().unwrap();
+    let pair = (coin.to_string(), "USD".to_string());
+    let last_trade_price = &find_exchange_pair_info(market.get_spine(), &pair)?.last_trade_price;
+
+    last_trade_price
+        .get_value()
+        .map(|v| (v, last_trade_price.get_timestamp()))
+}
+
+/// Returns coin average price and time of its update. Error contains message for the client.
+pub fn get_coin_average_price(
+    pair_average_price: &PairAveragePriceType,
+    coin: &str,
+) -> Result<(f64, DateTime<Utc>), String> {
+    let pair = (coin.to_string(), "USD".to_string());
+    let pair_average_price = pair_average_price
+        .get(&pair)
+        .ok_or_else(|| format!("Coin {} not supported.", coin))?
+        .lock()
+        .unwrap();
+
+    pair_average_price
+        .get_value()
+        .map(|v| (v, pair_average_price.get_timestamp()))
+        .ok_or_else(|| format!("No value for coin {} yet.", coin))
+}
@@ -1,3 +1,4 @@
+use crate::metrics::metrics::{METRICS, WS_CLIENT_PARSE_ERRORS, WS_CLIENT_RECONNECTS};
 use crate::repository::repositories::{
     MarketRepositoriesByMarketValue, MarketRepositoriesByPairTuple,
 };
@@ -20,11 +21,14 @@ use crate::worker::markets::okcoin::Okcoin;
 use crate::worker::markets::poloniex::Poloniex;
 use crate::worker::network_helpers::ws_client::WsClient;
 use crate::worker::network_helpers::ws_server::ws_channels_holder::WsChannelsHolderHashMap;
+use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time;
 
+pub type MarketsHashMap = HashMap<String, Arc<Mutex<dyn Market + Send>>>;
+
 pub fn market_factory(
     mut spine: MarketSpine,
     exchange_pairs: Vec<ExchangePair>,
@@ -100,22 +104,39 @@ pub fn subscribe_channel(
         .unwrap()
         .get_websocket_on_open_msg(&pair, channel);
 
-    let ws_client = WsClient::new(url, on_open_msg, pair, |pair: String, info: String| {
-        if is_graceful_shutdown(&market) {
-            return;
-        }
+    let market_name = market.lock().unwrap().get_spine().name.clone();
+    let channel_name = channel.to_string();
 
-        if let Ok(json) = serde_json::from_str(&info) {
-            // This "match" returns value, but we shouldn't use it
-            match channel {
-                MarketChannels::Ticker => market.lock().unwrap().parse_ticker_json(pair, json),
-                MarketChannels::Trades => market.lock().unwrap().parse_last_trade_json(pair, json),
-                MarketChannels::Book => market.lock().unwrap().parse_depth_json(pair, json),
-            };
-        } else {
-            // Either parse json error or received string is not json
-        }
-    });
+    let ws_client = WsClient::new(
+        url,
+        on_open_msg,
+        market_name.clone(),
+        pair,
+        channel_name.clone(),
+        |pair: String, info: String| {
+            if is_graceful_shutdown(&market) {
+                return;
+            }
+
+            // `None` of parse functions isn't counted as an error: it's also returned
+            // for heartbeats, pongs and subscription acks
+            if let Ok(json) = serde_json::from_str(&info) {
+                let _ = match channel {
+                    MarketChannels::Ticker => market.lock().unwrap().parse_ticker_json(pair, json),
+                    MarketChannels::Trades => {
+                        market.lock().unwrap().parse_last_trade_json(pair, json)
+                    }
+                    MarketChannels::Book => market.lock().unwrap().parse_depth_json(pair, json),
+                };
+            } else {
+                // Either parse json error or received string is not json
+                METRICS.inc(
+                    WS_CLIENT_PARSE_ERRORS,
+                    &[("market", &market_name), ("channel", &channel_name)],
+                );
+            }
+        },
+    );
     ws_client.start();
 }
 
@@ -261,6 +282,16 @@ fn update(market: Arc<Mutex<dyn Market + Send>>) {
 
                         subscribe_channel(Arc::clone(&market_2), pair.clone(), channel);
                         thread::sleep(time::Duration::from_millis(10000));
+                        // Connection is closed by graceful shutdown, not by error
+                        if is_graceful_shutdown(&market_2) {
+                            return;
+                        }
+
+                        let market_name = market_2.lock().unwrap().get_spine().name.clone();
+                        METRICS.inc(
+                            WS_CLIENT_RECONNECTS,
+                            &[("market", &market_name), ("channel", &channel.to_string())],
+                        );
                     })
                     .unwrap();
                 thread::sleep(time::Duration::from_millis(12000));
@@ -385,6 +416,7 @@ pub trait Market {
             volume,
         );
 
+        self.get_spine().publish_ticker(&pair, volume);
         self.get_spine_mut().set_total_volume(&pair, volume);
     }
 
@@ -405,6 +437,8 @@ pub trait Market {
             last_trade_price,
         );
 
+        self.get_spine()
+            .publish_trade(&pair, last_trade_volume, last_trade_price);
         self.get_spine_mut()
             .set_last_trade_volume(&pair, last_trade_volume);
         self.get_spine_mut()
@@ -439,6 +473,7 @@ pub trait Market {
             ask_sum,
             bid_sum
         );
+        self.get_spine().publish_book(&pair, ask_sum, bid_sum);
     }
 }
 
@@ -469,6 +504,8 @@ mod test {
             market_repositories,
             ws_channels_holder,
             pair_average_price: _,
+            output_sinks: _,
+            alerts: _,
         } = RepositoriesPrepared::make(&config);
 
         let (market_spine, rx) = make_spine(market_name);
@@ -503,6 +540,8 @@ mod test {
             market_repositories,
             ws_channels_holder,
             pair_average_price: _,
+            output_sinks: _,
+            alerts: _,
         } = RepositoriesPrepared::make(&config);
 
         let pair_string = market
@@ -1,3 +1,4 @@
+use std::fmt;
 use std::str::FromStr;
 
 #[derive(Debug, Clone, Copy)]
@@ -26,3 +27,15 @@ impl FromStr for MarketChannels {
         }
     }
 }
+
+impl fmt::Display for MarketChannels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Ticker => "ticker",
+            Self::Trades => "trades",
+            Self::Book => "book",
+        };
+
+        write!(f, "{}", name)
+    }
+}
@@ -1,3 +1,4 @@
+use crate::metrics::metrics::{INDEX_UPDATES, METRICS, REJECTED_OUTLIER_TICKS};
 use crate::repository::repositories::MarketRepositoriesByMarketValue;
 use crate::worker::market_helpers::conversion_type::ConversionType;
 use crate::worker::market_helpers::exchange_pair::ExchangePair;
@@ -5,7 +6,11 @@ use crate::worker::market_helpers::exchange_pair_info::ExchangePairInfo;
 use crate::worker::market_helpers::market::Market;
 use crate::worker::market_helpers::market_channels::MarketChannels;
 use crate::worker::market_helpers::pair_average_price::PairAveragePriceType;
+use crate::worker::network_helpers::output_sink::output_sink::{
+    Book, OutputEvent, OutputSinks, Ticker, Trade,
+};
 use crate::worker::network_helpers::ws_server::ws_channels_holder::WsChannelsHolderHashMap;
+use chrono::Utc;
 use std::collections::HashMap;
 use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
@@ -26,6 +31,7 @@ pub struct MarketSpine {
     pairs: HashMap<String, (String, String)>,
     pub channels: Vec<MarketChannels>,
     pub graceful_shutdown: Arc<Mutex<bool>>,
+    output_sinks: OutputSinks,
 }
 impl MarketSpine {
     pub fn new(
@@ -35,6 +41,7 @@ impl MarketSpine {
         name: String,
         channels: Vec<MarketChannels>,
         graceful_shutdown: Arc<Mutex<bool>>,
+        output_sinks: OutputSinks,
     ) -> Self {
         let channels = match name.as_str() {
             "poloniex" | "kucoin" => {
@@ -68,6 +75,7 @@ impl MarketSpine {
             pairs: HashMap::new(),
             channels,
             graceful_shutdown,
+            output_sinks,
         }
     }
 
@@ -118,6 +126,7 @@ impl MarketSpine {
                 ws_channels_holder,
                 self.name.clone(),
                 exchange_pair.pair.clone(),
+                self.output_sinks.clone(),
             ),
         );
         self.conversions
@@ -183,6 +192,7 @@ impl MarketSpine {
                     let new_avg = (new_price + old_avg) / 2.0;
 
                     info!("new {}-{} average trade price: {}", pair.0, pair.1, new_avg);
+                    METRICS.inc(INDEX_UPDATES, &[("coin", &pair.0)]);
 
                     pair_average_price_2.set_new_value(new_avg);
                 }
@@ -229,6 +239,12 @@ impl MarketSpine {
             }
             // If new value is inside Real sequence
             if value > old_value * 1.5 || value < old_value / 1.5 {
+                let coin = &self.pairs.get(pair).unwrap().0;
+                METRICS.inc(
+                    REJECTED_OUTLIER_TICKS,
+                    &[("market", &self.name), ("coin", coin)],
+                );
+
                 return;
             }
         }
@@ -243,6 +259,63 @@ impl MarketSpine {
         self.recalculate_pair_average_price(pair_tuple, value);
     }
 
+    /// Publishes event of the pair to output sinks.
+    /// `make_event` gets exchange name, coin and quote.
+    fn publish(&self, pair: &str, make_event: impl FnOnce(String, String, String) -> OutputEvent) {
+        if self.output_sinks.is_empty() {
+            return;
+        }
+
+        if let Some((coin, quote)) = self.pairs.get(pair) {
+            let event = make_event(self.name.clone(), coin.clone(), quote.clone());
+
+            for output_sink in &self.output_sinks {
+                output_sink.publish(&event);
+            }
+        }
+    }
+
+    /// Publishes trade to output sinks (before it's checked for being an outlier)
+    pub fn publish_trade(&self, pair: &str, volume: f64, price: f64) {
+        self.publish(pair, |exchange, coin, quote| {
+            OutputEvent::Trade(Trade {
+                exchange,
+                coin,
+                quote,
+                price,
+                volume,
+                timestamp: Utc::now(),
+            })
+        });
+    }
+
+    /// Publishes ticker to output sinks (whether or not the volume has changed)
+    pub fn publish_ticker(&self, pair: &str, volume: f64) {
+        self.publish(pair, |exchange, coin, quote| {
+            OutputEvent::Ticker(Ticker {
+                exchange,
+                coin,
+                quote,
+                volume,
+                timestamp: Utc::now(),
+            })
+        });
+    }
+
+    /// Publishes order book sums (in quote currency) to output sinks, after totals are updated
+    pub fn publish_book(&self, pair: &str, ask_sum: f64, bid_sum: f64) {
+        self.publish(pair, |exchange, coin, quote| {
+            OutputEvent::Book(Book {
+                exchange,
+                coin,
+                quote,
+                ask_sum,
+                bid_sum,
+                timestamp: Utc::now(),
+            })
+        });
+    }
+
     pub fn set_total_ask(&mut self, pair: &str, value: f64) {
         let old_value: f64 = self.get_exchange_pairs().get(pair).unwrap().get_total_ask();
 
@@ -289,6 +362,8 @@ pub mod test {
             market_repositories: _,
             ws_channels_holder: _,
             pair_average_price,
+            output_sinks,
+            alerts: _,
         } = RepositoriesPrepared::make(&config);
 
         let spine = MarketSpine::new(
@@ -298,6 +373,7 @@ pub mod test {
             market_name,
             config.market.channels,
             graceful_shutdown,
+            output_sinks,
         );
 
         (spine, rx)
@@ -323,6 +399,8 @@ pub mod test {
             market_repositories,
             ws_channels_holder,
             pair_average_price: _,
+            output_sinks: _,
+            alerts: _,
         } = RepositoriesPrepared::make(&config);
 
         let pair_string = "some_pair_string".to_string();
@@ -2,6 +2,7 @@ use crate::config_scheme::market_config::MarketConfig;
 use crate::repository::repositories::WorkerRepositoriesByPairTuple;
 use crate::worker::market_helpers::market_value::MarketValue;
 use crate::worker::market_helpers::stored_and_ws_transmissible_f64::StoredAndWsTransmissibleF64;
+use crate::worker::network_helpers::output_sink::output_sink::OutputSinks;
 use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
 use crate::worker::network_helpers::ws_server::ws_channels_holder::WsChannelsHolderHashMap;
 use std::collections::HashMap;
@@ -13,6 +14,7 @@ pub fn make_pair_average_price(
     market_config: &MarketConfig,
     mut repository: Option<WorkerRepositoriesByPairTuple>,
     ws_channels_holder: &WsChannelsHolderHashMap,
+    output_sinks: &OutputSinks,
 ) -> PairAveragePriceType {
     let mut hash_map = HashMap::new();
 
@@ -36,6 +38,7 @@ pub fn make_pair_average_price(
             None,
             pair.clone(),
             Arc::clone(ws_channels),
+            output_sinks.clone(),
         )));
 
         hash_map.insert(pair, pair_average_price);
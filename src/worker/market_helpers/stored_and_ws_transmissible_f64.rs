@@ -1,6 +1,14 @@
 use crate::repository::repositories::RepositoryForF64ByTimestamp;
-use crate::worker::market_helpers::hepler_functions::{send_ws_response_1, send_ws_response_2};
+use crate::worker::helper_functions::strip_usd;
+use crate::worker::market_helpers::hepler_functions::{
+    calculate_last_candle, make_ws_response_payload_1, send_ws_response_1, send_ws_response_2,
+};
+use crate::worker::network_helpers::output_sink::output_sink::{
+    ExchangeUpdate, IndexUpdate, OutputEvent, OutputSinks,
+};
+use crate::worker::network_helpers::ws_server::channels::ws_channel_subscription_request::WsChannelSubscriptionRequest;
 use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
 use crate::worker::network_helpers::ws_server::ws_channels::WsChannels;
 use chrono::{DateTime, Utc, MIN_DATETIME};
 use std::sync::{Arc, Mutex};
@@ -13,6 +21,7 @@ pub struct StoredAndWsTransmissibleF64 {
     ws_channel_names: Vec<WsChannelName>,
     market_name: Option<String>,
     pair: (String, String),
+    output_sinks: OutputSinks,
 }
 
 impl StoredAndWsTransmissibleF64 {
@@ -22,6 +31,7 @@ impl StoredAndWsTransmissibleF64 {
         market_name: Option<String>,
         pair: (String, String),
         ws_channels: Arc<Mutex<WsChannels>>,
+        output_sinks: OutputSinks,
     ) -> Self {
         for ws_channel_name in &ws_channel_names {
             if ws_channel_name.is_worker_channel() {
@@ -43,6 +53,7 @@ impl StoredAndWsTransmissibleF64 {
             ws_channel_names,
             market_name,
             pair,
+            output_sinks,
         }
     }
 
@@ -50,6 +61,42 @@ impl StoredAndWsTransmissibleF64 {
         self.value
     }
 
+    pub fn get_timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    /// Returns the current value (with its original timestamp) in the format of `request` channel
+    pub fn get_snapshot(
+        &self,
+        request: &WsChannelSubscriptionRequest,
+    ) -> Option<WsChannelResponsePayload> {
+        let value = self.value?;
+        let method = request.get_method();
+
+        match method {
+            WsChannelName::CoinAveragePrice
+            | WsChannelName::CoinExchangePrice
+            | WsChannelName::CoinExchangeVolume => make_ws_response_payload_1(
+                method,
+                &self.market_name,
+                &self.pair,
+                value,
+                self.timestamp,
+            ),
+            WsChannelName::CoinAveragePriceCandles => {
+                let repository = self.repository.as_ref()?;
+                let interval = request.get_interval()?;
+                let candle = calculate_last_candle(repository, interval, self.timestamp).ok()??;
+
+                Some(WsChannelResponsePayload::CoinAveragePriceCandles {
+                    coin: strip_usd(&self.pair)?,
+                    value: candle,
+                })
+            }
+            _ => None,
+        }
+    }
+
     pub fn set_new_value(&mut self, new_value: f64) {
         self.value = Some(new_value);
         self.timestamp = Utc::now();
@@ -84,5 +131,49 @@ impl StoredAndWsTransmissibleF64 {
                 _ => unreachable!(),
             }
         }
+
+        if !self.output_sinks.is_empty() {
+            if let Some(event) = self.make_output_event(new_value) {
+                for output_sink in &self.output_sinks {
+                    output_sink.publish(&event);
+                }
+            }
+        }
+    }
+
+    /// Worker's value is coin average price, kind of market's value is defined by its channel
+    fn make_output_event(&self, value: f64) -> Option<OutputEvent> {
+        let (coin, quote) = self.pair.clone();
+
+        let exchange = match &self.market_name {
+            Some(market_name) => market_name.clone(),
+            None => {
+                return Some(OutputEvent::CoinAveragePrice(IndexUpdate {
+                    coin,
+                    quote,
+                    value,
+                    timestamp: self.timestamp,
+                }));
+            }
+        };
+        let update = ExchangeUpdate {
+            exchange,
+            coin,
+            quote,
+            value,
+            timestamp: self.timestamp,
+        };
+
+        self.ws_channel_names
+            .iter()
+            .find_map(|ws_channel_name| match ws_channel_name {
+                WsChannelName::CoinExchangePrice => {
+                    Some(OutputEvent::CoinExchangePrice(update.clone()))
+                }
+                WsChannelName::CoinExchangeVolume => {
+                    Some(OutputEvent::CoinExchangeVolume(update.clone()))
+                }
+                _ => None,
+            })
     }
 }
@@ -0,0 +1,591 @@
+use crate::worker::network_helpers::alerts::alert_rule::{
+    AlertCondition, AlertNotification, AlertRule,
+};
+use crate::worker::network_helpers::output_sink::output_sink::OutputEvent;
+use crate::worker::network_helpers::ws_server::ws_server::{
+    JSONRPC_ERROR_FORBIDDEN, JSONRPC_ERROR_INVALID_PARAMS, JSONRPC_ERROR_LIMIT_EXCEEDED,
+};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+fn get_change_percent(from: f64, to: f64) -> f64 {
+    (to - from) / from * 100.0
+}
+
+/// Evaluation state of an alert
+#[derive(Default)]
+struct AlertState {
+    /// Last price (`cross` alerts)
+    last_price: Option<f64>,
+    /// Prices of the window in ascending order of both price and time (`change` alerts).
+    /// The first one is the min price of the window.
+    window_min: VecDeque<(DateTime<Utc>, f64)>,
+    /// The same as `window_min`, but in descending order of price
+    window_max: VecDeque<(DateTime<Utc>, f64)>,
+    /// Exchanges, which diverge from the average price (`divergence` alerts)
+    diverged: HashSet<String>,
+}
+
+impl AlertState {
+    fn push_to_window(
+        window: &mut VecDeque<(DateTime<Utc>, f64)>,
+        timestamp: DateTime<Utc>,
+        price: f64,
+        window_sec: u64,
+        is_replaced: fn(f64, f64) -> bool,
+    ) {
+        while window
+            .front()
+            .is_some_and(|(v, _)| (timestamp - *v).num_seconds() > window_sec as i64)
+        {
+            window.pop_front();
+        }
+        // Replaced prices can't become the min (max) of the window
+        while window.back().is_some_and(|(_, v)| is_replaced(*v, price)) {
+            window.pop_back();
+        }
+
+        window.push_back((timestamp, price));
+    }
+}
+
+struct Alert {
+    rule: AlertRule,
+    state: AlertState,
+    /// API key or connection id, which added the alert (`None` if alert is loaded from config)
+    owner: Option<String>,
+}
+
+impl Alert {
+    fn make_notification(
+        &self,
+        exchange: Option<&str>,
+        price: f64,
+        reference_price: f64,
+        timestamp: DateTime<Utc>,
+    ) -> AlertNotification {
+        AlertNotification {
+            alert_id: self.rule.id.clone(),
+            coin: self.rule.coin.clone(),
+            condition: self.rule.condition.clone(),
+            exchange: exchange.map(|v| v.to_string()),
+            price,
+            reference_price,
+            timestamp,
+        }
+    }
+
+    fn check_average_price(
+        &mut self,
+        price: f64,
+        timestamp: DateTime<Utc>,
+    ) -> Option<AlertNotification> {
+        match self.rule.condition {
+            AlertCondition::Cross { price: level } => {
+                let last_price = self.state.last_price.replace(price)?;
+                let is_crossed = (last_price < level && price >= level)
+                    || (last_price > level && price <= level);
+
+                is_crossed.then(|| self.make_notification(None, price, level, timestamp))
+            }
+            AlertCondition::Change {
+                percent,
+                window_sec,
+            } => {
+                let state = &mut self.state;
+                AlertState::push_to_window(
+                    &mut state.window_min,
+                    timestamp,
+                    price,
+                    window_sec,
+                    |v, price| v >= price,
+                );
+                AlertState::push_to_window(
+                    &mut state.window_max,
+                    timestamp,
+                    price,
+                    window_sec,
+                    |v, price| v <= price,
+                );
+
+                // The biggest move is either from the min or from the max price of the window
+                let reference_price = [state.window_min.front(), state.window_max.front()]
+                    .into_iter()
+                    .flatten()
+                    .map(|(_, v)| *v)
+                    .max_by(|a, b| {
+                        let a = get_change_percent(*a, price).abs();
+                        let b = get_change_percent(*b, price).abs();
+
+                        a.total_cmp(&b)
+                    })?;
+
+                if get_change_percent(reference_price, price).abs() > percent {
+                    // The next move is measured from the current price
+                    for window in [&mut state.window_min, &mut state.window_max] {
+                        window.clear();
+                        window.push_back((timestamp, price));
+                    }
+
+                    Some(self.make_notification(None, price, reference_price, timestamp))
+                } else {
+                    None
+                }
+            }
+            AlertCondition::Divergence { .. } => None,
+        }
+    }
+
+    fn check_exchange_price(
+        &mut self,
+        exchange: &str,
+        price: f64,
+        average_price: f64,
+        timestamp: DateTime<Utc>,
+    ) -> Option<AlertNotification> {
+        match &self.rule.condition {
+            AlertCondition::Divergence {
+                percent,
+                exchange: expected_exchange,
+            } => {
+                if expected_exchange.as_ref().is_some_and(|v| v != exchange) {
+                    return None;
+                }
+
+                // Alert is triggered once, when the exchange starts to diverge
+                if get_change_percent(average_price, price).abs() > *percent {
+                    self.state.diverged.insert(exchange.to_string()).then(|| {
+                        self.make_notification(Some(exchange), price, average_price, timestamp)
+                    })
+                } else {
+                    self.state.diverged.remove(exchange);
+
+                    None
+                }
+            }
+            AlertCondition::Cross { .. } | AlertCondition::Change { .. } => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct AlertRegistryInner {
+    /// Key - alert id
+    alerts: BTreeMap<String, Alert>,
+    /// Max number of alerts, added by requests
+    max_alerts: usize,
+    /// Max number of alerts, added by requests of one owner
+    max_alerts_per_owner: usize,
+    /// Last coin average prices. Key - coin.
+    average_prices: HashMap<String, f64>,
+}
+
+/// Registered alerts with their evaluation state.
+/// It's shared by the alert sink (evaluates alerts) and request handlers (add and remove alerts).
+#[derive(Clone, Default)]
+pub struct AlertRegistry(Arc<Mutex<AlertRegistryInner>>);
+
+impl AlertRegistry {
+    /// Registers alerts, loaded from config (they are read-only).
+    /// `max_alerts` - max number of alerts, added by requests,
+    /// `max_alerts_per_owner` - the same, but of one owner.
+    pub fn new(
+        rules: Vec<AlertRule>,
+        max_alerts: usize,
+        max_alerts_per_owner: usize,
+    ) -> Result<Self, String> {
+        let mut inner = AlertRegistryInner {
+            max_alerts,
+            max_alerts_per_owner,
+            ..Default::default()
+        };
+        for rule in rules {
+            Self::insert(&mut inner.alerts, rule, None)?;
+        }
+
+        Ok(Self(Arc::new(Mutex::new(inner))))
+    }
+
+    fn insert(
+        alerts: &mut BTreeMap<String, Alert>,
+        mut rule: AlertRule,
+        owner: Option<String>,
+    ) -> Result<AlertRule, String> {
+        rule.validate()?;
+        if rule.id.is_empty() {
+            rule.id = Uuid::new_v4().to_string();
+        }
+
+        if alerts.contains_key(&rule.id) {
+            return Err(format!("Alert {} already exists.", rule.id));
+        }
+
+        let alert = Alert {
+            rule: rule.clone(),
+            state: AlertState::default(),
+            owner,
+        };
+        alerts.insert(rule.id.clone(), alert);
+
+        Ok(rule)
+    }
+
+    /// Returns the registered alert (with generated id, if it wasn't set).
+    /// `owner` - API key or connection id, which is allowed to remove the alert.
+    /// Returns error code and message.
+    pub fn add(&self, rule: AlertRule, owner: &str) -> Result<AlertRule, (i64, String)> {
+        // Limits are checked under the same lock as the alert is inserted
+        let inner = &mut *self.0.lock().unwrap();
+        let added_alerts = inner.alerts.values().filter(|v| v.owner.is_some()).count();
+        if added_alerts >= inner.max_alerts {
+            return Err((
+                JSONRPC_ERROR_LIMIT_EXCEEDED,
+                "Limit exceeded. Max number of alerts is reached.".to_string(),
+            ));
+        }
+        let owner_alerts = inner
+            .alerts
+            .values()
+            .filter(|v| v.owner.as_deref() == Some(owner))
+            .count();
+        if owner_alerts >= inner.max_alerts_per_owner {
+            return Err((
+                JSONRPC_ERROR_LIMIT_EXCEEDED,
+                "Limit exceeded. Max number of alerts of the client is reached.".to_string(),
+            ));
+        }
+
+        Self::insert(&mut inner.alerts, rule, Some(owner.to_string()))
+            .map_err(|message| (JSONRPC_ERROR_INVALID_PARAMS, message))
+    }
+
+    /// Removes the alert, if it's added by `owner` (alerts, loaded from config, can't be removed).
+    /// Returns error code and message.
+    pub fn remove(&self, id: &str, owner: &str) -> Result<(), (i64, String)> {
+        let alerts = &mut self.0.lock().unwrap().alerts;
+
+        match alerts.get(id).map(|v| v.owner.as_deref()) {
+            Some(Some(alert_owner)) if alert_owner == owner => {
+                alerts.remove(id);
+
+                Ok(())
+            }
+            Some(_) => Err((
+                JSONRPC_ERROR_FORBIDDEN,
+                format!("Forbidden. Alert {} is not added by the client.", id),
+            )),
+            None => Err((
+                JSONRPC_ERROR_INVALID_PARAMS,
+                format!("Alert {} not found.", id),
+            )),
+        }
+    }
+
+    /// Removes all alerts of `owner` (alerts of a connection are removed after it's closed)
+    pub fn remove_owner(&self, owner: &str) {
+        self.0
+            .lock()
+            .unwrap()
+            .alerts
+            .retain(|_, v| v.owner.as_deref() != Some(owner));
+    }
+
+    /// Returns alerts, sorted by id
+    pub fn list(&self) -> Vec<AlertRule> {
+        self.0
+            .lock()
+            .unwrap()
+            .alerts
+            .values()
+            .map(|v| v.rule.clone())
+            .collect()
+    }
+
+    /// Returns alerts, triggered by the event
+    pub fn evaluate(&self, event: &OutputEvent) -> Vec<AlertNotification> {
+        let mut inner = self.0.lock().unwrap();
+        let AlertRegistryInner {
+            alerts,
+            average_prices,
+            ..
+        } = &mut *inner;
+
+        match event {
+            OutputEvent::CoinAveragePrice(update) if update.quote == "USD" => {
+                average_prices.insert(update.coin.clone(), update.value);
+
+                alerts
+                    .values_mut()
+                    .filter(|v| v.rule.coin == update.coin)
+                    .filter_map(|v| v.check_average_price(update.value, update.timestamp))
+                    .collect()
+            }
+            OutputEvent::CoinExchangePrice(update) if update.quote == "USD" => {
+                let average_price = match average_prices.get(&update.coin) {
+                    Some(average_price) => *average_price,
+                    None => return Vec::new(),
+                };
+
+                alerts
+                    .values_mut()
+                    .filter(|v| v.rule.coin == update.coin)
+                    .filter_map(|v| {
+                        v.check_exchange_price(
+                            &update.exchange,
+                            update.value,
+                            average_price,
+                            update.timestamp,
+                        )
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::alerts::alert_registry::AlertRegistry;
+    use crate::worker::network_helpers::alerts::alert_rule::{AlertCondition, AlertRule};
+    use crate::worker::network_helpers::output_sink::output_sink::{
+        ExchangeUpdate, IndexUpdate, OutputEvent,
+    };
+    use crate::worker::network_helpers::ws_server::ws_server::{
+        JSONRPC_ERROR_FORBIDDEN, JSONRPC_ERROR_INVALID_PARAMS, JSONRPC_ERROR_LIMIT_EXCEEDED,
+    };
+    use chrono::{Duration, TimeZone, Utc};
+
+    fn make_rule(id: &str, condition: AlertCondition) -> AlertRule {
+        AlertRule {
+            id: id.to_string(),
+            coin: "BTC".to_string(),
+            condition,
+        }
+    }
+
+    fn average_price(value: f64, second: i64) -> OutputEvent {
+        OutputEvent::CoinAveragePrice(IndexUpdate {
+            coin: "BTC".to_string(),
+            quote: "USD".to_string(),
+            value,
+            timestamp: Utc.timestamp_opt(1700000000, 0).unwrap() + Duration::seconds(second),
+        })
+    }
+
+    fn exchange_price(exchange: &str, value: f64) -> OutputEvent {
+        OutputEvent::CoinExchangePrice(ExchangeUpdate {
+            exchange: exchange.to_string(),
+            coin: "BTC".to_string(),
+            quote: "USD".to_string(),
+            value,
+            timestamp: Utc.timestamp_opt(1700000000, 0).unwrap(),
+        })
+    }
+
+    /// Returns ids and reference prices of triggered alerts
+    fn evaluate(registry: &AlertRegistry, event: OutputEvent) -> Vec<(String, f64)> {
+        registry
+            .evaluate(&event)
+            .into_iter()
+            .map(|v| (v.alert_id, v.reference_price))
+            .collect()
+    }
+
+    #[test]
+    fn test_add_and_remove() {
+        let rule = make_rule("", AlertCondition::Cross { price: 100.0 });
+        let registry =
+            AlertRegistry::new(vec![make_rule("a", rule.condition.clone())], 100, 100).unwrap();
+
+        let added = registry.add(rule.clone(), "owner").unwrap();
+        assert!(!added.id.is_empty());
+        assert_eq!(
+            registry.add(added.clone(), "owner"),
+            Err((
+                JSONRPC_ERROR_INVALID_PARAMS,
+                format!("Alert {} already exists.", added.id)
+            ))
+        );
+        assert!(registry
+            .add(
+                make_rule("b", AlertCondition::Cross { price: -1.0 }),
+                "owner"
+            )
+            .is_err());
+        assert_eq!(registry.list().len(), 2);
+
+        // Only the owner can remove the alert
+        assert_eq!(
+            registry.remove(&added.id, "other_owner").unwrap_err().0,
+            JSONRPC_ERROR_FORBIDDEN
+        );
+        assert!(registry.remove(&added.id, "owner").is_ok());
+        assert_eq!(
+            registry.remove(&added.id, "owner").unwrap_err().0,
+            JSONRPC_ERROR_INVALID_PARAMS
+        );
+
+        // Alerts, loaded from config, are read-only
+        assert_eq!(
+            registry.remove("a", "owner").unwrap_err().0,
+            JSONRPC_ERROR_FORBIDDEN
+        );
+        assert_eq!(registry.list(), vec![make_rule("a", rule.condition)]);
+    }
+
+    #[test]
+    fn test_max_alerts() {
+        let condition = AlertCondition::Cross { price: 100.0 };
+        let registry = AlertRegistry::new(vec![make_rule("a", condition.clone())], 2, 100).unwrap();
+
+        // Alerts, loaded from config, aren't counted
+        assert!(registry
+            .add(make_rule("b", condition.clone()), "owner")
+            .is_ok());
+        assert!(registry
+            .add(make_rule("c", condition.clone()), "other_owner")
+            .is_ok());
+        assert_eq!(
+            registry
+                .add(make_rule("d", condition.clone()), "owner")
+                .unwrap_err()
+                .0,
+            JSONRPC_ERROR_LIMIT_EXCEEDED
+        );
+
+        assert!(registry.remove("b", "owner").is_ok());
+        assert!(registry.add(make_rule("d", condition), "owner").is_ok());
+    }
+
+    #[test]
+    fn test_max_alerts_per_owner() {
+        let condition = AlertCondition::Cross { price: 100.0 };
+        let registry = AlertRegistry::new(Vec::new(), 100, 2).unwrap();
+
+        assert!(registry
+            .add(make_rule("a", condition.clone()), "owner")
+            .is_ok());
+        assert!(registry
+            .add(make_rule("b", condition.clone()), "owner")
+            .is_ok());
+        assert_eq!(
+            registry
+                .add(make_rule("c", condition.clone()), "owner")
+                .unwrap_err()
+                .0,
+            JSONRPC_ERROR_LIMIT_EXCEEDED
+        );
+        assert!(registry
+            .add(make_rule("c", condition.clone()), "other_owner")
+            .is_ok());
+
+        // Alerts of the owner are removed, alerts of other owners are kept
+        registry.remove_owner("owner");
+        assert_eq!(registry.list(), vec![make_rule("c", condition.clone())]);
+        assert!(registry.add(make_rule("a", condition), "owner").is_ok());
+    }
+
+    #[test]
+    fn test_cross() {
+        let registry = AlertRegistry::new(
+            vec![make_rule("cross", AlertCondition::Cross { price: 100.0 })],
+            100,
+            100,
+        )
+        .unwrap();
+
+        assert!(evaluate(&registry, average_price(110.0, 0)).is_empty());
+        assert!(evaluate(&registry, average_price(105.0, 1)).is_empty());
+        assert_eq!(
+            evaluate(&registry, average_price(95.0, 2)),
+            vec![("cross".to_string(), 100.0)]
+        );
+        assert!(evaluate(&registry, average_price(90.0, 3)).is_empty());
+        assert_eq!(
+            evaluate(&registry, average_price(100.0, 4)),
+            vec![("cross".to_string(), 100.0)]
+        );
+    }
+
+    #[test]
+    fn test_change() {
+        let registry = AlertRegistry::new(
+            vec![make_rule(
+                "change",
+                AlertCondition::Change {
+                    percent: 5.0,
+                    window_sec: 600,
+                },
+            )],
+            100,
+            100,
+        )
+        .unwrap();
+
+        assert!(evaluate(&registry, average_price(100.0, 0)).is_empty());
+        assert!(evaluate(&registry, average_price(103.0, 60)).is_empty());
+        assert!(evaluate(&registry, average_price(98.0, 120)).is_empty());
+        // Move from the max price of the window
+        assert_eq!(
+            evaluate(&registry, average_price(97.0, 180)),
+            vec![("change".to_string(), 103.0)]
+        );
+        // The next move is measured from the triggering price
+        assert!(evaluate(&registry, average_price(100.0, 240)).is_empty());
+        // The triggering price is out of the window
+        assert!(evaluate(&registry, average_price(102.0, 900)).is_empty());
+        assert_eq!(
+            evaluate(&registry, average_price(107.5, 960)),
+            vec![("change".to_string(), 102.0)]
+        );
+    }
+
+    #[test]
+    fn test_divergence() {
+        let registry = AlertRegistry::new(
+            vec![
+                make_rule(
+                    "any",
+                    AlertCondition::Divergence {
+                        percent: 1.0,
+                        exchange: None,
+                    },
+                ),
+                make_rule(
+                    "binance",
+                    AlertCondition::Divergence {
+                        percent: 1.0,
+                        exchange: Some("binance".to_string()),
+                    },
+                ),
+            ],
+            100,
+            100,
+        )
+        .unwrap();
+
+        // Average price is unknown
+        assert!(evaluate(&registry, exchange_price("binance", 200.0)).is_empty());
+
+        assert!(evaluate(&registry, average_price(100.0, 0)).is_empty());
+        assert!(evaluate(&registry, exchange_price("binance", 100.5)).is_empty());
+        assert_eq!(
+            evaluate(&registry, exchange_price("ftx", 98.0)),
+            vec![("any".to_string(), 100.0)]
+        );
+        assert_eq!(
+            evaluate(&registry, exchange_price("binance", 102.0)),
+            vec![("any".to_string(), 100.0), ("binance".to_string(), 100.0)]
+        );
+        // Alert is triggered again only after the exchange converges
+        assert!(evaluate(&registry, exchange_price("binance", 103.0)).is_empty());
+        assert!(evaluate(&registry, exchange_price("binance", 100.0)).is_empty());
+        assert_eq!(
+            evaluate(&registry, exchange_price("binance", 98.0)),
+            vec![("any".to_string(), 100.0), ("binance".to_string(), 100.0)]
+        );
+    }
+}
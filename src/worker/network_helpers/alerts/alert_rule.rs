@@ -0,0 +1,165 @@
+use crate::worker::network_helpers::ws_server::ser_date_into_timestamp;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+
+/// Condition of alert. Price is coin average price, unless stated otherwise.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// Price crosses `price` (in any direction)
+    Cross { price: f64 },
+    /// Price changes by more than `percent` within `window_sec`
+    Change { percent: f64, window_sec: u64 },
+    /// Exchange price diverges from the average price by more than `percent`.
+    /// `exchange` - `None` means any exchange.
+    Divergence {
+        percent: f64,
+        exchange: Option<String>,
+    },
+}
+
+impl AlertCondition {
+    pub fn get_kind(&self) -> &'static str {
+        match self {
+            Self::Cross { .. } => "cross",
+            Self::Change { .. } => "change",
+            Self::Divergence { .. } => "divergence",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct AlertRule {
+    /// Generated if empty
+    #[serde(default)]
+    pub id: String,
+    pub coin: String,
+    pub condition: AlertCondition,
+}
+
+#[derive(Deserialize)]
+struct AlertRulesFile {
+    alerts: Vec<AlertRule>,
+}
+
+impl AlertRule {
+    /// Loads alerts from file (supports the same formats as config files, e.g. _yaml_ and _toml_)
+    pub fn load(path: &str) -> Vec<Self> {
+        let mut file = config::Config::default();
+        file.merge(config::File::with_name(path))
+            .and_then(|file| file.clone().try_into::<AlertRulesFile>())
+            .map(|file| file.alerts)
+            .unwrap_or_else(|e| {
+                panic!(
+                    "Got wrong config value. service_config: alerts_file={}. Error: {}",
+                    path, e
+                )
+            })
+    }
+
+    /// Checks values of the condition (coin is checked by caller)
+    pub fn validate(&self) -> Result<(), String> {
+        let is_valid = match &self.condition {
+            AlertCondition::Cross { price } => *price > 0.0,
+            AlertCondition::Change {
+                percent,
+                window_sec,
+            } => *percent > 0.0 && *window_sec > 0,
+            AlertCondition::Divergence { percent, .. } => *percent > 0.0,
+        };
+
+        if is_valid {
+            Ok(())
+        } else {
+            Err("Parameter value is wrong: condition.".to_string())
+        }
+    }
+}
+
+/// Triggered alert. It's pushed to `alerts` channel and posted to webhook.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct AlertNotification {
+    pub alert_id: String,
+    pub coin: String,
+    pub condition: AlertCondition,
+    /// Exchange, whose price triggered the alert (only `divergence` alerts)
+    pub exchange: Option<String>,
+    /// Price, which triggered the alert
+    pub price: f64,
+    /// Price, which `price` is compared to: the crossed price, the price at the start of the move
+    /// or coin average price
+    pub reference_price: f64,
+    #[serde(with = "ser_date_into_timestamp")]
+    #[schemars(with = "i64")]
+    pub timestamp: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::alerts::alert_rule::{AlertCondition, AlertRule};
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn test_deserialize_rule() {
+        let rule: AlertRule = serde_json::from_value(json!({
+            "coin": "ETH",
+            "condition": {"kind": "change", "percent": 5, "window_sec": 600}
+        }))
+        .unwrap();
+
+        assert_eq!(
+            rule,
+            AlertRule {
+                id: "".to_string(),
+                coin: "ETH".to_string(),
+                condition: AlertCondition::Change {
+                    percent: 5.0,
+                    window_sec: 600
+                },
+            }
+        );
+        assert_eq!(rule.validate(), Ok(()));
+
+        let rule = AlertRule {
+            condition: AlertCondition::Divergence {
+                percent: 0.0,
+                exchange: None,
+            },
+            ..rule
+        };
+        assert!(rule.validate().is_err());
+    }
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join("index_daemon_test_alerts.yaml");
+        fs::write(
+            &path,
+            "alerts:\n  - id: btc_50k\n    coin: BTC\n    condition:\n      kind: cross\n      price: 50000\n  - coin: ETH\n    condition:\n      kind: divergence\n      percent: 1.5\n      exchange: binance\n",
+        )
+        .unwrap();
+
+        let rules = AlertRule::load(path.to_str().unwrap());
+        assert_eq!(
+            rules,
+            vec![
+                AlertRule {
+                    id: "btc_50k".to_string(),
+                    coin: "BTC".to_string(),
+                    condition: AlertCondition::Cross { price: 50000.0 },
+                },
+                AlertRule {
+                    id: "".to_string(),
+                    coin: "ETH".to_string(),
+                    condition: AlertCondition::Divergence {
+                        percent: 1.5,
+                        exchange: Some("binance".to_string()),
+                    },
+                },
+            ]
+        );
+
+        let _ = fs::remove_file(path);
+    }
+}
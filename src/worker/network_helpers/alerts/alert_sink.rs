@@ -0,0 +1,144 @@
+use crate::metrics::metrics::{ALERTS_TRIGGERED, METRICS, OUTPUT_SINK_MESSAGES_DROPPED};
+use crate::worker::market_helpers::market_value::MarketValue;
+use crate::worker::network_helpers::alerts::alert_registry::AlertRegistry;
+use crate::worker::network_helpers::alerts::alert_rule::{AlertNotification, AlertRule};
+use crate::worker::network_helpers::alerts::webhook::{Webhook, WebhookConfig};
+use crate::worker::network_helpers::output_sink::output_sink::{OutputEvent, OutputSink};
+use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+use crate::worker::network_helpers::ws_server::ws_channels_holder::WsChannelsHolderHashMap;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread;
+
+const NAME: &str = "alerts";
+
+#[derive(Debug, Clone, Default)]
+pub struct AlertsConfig {
+    /// Alerts, registered at start (loaded from `alerts_file`)
+    pub rules: Vec<AlertRule>,
+    /// Webhook config (`None` if alerts are only pushed to `alerts` channel)
+    pub webhook: Option<WebhookConfig>,
+    /// Max number of alerts, added by requests
+    pub max_alerts: usize,
+    /// Max number of alerts, added by requests of one API key or connection
+    pub max_alerts_per_owner: usize,
+}
+
+impl AlertsConfig {
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        Self {
+            rules,
+            webhook: None,
+            max_alerts: 1000,
+            max_alerts_per_owner: 100,
+        }
+    }
+
+    /// Starts evaluating thread of the alerts. Returns the registry of alerts and the sink,
+    /// which feeds it with index events.
+    pub fn start(
+        &self,
+        queue_size: usize,
+        ws_channels: &WsChannelsHolderHashMap,
+    ) -> (AlertRegistry, Box<dyn OutputSink>) {
+        let alerts = AlertRegistry::new(
+            self.rules.clone(),
+            self.max_alerts,
+            self.max_alerts_per_owner,
+        )
+        .unwrap_or_else(|e| {
+            panic!(
+                "Got wrong config value. service_config: alerts_file. Error: {}",
+                e
+            )
+        });
+        let webhook = self.webhook.as_ref().map(|v| v.start(queue_size));
+        let sink = AlertSink::start(alerts.clone(), webhook, ws_channels.clone(), queue_size);
+
+        (alerts, Box::new(sink))
+    }
+}
+
+/// Output sink, which evaluates alerts on coin average and exchange prices in a separate thread.
+/// Triggered alerts are pushed to `alerts` channel and posted to webhook.
+#[derive(Clone)]
+pub struct AlertSink {
+    tx: SyncSender<OutputEvent>,
+}
+
+impl AlertSink {
+    pub fn start(
+        alerts: AlertRegistry,
+        webhook: Option<Webhook>,
+        ws_channels: WsChannelsHolderHashMap,
+        queue_size: usize,
+    ) -> Self {
+        let (tx, rx) = mpsc::sync_channel(queue_size);
+
+        thread::Builder::new()
+            .name(format!("fn: output_sink, sink: {}", NAME))
+            .spawn(move || Self::run(alerts, webhook, ws_channels, rx))
+            .unwrap();
+
+        Self { tx }
+    }
+
+    fn drop_event(reason: &str) {
+        METRICS.inc(
+            OUTPUT_SINK_MESSAGES_DROPPED,
+            &[("sink", NAME), ("reason", reason)],
+        );
+    }
+
+    fn push_to_ws(ws_channels: &WsChannelsHolderHashMap, notification: &AlertNotification) {
+        let key = (
+            "worker".to_string(),
+            MarketValue::PairAveragePrice,
+            (notification.coin.clone(), "USD".to_string()),
+        );
+
+        if let Some(ws_channels) = ws_channels.get(&key) {
+            ws_channels
+                .lock()
+                .unwrap()
+                .send_general(WsChannelResponsePayload::Alert(notification.clone()));
+        }
+    }
+
+    /// Function evaluates alerts on queued events. Function ends when all the senders are dropped.
+    fn run(
+        alerts: AlertRegistry,
+        webhook: Option<Webhook>,
+        ws_channels: WsChannelsHolderHashMap,
+        rx: Receiver<OutputEvent>,
+    ) {
+        for event in rx {
+            for notification in alerts.evaluate(&event) {
+                info!("Alert triggered: {:?}", notification);
+                METRICS.inc(
+                    ALERTS_TRIGGERED,
+                    &[("kind", notification.condition.get_kind())],
+                );
+
+                Self::push_to_ws(&ws_channels, &notification);
+                if let Some(webhook) = &webhook {
+                    webhook.send(notification);
+                }
+            }
+        }
+    }
+}
+
+impl OutputSink for AlertSink {
+    fn publish(&self, event: &OutputEvent) {
+        match event {
+            OutputEvent::CoinAveragePrice(..) | OutputEvent::CoinExchangePrice(..) => {}
+            _ => return,
+        }
+
+        match self.tx.try_send(event.clone()) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => Self::drop_event("queue_full"),
+            Err(TrySendError::Disconnected(_)) => Self::drop_event("disconnected"),
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod alert_registry;
+pub mod alert_rule;
+pub mod alert_sink;
+pub mod webhook;
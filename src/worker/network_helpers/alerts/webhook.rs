@@ -0,0 +1,238 @@
+use crate::metrics::metrics::{ALERT_WEBHOOK_REQUESTS, METRICS};
+use crate::worker::network_helpers::alerts::alert_rule::AlertNotification;
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::StatusCode;
+use ring::hmac;
+use std::fmt::Write;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread;
+use std::time::Duration;
+
+/// Header with HMAC-SHA256 of the request body: `sha256=<hex>`
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// Delay before the first retry. It's doubled before every next retry.
+const RETRY_DELAY_MS: u64 = 500;
+
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Key of request signature (`None` if requests aren't signed)
+    pub secret: Option<String>,
+    /// Number of retries of failed request
+    pub retries: u32,
+    pub timeout_ms: u64,
+}
+
+impl WebhookConfig {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            secret: None,
+            retries: 3,
+            timeout_ms: 5000,
+        }
+    }
+
+    /// Starts posting thread of the webhook
+    pub fn start(&self, queue_size: usize) -> Webhook {
+        Webhook::start(self.clone(), queue_size)
+    }
+}
+
+/// Returns signature of request body
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+
+    hmac::sign(&key, body)
+        .as_ref()
+        .iter()
+        .fold("sha256=".to_string(), |mut res, v| {
+            let _ = write!(res, "{:02x}", v);
+            res
+        })
+}
+
+/// Posts triggered alerts to webhook URL in a separate thread, so slow webhook doesn't delay alerts.
+/// Alerts are dropped if the queue is full or all the retries failed.
+#[derive(Clone)]
+pub struct Webhook {
+    tx: SyncSender<AlertNotification>,
+}
+
+impl Webhook {
+    pub fn start(config: WebhookConfig, queue_size: usize) -> Self {
+        let (tx, rx) = mpsc::sync_channel(queue_size);
+
+        thread::Builder::new()
+            .name("fn: alert_webhook".to_string())
+            .spawn(move || Self::run(config, rx))
+            .unwrap();
+
+        Self { tx }
+    }
+
+    pub fn send(&self, notification: AlertNotification) {
+        if let Err(TrySendError::Full(_)) = self.tx.try_send(notification) {
+            METRICS.inc(ALERT_WEBHOOK_REQUESTS, &[("result", "queue_full")]);
+        }
+    }
+
+    /// Function posts queued alerts. Function ends when all the senders are dropped.
+    fn run(config: WebhookConfig, rx: Receiver<AlertNotification>) {
+        let client = Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .unwrap();
+
+        for notification in rx {
+            let body = serde_json::to_vec(&notification).unwrap();
+
+            match Self::post(&client, &config, body) {
+                Ok(()) => METRICS.inc(ALERT_WEBHOOK_REQUESTS, &[("result", "delivered")]),
+                Err(e) => {
+                    error!(
+                        "Alert webhook error. Alert: {}, error: {}",
+                        notification.alert_id, e
+                    );
+                    METRICS.inc(ALERT_WEBHOOK_REQUESTS, &[("result", "failed")]);
+                }
+            }
+        }
+    }
+
+    /// Posts body, retrying on network errors, server errors and throttling (with exponential backoff)
+    fn post(client: &Client, config: &WebhookConfig, body: Vec<u8>) -> Result<(), String> {
+        let signature = config.secret.as_ref().map(|v| sign(v, &body));
+        let mut error = String::new();
+
+        for attempt in 0..=config.retries {
+            if attempt > 0 {
+                thread::sleep(Duration::from_millis(RETRY_DELAY_MS << (attempt - 1)));
+            }
+
+            let mut request = client
+                .post(&config.url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.clone());
+            if let Some(signature) = &signature {
+                request = request.header(SIGNATURE_HEADER, signature);
+            }
+
+            match request.send() {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    error = format!("Response status: {}", status);
+
+                    if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+                        break;
+                    }
+                }
+                Err(e) => error = e.to_string(),
+            }
+        }
+
+        Err(error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::alerts::alert_rule::{AlertCondition, AlertNotification};
+    use crate::worker::network_helpers::alerts::webhook::{sign, WebhookConfig};
+    use chrono::{TimeZone, Utc};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sign() {
+        // Example from RFC 4231 (test case 2)
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    /// Reads HTTP request, returns its signature header and body
+    fn read_request(stream: &mut std::net::TcpStream) -> (Option<String>, String) {
+        let mut reader = BufReader::new(stream);
+        let mut content_length = 0;
+        let mut signature = None;
+
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+
+            let (name, value) = line.split_once(": ").unwrap_or((line, ""));
+            match name.to_lowercase().as_str() {
+                "content-length" => content_length = value.parse().unwrap(),
+                "x-signature" => signature = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+
+        (signature, String::from_utf8(body).unwrap())
+    }
+
+    #[test]
+    fn test_webhook() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/alerts", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        // The first request fails, so the alert is retried
+        let _ = thread::spawn(move || {
+            for (i, stream) in listener.incoming().enumerate() {
+                let mut stream = stream.unwrap();
+                let request = read_request(&mut stream);
+                let status = if i == 0 {
+                    "503 Service Unavailable"
+                } else {
+                    "200 OK"
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                )
+                .unwrap();
+
+                tx.send(request).unwrap();
+            }
+        });
+
+        let webhook = WebhookConfig {
+            secret: Some("secret".to_string()),
+            ..WebhookConfig::new(url)
+        }
+        .start(10);
+        let notification = AlertNotification {
+            alert_id: "btc_50k".to_string(),
+            coin: "BTC".to_string(),
+            condition: AlertCondition::Cross { price: 50000.0 },
+            exchange: None,
+            price: 50010.0,
+            reference_price: 50000.0,
+            timestamp: Utc.timestamp_opt(1700000000, 0).unwrap(),
+        };
+        webhook.send(notification);
+
+        let body = r#"{"alert_id":"btc_50k","coin":"BTC","condition":{"kind":"cross","price":50000.0},"exchange":null,"price":50010.0,"reference_price":50000.0,"timestamp":1700000000}"#;
+        let expected = (Some(sign("secret", body.as_bytes())), body.to_string());
+        for _ in 0..2 {
+            assert_eq!(rx.recv_timeout(Duration::from_secs(5)).unwrap(), expected);
+        }
+    }
+}
@@ -0,0 +1,635 @@
+use crate::repository::repositories::WorkerRepositoriesByPairTuple;
+use crate::worker::market_helpers::hepler_functions::{
+    get_coin_average_price, get_coin_exchange_price,
+};
+use crate::worker::market_helpers::market::MarketsHashMap;
+use crate::worker::market_helpers::pair_average_price::PairAveragePriceType;
+use crate::worker::network_helpers::grpc_server::proto::index_daemon_server::{
+    IndexDaemon, IndexDaemonServer,
+};
+use crate::worker::network_helpers::grpc_server::proto::{
+    self, Candle, CoinExchangeRequest, CoinExchangeValue, CoinRequest, CoinValue,
+    HistoricalCandles, HistoricalRequest, HistoricalValues, SubscribeExchangeRequest,
+    SubscribeRequest, TimestampedValue,
+};
+use crate::worker::network_helpers::ws_server::channels::ws_channel_action::WsChannelAction;
+use crate::worker::network_helpers::ws_server::interval::Interval;
+use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcRequest;
+use crate::worker::network_helpers::ws_server::outbound_queue::OutboundQueuePolicy;
+use crate::worker::network_helpers::ws_server::ws_auth::{ApiKeys, WsAuth};
+use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+use crate::worker::network_helpers::ws_server::ws_channel_subscription::WsChannelSubscription;
+use crate::worker::network_helpers::ws_server::ws_channels_holder::WsChannelsHolder;
+use crate::worker::network_helpers::ws_server::ws_limits::{WsConnectionLimits, WsLimits};
+use crate::worker::network_helpers::ws_server::ws_request::WsRequest;
+use crate::worker::network_helpers::ws_server::ws_server::{
+    WsServer, JSONRPC_ERROR_FORBIDDEN, JSONRPC_ERROR_INVALID_PARAMS, JSONRPC_ERROR_INVALID_REQUEST,
+    JSONRPC_ERROR_LIMIT_EXCEEDED, JSONRPC_ERROR_UNAUTHORIZED,
+};
+use async_tungstenite::tungstenite::protocol::Message;
+use futures::{future, Stream, StreamExt};
+use serde_json::json;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tonic::metadata::MetadataMap;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+type GrpcStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+pub struct GrpcServer {
+    pub grpc_addr: String,
+    /// Pending (conflated) messages are flushed by websocket server, if it's on
+    pub ws: bool,
+    pub markets: MarketsHashMap,
+    pub pair_average_price: PairAveragePriceType,
+    /// Repositories aren't `Sync`, but the service is shared between tasks
+    pub pair_average_price_repositories: Mutex<Option<WorkerRepositoriesByPairTuple>>,
+    /// Channels of websocket server, shared with streaming RPCs
+    pub ws_channels_holder: WsChannelsHolder,
+    pub ws_api_keys: Option<Arc<ApiKeys>>,
+    /// Limits of websocket server. Stream is limited as a connection with one subscription.
+    pub ws_limits: WsLimits,
+    pub ws_answer_timeout_ms: u64,
+    pub ws_outbound_queue_size: usize,
+    pub ws_outbound_queue_policy: OutboundQueuePolicy,
+    pub graceful_shutdown: Arc<Mutex<bool>>,
+}
+
+impl GrpcServer {
+    pub fn start(self) {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        if let Err(e) = runtime.block_on(Self::run(self)) {
+            error!("Grpc server error: {}", e);
+        }
+    }
+
+    /// Maps jsonrpc error code to grpc status
+    fn make_status((code, message): (i64, String)) -> Status {
+        match code {
+            JSONRPC_ERROR_UNAUTHORIZED => Status::unauthenticated(message),
+            JSONRPC_ERROR_FORBIDDEN => Status::permission_denied(message),
+            JSONRPC_ERROR_LIMIT_EXCEEDED => Status::resource_exhausted(message),
+            JSONRPC_ERROR_INVALID_REQUEST | JSONRPC_ERROR_INVALID_PARAMS => {
+                Status::invalid_argument(message)
+            }
+            _ => Status::internal(message),
+        }
+    }
+
+    fn make_interval(interval: i32) -> Option<Interval> {
+        match proto::Interval::try_from(interval) {
+            Ok(proto::Interval::Second) => Some(Interval::Second),
+            Ok(proto::Interval::Minute) => Some(Interval::Minute),
+            Ok(proto::Interval::Hour) => Some(Interval::Hour),
+            Ok(proto::Interval::Day) => Some(Interval::Day),
+            Ok(proto::Interval::Week) => Some(Interval::Week),
+            Ok(proto::Interval::Month) => Some(Interval::Month),
+            Ok(proto::Interval::Unspecified) | Err(_) => None,
+        }
+    }
+
+    /// API key from `authorization: Bearer <key>` metadata
+    fn get_token(metadata: &MetadataMap) -> Option<&str> {
+        metadata
+            .get("authorization")?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")
+    }
+
+    /// Parses request the same way as websocket request, then authenticates and authorizes it.
+    /// Returns the request and authentication state, or error code and message.
+    fn parse_request(
+        &self,
+        metadata: &MetadataMap,
+        method: WsChannelName,
+        params: serde_json::Value,
+    ) -> Result<(WsRequest, WsAuth), (i64, String)> {
+        let request = JsonRpcRequest {
+            id: None,
+            method,
+            params,
+        };
+        let request = WsRequest::try_from(request)
+            .map_err(|message| (JSONRPC_ERROR_INVALID_REQUEST, message))?;
+
+        let auth = WsAuth::new(self.ws_api_keys.clone());
+        if let Some(token) = Self::get_token(metadata) {
+            if !auth.authenticate(token) {
+                return Err((
+                    JSONRPC_ERROR_UNAUTHORIZED,
+                    "Unauthorized. Token is invalid.".to_string(),
+                ));
+            }
+        }
+        auth.authorize(&request)?;
+
+        Ok((request, auth))
+    }
+
+    /// Adds subscription of a stream.
+    /// Returns the subscription and limits of the connection
+    /// (stream is counted as a connection until they are dropped).
+    fn subscribe(
+        &self,
+        metadata: &MetadataMap,
+        remote_addr: Option<SocketAddr>,
+        method: WsChannelName,
+        params: serde_json::Value,
+    ) -> Result<(WsChannelSubscription, WsConnectionLimits), (i64, String)> {
+        let ip = remote_addr
+            .map(|v| v.ip())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let limits = self.ws_limits.acquire_connection(ip).map_err(|_| {
+            (
+                JSONRPC_ERROR_LIMIT_EXCEEDED,
+                "Limit exceeded. Too many connections.".to_string(),
+            )
+        })?;
+        limits.check_request()?;
+
+        let (request, auth) = self.parse_request(metadata, method, params)?;
+        let request = match request {
+            WsRequest::Channel(WsChannelAction::Subscribe(request)) => request,
+            WsRequest::Channel(WsChannelAction::Unsubscribe(..))
+            | WsRequest::Method(..)
+            | WsRequest::Configure(..)
+            | WsRequest::Auth(..)
+            | WsRequest::Discovery(..)
+            | WsRequest::Alert(..) => unreachable!(),
+        };
+
+        // Subscriptions of streams are counted against API key's limit, as websocket ones.
+        // Stream has no other subscriptions.
+        let api_key_slot = auth.reserve_subscription()?;
+        let _subscription_slot = limits.reserve_subscription(|| 0)?;
+        let subscription = WsChannelSubscription::new(
+            &self.ws_channels_holder,
+            request,
+            api_key_slot,
+            self.ws_answer_timeout_ms,
+            self.ws_outbound_queue_size,
+            self.ws_outbound_queue_policy,
+        )?;
+
+        Ok((subscription, limits))
+    }
+
+    /// Parses websocket notification of `method`. Returns payload and whether it's a snapshot.
+    fn parse_notification(
+        method: WsChannelName,
+        message: Message,
+    ) -> Option<(WsChannelResponsePayload, bool)> {
+        let message = match message {
+            Message::Text(message) => message,
+            _ => return None,
+        };
+        let mut message: serde_json::Value = serde_json::from_str(&message).ok()?;
+        let params = message.get_mut("params")?.take();
+        let snapshot = params["snapshot"].as_bool().unwrap_or(false);
+
+        WsChannelResponsePayload::from_value(method, params)
+            .ok()
+            .map(|payload| (payload, snapshot))
+    }
+
+    fn make_coin_value(payload: WsChannelResponsePayload, snapshot: bool) -> Option<CoinValue> {
+        match payload {
+            WsChannelResponsePayload::CoinAveragePrice {
+                coin,
+                value,
+                timestamp,
+            } => Some(CoinValue {
+                coin,
+                value,
+                timestamp: timestamp.timestamp(),
+                snapshot,
+            }),
+            _ => None,
+        }
+    }
+
+    fn make_coin_exchange_value(
+        payload: WsChannelResponsePayload,
+        snapshot: bool,
+    ) -> Option<CoinExchangeValue> {
+        match payload {
+            WsChannelResponsePayload::CoinExchangePrice {
+                coin,
+                exchange,
+                value,
+                timestamp,
+            }
+            | WsChannelResponsePayload::CoinExchangeVolume {
+                coin,
+                exchange,
+                value,
+                timestamp,
+            } => Some(CoinExchangeValue {
+                coin,
+                exchange,
+                value,
+                timestamp: timestamp.timestamp(),
+                snapshot,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Converts websocket notifications of the subscription to grpc messages.
+    /// Subscription is removed and limits are released after the stream is dropped
+    /// (client is disconnected).
+    fn make_stream<T, F>(
+        (subscription, limits): (WsChannelSubscription, WsConnectionLimits),
+        method: WsChannelName,
+        convert: F,
+    ) -> GrpcStream<T>
+    where
+        T: Send + 'static,
+        F: Fn(WsChannelResponsePayload, bool) -> Option<T> + Send + 'static,
+    {
+        Box::pin(subscription.filter_map(move |message| {
+            let _limits = &limits;
+            let res = Self::parse_notification(method, message)
+                .and_then(|(payload, snapshot)| convert(payload, snapshot));
+
+            future::ready(res.map(Ok))
+        }))
+    }
+
+    fn subscribe_coin_exchange(
+        &self,
+        request: Request<SubscribeExchangeRequest>,
+        method: WsChannelName,
+    ) -> Result<GrpcStream<CoinExchangeValue>, (i64, String)> {
+        let SubscribeExchangeRequest {
+            coins,
+            exchanges,
+            frequency_ms,
+        } = request.get_ref();
+        let params = json!({
+            "coins": coins,
+            "exchanges": exchanges,
+            "frequency_ms": frequency_ms,
+        });
+        let subscription =
+            self.subscribe(request.metadata(), request.remote_addr(), method, params)?;
+
+        Ok(Self::make_stream(
+            subscription,
+            method,
+            Self::make_coin_exchange_value,
+        ))
+    }
+
+    /// Same as the corresponding websocket method request
+    fn historical(
+        &self,
+        request: Request<HistoricalRequest>,
+        method: WsChannelName,
+    ) -> Result<WsChannelResponsePayload, (i64, String)> {
+        let HistoricalRequest {
+            coin,
+            interval,
+            from,
+            to,
+        } = request.get_ref();
+        let mut params = json!({
+            "coin": coin,
+            "from": from,
+            "to": to,
+        });
+        // Missing interval is reported the same way as in websocket request
+        if let Some(interval) = Self::make_interval(*interval) {
+            params["interval"] = json!(interval);
+        }
+
+        let request = match self.parse_request(request.metadata(), method, params)?.0 {
+            WsRequest::Method(request) => request,
+            WsRequest::Channel(..)
+            | WsRequest::Configure(..)
+            | WsRequest::Auth(..)
+            | WsRequest::Discovery(..)
+            | WsRequest::Alert(..) => unreachable!(),
+        };
+
+        let pair_average_price_repositories = self.pair_average_price_repositories.lock().unwrap();
+        match WsServer::make_method_response(request, &pair_average_price_repositories).result {
+            WsChannelResponsePayload::Err { code, message, .. } => Err((code, message)),
+            payload => Ok(payload),
+        }
+    }
+
+    /// Function ends on graceful shutdown
+    async fn wait_graceful_shutdown(graceful_shutdown: Arc<Mutex<bool>>) {
+        while !*graceful_shutdown.lock().unwrap() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Function periodically sends pending (conflated) responses, if websocket server is off.
+    /// Function ends on graceful shutdown.
+    async fn flush_pending(
+        ws_channels_holder: WsChannelsHolder,
+        period_ms: u64,
+        graceful_shutdown: Arc<Mutex<bool>>,
+    ) {
+        while !*graceful_shutdown.lock().unwrap() {
+            tokio::time::sleep(Duration::from_millis(period_ms)).await;
+
+            ws_channels_holder.flush_pending();
+        }
+    }
+
+    /// Function serves grpc requests until graceful shutdown
+    async fn run(self) -> Result<(), tonic::transport::Error> {
+        let grpc_addr = self
+            .grpc_addr
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut v| v.next())
+            .expect("Failed to resolve grpc address");
+
+        if !self.ws {
+            tokio::spawn(Self::flush_pending(
+                self.ws_channels_holder.clone(),
+                self.ws_answer_timeout_ms,
+                Arc::clone(&self.graceful_shutdown),
+            ));
+        }
+
+        info!("Grpc server started on: {}", self.grpc_addr);
+
+        let graceful_shutdown = Arc::clone(&self.graceful_shutdown);
+        Server::builder()
+            .add_service(IndexDaemonServer::new(self))
+            .serve_with_shutdown(grpc_addr, Self::wait_graceful_shutdown(graceful_shutdown))
+            .await
+    }
+}
+
+#[tonic::async_trait]
+impl IndexDaemon for GrpcServer {
+    async fn get_coin_average_price(
+        &self,
+        request: Request<CoinRequest>,
+    ) -> Result<Response<CoinValue>, Status> {
+        let coin = &request.get_ref().coin;
+        // Current value is authorized the same way as subscription
+        self.parse_request(
+            request.metadata(),
+            WsChannelName::CoinAveragePrice,
+            json!({ "coins": [coin] }),
+        )
+        .map_err(Self::make_status)?;
+
+        let (value, timestamp) =
+            get_coin_average_price(&self.pair_average_price, coin).map_err(Status::not_found)?;
+
+        Ok(Response::new(CoinValue {
+            coin: coin.to_string(),
+            value,
+            timestamp: timestamp.timestamp(),
+            snapshot: false,
+        }))
+    }
+
+    async fn get_coin_exchange_price(
+        &self,
+        request: Request<CoinExchangeRequest>,
+    ) -> Result<Response<CoinExchangeValue>, Status> {
+        let CoinExchangeRequest { coin, exchange } = request.get_ref();
+        self.parse_request(
+            request.metadata(),
+            WsChannelName::CoinExchangePrice,
+            json!({ "coins": [coin], "exchanges": [exchange] }),
+        )
+        .map_err(Self::make_status)?;
+
+        let (value, timestamp) = get_coin_exchange_price(&self.markets, exchange, coin)
+            .ok_or_else(|| {
+                Status::not_found(format!(
+                    "No value for coin {} on exchange {}.",
+                    coin, exchange
+                ))
+            })?;
+
+        Ok(Response::new(CoinExchangeValue {
+            coin: coin.to_string(),
+            exchange: exchange.to_string(),
+            value,
+            timestamp: timestamp.timestamp(),
+            snapshot: false,
+        }))
+    }
+
+    async fn get_coin_average_price_historical(
+        &self,
+        request: Request<HistoricalRequest>,
+    ) -> Result<Response<HistoricalValues>, Status> {
+        match self
+            .historical(request, WsChannelName::CoinAveragePriceHistorical)
+            .map_err(Self::make_status)?
+        {
+            WsChannelResponsePayload::CoinAveragePriceHistorical { coin, values } => {
+                Ok(Response::new(HistoricalValues {
+                    coin,
+                    values: values
+                        .0
+                        .into_iter()
+                        .map(|v| TimestampedValue {
+                            value: v.value,
+                            timestamp: v.timestamp.timestamp(),
+                        })
+                        .collect(),
+                }))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    async fn get_coin_average_price_candles_historical(
+        &self,
+        request: Request<HistoricalRequest>,
+    ) -> Result<Response<HistoricalCandles>, Status> {
+        match self
+            .historical(request, WsChannelName::CoinAveragePriceCandlesHistorical)
+            .map_err(Self::make_status)?
+        {
+            WsChannelResponsePayload::CoinAveragePriceCandlesHistorical { coin, values } => {
+                Ok(Response::new(HistoricalCandles {
+                    coin,
+                    values: values
+                        .0
+                        .into_iter()
+                        .map(|v| Candle {
+                            open: v.open,
+                            close: v.close,
+                            min: v.min,
+                            max: v.max,
+                            avg: v.avg,
+                            timestamp: v.timestamp.timestamp(),
+                        })
+                        .collect(),
+                }))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    type SubscribeCoinAveragePriceStream = GrpcStream<CoinValue>;
+
+    async fn subscribe_coin_average_price(
+        &self,
+        request: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeCoinAveragePriceStream>, Status> {
+        let method = WsChannelName::CoinAveragePrice;
+        let SubscribeRequest {
+            coins,
+            frequency_ms,
+        } = request.get_ref();
+        let params = json!({
+            "coins": coins,
+            "frequency_ms": frequency_ms,
+        });
+        let subscription = self
+            .subscribe(request.metadata(), request.remote_addr(), method, params)
+            .map_err(Self::make_status)?;
+
+        Ok(Response::new(Self::make_stream(
+            subscription,
+            method,
+            Self::make_coin_value,
+        )))
+    }
+
+    type SubscribeCoinExchangePriceStream = GrpcStream<CoinExchangeValue>;
+
+    async fn subscribe_coin_exchange_price(
+        &self,
+        request: Request<SubscribeExchangeRequest>,
+    ) -> Result<Response<Self::SubscribeCoinExchangePriceStream>, Status> {
+        self.subscribe_coin_exchange(request, WsChannelName::CoinExchangePrice)
+            .map(Response::new)
+            .map_err(Self::make_status)
+    }
+
+    type SubscribeCoinExchangeVolumeStream = GrpcStream<CoinExchangeValue>;
+
+    async fn subscribe_coin_exchange_volume(
+        &self,
+        request: Request<SubscribeExchangeRequest>,
+    ) -> Result<Response<Self::SubscribeCoinExchangeVolumeStream>, Status> {
+        self.subscribe_coin_exchange(request, WsChannelName::CoinExchangeVolume)
+            .map(Response::new)
+            .map_err(Self::make_status)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::grpc_server::grpc_server::GrpcServer;
+    use crate::worker::network_helpers::grpc_server::proto;
+    use crate::worker::network_helpers::ws_server::interval::Interval;
+    use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+    use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+    use crate::worker::network_helpers::ws_server::ws_server::{
+        JSONRPC_ERROR_FORBIDDEN, JSONRPC_ERROR_INTERNAL_ERROR, JSONRPC_ERROR_INVALID_PARAMS,
+        JSONRPC_ERROR_UNAUTHORIZED,
+    };
+    use async_tungstenite::tungstenite::protocol::Message;
+    use tonic::metadata::MetadataMap;
+    use tonic::Code;
+
+    #[test]
+    fn test_make_status() {
+        let codes = [
+            (JSONRPC_ERROR_UNAUTHORIZED, Code::Unauthenticated),
+            (JSONRPC_ERROR_FORBIDDEN, Code::PermissionDenied),
+            (JSONRPC_ERROR_INVALID_PARAMS, Code::InvalidArgument),
+            (JSONRPC_ERROR_INTERNAL_ERROR, Code::Internal),
+        ];
+
+        for (code, expected) in codes {
+            let status = GrpcServer::make_status((code, "Error.".to_string()));
+
+            assert_eq!(status.code(), expected);
+            assert_eq!(status.message(), "Error.");
+        }
+    }
+
+    #[test]
+    fn test_make_interval() {
+        assert_eq!(
+            GrpcServer::make_interval(proto::Interval::Minute as i32),
+            Some(Interval::Minute)
+        );
+        assert_eq!(
+            GrpcServer::make_interval(proto::Interval::Month as i32),
+            Some(Interval::Month)
+        );
+        assert_eq!(
+            GrpcServer::make_interval(proto::Interval::Unspecified as i32),
+            None
+        );
+        assert_eq!(GrpcServer::make_interval(100), None);
+    }
+
+    #[test]
+    fn test_get_token() {
+        let mut metadata = MetadataMap::new();
+        assert_eq!(GrpcServer::get_token(&metadata), None);
+
+        metadata.insert("authorization", "Bearer abc".parse().unwrap());
+        assert_eq!(GrpcServer::get_token(&metadata), Some("abc"));
+
+        metadata.insert("authorization", "abc".parse().unwrap());
+        assert_eq!(GrpcServer::get_token(&metadata), None);
+    }
+
+    #[test]
+    fn test_parse_notification() {
+        let message = Message::Text(
+            r#"{"jsonrpc":"2.0","method":"coin_exchange_volume","params":{"subscription_id":"1","snapshot":true,"coin":"BTC","exchange":"binance","value":2.5,"timestamp":1700000000}}"#
+                .to_string(),
+        );
+        let (payload, snapshot) =
+            GrpcServer::parse_notification(WsChannelName::CoinExchangeVolume, message).unwrap();
+        assert!(snapshot);
+
+        let value = GrpcServer::make_coin_exchange_value(payload, snapshot).unwrap();
+        assert_eq!(value.coin, "BTC");
+        assert_eq!(value.exchange, "binance");
+        assert_eq!(value.value, 2.5);
+        assert_eq!(value.timestamp, 1700000000);
+        assert!(value.snapshot);
+
+        let message = Message::Text(
+            r#"{"jsonrpc":"2.0","method":"coin_average_price","params":{"subscription_id":"1","coin":"BTC","value":100.0,"timestamp":1700000000}}"#
+                .to_string(),
+        );
+        let (payload, snapshot) =
+            GrpcServer::parse_notification(WsChannelName::CoinAveragePrice, message).unwrap();
+        assert!(!snapshot);
+        assert!(matches!(
+            payload,
+            WsChannelResponsePayload::CoinAveragePrice { .. }
+        ));
+        // Payload of another method isn't converted
+        assert!(GrpcServer::make_coin_exchange_value(payload, snapshot).is_none());
+
+        assert!(GrpcServer::parse_notification(
+            WsChannelName::CoinAveragePrice,
+            Message::Binary(Vec::new())
+        )
+        .is_none());
+    }
+}
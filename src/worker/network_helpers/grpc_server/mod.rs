@@ -0,0 +1,2 @@
+pub mod grpc_server;
+pub mod proto;
@@ -0,0 +1,5 @@
+//! Types and services generated from `proto/index_daemon.proto`
+
+#![allow(clippy::all)]
+
+tonic::include_proto!("index_daemon.v1");
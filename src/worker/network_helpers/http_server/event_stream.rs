@@ -0,0 +1,100 @@
+use async_std::future;
+use async_tungstenite::tungstenite::protocol::Message;
+use futures::{AsyncWrite, AsyncWriteExt, Stream, StreamExt};
+use std::time::Duration;
+
+const EVENT_STREAM_HEAD: &str = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nX-Accel-Buffering: no\r\nConnection: close\r\n\r\n";
+
+/// Writes response head, then text messages as Server-Sent Events of type `event`.
+/// If there are no messages during `keepalive`, comment is written, so a disconnected client is detected
+/// and proxies don't close the idle connection.
+/// Function ends when `messages` ends, or on close message, or on write error.
+pub async fn write_event_stream<W, M>(
+    mut writer: W,
+    mut messages: M,
+    event: &str,
+    keepalive: Duration,
+) where
+    W: AsyncWrite + Unpin,
+    M: Stream<Item = Message> + Unpin,
+{
+    if writer
+        .write_all(EVENT_STREAM_HEAD.as_bytes())
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    loop {
+        let chunk = match future::timeout(keepalive, messages.next()).await {
+            Ok(Some(Message::Text(text))) => format!("event: {}\ndata: {}\n\n", event, text),
+            Ok(Some(Message::Close(_))) | Ok(None) => break,
+            Ok(Some(message)) => {
+                // Binary messages are not allowed for text-only transports
+                error!("Unexpected message for event stream: {:?}", message);
+                continue;
+            }
+            Err(_) => ":\n\n".to_string(),
+        };
+
+        if writer.write_all(chunk.as_bytes()).await.is_err() || writer.flush().await.is_err() {
+            break;
+        }
+    }
+
+    let _ = writer.close().await;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::http_server::event_stream::write_event_stream;
+    use async_std::task;
+    use async_tungstenite::tungstenite::protocol::Message;
+    use futures::{stream, StreamExt};
+    use std::time::Duration;
+
+    #[test]
+    fn test_write_event_stream() {
+        let messages = vec![
+            Message::Text("{\"a\":1}".to_string()),
+            Message::Text("{\"b\":2}".to_string()),
+            Message::Close(None),
+            Message::Text("{\"c\":3}".to_string()),
+        ];
+        let mut written = Vec::new();
+
+        task::block_on(write_event_stream(
+            &mut written,
+            stream::iter(messages),
+            "coin_average_price",
+            Duration::from_secs(10),
+        ));
+
+        let written = String::from_utf8(written).unwrap();
+        let (head, body) = written.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(head.contains("Content-Type: text/event-stream"));
+        assert_eq!(
+            body,
+            "event: coin_average_price\ndata: {\"a\":1}\n\nevent: coin_average_price\ndata: {\"b\":2}\n\n"
+        );
+    }
+
+    #[test]
+    fn test_write_event_stream_keepalive() {
+        let messages =
+            stream::pending().take_until(Box::pin(task::sleep(Duration::from_millis(250))));
+        let mut written = Vec::new();
+
+        task::block_on(write_event_stream(
+            &mut written,
+            messages,
+            "coin_average_price",
+            Duration::from_millis(100),
+        ));
+
+        let written = String::from_utf8(written).unwrap();
+        assert_eq!(written.split_once("\r\n\r\n").unwrap().1, ":\n\n:\n\n");
+    }
+}
@@ -0,0 +1,129 @@
+use async_std::io::{prelude::BufReadExt, BufReader, Read};
+use futures::StreamExt;
+use std::collections::HashMap;
+
+const MAX_HEADER_COUNT: usize = 100;
+
+#[derive(Debug)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    /// Header names are lowercase
+    pub headers: HashMap<String, String>,
+}
+
+impl HttpRequest {
+    /// Decodes percent-encoded (and `+` as space) component of URL
+    pub fn decode_url_component(component: &str) -> String {
+        let bytes = component.as_bytes();
+        let mut res = Vec::with_capacity(bytes.len());
+
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'+' => res.push(b' '),
+                b'%' if i + 2 < bytes.len() => {
+                    let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+
+                    match u8::from_str_radix(hex, 16) {
+                        Ok(byte) => {
+                            res.push(byte);
+                            i += 2;
+                        }
+                        Err(_) => res.push(b'%'),
+                    }
+                }
+                byte => res.push(byte),
+            }
+            i += 1;
+        }
+
+        String::from_utf8_lossy(&res).to_string()
+    }
+
+    /// Parses request line and headers. Request body is not supported.
+    pub fn parse(request_line: &str, header_lines: &[String]) -> Option<Self> {
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next()?.to_string();
+        let target = parts.next()?;
+        parts.next()?.strip_prefix("HTTP/")?;
+
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let query = query
+            .split('&')
+            .filter(|v| !v.is_empty())
+            .map(|v| v.split_once('=').unwrap_or((v, "")))
+            .map(|(k, v)| (Self::decode_url_component(k), Self::decode_url_component(v)))
+            .collect();
+
+        let headers = header_lines
+            .iter()
+            .map(|line| {
+                line.split_once(':')
+                    .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Self {
+            method,
+            path: Self::decode_url_component(path),
+            query,
+            headers,
+        })
+    }
+
+    pub async fn read<T: Read + Unpin>(stream: T) -> Option<Self> {
+        let mut lines = BufReader::new(stream).lines();
+
+        let request_line = lines.next().await?.ok()?;
+
+        let mut header_lines = Vec::new();
+        loop {
+            let line = lines.next().await?.ok()?;
+            if line.is_empty() {
+                break;
+            }
+
+            header_lines.push(line);
+            if header_lines.len() > MAX_HEADER_COUNT {
+                return None;
+            }
+        }
+
+        Self::parse(&request_line, &header_lines)
+    }
+
+    /// Returns path segments, e.g. `/v1/price/BTC` -> `["v1", "price", "BTC"]`
+    pub fn get_path_segments(&self) -> Vec<&str> {
+        self.path.split('/').filter(|v| !v.is_empty()).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::http_server::http_request::HttpRequest;
+
+    #[test]
+    fn test_parse() {
+        let request = HttpRequest::parse(
+            "GET /some%20path/metrics?a=b&c=d%2Ce HTTP/1.1",
+            &["Host: localhost:8081".to_string()],
+        )
+        .unwrap();
+
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/some path/metrics");
+        assert_eq!(request.get_path_segments(), vec!["some path", "metrics"]);
+        assert_eq!(request.query.get("a").unwrap(), "b");
+        assert_eq!(request.query.get("c").unwrap(), "d,e");
+        assert_eq!(request.headers.get("host").unwrap(), "localhost:8081");
+    }
+
+    #[test]
+    fn test_parse_wrong_request_line() {
+        assert!(HttpRequest::parse("GET /metrics", &[]).is_none());
+        assert!(HttpRequest::parse("GET /metrics SMTP", &[]).is_none());
+        assert!(HttpRequest::parse("GET /metrics HTTP/1.1", &["Host".to_string()]).is_none());
+    }
+}
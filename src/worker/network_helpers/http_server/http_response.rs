@@ -0,0 +1,61 @@
+use async_std::io::{prelude::WriteExt, Write};
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub content_type: String,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, content_type: &str, body: String) -> Self {
+        Self {
+            status,
+            content_type: content_type.to_string(),
+            body,
+        }
+    }
+
+    pub fn text(status: u16, body: &str) -> Self {
+        Self::new(status, "text/plain; charset=utf-8", body.to_string())
+    }
+
+    pub fn not_found() -> Self {
+        Self::text(404, "Not found.")
+    }
+
+    pub fn method_not_allowed() -> Self {
+        Self::text(405, "Method not allowed.")
+    }
+
+    fn get_reason_phrase(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            429 => "Too Many Requests",
+            500 => "Internal Server Error",
+            503 => "Service Unavailable",
+            _ => "",
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            self.get_reason_phrase(),
+            self.content_type,
+            self.body.len(),
+        );
+
+        [head.into_bytes(), self.body.clone().into_bytes()].concat()
+    }
+
+    pub async fn write<T: Write + Unpin>(&self, mut stream: T) -> std::io::Result<()> {
+        stream.write_all(&self.to_bytes()).await?;
+        stream.flush().await
+    }
+}
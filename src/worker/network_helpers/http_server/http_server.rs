@@ -0,0 +1,776 @@
+use crate::config_scheme::storage::Storage;
+use crate::metrics::metrics::METRICS;
+use crate::repository::repositories::WorkerRepositoriesByPairTuple;
+use crate::worker::helper_functions::strip_usd;
+use crate::worker::market_helpers::exchange_pair::ExchangePair;
+use crate::worker::market_helpers::hepler_functions::{
+    get_coin_average_price, get_coin_exchange_price, get_live_markets_by_coin,
+    LIVE_MARKET_MAX_AGE_SEC,
+};
+use crate::worker::market_helpers::market::MarketsHashMap;
+use crate::worker::market_helpers::pair_average_price::PairAveragePriceType;
+use crate::worker::network_helpers::http_server::event_stream::write_event_stream;
+use crate::worker::network_helpers::http_server::http_request::HttpRequest;
+use crate::worker::network_helpers::http_server::http_response::HttpResponse;
+use crate::worker::network_helpers::ws_server::channels::ws_channel_action::WsChannelAction;
+use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcRequest;
+use crate::worker::network_helpers::ws_server::outbound_queue::OutboundQueuePolicy;
+use crate::worker::network_helpers::ws_server::ws_auth::{ApiKeys, WsAuth};
+use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+use crate::worker::network_helpers::ws_server::ws_channel_subscription::WsChannelSubscription;
+use crate::worker::network_helpers::ws_server::ws_channels_holder::WsChannelsHolder;
+use crate::worker::network_helpers::ws_server::ws_limits::{WsConnectionLimits, WsLimits};
+use crate::worker::network_helpers::ws_server::ws_request::WsRequest;
+use crate::worker::network_helpers::ws_server::ws_server::{
+    WsServer, JSONRPC_ERROR_FORBIDDEN, JSONRPC_ERROR_INVALID_PARAMS, JSONRPC_ERROR_INVALID_REQUEST,
+    JSONRPC_ERROR_LIMIT_EXCEEDED, JSONRPC_ERROR_UNAUTHORIZED,
+};
+use async_std::{
+    net::{TcpListener, TcpStream},
+    task,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Comment is sent to event stream after this time without messages
+const EVENT_STREAM_KEEPALIVE_SEC: u64 = 15;
+
+#[derive(Clone)]
+pub struct HttpServer {
+    pub http_addr: String,
+    pub metrics: bool,
+    pub ws: bool,
+    pub ws_listener_bound: Arc<Mutex<bool>>,
+    pub storage: Option<Storage>,
+    pub markets: MarketsHashMap,
+    pub exchange_pairs: Vec<ExchangePair>,
+    pub ready_min_exchanges: usize,
+    pub pair_average_price: PairAveragePriceType,
+    pub pair_average_price_repositories: Option<WorkerRepositoriesByPairTuple>,
+    /// Channels of websocket server, shared with event stream (`/v1/stream`)
+    pub ws_channels_holder: WsChannelsHolder,
+    pub ws_api_keys: Option<Arc<ApiKeys>>,
+    /// Limits of websocket server. Event stream is limited as a connection with one subscription.
+    pub ws_limits: WsLimits,
+    pub ws_answer_timeout_ms: u64,
+    pub ws_outbound_queue_size: usize,
+    pub ws_outbound_queue_policy: OutboundQueuePolicy,
+    pub graceful_shutdown: Arc<Mutex<bool>>,
+}
+
+impl HttpServer {
+    pub fn start(self) {
+        let _ = task::block_on(Self::run(self));
+    }
+
+    fn make_json_response(ok: bool, body: serde_json::Value) -> HttpResponse {
+        let status = if ok { 200 } else { 503 };
+
+        HttpResponse::new(status, "application/json", body.to_string())
+    }
+
+    /// Process is alive, websocket listener is bound (if websocket server is on)
+    /// and storage is open (if storage is on)
+    fn healthz(&self) -> HttpResponse {
+        let ws_listener_bound = !self.ws || *self.ws_listener_bound.lock().unwrap();
+        let storage_open = self.storage.as_ref().map(|v| v.is_open()).unwrap_or(true);
+
+        Self::make_json_response(
+            ws_listener_bound && storage_open,
+            json!({
+                "ws_listener_bound": ws_listener_bound,
+                "storage_open": storage_open,
+            }),
+        )
+    }
+
+    /// Every configured coin has at least `ready_min_exchanges` live exchanges
+    fn readyz(&self) -> HttpResponse {
+        let live_markets = get_live_markets_by_coin(&self.markets, LIVE_MARKET_MAX_AGE_SEC);
+
+        let mut coins = HashMap::new();
+        for exchange_pair in &self.exchange_pairs {
+            if let Some(coin) = strip_usd(&exchange_pair.pair) {
+                let live_market_count = live_markets.get(&coin).map(|v| v.len()).unwrap_or(0);
+
+                coins.insert(coin, live_market_count);
+            }
+        }
+        let ready = coins.values().all(|v| *v >= self.ready_min_exchanges);
+
+        Self::make_json_response(
+            ready,
+            json!({
+                "ready": ready,
+                "min_exchanges": self.ready_min_exchanges,
+                "live_exchanges": coins,
+            }),
+        )
+    }
+
+    fn make_payload_response(payload: WsChannelResponsePayload) -> HttpResponse {
+        let status = match payload {
+            WsChannelResponsePayload::Err { code, .. } => match code {
+                JSONRPC_ERROR_INVALID_REQUEST | JSONRPC_ERROR_INVALID_PARAMS => 400,
+                JSONRPC_ERROR_UNAUTHORIZED => 401,
+                JSONRPC_ERROR_FORBIDDEN => 403,
+                JSONRPC_ERROR_LIMIT_EXCEEDED => 429,
+                _ => 500,
+            },
+            _ => 200,
+        };
+        let body = serde_json::to_string(&payload).unwrap();
+
+        HttpResponse::new(status, "application/json", body)
+    }
+
+    fn make_not_found_response(method: WsChannelName, message: String) -> HttpResponse {
+        let payload = WsChannelResponsePayload::Err {
+            method: Some(method),
+            code: JSONRPC_ERROR_INVALID_PARAMS,
+            message,
+        };
+        let body = serde_json::to_string(&payload).unwrap();
+
+        HttpResponse::new(404, "application/json", body)
+    }
+
+    /// `GET /v1/price/{coin}`
+    fn coin_average_price(&self, request: &HttpRequest, coin: &str) -> HttpResponse {
+        let method = WsChannelName::CoinAveragePrice;
+        let params = json!({ "coins": [coin] });
+        if let Err(response) = self.authorize(request, method, params) {
+            return response;
+        }
+
+        match get_coin_average_price(&self.pair_average_price, coin) {
+            Ok((value, timestamp)) => {
+                Self::make_payload_response(WsChannelResponsePayload::CoinAveragePrice {
+                    coin: coin.to_string(),
+                    value,
+                    timestamp,
+                })
+            }
+            Err(message) => Self::make_not_found_response(WsChannelName::CoinAveragePrice, message),
+        }
+    }
+
+    /// `GET /v1/price/{coin}/{exchange}`
+    fn coin_exchange_price(
+        &self,
+        request: &HttpRequest,
+        coin: &str,
+        exchange: &str,
+    ) -> HttpResponse {
+        let method = WsChannelName::CoinExchangePrice;
+        let params = json!({ "coins": [coin], "exchanges": [exchange] });
+        if let Err(response) = self.authorize(request, method, params) {
+            return response;
+        }
+
+        match get_coin_exchange_price(&self.markets, exchange, coin) {
+            Some((value, timestamp)) => {
+                Self::make_payload_response(WsChannelResponsePayload::CoinExchangePrice {
+                    coin: coin.to_string(),
+                    exchange: exchange.to_string(),
+                    value,
+                    timestamp,
+                })
+            }
+            None => Self::make_not_found_response(
+                WsChannelName::CoinExchangePrice,
+                format!("No value for coin {} on exchange {}.", coin, exchange),
+            ),
+        }
+    }
+
+    /// Makes params of websocket request from query params.
+    /// Numbers are parsed, lists (`coins`, `exchanges`) are comma-separated.
+    fn make_params(query: &HashMap<String, String>) -> serde_json::Map<String, serde_json::Value> {
+        query
+            .iter()
+            .map(|(k, v)| {
+                let v = match k.as_str() {
+                    "coins" | "exchanges" => serde_json::Value::from(
+                        v.split(',')
+                            .filter(|v| !v.is_empty())
+                            .collect::<Vec<&str>>(),
+                    ),
+                    _ => v
+                        .parse::<u64>()
+                        .map(serde_json::Value::from)
+                        .unwrap_or_else(|_| serde_json::Value::from(v.as_str())),
+                };
+
+                (k.to_string(), v)
+            })
+            .collect()
+    }
+
+    /// `GET /v1/history/{coin}` and `GET /v1/candles/{coin}`.
+    /// Query params are the same as params of the corresponding websocket method.
+    fn method_request(
+        &self,
+        request: &HttpRequest,
+        method: WsChannelName,
+        coin: &str,
+    ) -> HttpResponse {
+        let mut params = Self::make_params(&request.query);
+        params.remove("token");
+        params.insert("coin".to_string(), serde_json::Value::from(coin));
+
+        let payload = match self.authorize(request, method, serde_json::Value::Object(params)) {
+            Ok((WsRequest::Method(request), _)) => {
+                WsServer::make_method_response(request, &self.pair_average_price_repositories)
+                    .result
+            }
+            Ok((WsRequest::Channel(..), _))
+            | Ok((WsRequest::Configure(..), _))
+            | Ok((WsRequest::Auth(..), _))
+            | Ok((WsRequest::Discovery(..), _))
+            | Ok((WsRequest::Alert(..), _)) => unreachable!(),
+            Err(response) => return response,
+        };
+
+        Self::make_payload_response(payload)
+    }
+
+    fn make_error_response(
+        method: Option<WsChannelName>,
+        code: i64,
+        message: &str,
+    ) -> HttpResponse {
+        Self::make_payload_response(WsChannelResponsePayload::Err {
+            method,
+            code,
+            message: message.to_string(),
+        })
+    }
+
+    /// Makes websocket request of `method` with `params`, then authenticates the client
+    /// (via `token` query param or `Authorization: Bearer` header) and authorizes the request
+    /// the same way as websocket requests. Returns the request and authentication state.
+    fn authorize(
+        &self,
+        request: &HttpRequest,
+        method: WsChannelName,
+        params: serde_json::Value,
+    ) -> Result<(WsRequest, WsAuth), HttpResponse> {
+        let ws_request = JsonRpcRequest {
+            id: None,
+            method,
+            params,
+        };
+        let ws_request = WsRequest::try_from(ws_request).map_err(|message| {
+            Self::make_error_response(Some(method), JSONRPC_ERROR_INVALID_REQUEST, &message)
+        })?;
+
+        let token = request.query.get("token").map(String::as_str).or_else(|| {
+            request
+                .headers
+                .get("authorization")
+                .and_then(|v| v.strip_prefix("Bearer "))
+        });
+
+        let auth = WsAuth::new(self.ws_api_keys.clone());
+        if let Some(token) = token {
+            if !auth.authenticate(token) {
+                return Err(Self::make_error_response(
+                    Some(method),
+                    JSONRPC_ERROR_UNAUTHORIZED,
+                    "Unauthorized. Token is invalid.",
+                ));
+            }
+        }
+        auth.authorize(&ws_request)
+            .map_err(|(code, message)| Self::make_error_response(Some(method), code, &message))?;
+
+        Ok((ws_request, auth))
+    }
+
+    /// Adds subscription of event stream. Query params are the same as params of the corresponding
+    /// websocket channel, plus `method` and `token` (API key).
+    /// Returns method, the subscription and limits of the connection
+    /// (event stream is counted as a connection until they are dropped).
+    fn subscribe(
+        &self,
+        request: &HttpRequest,
+        ip: IpAddr,
+    ) -> Result<(WsChannelName, WsChannelSubscription, WsConnectionLimits), HttpResponse> {
+        let query = &request.query;
+        let method = match query.get("method").map(|v| v.parse::<WsChannelName>()) {
+            Some(Ok(method)) if method.is_channel() => method,
+            Some(_) => {
+                return Err(Self::make_error_response(
+                    None,
+                    JSONRPC_ERROR_INVALID_REQUEST,
+                    "Method is not a channel.",
+                ))
+            }
+            None => {
+                return Err(Self::make_error_response(
+                    None,
+                    JSONRPC_ERROR_INVALID_REQUEST,
+                    "Parameter is required: method.",
+                ))
+            }
+        };
+
+        let make_error_response = |(code, message): (i64, String)| {
+            Self::make_error_response(Some(method), code, &message)
+        };
+
+        let limits = self.ws_limits.acquire_connection(ip).map_err(|status| {
+            let mut response = make_error_response((
+                JSONRPC_ERROR_LIMIT_EXCEEDED,
+                "Limit exceeded. Too many connections.".to_string(),
+            ));
+            response.status = status.as_u16();
+
+            response
+        })?;
+        limits.check_request().map_err(make_error_response)?;
+
+        let mut params = Self::make_params(query);
+        params.remove("method");
+        params.remove("token");
+
+        let (request, auth) = self.authorize(request, method, serde_json::Value::Object(params))?;
+
+        let request = match request {
+            WsRequest::Channel(WsChannelAction::Subscribe(request)) => request,
+            WsRequest::Channel(WsChannelAction::Unsubscribe(..))
+            | WsRequest::Method(..)
+            | WsRequest::Configure(..)
+            | WsRequest::Auth(..)
+            | WsRequest::Discovery(..)
+            | WsRequest::Alert(..) => unreachable!(),
+        };
+
+        // Subscriptions of event streams are counted against API key's limit, as websocket ones.
+        // Event stream has no other subscriptions.
+        let api_key_slot = auth.reserve_subscription().map_err(make_error_response)?;
+        let _subscription_slot = limits
+            .reserve_subscription(|| 0)
+            .map_err(make_error_response)?;
+        let subscription = WsChannelSubscription::new(
+            &self.ws_channels_holder,
+            request,
+            api_key_slot,
+            self.ws_answer_timeout_ms,
+            self.ws_outbound_queue_size,
+            self.ws_outbound_queue_policy,
+        )
+        .map_err(make_error_response)?;
+
+        Ok((method, subscription, limits))
+    }
+
+    /// `GET /v1/stream`: Server-Sent Events of one subscription.
+    /// Function is executing until client is disconnected.
+    async fn stream(self, request: &HttpRequest, stream: &TcpStream, ip: IpAddr) {
+        let (method, subscription, _limits) = match self.subscribe(request, ip) {
+            Ok(subscription) => subscription,
+            Err(response) => {
+                let _ = response.write(stream).await;
+                return;
+            }
+        };
+
+        // Subscription is removed after the client is disconnected
+        write_event_stream(
+            stream,
+            subscription,
+            &method.to_string(),
+            Duration::from_secs(EVENT_STREAM_KEEPALIVE_SEC),
+        )
+        .await;
+    }
+
+    fn route(&self, request: &HttpRequest) -> HttpResponse {
+        if request.method != "GET" {
+            return HttpResponse::method_not_allowed();
+        }
+
+        match request.get_path_segments().as_slice() {
+            ["metrics"] if self.metrics => HttpResponse::new(
+                200,
+                "text/plain; version=0.0.4; charset=utf-8",
+                METRICS.render(),
+            ),
+            ["healthz"] => self.healthz(),
+            ["readyz"] => self.readyz(),
+            ["v1", "price", coin] => self.coin_average_price(request, coin),
+            ["v1", "price", coin, exchange] => self.coin_exchange_price(request, coin, exchange),
+            ["v1", "history", coin] => {
+                self.method_request(request, WsChannelName::CoinAveragePriceHistorical, coin)
+            }
+            ["v1", "candles", coin] => self.method_request(
+                request,
+                WsChannelName::CoinAveragePriceCandlesHistorical,
+                coin,
+            ),
+            _ => HttpResponse::not_found(),
+        }
+    }
+
+    /// Function handles one connection: reads one request and writes one response
+    async fn handle_connection(self, stream: TcpStream, client_addr: SocketAddr) {
+        match HttpRequest::read(&stream).await {
+            Some(request) => {
+                trace!("Client with addr: {} requested: {:?}", client_addr, request);
+
+                // Event stream is served only with websocket server (it flushes conflated messages)
+                if request.method == "GET"
+                    && request.get_path_segments() == ["v1", "stream"]
+                    && self.ws
+                {
+                    self.stream(&request, &stream, client_addr.ip()).await;
+                    return;
+                }
+
+                let response = self.route(&request);
+                let _ = response.write(&stream).await;
+            }
+            None => {
+                let _ = HttpResponse::text(400, "Bad request.").write(&stream).await;
+            }
+        }
+    }
+
+    /// Function listens and establishes connections. Function never ends.
+    async fn run(self) -> Result<(), io::Error> {
+        let listener = TcpListener::bind(&self.http_addr)
+            .await
+            .expect("Failed to bind");
+        info!("Http server started on: {}", self.http_addr);
+
+        while let Ok((stream, client_addr)) = listener.accept().await {
+            if *self.graceful_shutdown.lock().unwrap() {
+                break;
+            }
+
+            task::spawn(self.clone().handle_connection(stream, client_addr));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config_scheme::helper_functions::make_exchange_pairs;
+    use crate::config_scheme::market_config::MarketConfig;
+    use crate::worker::network_helpers::http_server::http_request::HttpRequest;
+    use crate::worker::network_helpers::http_server::http_server::HttpServer;
+    use crate::worker::network_helpers::ws_server::outbound_queue::OutboundQueuePolicy;
+    use crate::worker::network_helpers::ws_server::ws_auth::{ApiKey, ApiKeys};
+    use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+    use crate::worker::network_helpers::ws_server::ws_channels_holder::WsChannelsHolder;
+    use crate::worker::network_helpers::ws_server::ws_limits::{WsLimits, WsLimitsConfig};
+    use std::collections::HashMap;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::{Arc, Mutex};
+
+    fn make_http_server(ws_listener_bound: bool, ready_min_exchanges: usize) -> HttpServer {
+        HttpServer {
+            http_addr: "127.0.0.1:8081".to_string(),
+            metrics: false,
+            ws: true,
+            ws_listener_bound: Arc::new(Mutex::new(ws_listener_bound)),
+            storage: None,
+            markets: HashMap::new(),
+            exchange_pairs: make_exchange_pairs(vec!["BTC".to_string()], None),
+            ready_min_exchanges,
+            pair_average_price: HashMap::new(),
+            pair_average_price_repositories: None,
+            ws_channels_holder: WsChannelsHolder::new(
+                WsChannelsHolder::make_hashmap(&MarketConfig::default()),
+                HashMap::new(),
+                HashMap::new(),
+            ),
+            ws_api_keys: None,
+            ws_limits: make_ws_limits(100, 100),
+            ws_answer_timeout_ms: 100,
+            ws_outbound_queue_size: 10,
+            ws_outbound_queue_policy: OutboundQueuePolicy::DropOldest,
+            graceful_shutdown: Arc::new(Mutex::new(false)),
+        }
+    }
+
+    fn make_ws_limits(max_connections_per_ip: usize, max_subscriptions: usize) -> WsLimits {
+        WsLimits::new(WsLimitsConfig {
+            max_connections: 100,
+            max_connections_per_ip,
+            max_subscriptions,
+            requests_per_sec: 100,
+            requests_per_sec_per_ip: 100,
+        })
+    }
+
+    fn get_status(http_server: &HttpServer, request_line: &str) -> u16 {
+        get_status_with_headers(http_server, request_line, &[])
+    }
+
+    fn get_status_with_headers(
+        http_server: &HttpServer,
+        request_line: &str,
+        header_lines: &[String],
+    ) -> u16 {
+        let request = HttpRequest::parse(request_line, header_lines).unwrap();
+
+        http_server.route(&request).status
+    }
+
+    #[test]
+    fn test_healthz() {
+        let http_server = make_http_server(false, 1);
+        assert_eq!(get_status(&http_server, "GET /healthz HTTP/1.1"), 503);
+
+        let http_server = make_http_server(true, 1);
+        assert_eq!(get_status(&http_server, "GET /healthz HTTP/1.1"), 200);
+    }
+
+    #[test]
+    fn test_readyz() {
+        let http_server = make_http_server(true, 1);
+        assert_eq!(get_status(&http_server, "GET /readyz HTTP/1.1"), 503);
+
+        let http_server = make_http_server(true, 0);
+        assert_eq!(get_status(&http_server, "GET /readyz HTTP/1.1"), 200);
+    }
+
+    #[test]
+    fn test_route_not_found() {
+        let http_server = make_http_server(true, 1);
+
+        assert_eq!(get_status(&http_server, "GET /metrics HTTP/1.1"), 404);
+        assert_eq!(get_status(&http_server, "POST /healthz HTTP/1.1"), 405);
+    }
+
+    #[test]
+    fn test_rest_api() {
+        let http_server = make_http_server(true, 1);
+
+        assert_eq!(get_status(&http_server, "GET /v1/price/BTC HTTP/1.1"), 404);
+        assert_eq!(
+            get_status(&http_server, "GET /v1/price/BTC/binance HTTP/1.1"),
+            404
+        );
+        assert_eq!(
+            get_status(&http_server, "GET /v1/history/BTC HTTP/1.1"),
+            400
+        );
+        assert_eq!(
+            get_status(
+                &http_server,
+                "GET /v1/candles/BTC?interval=wrong&from=1 HTTP/1.1"
+            ),
+            400
+        );
+        // Historical storage is turned off
+        assert_eq!(
+            get_status(
+                &http_server,
+                "GET /v1/history/BTC?interval=minute&from=1 HTTP/1.1"
+            ),
+            500
+        );
+    }
+
+    #[test]
+    fn test_rest_api_auth() {
+        let mut http_server = make_http_server(true, 1);
+        http_server.ws_api_keys = Some(Arc::new(ApiKeys::new(vec![ApiKey {
+            key: "some_key".to_string(),
+            methods: Some(vec![WsChannelName::CoinAveragePrice]),
+            coins: Some(vec!["BTC".to_string()]),
+            max_subscriptions: None,
+        }])));
+
+        // Not authenticated
+        assert_eq!(get_status(&http_server, "GET /v1/price/BTC HTTP/1.1"), 401);
+        assert_eq!(
+            get_status(&http_server, "GET /v1/price/BTC?token=wrong_key HTTP/1.1"),
+            401
+        );
+        assert_eq!(
+            get_status(
+                &http_server,
+                "GET /v1/history/BTC?interval=minute&from=1 HTTP/1.1"
+            ),
+            401
+        );
+
+        // Authenticated via query param or header (no value for the coin)
+        assert_eq!(
+            get_status(&http_server, "GET /v1/price/BTC?token=some_key HTTP/1.1"),
+            404
+        );
+        assert_eq!(
+            get_status_with_headers(
+                &http_server,
+                "GET /v1/price/BTC HTTP/1.1",
+                &["Authorization: Bearer some_key".to_string()]
+            ),
+            404
+        );
+
+        // Coin or method is not allowed
+        assert_eq!(
+            get_status(&http_server, "GET /v1/price/ETH?token=some_key HTTP/1.1"),
+            403
+        );
+        assert_eq!(
+            get_status(
+                &http_server,
+                "GET /v1/price/BTC/binance?token=some_key HTTP/1.1"
+            ),
+            403
+        );
+        assert_eq!(
+            get_status(
+                &http_server,
+                "GET /v1/candles/BTC?interval=minute&from=1&token=some_key HTTP/1.1"
+            ),
+            403
+        );
+    }
+
+    fn get_stream_status(http_server: &HttpServer, query: &str) -> Result<WsChannelName, u16> {
+        let request =
+            HttpRequest::parse(&format!("GET /v1/stream?{} HTTP/1.1", query), &[]).unwrap();
+
+        http_server
+            .subscribe(&request, IpAddr::V4(Ipv4Addr::LOCALHOST))
+            .map(|(method, ..)| method)
+            .map_err(|response| response.status)
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let mut http_server = make_http_server(true, 1);
+
+        assert_eq!(
+            get_stream_status(
+                &http_server,
+                "method=coin_average_price&coins=BTC&frequency_ms=500"
+            ),
+            Ok(WsChannelName::CoinAveragePrice)
+        );
+        let request = HttpRequest::parse(
+            "GET /v1/stream?method=coin_exchange_price&coins=BTC&exchanges=binance,coinbase HTTP/1.1",
+            &[],
+        )
+        .unwrap();
+        let (method, subscription, _limits) = http_server
+            .subscribe(&request, IpAddr::V4(Ipv4Addr::LOCALHOST))
+            .ok()
+            .unwrap();
+        assert_eq!(method, WsChannelName::CoinExchangePrice);
+        let conn_id = subscription.get_conn_id().to_string();
+        // One subscription is added to channels of both exchanges
+        assert_eq!(
+            http_server.ws_channels_holder.count_subscriptions(&conn_id),
+            1
+        );
+        drop(subscription);
+        assert_eq!(
+            http_server.ws_channels_holder.count_subscriptions(&conn_id),
+            0
+        );
+
+        assert_eq!(get_stream_status(&http_server, "coins=BTC"), Err(400));
+        assert_eq!(
+            get_stream_status(
+                &http_server,
+                "method=coin_average_price_historical&coin=BTC"
+            ),
+            Err(400)
+        );
+        assert_eq!(
+            get_stream_status(&http_server, "method=coin_average_price&coins=WRONG"),
+            Err(400)
+        );
+        assert_eq!(
+            get_stream_status(&http_server, "method=coin_exchange_price&coins=BTC"),
+            Err(400)
+        );
+
+        http_server.ws_api_keys = Some(Arc::new(ApiKeys::new(vec![ApiKey {
+            key: "some_key".to_string(),
+            methods: Some(vec![WsChannelName::CoinAveragePrice]),
+            coins: None,
+            max_subscriptions: Some(1),
+        }])));
+        let query = "method=coin_average_price&coins=BTC";
+        assert_eq!(get_stream_status(&http_server, query), Err(401));
+        assert_eq!(
+            get_stream_status(&http_server, &format!("{}&token=wrong_key", query)),
+            Err(401)
+        );
+        assert_eq!(
+            get_stream_status(&http_server, &format!("{}&token=some_key", query)),
+            Ok(WsChannelName::CoinAveragePrice)
+        );
+        assert_eq!(
+            get_stream_status(
+                &http_server,
+                "method=coin_average_price_candles&coins=BTC&interval=minute&token=some_key"
+            ),
+            Err(403)
+        );
+
+        // Subscription is counted against API key's limit while it exists
+        let request = HttpRequest::parse(
+            &format!("GET /v1/stream?{}&token=some_key HTTP/1.1", query),
+            &[],
+        )
+        .unwrap();
+        let subscription = http_server
+            .subscribe(&request, IpAddr::V4(Ipv4Addr::LOCALHOST))
+            .ok()
+            .unwrap();
+        assert_eq!(
+            get_stream_status(&http_server, &format!("{}&token=some_key", query)),
+            Err(403)
+        );
+        drop(subscription);
+        assert_eq!(
+            get_stream_status(&http_server, &format!("{}&token=some_key", query)),
+            Ok(WsChannelName::CoinAveragePrice)
+        );
+    }
+
+    #[test]
+    fn test_subscribe_limits() {
+        let mut http_server = make_http_server(true, 1);
+        http_server.ws_limits = make_ws_limits(1, 100);
+        let query = "GET /v1/stream?method=coin_average_price&coins=BTC HTTP/1.1";
+        let request = HttpRequest::parse(query, &[]).unwrap();
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        // Event stream is counted as a connection of the IP, while it exists
+        let subscription = http_server.subscribe(&request, ip).ok().unwrap();
+        assert_eq!(
+            http_server.subscribe(&request, ip).err().unwrap().status,
+            429
+        );
+        assert!(http_server
+            .subscribe(&request, IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)))
+            .is_ok());
+        drop(subscription);
+        assert!(http_server.subscribe(&request, ip).is_ok());
+
+        http_server.ws_limits = make_ws_limits(1, 0);
+        assert_eq!(
+            http_server.subscribe(&request, ip).err().unwrap().status,
+            429
+        );
+    }
+}
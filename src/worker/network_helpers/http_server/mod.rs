@@ -0,0 +1,4 @@
+pub mod event_stream;
+pub mod http_request;
+pub mod http_response;
+pub mod http_server;
@@ -1,2 +1,6 @@
+pub mod alerts;
+pub mod grpc_server;
+pub mod http_server;
+pub mod output_sink;
 pub mod ws_client;
 pub mod ws_server;
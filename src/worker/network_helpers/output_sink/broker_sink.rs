@@ -0,0 +1,236 @@
+use crate::metrics::metrics::{
+    METRICS, OUTPUT_SINK_MESSAGES_DROPPED, OUTPUT_SINK_MESSAGES_PUBLISHED,
+};
+use crate::worker::network_helpers::output_sink::output_sink::{
+    IndexUpdate, OutputEvent, OutputSink,
+};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Broker isn't reconnected more often than once per this time
+const RECONNECT_DELAY_SEC: u64 = 1;
+/// Idle connection is kept alive (see `BrokerConnection::keep_alive`) once per this time
+const KEEP_ALIVE_CHECK_SEC: u64 = 1;
+
+/// Connection to a message broker. It's used only by the publishing thread, so it may block.
+pub trait BrokerConnection: Send + 'static {
+    fn connect(&mut self) -> Result<(), String>;
+    fn publish(&mut self, topic: &str, update: &IndexUpdate) -> Result<(), String>;
+    /// It's called while there are no updates to publish, so the broker doesn't close
+    /// idle connection
+    fn keep_alive(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Output sink, which publishes index updates (coin average price) to a message broker
+/// in a separate thread. Updates are dropped if the queue is full or the broker is unavailable.
+#[derive(Clone)]
+pub struct BrokerSink {
+    name: &'static str,
+    topic_template: String,
+    tx: SyncSender<(String, IndexUpdate)>,
+}
+
+impl BrokerSink {
+    pub fn start<C: BrokerConnection>(
+        name: &'static str,
+        connection: C,
+        topic_template: String,
+        queue_size: usize,
+    ) -> Self {
+        let (tx, rx) = mpsc::sync_channel(queue_size);
+
+        thread::Builder::new()
+            .name(format!("fn: output_sink, sink: {}", name))
+            .spawn(move || Self::run(name, connection, rx))
+            .unwrap();
+
+        Self {
+            name,
+            topic_template,
+            tx,
+        }
+    }
+
+    fn drop_update(name: &str, reason: &str) {
+        METRICS.inc(
+            OUTPUT_SINK_MESSAGES_DROPPED,
+            &[("sink", name), ("reason", reason)],
+        );
+    }
+
+    /// Function publishes queued updates, (re)connecting to the broker if needed.
+    /// Function ends when all the senders are dropped.
+    fn run<C: BrokerConnection>(
+        name: &'static str,
+        mut connection: C,
+        rx: Receiver<(String, IndexUpdate)>,
+    ) {
+        let mut connected = false;
+        let mut last_connect: Option<Instant> = None;
+
+        loop {
+            let (topic, update) = match rx.recv_timeout(Duration::from_secs(KEEP_ALIVE_CHECK_SEC)) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => {
+                    if connected {
+                        if let Err(e) = connection.keep_alive() {
+                            error!("Output sink {} keep alive error: {}", name, e);
+                            connected = false;
+                        }
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            if !connected {
+                let reconnect_delay = Duration::from_secs(RECONNECT_DELAY_SEC);
+                if last_connect.is_some_and(|v| v.elapsed() < reconnect_delay) {
+                    Self::drop_update(name, "disconnected");
+                    continue;
+                }
+
+                last_connect = Some(Instant::now());
+                match connection.connect() {
+                    Ok(()) => {
+                        info!("Output sink {} connected.", name);
+                        connected = true;
+                    }
+                    Err(e) => {
+                        error!("Output sink {} connect error: {}", name, e);
+                        Self::drop_update(name, "disconnected");
+                        continue;
+                    }
+                }
+            }
+
+            match connection.publish(&topic, &update) {
+                Ok(()) => METRICS.inc(OUTPUT_SINK_MESSAGES_PUBLISHED, &[("sink", name)]),
+                Err(e) => {
+                    error!("Output sink {} publish error: {}", name, e);
+                    Self::drop_update(name, "publish_error");
+                    connected = false;
+                }
+            }
+        }
+    }
+}
+
+impl OutputSink for BrokerSink {
+    fn publish(&self, event: &OutputEvent) {
+        let update = match event {
+            OutputEvent::CoinAveragePrice(update) => update,
+            _ => return,
+        };
+        let topic = event.make_topic(&self.topic_template);
+
+        match self.tx.try_send((topic, update.clone())) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => Self::drop_update(self.name, "queue_full"),
+            Err(TrySendError::Disconnected(_)) => Self::drop_update(self.name, "disconnected"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::output_sink::broker_sink::{
+        BrokerConnection, BrokerSink, RECONNECT_DELAY_SEC,
+    };
+    use crate::worker::network_helpers::output_sink::output_sink::{
+        IndexUpdate, OutputEvent, OutputSink,
+    };
+    use chrono::Utc;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// Connection, which fails to publish the first update
+    struct TestConnection {
+        connects: Arc<Mutex<usize>>,
+        published: Arc<Mutex<Vec<(String, f64)>>>,
+        keep_alives: Arc<Mutex<usize>>,
+        fail: bool,
+    }
+
+    impl BrokerConnection for TestConnection {
+        fn connect(&mut self) -> Result<(), String> {
+            *self.connects.lock().unwrap() += 1;
+            Ok(())
+        }
+
+        fn publish(&mut self, topic: &str, update: &IndexUpdate) -> Result<(), String> {
+            if self.fail {
+                self.fail = false;
+                return Err("Broken pipe".to_string());
+            }
+
+            self.published
+                .lock()
+                .unwrap()
+                .push((topic.to_string(), update.value));
+            Ok(())
+        }
+
+        fn keep_alive(&mut self) -> Result<(), String> {
+            *self.keep_alives.lock().unwrap() += 1;
+            Ok(())
+        }
+    }
+
+    fn make_update(coin: &str, value: f64) -> OutputEvent {
+        OutputEvent::CoinAveragePrice(IndexUpdate {
+            coin: coin.to_string(),
+            quote: "USD".to_string(),
+            value,
+            timestamp: Utc::now(),
+        })
+    }
+
+    /// Polls `condition` until it's true. Panics after 5 seconds.
+    fn wait_for<F: Fn() -> bool>(condition: F) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            assert!(Instant::now() < deadline, "Condition isn't met in time.");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_broker_sink() {
+        let connects = Arc::new(Mutex::new(0));
+        let published = Arc::new(Mutex::new(Vec::new()));
+        let keep_alives = Arc::new(Mutex::new(0));
+        let connection = TestConnection {
+            connects: Arc::clone(&connects),
+            published: Arc::clone(&published),
+            keep_alives: Arc::clone(&keep_alives),
+            fail: true,
+        };
+        let start = Instant::now();
+        let sink = BrokerSink::start("test", connection, "index.{coin}".to_string(), 10);
+
+        // The first update is lost with the connection, the next ones are dropped
+        // until reconnect delay has passed
+        sink.publish(&make_update("BTC", 100.0));
+        wait_for(|| *connects.lock().unwrap() == 1);
+        while published.lock().unwrap().is_empty() {
+            sink.publish(&make_update("ETH", 300.0));
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(start.elapsed() >= Duration::from_secs(RECONNECT_DELAY_SEC));
+        assert_eq!(*connects.lock().unwrap(), 2);
+        assert!(published
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|v| v == &("index.ETH".to_string(), 300.0)));
+
+        // Idle connection is kept alive
+        wait_for(|| *keep_alives.lock().unwrap() > 0);
+    }
+}
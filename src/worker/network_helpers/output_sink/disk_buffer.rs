@@ -0,0 +1,132 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Bounded persistent FIFO queue. Items are kept between restarts of the daemon.
+pub struct DiskBuffer {
+    db: vsdbsled::Db,
+    max_len: usize,
+    len: AtomicUsize,
+}
+
+impl DiskBuffer {
+    pub fn open(path: &str, max_len: usize) -> Result<Self, String> {
+        let db = vsdbsled::open(path).map_err(|e| e.to_string())?;
+        let len = AtomicUsize::new(db.len());
+
+        Ok(Self { db, max_len, len })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::SeqCst)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends item to the end of the queue. Returns `false` if the queue is full.
+    pub fn push(&self, item: &[u8]) -> Result<bool, String> {
+        if self.len() >= self.max_len {
+            return Ok(false);
+        }
+
+        // Ids are monotonic, so big-endian keys keep the order of items
+        let id = self.db.generate_id().map_err(|e| e.to_string())?;
+        self.db
+            .insert(id.to_be_bytes(), item)
+            .map_err(|e| e.to_string())?;
+        self.len.fetch_add(1, Ordering::SeqCst);
+
+        Ok(true)
+    }
+
+    /// Returns up to `count` first items of the queue with their ids. Items are kept in the queue.
+    pub fn peek(&self, count: usize) -> Result<Vec<(u64, Vec<u8>)>, String> {
+        self.db
+            .iter()
+            .take(count)
+            .map(|item| {
+                let (key, value) = item.map_err(|e| e.to_string())?;
+                let id = u64::from_be_bytes(key.as_ref().try_into().map_err(|_| "Wrong key")?);
+
+                Ok((id, value.to_vec()))
+            })
+            .collect()
+    }
+
+    /// Removes item by its id. Returns `false` if there is no such item.
+    pub fn remove(&self, id: u64) -> Result<bool, String> {
+        let item = self
+            .db
+            .remove(id.to_be_bytes())
+            .map_err(|e| e.to_string())?;
+        if item.is_some() {
+            self.len.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        Ok(item.is_some())
+    }
+
+    pub fn flush(&self) -> Result<(), String> {
+        self.db.flush().map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::output_sink::disk_buffer::DiskBuffer;
+    use std::fs;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_disk_buffer() {
+        let path = std::env::temp_dir().join("index_daemon_test_disk_buffer");
+        let _ = fs::remove_dir_all(&path);
+        let path = path.to_str().unwrap();
+
+        {
+            let buffer = DiskBuffer::open(path, 3).unwrap();
+            assert!(buffer.is_empty());
+
+            for item in ["a", "b", "c"] {
+                assert_eq!(buffer.push(item.as_bytes()), Ok(true));
+            }
+            assert_eq!(buffer.push(b"d"), Ok(false));
+
+            let items = buffer.peek(2).unwrap();
+            assert_eq!(
+                items.iter().map(|(_, v)| v.as_slice()).collect::<Vec<_>>(),
+                [b"a", b"b"]
+            );
+            assert_eq!(buffer.remove(items[0].0), Ok(true));
+            assert_eq!(buffer.remove(items[0].0), Ok(false));
+            assert_eq!(buffer.len(), 2);
+            buffer.flush().unwrap();
+        }
+
+        // Items are kept after reopening. Lock of the dropped db is released in the background.
+        let started = Instant::now();
+        let buffer = loop {
+            match DiskBuffer::open(path, 3) {
+                Ok(buffer) => break buffer,
+                Err(e) => assert!(started.elapsed() < Duration::from_secs(5), "{}", e),
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer.push(b"d"), Ok(true));
+        let items = buffer.peek(10).unwrap();
+        assert_eq!(
+            items.iter().map(|(_, v)| v.as_slice()).collect::<Vec<_>>(),
+            [b"b", b"c", b"d"]
+        );
+        for (id, _) in items {
+            assert_eq!(buffer.remove(id), Ok(true));
+        }
+        assert_eq!(buffer.peek(10), Ok(Vec::new()));
+        assert!(buffer.is_empty());
+
+        drop(buffer);
+        let _ = fs::remove_dir_all(path);
+    }
+}
@@ -0,0 +1,497 @@
+use crate::metrics::metrics::{
+    METRICS, OUTPUT_SINK_MESSAGES_DROPPED, OUTPUT_SINK_MESSAGES_PUBLISHED,
+};
+use crate::worker::network_helpers::output_sink::output_sink::{OutputEvent, OutputSink};
+use chrono::Utc;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const NAME: &str = "file";
+
+/// Written events are flushed to the file at least once per this time
+const FLUSH_INTERVAL_MS: u64 = 1000;
+
+/// Name of the current file (without extension). Rotated files get the time of rotation.
+const FILE_NAME: &str = "events";
+
+const CSV_HEADER: &str = "timestamp,event,exchange,coin,quote,price,volume,ask_sum,bid_sum";
+
+/// `exchange`, `price`, `volume`, `ask_sum`, `bid_sum` columns of a CSV line
+type CsvColumns<'a> = (
+    Option<&'a str>,
+    Option<f64>,
+    Option<f64>,
+    Option<f64>,
+    Option<f64>,
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSinkFormat {
+    JsonLines,
+    Csv,
+}
+
+impl FileSinkFormat {
+    fn get_extension(&self) -> &'static str {
+        match self {
+            Self::JsonLines => "jsonl",
+            Self::Csv => "csv",
+        }
+    }
+
+    fn get_csv_columns(event: &OutputEvent) -> CsvColumns<'_> {
+        match event {
+            OutputEvent::CoinAveragePrice(v) => (None, Some(v.value), None, None, None),
+            OutputEvent::CoinExchangePrice(v) => {
+                (Some(&v.exchange), Some(v.value), None, None, None)
+            }
+            OutputEvent::CoinExchangeVolume(v) => {
+                (Some(&v.exchange), None, Some(v.value), None, None)
+            }
+            OutputEvent::Trade(v) => (Some(&v.exchange), Some(v.price), Some(v.volume), None, None),
+            OutputEvent::Ticker(v) => (Some(&v.exchange), None, Some(v.volume), None, None),
+            OutputEvent::Book(v) => (
+                Some(&v.exchange),
+                None,
+                None,
+                Some(v.ask_sum),
+                Some(v.bid_sum),
+            ),
+        }
+    }
+
+    /// Formats event as a line (with line break)
+    fn format(&self, event: &OutputEvent) -> String {
+        match self {
+            Self::JsonLines => serde_json::to_string(event).unwrap() + "\n",
+            Self::Csv => {
+                let (exchange, price, volume, ask_sum, bid_sum) = Self::get_csv_columns(event);
+                let (coin, quote) = event.get_pair();
+                let number = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+
+                format!(
+                    "{},{},{},{},{},{},{},{},{}\n",
+                    event.get_timestamp().timestamp(),
+                    event.get_name(),
+                    exchange.unwrap_or_default(),
+                    coin,
+                    quote,
+                    number(price),
+                    number(volume),
+                    number(ask_sum),
+                    number(bid_sum),
+                )
+            }
+        }
+    }
+}
+
+impl FromStr for FileSinkFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsonl" => Ok(Self::JsonLines),
+            "csv" => Ok(Self::Csv),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileSinkConfig {
+    /// Directory of the files
+    pub path: String,
+    pub format: FileSinkFormat,
+    /// The file is rotated when it reaches this size
+    pub rotate_size_bytes: u64,
+    /// The file is rotated when it's written for this time (`None` - rotation by time is off)
+    pub rotate_interval_sec: Option<u64>,
+    /// Whether rotated files are compressed
+    pub gzip: bool,
+}
+
+impl FileSinkConfig {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            format: FileSinkFormat::JsonLines,
+            rotate_size_bytes: 100 * 1024 * 1024,
+            rotate_interval_sec: None,
+            gzip: false,
+        }
+    }
+
+    /// Starts writing thread of the sink
+    pub fn start(&self, queue_size: usize) -> Box<dyn OutputSink> {
+        Box::new(FileSink::start(self.clone(), queue_size))
+    }
+}
+
+/// The current file, which is rotated by size or time
+struct RotatingFile {
+    config: FileSinkConfig,
+    file: Option<BufWriter<File>>,
+    size: u64,
+    opened: Instant,
+}
+
+impl RotatingFile {
+    fn new(config: FileSinkConfig) -> Self {
+        Self {
+            config,
+            file: None,
+            size: 0,
+            opened: Instant::now(),
+        }
+    }
+
+    fn get_path(&self) -> PathBuf {
+        Path::new(&self.config.path)
+            .join(FILE_NAME)
+            .with_extension(self.config.format.get_extension())
+    }
+
+    /// Opens the current file for appending (a file, left from the previous run, is continued)
+    fn open(&mut self) -> io::Result<&mut BufWriter<File>> {
+        if self.file.is_none() {
+            fs::create_dir_all(&self.config.path)?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.get_path())?;
+            self.size = file.metadata()?.len();
+            self.opened = Instant::now();
+
+            let mut file = BufWriter::new(file);
+            if self.size == 0 && self.config.format == FileSinkFormat::Csv {
+                let header = format!("{}\n", CSV_HEADER);
+                file.write_all(header.as_bytes())?;
+                self.size += header.len() as u64;
+            }
+
+            self.file = Some(file);
+        }
+
+        Ok(self.file.as_mut().unwrap())
+    }
+
+    fn write(&mut self, event: &OutputEvent) -> io::Result<()> {
+        let line = self.config.format.format(event);
+        self.open()?.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+
+        if self.size >= self.config.rotate_size_bytes {
+            self.rotate()?;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        self.file.is_some()
+            && self
+                .config
+                .rotate_interval_sec
+                .is_some_and(|v| self.opened.elapsed() >= Duration::from_secs(v))
+    }
+
+    /// Renames the current file, so the next event is written to a new one
+    fn rotate(&mut self) -> io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+
+        let extension = self.config.format.get_extension();
+        let time = Utc::now().format("%Y%m%dT%H%M%S%.3f").to_string();
+        let mut rotated_path = self.get_path();
+        // Files may be rotated more often than once per millisecond
+        for i in 0.. {
+            let file_name = match i {
+                0 => format!("{}-{}.{}", FILE_NAME, time, extension),
+                i => format!("{}-{}-{}.{}", FILE_NAME, time, i, extension),
+            };
+            rotated_path.set_file_name(&file_name);
+
+            if !rotated_path.exists() && !rotated_path.with_file_name(file_name + ".gz").exists() {
+                break;
+            }
+        }
+        fs::rename(self.get_path(), &rotated_path)?;
+
+        if self.config.gzip {
+            // Compression doesn't delay writing of new events
+            thread::Builder::new()
+                .name("fn: compress_rotated_file".to_string())
+                .spawn(move || {
+                    if let Err(e) = Self::compress(&rotated_path) {
+                        error!("File {:?} compression error: {}", rotated_path, e);
+                    }
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the file with its gzipped copy
+    fn compress(path: &Path) -> io::Result<()> {
+        let mut gz_path = path.as_os_str().to_owned();
+        gz_path.push(".gz");
+
+        let mut encoder = GzEncoder::new(File::create(&gz_path)?, Compression::default());
+        io::copy(&mut File::open(path)?, &mut encoder)?;
+        encoder.finish()?;
+
+        fs::remove_file(path)
+    }
+}
+
+/// Output sink, which appends all the events to a rotating file in a separate thread.
+/// Events are dropped if the queue is full or the file can't be written.
+#[derive(Clone)]
+pub struct FileSink {
+    tx: SyncSender<OutputEvent>,
+}
+
+impl FileSink {
+    pub fn start(config: FileSinkConfig, queue_size: usize) -> Self {
+        let (tx, rx) = mpsc::sync_channel(queue_size);
+
+        thread::Builder::new()
+            .name(format!("fn: output_sink, sink: {}", NAME))
+            .spawn(move || Self::run(RotatingFile::new(config), rx))
+            .unwrap();
+
+        Self { tx }
+    }
+
+    fn drop_event(reason: &str) {
+        METRICS.inc(
+            OUTPUT_SINK_MESSAGES_DROPPED,
+            &[("sink", NAME), ("reason", reason)],
+        );
+    }
+
+    /// Function writes queued events. Function ends when all the senders are dropped.
+    fn run(mut file: RotatingFile, rx: Receiver<OutputEvent>) {
+        let mut last_flush = Instant::now();
+
+        loop {
+            match rx.recv_timeout(Duration::from_millis(FLUSH_INTERVAL_MS)) {
+                Ok(event) => match file.write(&event) {
+                    Ok(()) => METRICS.inc(OUTPUT_SINK_MESSAGES_PUBLISHED, &[("sink", NAME)]),
+                    Err(e) => {
+                        error!("Output sink {} write error: {}", NAME, e);
+                        Self::drop_event("write_error");
+                    }
+                },
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            if last_flush.elapsed() >= Duration::from_millis(FLUSH_INTERVAL_MS) {
+                last_flush = Instant::now();
+
+                let res = if file.is_expired() {
+                    file.rotate()
+                } else {
+                    file.flush()
+                };
+                if let Err(e) = res {
+                    error!("Output sink {} write error: {}", NAME, e);
+                }
+            }
+        }
+
+        let _ = file.flush();
+    }
+}
+
+impl OutputSink for FileSink {
+    fn publish(&self, event: &OutputEvent) {
+        match self.tx.try_send(event.clone()) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => Self::drop_event("queue_full"),
+            Err(TrySendError::Disconnected(_)) => Self::drop_event("disconnected"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::output_sink::file_sink::{
+        FileSinkConfig, FileSinkFormat, RotatingFile,
+    };
+    use crate::worker::network_helpers::output_sink::output_sink::{
+        Book, IndexUpdate, OutputEvent, Trade,
+    };
+    use chrono::{TimeZone, Utc};
+    use flate2::read::GzDecoder;
+    use std::fs::{self, File};
+    use std::io::Read;
+    use std::path::PathBuf;
+    use std::thread;
+    use std::time::Duration;
+
+    fn make_trade(price: f64) -> OutputEvent {
+        OutputEvent::Trade(Trade {
+            exchange: "binance".to_string(),
+            coin: "BTC".to_string(),
+            quote: "USD".to_string(),
+            price,
+            volume: 0.5,
+            timestamp: Utc.timestamp_opt(1700000000, 0).unwrap(),
+        })
+    }
+
+    fn make_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("index_daemon_test_file_sink_{}", name));
+        let _ = fs::remove_dir_all(&path);
+
+        path
+    }
+
+    /// Returns names of files in the directory (sorted)
+    fn read_dir(path: &PathBuf) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(path)
+            .unwrap()
+            .map(|v| v.unwrap().file_name().to_str().unwrap().to_string())
+            .collect();
+        names.sort();
+
+        names
+    }
+
+    #[test]
+    fn test_format() {
+        let index_update = OutputEvent::CoinAveragePrice(IndexUpdate {
+            coin: "BTC".to_string(),
+            quote: "USD".to_string(),
+            value: 100.0,
+            timestamp: Utc.timestamp_opt(1700000000, 0).unwrap(),
+        });
+        let book = OutputEvent::Book(Book {
+            exchange: "kraken".to_string(),
+            coin: "ETH".to_string(),
+            quote: "USD".to_string(),
+            ask_sum: 10.5,
+            bid_sum: 2000.0,
+            timestamp: Utc.timestamp_opt(1700000000, 0).unwrap(),
+        });
+
+        assert_eq!(
+            FileSinkFormat::JsonLines.format(&make_trade(100.0)),
+            "{\"event\":\"trade\",\"exchange\":\"binance\",\"coin\":\"BTC\",\"quote\":\"USD\",\"price\":100.0,\"volume\":0.5,\"timestamp\":1700000000}\n"
+        );
+        assert_eq!(
+            FileSinkFormat::Csv.format(&make_trade(100.0)),
+            "1700000000,trade,binance,BTC,USD,100,0.5,,\n"
+        );
+        assert_eq!(
+            FileSinkFormat::Csv.format(&index_update),
+            "1700000000,coin_average_price,,BTC,USD,100,,,\n"
+        );
+        assert_eq!(
+            FileSinkFormat::Csv.format(&book),
+            "1700000000,book,kraken,ETH,USD,,,10.5,2000\n"
+        );
+    }
+
+    #[test]
+    fn test_rotate_by_size() {
+        let path = make_dir("size");
+        let line_len = FileSinkFormat::Csv.format(&make_trade(100.0)).len() as u64;
+        let mut file = RotatingFile::new(FileSinkConfig {
+            format: FileSinkFormat::Csv,
+            // Header and two lines
+            rotate_size_bytes: 100 + 2 * line_len,
+            gzip: true,
+            ..FileSinkConfig::new(path.to_str().unwrap().to_string())
+        });
+
+        for price in [100.0, 200.0, 300.0, 400.0] {
+            file.write(&make_trade(price)).unwrap();
+        }
+        file.flush().unwrap();
+        // Compression is done in a separate thread
+        thread::sleep(Duration::from_millis(500));
+
+        let names = read_dir(&path);
+        assert_eq!(names.len(), 2);
+        assert!(names[0].starts_with("events-") && names[0].ends_with(".csv.gz"));
+        assert_eq!(names[1], "events.csv");
+
+        let mut rotated = String::new();
+        GzDecoder::new(File::open(path.join(&names[0])).unwrap())
+            .read_to_string(&mut rotated)
+            .unwrap();
+        assert_eq!(
+            rotated.lines().collect::<Vec<&str>>(),
+            vec![
+                "timestamp,event,exchange,coin,quote,price,volume,ask_sum,bid_sum",
+                "1700000000,trade,binance,BTC,USD,100,0.5,,",
+                "1700000000,trade,binance,BTC,USD,200,0.5,,",
+                "1700000000,trade,binance,BTC,USD,300,0.5,,",
+            ]
+        );
+
+        // The current file is continued after restart, the header isn't repeated
+        let mut file = RotatingFile::new(FileSinkConfig {
+            format: FileSinkFormat::Csv,
+            ..FileSinkConfig::new(path.to_str().unwrap().to_string())
+        });
+        file.write(&make_trade(500.0)).unwrap();
+        file.flush().unwrap();
+        assert_eq!(
+            fs::read_to_string(path.join("events.csv")).unwrap(),
+            "timestamp,event,exchange,coin,quote,price,volume,ask_sum,bid_sum\n\
+             1700000000,trade,binance,BTC,USD,400,0.5,,\n\
+             1700000000,trade,binance,BTC,USD,500,0.5,,\n"
+        );
+
+        let _ = fs::remove_dir_all(path);
+    }
+
+    #[test]
+    fn test_rotate_by_time() {
+        let path = make_dir("time");
+        let mut file = RotatingFile::new(FileSinkConfig {
+            rotate_interval_sec: Some(1),
+            ..FileSinkConfig::new(path.to_str().unwrap().to_string())
+        });
+
+        // File isn't rotated, when nothing is written
+        assert!(!file.is_expired());
+        file.write(&make_trade(100.0)).unwrap();
+        assert!(!file.is_expired());
+
+        thread::sleep(Duration::from_millis(1000));
+        assert!(file.is_expired());
+        file.rotate().unwrap();
+        assert!(!file.is_expired());
+
+        let names = read_dir(&path);
+        assert_eq!(names.len(), 1);
+        assert!(names[0].starts_with("events-") && names[0].ends_with(".jsonl"));
+        assert_eq!(
+            fs::read_to_string(path.join(&names[0])).unwrap(),
+            FileSinkFormat::JsonLines.format(&make_trade(100.0))
+        );
+
+        let _ = fs::remove_dir_all(path);
+    }
+}
@@ -0,0 +1,480 @@
+use crate::metrics::metrics::{
+    METRICS, OUTPUT_SINK_BUFFERED_MESSAGES, OUTPUT_SINK_MESSAGES_DROPPED,
+    OUTPUT_SINK_MESSAGES_PUBLISHED,
+};
+use crate::worker::network_helpers::output_sink::disk_buffer::DiskBuffer;
+use crate::worker::network_helpers::output_sink::output_sink::{OutputEvent, OutputSink};
+use rdkafka::client::ClientContext;
+use rdkafka::config::ClientConfig;
+use rdkafka::producer::{BaseProducer, BaseRecord, DeliveryResult, Producer, ProducerContext};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TryRecvError, TrySendError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const NAME: &str = "kafka";
+
+const POLL_INTERVAL_MS: u64 = 100;
+
+/// Max number of buffered messages, which are resent at once
+const REPLAY_BATCH_SIZE: usize = 1000;
+
+const FLUSH_TIMEOUT_SEC: u64 = 5;
+
+#[derive(Debug, Clone)]
+pub struct KafkaSinkConfig {
+    /// Bootstrap servers (comma separated list of host:port)
+    pub brokers: String,
+    /// Template of topic with `{event}`, `{coin}` and `{quote}` placeholders
+    pub topic_template: String,
+    /// Messages, which aren't delivered during this time, are moved to the disk buffer
+    pub message_timeout_ms: u64,
+    pub buffer_path: String,
+    pub buffer_max_messages: usize,
+}
+
+impl KafkaSinkConfig {
+    pub fn new(brokers: String) -> Self {
+        Self {
+            brokers,
+            topic_template: "index.{event}".to_string(),
+            message_timeout_ms: 10000,
+            buffer_path: "kafka_buffer".to_string(),
+            buffer_max_messages: 1000000,
+        }
+    }
+
+    /// Starts producing thread of the sink
+    pub fn start(&self, queue_size: usize) -> Box<dyn OutputSink> {
+        Box::new(KafkaSink::start(self, queue_size))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct KafkaMessage {
+    topic: String,
+    key: String,
+    payload: String,
+}
+
+/// Produced message with its id in the disk buffer, if it's resent from the buffer
+struct ProducedMessage {
+    message: KafkaMessage,
+    buffer_id: Option<u64>,
+}
+
+struct KafkaSinkContext {
+    buffer: DiskBuffer,
+    /// Whether the latest delivery succeeded (i.e. the broker is available)
+    delivering: AtomicBool,
+}
+
+impl KafkaSinkContext {
+    fn buffer_message(&self, message: &KafkaMessage) {
+        match self.buffer.push(&serde_json::to_vec(message).unwrap()) {
+            Ok(true) => METRICS.inc(OUTPUT_SINK_BUFFERED_MESSAGES, &[("sink", NAME)]),
+            Ok(false) => KafkaSink::drop_message("buffer_full"),
+            Err(e) => {
+                error!("Output sink {} buffer error: {}", NAME, e);
+                KafkaSink::drop_message("buffer_error");
+            }
+        }
+    }
+
+    fn get_buffered_messages(&self, count: usize) -> Vec<ProducedMessage> {
+        match self.buffer.peek(count) {
+            Ok(items) => items
+                .into_iter()
+                .filter_map(|(id, item)| {
+                    let message = serde_json::from_slice(&item).ok();
+                    if message.is_none() {
+                        // Unreadable message would block the buffer
+                        self.remove_buffered_message(id);
+                    }
+
+                    message.map(|message| ProducedMessage {
+                        message,
+                        buffer_id: Some(id),
+                    })
+                })
+                .collect(),
+            Err(e) => {
+                error!("Output sink {} buffer error: {}", NAME, e);
+                Vec::new()
+            }
+        }
+    }
+
+    fn remove_buffered_message(&self, id: u64) {
+        match self.buffer.remove(id) {
+            Ok(true) => METRICS.dec(OUTPUT_SINK_BUFFERED_MESSAGES, &[("sink", NAME)]),
+            Ok(false) => {}
+            Err(e) => error!("Output sink {} buffer error: {}", NAME, e),
+        }
+    }
+
+    /// Buffered messages stay in place, so they are resent in the original order
+    fn fail(&self, message: &ProducedMessage) {
+        if message.buffer_id.is_none() {
+            self.buffer_message(&message.message);
+        }
+    }
+}
+
+impl ClientContext for KafkaSinkContext {}
+
+impl ProducerContext for KafkaSinkContext {
+    type DeliveryOpaque = Box<ProducedMessage>;
+
+    fn delivery(&self, delivery_result: &DeliveryResult<'_>, message: Box<ProducedMessage>) {
+        match delivery_result {
+            Ok(_) => {
+                self.delivering.store(true, Ordering::SeqCst);
+                METRICS.inc(OUTPUT_SINK_MESSAGES_PUBLISHED, &[("sink", NAME)]);
+                if let Some(id) = message.buffer_id {
+                    self.remove_buffered_message(id);
+                }
+            }
+            Err((e, _)) => {
+                if self.delivering.swap(false, Ordering::SeqCst) {
+                    error!("Output sink {} delivery error: {}", NAME, e);
+                }
+                self.fail(&message);
+            }
+        }
+    }
+}
+
+/// Output sink, which produces index, exchange and trade events to Kafka in a separate thread.
+/// Messages are keyed by coin, so events of a coin are in the same partition.
+/// Messages, which aren't delivered, are kept in a disk buffer. While the buffer isn't empty,
+/// new messages are put after the buffered ones, so the buffer is resent in the original order.
+/// Delivery is at-least-once: a timed out message may be already written by the broker,
+/// so it's duplicated when resent.
+#[derive(Clone)]
+pub struct KafkaSink {
+    topic_template: String,
+    tx: SyncSender<KafkaMessage>,
+}
+
+impl KafkaSink {
+    pub fn start(config: &KafkaSinkConfig, queue_size: usize) -> Self {
+        let producer = Self::create_producer(config);
+        let (tx, rx) = mpsc::sync_channel(queue_size);
+
+        thread::Builder::new()
+            .name(format!("fn: output_sink, sink: {}", NAME))
+            .spawn(move || Self::run(producer, rx))
+            .unwrap();
+
+        Self {
+            topic_template: config.topic_template.clone(),
+            tx,
+        }
+    }
+
+    fn create_producer(config: &KafkaSinkConfig) -> BaseProducer<KafkaSinkContext> {
+        let buffer = DiskBuffer::open(&config.buffer_path, config.buffer_max_messages)
+            .expect("Open Kafka buffer error.");
+        METRICS.add(
+            OUTPUT_SINK_BUFFERED_MESSAGES,
+            &[("sink", NAME)],
+            buffer.len() as f64,
+        );
+
+        let context = KafkaSinkContext {
+            buffer,
+            // Buffered messages are resent one by one until the first delivery
+            delivering: AtomicBool::new(false),
+        };
+
+        ClientConfig::new()
+            .set("bootstrap.servers", &config.brokers)
+            // Internal retries neither duplicate nor reorder messages
+            .set("enable.idempotence", "true")
+            .set("acks", "all")
+            // Keys are mapped to partitions the same way as by Java clients
+            .set("partitioner", "murmur2_random")
+            .set("message.timeout.ms", config.message_timeout_ms.to_string())
+            .create_with_context(context)
+            .expect("Create Kafka producer error.")
+    }
+
+    fn drop_message(reason: &str) {
+        METRICS.inc(
+            OUTPUT_SINK_MESSAGES_DROPPED,
+            &[("sink", NAME), ("reason", reason)],
+        );
+    }
+
+    fn send(producer: &BaseProducer<KafkaSinkContext>, message: ProducedMessage) {
+        let KafkaMessage {
+            topic,
+            key,
+            payload,
+        } = message.message.clone();
+        let record = BaseRecord::with_opaque_to(&topic, Box::new(message))
+            .key(&key)
+            .payload(&payload);
+
+        // Producer's queue is full
+        if let Err((e, record)) = producer.send(record) {
+            error!("Output sink {} send error: {}", NAME, e);
+            producer.context().fail(&record.delivery_opaque);
+        }
+    }
+
+    /// Resends the first buffered messages. Only one message is resent until the broker
+    /// is available again.
+    fn replay(producer: &BaseProducer<KafkaSinkContext>) {
+        let context = producer.context();
+        let count = if context.delivering.load(Ordering::SeqCst) {
+            REPLAY_BATCH_SIZE
+        } else {
+            1
+        };
+
+        for message in context.get_buffered_messages(count) {
+            Self::send(producer, message);
+        }
+    }
+
+    /// Function produces queued messages and serves delivery reports.
+    /// Function ends when all the senders are dropped.
+    fn run(producer: BaseProducer<KafkaSinkContext>, rx: Receiver<KafkaMessage>) {
+        'produce: loop {
+            producer.poll(Duration::ZERO);
+
+            if producer.context().buffer.is_empty() {
+                match rx.recv_timeout(Duration::from_millis(POLL_INTERVAL_MS)) {
+                    Ok(message) => Self::send(
+                        &producer,
+                        ProducedMessage {
+                            message,
+                            buffer_id: None,
+                        },
+                    ),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                continue;
+            }
+
+            // Messages in flight may get to the buffer, so new messages wait for their reports
+            // and resent messages are reported before the next ones are taken from the buffer
+            if producer.in_flight_count() == 0 {
+                loop {
+                    match rx.try_recv() {
+                        Ok(message) => producer.context().buffer_message(&message),
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => break 'produce,
+                    }
+                }
+                Self::replay(&producer);
+            }
+
+            producer.poll(Duration::from_millis(POLL_INTERVAL_MS));
+        }
+
+        let _ = producer.flush(Duration::from_secs(FLUSH_TIMEOUT_SEC));
+
+        // Dropped producer purges undelivered messages, so they get to the buffer
+        let context = Arc::clone(producer.context());
+        drop(producer);
+        let _ = context.buffer.flush();
+    }
+}
+
+impl OutputSink for KafkaSink {
+    fn publish(&self, event: &OutputEvent) {
+        // Raw exchange data isn't produced
+        if matches!(event, OutputEvent::Ticker(..) | OutputEvent::Book(..)) {
+            return;
+        }
+
+        let message = KafkaMessage {
+            topic: event.make_topic(&self.topic_template),
+            key: event.get_pair().0.to_string(),
+            payload: serde_json::to_string(event).unwrap(),
+        };
+
+        match self.tx.try_send(message) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => Self::drop_message("queue_full"),
+            Err(TrySendError::Disconnected(_)) => Self::drop_message("disconnected"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::output_sink::kafka_sink::{KafkaSink, KafkaSinkConfig};
+    use crate::worker::network_helpers::output_sink::output_sink::{
+        IndexUpdate, OutputEvent, OutputSink,
+    };
+    use chrono::Utc;
+    use rdkafka::config::ClientConfig;
+    use rdkafka::consumer::{BaseConsumer, Consumer};
+    use rdkafka::mocking::MockCluster;
+    use rdkafka::producer::Producer;
+    use rdkafka::{Message, Offset, TopicPartitionList};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    const TOPIC: &str = "index.coin_average_price";
+    const PARTITIONS: i32 = 3;
+
+    fn make_consumer(bootstrap_servers: &str) -> BaseConsumer {
+        let consumer: BaseConsumer = ClientConfig::new()
+            .set("bootstrap.servers", bootstrap_servers)
+            .set("group.id", "test")
+            .create()
+            .unwrap();
+        let mut partitions = TopicPartitionList::new();
+        for partition in 0..PARTITIONS {
+            partitions
+                .add_partition_offset(TOPIC, partition, Offset::Beginning)
+                .unwrap();
+        }
+        consumer.assign(&partitions).unwrap();
+
+        consumer
+    }
+
+    /// Returns partition, key and value of consumed messages
+    fn consume(
+        consumer: &BaseConsumer,
+        count: usize,
+        timeout: Duration,
+    ) -> Vec<(i32, String, f64)> {
+        let mut messages = Vec::new();
+        let started = Instant::now();
+        while messages.len() < count && started.elapsed() < timeout {
+            if let Some(Ok(message)) = consumer.poll(Duration::from_millis(100)) {
+                let key = String::from_utf8(message.key().unwrap().to_vec()).unwrap();
+                let payload: serde_json::Value =
+                    serde_json::from_slice(message.payload().unwrap()).unwrap();
+                assert_eq!(payload["event"], "coin_average_price");
+                assert_eq!(payload["coin"], key);
+
+                messages.push((message.partition(), key, payload["value"].as_f64().unwrap()));
+            }
+        }
+
+        messages
+    }
+
+    fn make_event(coin: &str, value: f64) -> OutputEvent {
+        OutputEvent::CoinAveragePrice(IndexUpdate {
+            coin: coin.to_string(),
+            quote: "USD".to_string(),
+            value,
+            timestamp: Utc::now(),
+        })
+    }
+
+    fn make_config(cluster: &MockCluster<'static, impl rdkafka::ClientContext>) -> KafkaSinkConfig {
+        let buffer_path = std::env::temp_dir().join(format!(
+            "index_daemon_test_kafka_buffer_{}",
+            uuid::Uuid::new_v4().to_simple()
+        ));
+
+        KafkaSinkConfig {
+            message_timeout_ms: 1000,
+            buffer_path: buffer_path.to_str().unwrap().to_string(),
+            ..KafkaSinkConfig::new(cluster.bootstrap_servers())
+        }
+    }
+
+    #[test]
+    fn test_kafka_sink() {
+        let cluster = MockCluster::new(1).unwrap();
+        cluster.create_topic(TOPIC, PARTITIONS, 1).unwrap();
+        let config = make_config(&cluster);
+        let sink = config.start(100);
+
+        for value in 1..=10 {
+            for coin in ["BTC", "ETH", "XRP", "LTC"] {
+                sink.publish(&make_event(coin, value as f64));
+            }
+        }
+
+        let consumer = make_consumer(&cluster.bootstrap_servers());
+        let messages = consume(&consumer, 40, Duration::from_secs(10));
+        assert_eq!(messages.len(), 40);
+
+        // Messages of a coin are in the same partition in the original order
+        let mut partitions: HashMap<String, i32> = HashMap::new();
+        let mut values: HashMap<String, Vec<f64>> = HashMap::new();
+        for (partition, key, value) in messages {
+            assert_eq!(
+                *partitions.entry(key.clone()).or_insert(partition),
+                partition
+            );
+            values.entry(key).or_default().push(value);
+        }
+        for coin_values in values.values() {
+            assert_eq!(
+                *coin_values,
+                (1..=10).map(|v| v as f64).collect::<Vec<f64>>()
+            );
+        }
+
+        let _ = fs::remove_dir_all(config.buffer_path);
+    }
+
+    /// Waits until the condition is met
+    fn wait_for(condition: impl Fn() -> bool, timeout: Duration) {
+        let started = Instant::now();
+        while !condition() {
+            assert!(started.elapsed() < timeout, "Condition isn't met in time");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_kafka_sink_buffer() {
+        let cluster = MockCluster::new(1).unwrap();
+        cluster.create_topic(TOPIC, PARTITIONS, 1).unwrap();
+        let config = make_config(&cluster);
+
+        let producer = KafkaSink::create_producer(&config);
+        let context = Arc::clone(producer.context());
+        let (tx, rx) = mpsc::sync_channel(100);
+        let thread = thread::spawn(move || KafkaSink::run(producer, rx));
+        let sink = KafkaSink {
+            topic_template: config.topic_template.clone(),
+            tx,
+        };
+
+        let consumer = make_consumer(&cluster.bootstrap_servers());
+        sink.publish(&make_event("BTC", 100.0));
+        assert_eq!(consume(&consumer, 1, Duration::from_secs(10)).len(), 1);
+
+        // Message isn't delivered during the timeout, so it's moved to the buffer
+        cluster.broker_down(1).unwrap();
+        sink.publish(&make_event("BTC", 200.0));
+        wait_for(|| !context.buffer.is_empty(), Duration::from_secs(10));
+
+        // New messages are put after the buffered one
+        sink.publish(&make_event("BTC", 300.0));
+        sink.publish(&make_event("BTC", 400.0));
+        cluster.broker_up(1).unwrap();
+
+        let values: Vec<f64> = consume(&consumer, 3, Duration::from_secs(30))
+            .into_iter()
+            .map(|(_, _, value)| value)
+            .collect();
+        assert_eq!(values, [200.0, 300.0, 400.0]);
+        wait_for(|| context.buffer.is_empty(), Duration::from_secs(10));
+
+        drop(sink);
+        thread.join().unwrap();
+        drop(context);
+        let _ = fs::remove_dir_all(config.buffer_path);
+    }
+}
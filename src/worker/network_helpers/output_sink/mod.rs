@@ -0,0 +1,8 @@
+pub mod broker_sink;
+pub mod disk_buffer;
+pub mod file_sink;
+pub mod kafka_sink;
+pub mod mqtt_connection;
+pub mod nats_connection;
+pub mod output_sink;
+pub mod redis_stream_connection;
@@ -0,0 +1,242 @@
+use crate::worker::network_helpers::output_sink::broker_sink::BrokerConnection;
+use crate::worker::network_helpers::output_sink::output_sink::IndexUpdate;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+const TIMEOUT_SEC: u64 = 5;
+/// Broker closes the connection, if no packet is received during 1.5 of this time
+const KEEP_ALIVE_SEC: u16 = 60;
+
+const PACKET_CONNECT: u8 = 0x10;
+const PACKET_CONNACK: u8 = 0x20;
+/// QoS 0, not retained
+const PACKET_PUBLISH: u8 = 0x30;
+const PACKET_PINGREQ: u8 = 0xc0;
+const PACKET_PINGRESP: u8 = 0xd0;
+
+/// Publishing connection to MQTT broker (MQTT 3.1.1, QoS 0)
+pub struct MqttConnection {
+    addr: String,
+    client_id: String,
+    stream: Option<TcpStream>,
+    /// Time of the last packet, sent to the broker
+    last_sent: Instant,
+}
+
+impl MqttConnection {
+    pub fn new(addr: String) -> Self {
+        // Brokers must accept client ids up to 23 characters
+        let client_id = format!(
+            "index-daemon-{}",
+            &Uuid::new_v4().to_simple().to_string()[..8]
+        );
+
+        Self {
+            addr,
+            client_id,
+            stream: None,
+            last_sent: Instant::now(),
+        }
+    }
+
+    fn encode_remaining_length(mut length: usize, buf: &mut Vec<u8>) {
+        loop {
+            let mut byte = (length % 128) as u8;
+            length /= 128;
+            if length > 0 {
+                byte |= 0x80;
+            }
+            buf.push(byte);
+
+            if length == 0 {
+                break;
+            }
+        }
+    }
+
+    fn encode_string(value: &str, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        buf.extend_from_slice(value.as_bytes());
+    }
+
+    fn make_packet(packet_type: u8, body: Vec<u8>) -> Vec<u8> {
+        let mut packet = vec![packet_type];
+        Self::encode_remaining_length(body.len(), &mut packet);
+        packet.extend(body);
+
+        packet
+    }
+
+    fn make_connect_packet(client_id: &str) -> Vec<u8> {
+        let mut body = Vec::new();
+        Self::encode_string("MQTT", &mut body);
+        // Protocol level (3.1.1), clean session flag
+        body.extend_from_slice(&[4, 0x02]);
+        body.extend_from_slice(&KEEP_ALIVE_SEC.to_be_bytes());
+        Self::encode_string(client_id, &mut body);
+
+        Self::make_packet(PACKET_CONNECT, body)
+    }
+
+    fn make_publish_packet(topic: &str, payload: &[u8]) -> Vec<u8> {
+        let mut body = Vec::new();
+        Self::encode_string(topic, &mut body);
+        body.extend_from_slice(payload);
+
+        Self::make_packet(PACKET_PUBLISH, body)
+    }
+}
+
+impl BrokerConnection for MqttConnection {
+    fn connect(&mut self) -> Result<(), String> {
+        let mut stream = TcpStream::connect(&self.addr).map_err(|e| e.to_string())?;
+        let timeout = Some(Duration::from_secs(TIMEOUT_SEC));
+        stream
+            .set_read_timeout(timeout)
+            .map_err(|e| e.to_string())?;
+        stream
+            .set_write_timeout(timeout)
+            .map_err(|e| e.to_string())?;
+
+        stream
+            .write_all(&Self::make_connect_packet(&self.client_id))
+            .map_err(|e| e.to_string())?;
+
+        let mut connack = [0; 4];
+        stream.read_exact(&mut connack).map_err(|e| e.to_string())?;
+        if connack[0] != PACKET_CONNACK {
+            return Err(format!("Unexpected packet: {}", connack[0]));
+        }
+        if connack[3] != 0 {
+            return Err(format!("Connection refused, return code: {}", connack[3]));
+        }
+
+        self.stream = Some(stream);
+        self.last_sent = Instant::now();
+
+        Ok(())
+    }
+
+    fn publish(&mut self, topic: &str, update: &IndexUpdate) -> Result<(), String> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or_else(|| "Not connected.".to_string())?;
+        let payload = serde_json::to_vec(update).unwrap();
+
+        stream
+            .write_all(&Self::make_publish_packet(topic, &payload))
+            .map_err(|e| e.to_string())?;
+        self.last_sent = Instant::now();
+
+        Ok(())
+    }
+
+    /// Sends ping, if nothing is sent during a half of keep alive time (published messages
+    /// keep the connection alive too)
+    fn keep_alive(&mut self) -> Result<(), String> {
+        if self.last_sent.elapsed() < Duration::from_secs(KEEP_ALIVE_SEC as u64 / 2) {
+            return Ok(());
+        }
+
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or_else(|| "Not connected.".to_string())?;
+        stream
+            .write_all(&Self::make_packet(PACKET_PINGREQ, Vec::new()))
+            .map_err(|e| e.to_string())?;
+
+        // Nothing else is sent by the broker to a client without subscriptions
+        let mut pingresp = [0; 2];
+        stream
+            .read_exact(&mut pingresp)
+            .map_err(|e| e.to_string())?;
+        if pingresp[0] != PACKET_PINGRESP {
+            return Err(format!("Unexpected packet: {}", pingresp[0]));
+        }
+        self.last_sent = Instant::now();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::output_sink::broker_sink::BrokerConnection;
+    use crate::worker::network_helpers::output_sink::mqtt_connection::{
+        MqttConnection, KEEP_ALIVE_SEC,
+    };
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_encode_remaining_length() {
+        for (length, expected) in [
+            (0, vec![0x00]),
+            (127, vec![0x7f]),
+            (128, vec![0x80, 0x01]),
+            (16383, vec![0xff, 0x7f]),
+            (16384, vec![0x80, 0x80, 0x01]),
+        ] {
+            let mut buf = Vec::new();
+            MqttConnection::encode_remaining_length(length, &mut buf);
+
+            assert_eq!(buf, expected);
+        }
+    }
+
+    #[test]
+    fn test_make_packets() {
+        assert_eq!(
+            MqttConnection::make_connect_packet("id"),
+            b"\x10\x0e\x00\x04MQTT\x04\x02\x00\x3c\x00\x02id"
+        );
+        assert_eq!(
+            MqttConnection::make_publish_packet("a/b", b"{}"),
+            b"\x30\x07\x00\x03a/b{}"
+        );
+        assert!(
+            MqttConnection::new("127.0.0.1:1883".to_string())
+                .client_id
+                .len()
+                <= 23
+        );
+    }
+
+    #[test]
+    fn test_keep_alive() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let broker = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // Packets are short, so remaining length is one byte
+            let mut header = [0; 2];
+            stream.read_exact(&mut header).unwrap();
+            let mut connect = vec![0; header[1] as usize];
+            stream.read_exact(&mut connect).unwrap();
+            stream.write_all(&[0x20, 0x02, 0x00, 0x00]).unwrap();
+
+            let mut pingreq = [0; 2];
+            stream.read_exact(&mut pingreq).unwrap();
+            stream.write_all(&[0xd0, 0x00]).unwrap();
+
+            pingreq
+        });
+
+        let mut connection = MqttConnection::new(addr);
+        connection.connect().unwrap();
+
+        // Ping isn't needed right after the connect packet
+        assert!(connection.keep_alive().is_ok());
+
+        connection.last_sent = Instant::now() - Duration::from_secs(KEEP_ALIVE_SEC as u64);
+        assert!(connection.keep_alive().is_ok());
+        assert_eq!(broker.join().unwrap(), [0xc0, 0x00]);
+        assert!(connection.last_sent.elapsed() < Duration::from_secs(KEEP_ALIVE_SEC as u64));
+    }
+}
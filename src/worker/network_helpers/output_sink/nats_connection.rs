@@ -0,0 +1,180 @@
+use crate::worker::network_helpers::output_sink::broker_sink::BrokerConnection;
+use crate::worker::network_helpers::output_sink::output_sink::IndexUpdate;
+use serde_json::json;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const TIMEOUT_SEC: u64 = 5;
+
+/// Publishing connection to NATS server (core NATS text protocol)
+pub struct NatsConnection {
+    addr: String,
+    stream: Option<TcpStream>,
+    /// Received data, which isn't processed yet
+    incoming: Vec<u8>,
+}
+
+impl NatsConnection {
+    pub fn new(addr: String) -> Self {
+        Self {
+            addr,
+            stream: None,
+            incoming: Vec::new(),
+        }
+    }
+
+    fn make_connect_command() -> Vec<u8> {
+        let options = json!({
+            "verbose": false,
+            "pedantic": false,
+            "name": "index-daemon",
+            "lang": "rust",
+            "version": env!("CARGO_PKG_VERSION"),
+        });
+
+        format!("CONNECT {}\r\nPING\r\n", options).into_bytes()
+    }
+
+    fn make_pub_command(subject: &str, payload: &[u8]) -> Vec<u8> {
+        let mut command = format!("PUB {} {}\r\n", subject, payload.len()).into_bytes();
+        command.extend_from_slice(payload);
+        command.extend_from_slice(b"\r\n");
+
+        command
+    }
+
+    /// Removes the first complete line from `incoming`
+    fn take_line(incoming: &mut Vec<u8>) -> Option<String> {
+        let position = incoming.windows(2).position(|v| v == b"\r\n")?;
+        let line = String::from_utf8_lossy(&incoming[..position]).to_string();
+        incoming.drain(..position + 2);
+
+        Some(line)
+    }
+
+    fn get_stream(&mut self) -> Result<&mut TcpStream, String> {
+        self.stream
+            .as_mut()
+            .ok_or_else(|| "Not connected.".to_string())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        self.get_stream()?
+            .write_all(data)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Reads available data. Waits for data if `blocking`.
+    fn read(&mut self, blocking: bool) -> Result<(), String> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or_else(|| "Not connected.".to_string())?;
+        let mut buf = [0; 4096];
+
+        stream
+            .set_nonblocking(!blocking)
+            .map_err(|e| e.to_string())?;
+        let res = loop {
+            match stream.read(&mut buf) {
+                Ok(0) => break Err("Connection is closed.".to_string()),
+                Ok(n) => {
+                    self.incoming.extend_from_slice(&buf[..n]);
+                    if blocking {
+                        break Ok(());
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock && !blocking => break Ok(()),
+                Err(e) => break Err(e.to_string()),
+            }
+        };
+        let _ = stream.set_nonblocking(false);
+
+        res
+    }
+
+    /// Handles complete lines of received data: answers pings, returns server errors.
+    /// Returns whether `PONG` was received.
+    fn handle_incoming(&mut self) -> Result<bool, String> {
+        let mut pong = false;
+
+        while let Some(line) = Self::take_line(&mut self.incoming) {
+            if line.starts_with("-ERR") {
+                return Err(format!("Server error: {}", line));
+            }
+
+            match line.as_str() {
+                "PING" => self.write(b"PONG\r\n")?,
+                "PONG" => pong = true,
+                // `INFO` and `+OK`
+                _ => {}
+            }
+        }
+
+        Ok(pong)
+    }
+}
+
+impl BrokerConnection for NatsConnection {
+    fn connect(&mut self) -> Result<(), String> {
+        let stream = TcpStream::connect(&self.addr).map_err(|e| e.to_string())?;
+        let timeout = Some(Duration::from_secs(TIMEOUT_SEC));
+        stream
+            .set_read_timeout(timeout)
+            .map_err(|e| e.to_string())?;
+        stream
+            .set_write_timeout(timeout)
+            .map_err(|e| e.to_string())?;
+
+        self.stream = Some(stream);
+        self.incoming.clear();
+
+        // Connection is established, when server answers the ping after `CONNECT`
+        self.write(&Self::make_connect_command())?;
+        loop {
+            self.read(true)?;
+            if self.handle_incoming()? {
+                return Ok(());
+            }
+        }
+    }
+
+    fn publish(&mut self, topic: &str, update: &IndexUpdate) -> Result<(), String> {
+        // Server closes the connection, if its pings aren't answered
+        self.read(false)?;
+        self.handle_incoming()?;
+
+        let payload = serde_json::to_vec(update).unwrap();
+        self.write(&Self::make_pub_command(topic, &payload))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::output_sink::nats_connection::NatsConnection;
+
+    #[test]
+    fn test_make_pub_command() {
+        assert_eq!(
+            NatsConnection::make_pub_command("index.BTC.USD", b"{}"),
+            b"PUB index.BTC.USD 2\r\n{}\r\n"
+        );
+    }
+
+    #[test]
+    fn test_take_line() {
+        let mut incoming = b"INFO {}\r\nPING\r\nPO".to_vec();
+
+        assert_eq!(
+            NatsConnection::take_line(&mut incoming),
+            Some("INFO {}".to_string())
+        );
+        assert_eq!(
+            NatsConnection::take_line(&mut incoming),
+            Some("PING".to_string())
+        );
+        assert_eq!(NatsConnection::take_line(&mut incoming), None);
+        assert_eq!(incoming, b"PO");
+    }
+}
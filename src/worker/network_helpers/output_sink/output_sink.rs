@@ -0,0 +1,233 @@
+use crate::worker::network_helpers::output_sink::broker_sink::BrokerSink;
+use crate::worker::network_helpers::output_sink::mqtt_connection::MqttConnection;
+use crate::worker::network_helpers::output_sink::nats_connection::NatsConnection;
+use crate::worker::network_helpers::output_sink::redis_stream_connection::RedisStreamConnection;
+use crate::worker::network_helpers::ws_server::ser_date_into_timestamp;
+use chrono::{DateTime, Utc};
+use dyn_clone::{clone_trait_object, DynClone};
+
+/// Receiver of index events. Sinks skip kinds of events they don't publish.
+/// It's invoked on every new value, so it must not block.
+pub trait OutputSink: DynClone + Send {
+    fn publish(&self, event: &OutputEvent);
+}
+
+clone_trait_object!(OutputSink);
+
+pub type OutputSinks = Vec<Box<dyn OutputSink>>;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct IndexUpdate {
+    pub coin: String,
+    pub quote: String,
+    pub value: f64,
+    #[serde(with = "ser_date_into_timestamp")]
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ExchangeUpdate {
+    pub exchange: String,
+    pub coin: String,
+    pub quote: String,
+    pub value: f64,
+    #[serde(with = "ser_date_into_timestamp")]
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Trade {
+    pub exchange: String,
+    pub coin: String,
+    pub quote: String,
+    pub price: f64,
+    pub volume: f64,
+    #[serde(with = "ser_date_into_timestamp")]
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Ticker {
+    pub exchange: String,
+    pub coin: String,
+    pub quote: String,
+    pub volume: f64,
+    #[serde(with = "ser_date_into_timestamp")]
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Summary of order book: total size of asks and total cost of bids
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Book {
+    pub exchange: String,
+    pub coin: String,
+    pub quote: String,
+    pub ask_sum: f64,
+    pub bid_sum: f64,
+    #[serde(with = "ser_date_into_timestamp")]
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Event, published to output sinks. Ticker and book are raw (normalized) exchange data.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum OutputEvent {
+    CoinAveragePrice(IndexUpdate),
+    CoinExchangePrice(ExchangeUpdate),
+    CoinExchangeVolume(ExchangeUpdate),
+    Trade(Trade),
+    Ticker(Ticker),
+    Book(Book),
+}
+
+impl OutputEvent {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::CoinAveragePrice(..) => "coin_average_price",
+            Self::CoinExchangePrice(..) => "coin_exchange_price",
+            Self::CoinExchangeVolume(..) => "coin_exchange_volume",
+            Self::Trade(..) => "trade",
+            Self::Ticker(..) => "ticker",
+            Self::Book(..) => "book",
+        }
+    }
+
+    pub fn get_pair(&self) -> (&str, &str) {
+        match self {
+            Self::CoinAveragePrice(v) => (&v.coin, &v.quote),
+            Self::CoinExchangePrice(v) | Self::CoinExchangeVolume(v) => (&v.coin, &v.quote),
+            Self::Trade(v) => (&v.coin, &v.quote),
+            Self::Ticker(v) => (&v.coin, &v.quote),
+            Self::Book(v) => (&v.coin, &v.quote),
+        }
+    }
+
+    pub fn get_timestamp(&self) -> DateTime<Utc> {
+        match self {
+            Self::CoinAveragePrice(v) => v.timestamp,
+            Self::CoinExchangePrice(v) | Self::CoinExchangeVolume(v) => v.timestamp,
+            Self::Trade(v) => v.timestamp,
+            Self::Ticker(v) => v.timestamp,
+            Self::Book(v) => v.timestamp,
+        }
+    }
+
+    /// Makes topic (subject, stream key) from a template with `{event}`, `{coin}` and `{quote}` placeholders
+    pub fn make_topic(&self, template: &str) -> String {
+        let (coin, quote) = self.get_pair();
+
+        template
+            .replace("{event}", self.get_name())
+            .replace("{coin}", coin)
+            .replace("{quote}", quote)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputSinkKind {
+    Nats,
+    Mqtt,
+    RedisStream,
+}
+
+impl OutputSinkKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Nats => "nats",
+            Self::Mqtt => "mqtt",
+            Self::RedisStream => "redis",
+        }
+    }
+
+    pub fn get_default_topic_template(&self) -> &'static str {
+        match self {
+            Self::Nats | Self::RedisStream => "index.{coin}.{quote}",
+            // Levels of MQTT topics are separated by slashes
+            Self::Mqtt => "index/{coin}/{quote}",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OutputSinkConfig {
+    pub kind: OutputSinkKind,
+    /// Broker address (host:port)
+    pub addr: String,
+    /// Template of topic (subject, stream key) with `{coin}` and `{quote}` placeholders
+    pub topic_template: String,
+}
+
+impl OutputSinkConfig {
+    /// Starts publishing thread of the sink
+    pub fn start(&self, queue_size: usize) -> Box<dyn OutputSink> {
+        let name = self.kind.as_str();
+        let addr = self.addr.clone();
+        let topic_template = self.topic_template.clone();
+
+        let sink = match self.kind {
+            OutputSinkKind::Nats => {
+                BrokerSink::start(name, NatsConnection::new(addr), topic_template, queue_size)
+            }
+            OutputSinkKind::Mqtt => {
+                BrokerSink::start(name, MqttConnection::new(addr), topic_template, queue_size)
+            }
+            OutputSinkKind::RedisStream => BrokerSink::start(
+                name,
+                RedisStreamConnection::new(addr),
+                topic_template,
+                queue_size,
+            ),
+        };
+
+        Box::new(sink)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::output_sink::output_sink::{
+        IndexUpdate, OutputEvent, OutputSinkKind, Trade,
+    };
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_make_topic() {
+        let update = OutputEvent::CoinAveragePrice(IndexUpdate {
+            coin: "BTC".to_string(),
+            quote: "USD".to_string(),
+            value: 100.0,
+            timestamp: Utc.timestamp_opt(1700000000, 0).unwrap(),
+        });
+
+        assert_eq!(
+            update.make_topic(OutputSinkKind::Nats.get_default_topic_template()),
+            "index.BTC.USD"
+        );
+        assert_eq!(
+            update.make_topic(OutputSinkKind::Mqtt.get_default_topic_template()),
+            "index/BTC/USD"
+        );
+        assert_eq!(update.make_topic("prices:{coin}"), "prices:BTC");
+        assert_eq!(
+            update.make_topic("index.{event}"),
+            "index.coin_average_price"
+        );
+    }
+
+    #[test]
+    fn test_serialize_event() {
+        let trade = OutputEvent::Trade(Trade {
+            exchange: "binance".to_string(),
+            coin: "BTC".to_string(),
+            quote: "USD".to_string(),
+            price: 100.0,
+            volume: 0.5,
+            timestamp: Utc.timestamp_opt(1700000000, 0).unwrap(),
+        });
+
+        assert_eq!(
+            serde_json::to_string(&trade).unwrap(),
+            r#"{"event":"trade","exchange":"binance","coin":"BTC","quote":"USD","price":100.0,"volume":0.5,"timestamp":1700000000}"#
+        );
+    }
+}
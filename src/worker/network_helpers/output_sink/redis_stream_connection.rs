@@ -0,0 +1,142 @@
+use crate::worker::network_helpers::output_sink::broker_sink::BrokerConnection;
+use crate::worker::network_helpers::output_sink::output_sink::IndexUpdate;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const TIMEOUT_SEC: u64 = 5;
+
+/// Approximate max number of entries of a stream (older entries are trimmed)
+const STREAM_MAX_LEN: &str = "100000";
+
+/// Publishing connection to Redis. Updates are appended to streams (`XADD`).
+pub struct RedisStreamConnection {
+    addr: String,
+    reader: Option<BufReader<TcpStream>>,
+}
+
+impl RedisStreamConnection {
+    pub fn new(addr: String) -> Self {
+        Self { addr, reader: None }
+    }
+
+    /// Encodes command as RESP array of bulk strings
+    fn make_command(args: &[&str]) -> Vec<u8> {
+        let mut command = format!("*{}\r\n", args.len());
+        for arg in args {
+            command += &format!("${}\r\n{}\r\n", arg.len(), arg);
+        }
+
+        command.into_bytes()
+    }
+
+    /// Reads reply of a command. Returns error message if it's an error reply.
+    fn read_reply<R: BufRead>(reader: &mut R) -> Result<(), String> {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            return Err("Connection is closed.".to_string());
+        }
+        let line = line.trim_end();
+
+        match line.chars().next() {
+            Some('-') => Err(line[1..].to_string()),
+            Some('$') => {
+                let length: i64 = line[1..]
+                    .parse()
+                    .map_err(|_| format!("Wrong reply: {}", line))?;
+
+                // Bulk string is followed by CRLF. Null bulk string has length -1.
+                if length >= 0 {
+                    let mut value = vec![0; length as usize + 2];
+                    reader.read_exact(&mut value).map_err(|e| e.to_string())?;
+                }
+
+                Ok(())
+            }
+            Some('+') | Some(':') => Ok(()),
+            _ => Err(format!("Wrong reply: {}", line)),
+        }
+    }
+
+    fn execute(&mut self, args: &[&str]) -> Result<(), String> {
+        let reader = self
+            .reader
+            .as_mut()
+            .ok_or_else(|| "Not connected.".to_string())?;
+
+        reader
+            .get_mut()
+            .write_all(&Self::make_command(args))
+            .map_err(|e| e.to_string())?;
+
+        Self::read_reply(reader)
+    }
+}
+
+impl BrokerConnection for RedisStreamConnection {
+    fn connect(&mut self) -> Result<(), String> {
+        let stream = TcpStream::connect(&self.addr).map_err(|e| e.to_string())?;
+        let timeout = Some(Duration::from_secs(TIMEOUT_SEC));
+        stream
+            .set_read_timeout(timeout)
+            .map_err(|e| e.to_string())?;
+        stream
+            .set_write_timeout(timeout)
+            .map_err(|e| e.to_string())?;
+
+        self.reader = Some(BufReader::new(stream));
+
+        self.execute(&["PING"])
+    }
+
+    fn publish(&mut self, topic: &str, update: &IndexUpdate) -> Result<(), String> {
+        let value = update.value.to_string();
+        let timestamp = update.timestamp.timestamp().to_string();
+
+        self.execute(&[
+            "XADD",
+            topic,
+            "MAXLEN",
+            "~",
+            STREAM_MAX_LEN,
+            "*",
+            "coin",
+            &update.coin,
+            "quote",
+            &update.quote,
+            "value",
+            &value,
+            "timestamp",
+            &timestamp,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::output_sink::redis_stream_connection::RedisStreamConnection;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_make_command() {
+        assert_eq!(
+            RedisStreamConnection::make_command(&["XADD", "index.BTC.USD"]),
+            b"*2\r\n$4\r\nXADD\r\n$13\r\nindex.BTC.USD\r\n"
+        );
+    }
+
+    #[test]
+    fn test_read_reply() {
+        let mut reader = Cursor::new(b"$15\r\n1526919030474-0\r\n+PONG\r\n$-1\r\n".to_vec());
+        assert!(RedisStreamConnection::read_reply(&mut reader).is_ok());
+        assert!(RedisStreamConnection::read_reply(&mut reader).is_ok());
+        assert!(RedisStreamConnection::read_reply(&mut reader).is_ok());
+        assert!(RedisStreamConnection::read_reply(&mut reader).is_err());
+
+        let mut reader = Cursor::new(b"-ERR wrong number of arguments\r\n".to_vec());
+        assert_eq!(
+            RedisStreamConnection::read_reply(&mut reader),
+            Err("ERR wrong number of arguments".to_string())
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::metrics::metrics::{METRICS, WS_CLIENT_MESSAGES_RECEIVED};
 use async_std::task;
 use async_tungstenite::async_std::connect_async;
 use async_tungstenite::tungstenite::protocol::Message;
@@ -17,7 +18,9 @@ where
 {
     uri: String,
     on_open_msg: Option<String>,
+    market_name: String,
     pair: String,
+    channel: String,
     callback: F,
 }
 
@@ -25,11 +28,20 @@ impl<F> WsClient<F>
 where
     F: Fn(String, String),
 {
-    pub fn new(uri: String, on_open_msg: Option<String>, pair: String, callback: F) -> Self {
+    pub fn new(
+        uri: String,
+        on_open_msg: Option<String>,
+        market_name: String,
+        pair: String,
+        channel: String,
+        callback: F,
+    ) -> Self {
         Self {
             uri,
             on_open_msg,
+            market_name,
             pair,
+            channel,
             callback,
         }
     }
@@ -74,6 +86,11 @@ where
                 let stdin_to_ws = stdin_rx.map(Ok).forward(write);
                 let ws_to_stdout = read.for_each(|message| async {
                     if let Ok(message) = message {
+                        METRICS.inc(
+                            WS_CLIENT_MESSAGES_RECEIVED,
+                            &[("market", &self.market_name), ("channel", &self.channel)],
+                        );
+
                         let mut market_is_okcoin = false;
 
                         let message = if let Ok(message) = message.clone().into_text() {
@@ -2,9 +2,10 @@ use crate::worker::helper_functions::date_time_from_timestamp_sec;
 use crate::worker::network_helpers::ws_server::interval::Interval;
 use crate::worker::network_helpers::ws_server::ser_date_into_timestamp;
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 
-#[derive(Serialize, Clone)]
-pub struct Candles(Vec<Candle>);
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Candles(pub Vec<Candle>);
 
 impl Candles {
     pub fn calculate(values: Vec<(DateTime<Utc>, f64)>, interval: Interval) -> Self {
@@ -44,14 +45,15 @@ impl Candles {
     }
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub struct Candle {
-    open: f64,
-    close: f64,
-    min: f64,
-    max: f64,
-    avg: f64,
+    pub open: f64,
+    pub close: f64,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
     #[serde(with = "ser_date_into_timestamp")]
+    #[schemars(with = "i64")]
     pub timestamp: DateTime<Utc>,
 }
 
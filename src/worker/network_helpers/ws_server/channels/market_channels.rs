@@ -1,14 +1,19 @@
 use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcId;
+use schemars::JsonSchema;
 
-#[derive(Debug, Clone)]
+/// Schema describes `params` of the request, keyed by method
+#[derive(Debug, Clone, JsonSchema)]
+#[schemars(rename_all = "snake_case")]
 pub enum MarketChannels {
     CoinExchangePrice {
+        #[schemars(skip)]
         id: Option<JsonRpcId>,
         coins: Vec<String>,
         exchanges: Vec<String>,
         frequency_ms: Option<u64>,
     },
     CoinExchangeVolume {
+        #[schemars(skip)]
         id: Option<JsonRpcId>,
         coins: Vec<String>,
         exchanges: Vec<String>,
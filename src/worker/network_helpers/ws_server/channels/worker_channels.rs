@@ -1,17 +1,28 @@
 use crate::worker::network_helpers::ws_server::interval::Interval;
 use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcId;
+use schemars::JsonSchema;
 
-#[derive(Debug, Clone)]
+/// Schema describes `params` of the request, keyed by method
+#[derive(Debug, Clone, JsonSchema)]
+#[schemars(rename_all = "snake_case")]
 pub enum WorkerChannels {
     CoinAveragePrice {
+        #[schemars(skip)]
         id: Option<JsonRpcId>,
         coins: Vec<String>,
         frequency_ms: Option<u64>,
     },
     CoinAveragePriceCandles {
+        #[schemars(skip)]
         id: Option<JsonRpcId>,
         coins: Vec<String>,
         frequency_ms: Option<u64>,
         interval: Interval,
     },
+    /// Alerts aren't conflated, so there is no `frequency_ms`
+    Alerts {
+        #[schemars(skip)]
+        id: Option<JsonRpcId>,
+        coins: Vec<String>,
+    },
 }
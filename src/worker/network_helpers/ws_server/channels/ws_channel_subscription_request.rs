@@ -1,7 +1,9 @@
 use crate::worker::network_helpers::ws_server::channels::market_channels::MarketChannels;
 use crate::worker::network_helpers::ws_server::channels::worker_channels::WorkerChannels;
+use crate::worker::network_helpers::ws_server::interval::Interval;
 use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcId;
 use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use serde_json::json;
 
 #[derive(Debug, Clone)]
 pub enum WsChannelSubscriptionRequest {
@@ -14,7 +16,8 @@ impl WsChannelSubscriptionRequest {
         match self {
             Self::WorkerChannels(channel) => match channel {
                 WorkerChannels::CoinAveragePrice { id, .. }
-                | WorkerChannels::CoinAveragePriceCandles { id, .. } => id.clone(),
+                | WorkerChannels::CoinAveragePriceCandles { id, .. }
+                | WorkerChannels::Alerts { id, .. } => id.clone(),
             },
             Self::MarketChannels(channel) => match channel {
                 MarketChannels::CoinExchangePrice { id, .. }
@@ -27,7 +30,8 @@ impl WsChannelSubscriptionRequest {
         match self {
             Self::WorkerChannels(channel) => match channel {
                 WorkerChannels::CoinAveragePrice { coins, .. }
-                | WorkerChannels::CoinAveragePriceCandles { coins, .. } => coins,
+                | WorkerChannels::CoinAveragePriceCandles { coins, .. }
+                | WorkerChannels::Alerts { coins, .. } => coins,
             },
             Self::MarketChannels(channel) => match channel {
                 MarketChannels::CoinExchangePrice { coins, .. }
@@ -36,11 +40,21 @@ impl WsChannelSubscriptionRequest {
         }
     }
 
+    pub fn get_interval(&self) -> Option<Interval> {
+        match self {
+            Self::WorkerChannels(WorkerChannels::CoinAveragePriceCandles { interval, .. }) => {
+                Some(*interval)
+            }
+            _ => None,
+        }
+    }
+
     pub fn get_frequency_ms(&self) -> Option<u64> {
         match self {
             Self::WorkerChannels(channel) => match channel {
                 WorkerChannels::CoinAveragePrice { frequency_ms, .. }
                 | WorkerChannels::CoinAveragePriceCandles { frequency_ms, .. } => *frequency_ms,
+                WorkerChannels::Alerts { .. } => None,
             },
             Self::MarketChannels(channel) => match channel {
                 MarketChannels::CoinExchangePrice { frequency_ms, .. }
@@ -56,6 +70,7 @@ impl WsChannelSubscriptionRequest {
                 | WorkerChannels::CoinAveragePriceCandles { frequency_ms, .. } => {
                     *frequency_ms = Some(new_value)
                 }
+                WorkerChannels::Alerts { .. } => {}
             },
             Self::MarketChannels(channel) => match channel {
                 MarketChannels::CoinExchangePrice { frequency_ms, .. }
@@ -66,6 +81,23 @@ impl WsChannelSubscriptionRequest {
         }
     }
 
+    /// Returns `params` of jsonrpc request
+    pub fn get_params(&self) -> serde_json::Value {
+        let mut params = json!({ "coins": self.get_coins() });
+
+        if let Self::MarketChannels(channel) = self {
+            params["exchanges"] = json!(channel.get_exchanges());
+        }
+        if let Some(frequency_ms) = self.get_frequency_ms() {
+            params["frequency_ms"] = json!(frequency_ms);
+        }
+        if let Some(interval) = self.get_interval() {
+            params["interval"] = json!(interval);
+        }
+
+        params
+    }
+
     pub fn get_method(&self) -> WsChannelName {
         match self {
             Self::WorkerChannels(channel) => match channel {
@@ -73,6 +105,7 @@ impl WsChannelSubscriptionRequest {
                 WorkerChannels::CoinAveragePriceCandles { .. } => {
                     WsChannelName::CoinAveragePriceCandles
                 }
+                WorkerChannels::Alerts { .. } => WsChannelName::Alerts,
             },
             Self::MarketChannels(channel) => match channel {
                 MarketChannels::CoinExchangePrice { .. } => WsChannelName::CoinExchangePrice,
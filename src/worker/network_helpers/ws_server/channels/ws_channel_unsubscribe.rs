@@ -1,18 +1,29 @@
-use crate::worker::network_helpers::ws_server::channels::ws_channel_subscription_request::WsChannelSubscriptionRequest;
 use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcId;
 use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use crate::worker::network_helpers::ws_server::ws_channel_response_sender::WsChannelResponseSender;
+use schemars::JsonSchema;
 
-#[derive(Debug, Clone)]
+/// Removes either one subscription (by `subscription_id`) or all subscriptions of `method`
+#[derive(Debug, Clone, JsonSchema)]
 pub struct WsChannelUnsubscribe {
+    #[schemars(skip)]
     pub id: Option<JsonRpcId>,
-    pub method: WsChannelName,
+    pub method: Option<WsChannelName>,
+    pub subscription_id: Option<String>,
 }
 
-impl From<WsChannelSubscriptionRequest> for WsChannelUnsubscribe {
-    fn from(request: WsChannelSubscriptionRequest) -> Self {
-        Self {
-            id: request.get_id(),
-            method: request.get_method(),
-        }
+impl WsChannelUnsubscribe {
+    pub fn matches(&self, channel: &WsChannelResponseSender) -> bool {
+        let method_matches = self
+            .method
+            .map(|v| v == channel.request.get_method())
+            .unwrap_or(true);
+        let subscription_id_matches = self
+            .subscription_id
+            .as_ref()
+            .map(|v| v == &channel.subscription_id)
+            .unwrap_or(true);
+
+        method_matches && subscription_id_matches
     }
 }
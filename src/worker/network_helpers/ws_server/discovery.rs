@@ -0,0 +1,88 @@
+use crate::worker::network_helpers::ws_server::interval::Interval;
+use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use schemars::JsonSchema;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+pub struct ExchangeCoinInfo {
+    pub coin: String,
+    /// Exchange updated the coin's values within the last minute
+    pub live: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+pub struct ExchangeInfo {
+    pub exchange: String,
+    /// Any coin of the exchange is live
+    pub live: bool,
+    pub coins: Vec<ExchangeCoinInfo>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MethodKind {
+    /// Subscription
+    Channel,
+    Request,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MethodInfo {
+    pub method: WsChannelName,
+    pub kind: MethodKind,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntervalInfo {
+    pub interval: Interval,
+    pub seconds: u64,
+}
+
+pub fn list_methods() -> Vec<MethodInfo> {
+    WsChannelName::ALL
+        .iter()
+        .map(|method| MethodInfo {
+            method: *method,
+            kind: if method.is_channel() {
+                MethodKind::Channel
+            } else {
+                MethodKind::Request
+            },
+        })
+        .collect()
+}
+
+pub fn list_intervals() -> Vec<IntervalInfo> {
+    Interval::ALL
+        .iter()
+        .map(|interval| IntervalInfo {
+            interval: *interval,
+            seconds: interval.into_seconds(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::ws_server::discovery::{list_intervals, list_methods};
+    use serde_json::json;
+
+    #[test]
+    fn test_list_methods() {
+        let methods = serde_json::to_value(list_methods()).unwrap();
+        let methods = methods.as_array().unwrap();
+
+        assert!(methods.contains(&json!({"method": "coin_average_price", "kind": "channel"})));
+        assert!(methods
+            .contains(&json!({"method": "coin_average_price_historical", "kind": "request"})));
+        assert!(methods.contains(&json!({"method": "list_methods", "kind": "request"})));
+    }
+
+    #[test]
+    fn test_list_intervals() {
+        let intervals = serde_json::to_value(list_intervals()).unwrap();
+
+        assert_eq!(intervals[0], json!({"interval": "second", "seconds": 1}));
+        assert_eq!(intervals[3], json!({"interval": "day", "seconds": 86400}));
+        assert_eq!(intervals.as_array().unwrap().len(), 6);
+    }
+}
@@ -2,9 +2,10 @@ use crate::worker::network_helpers::ws_server::hepler_functions::thin_by_interva
 use crate::worker::network_helpers::ws_server::interval::Interval;
 use crate::worker::network_helpers::ws_server::ser_date_into_timestamp;
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 
-#[derive(Serialize, Clone)]
-pub struct F64Snapshots(Vec<F64Snapshot>);
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct F64Snapshots(pub Vec<F64Snapshot>);
 
 impl F64Snapshots {
     pub fn with_interval(values: Vec<(DateTime<Utc>, f64)>, interval: Interval) -> Self {
@@ -18,9 +19,10 @@ impl F64Snapshots {
     }
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub struct F64Snapshot {
-    value: f64,
+    pub value: f64,
     #[serde(with = "ser_date_into_timestamp")]
-    timestamp: DateTime<Utc>,
+    #[schemars(with = "i64")]
+    pub timestamp: DateTime<Utc>,
 }
@@ -1,11 +1,16 @@
 use crate::worker::network_helpers::ws_server::interval::Interval;
+use crate::worker::network_helpers::ws_server::outbound_queue::{
+    OutboundQueueClosed, OutboundQueueSender,
+};
 use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
-use crate::worker::network_helpers::ws_server::ws_channel_response::WsChannelResponse;
+use crate::worker::network_helpers::ws_server::ws_channel_response::{
+    WsChannelResponse, WsChannelResponseEnvelope,
+};
+use crate::worker::network_helpers::ws_server::ws_encoding::WsEncoding;
 use async_tungstenite::tungstenite::protocol::Message;
 use chrono::{DateTime, Utc, MIN_DATETIME};
-use futures::channel::mpsc::{TrySendError, UnboundedSender};
 
-type Tx = UnboundedSender<Message>;
+type Tx = OutboundQueueSender;
 
 pub fn add_jsonrpc_version_and_method(response: &mut String, method: Option<WsChannelName>) {
     let mut value: serde_json::Value = serde_json::from_str(response).unwrap();
@@ -29,13 +34,24 @@ pub fn ws_send_response(
     broadcast_recipient: &Tx,
     response: WsChannelResponse,
     method: Option<WsChannelName>,
-) -> Result<(), TrySendError<Message>> {
-    let mut response = serde_json::to_string(&response).unwrap();
-    add_jsonrpc_version_and_method(&mut response, method);
+) -> Result<(), OutboundQueueClosed> {
+    let response = match broadcast_recipient.get_encoding() {
+        WsEncoding::Json => {
+            let mut response = serde_json::to_string(&response).unwrap();
+            add_jsonrpc_version_and_method(&mut response, method);
 
-    let response = Message::from(response);
+            Message::from(response)
+        }
+        encoding => encoding.encode(&WsChannelResponseEnvelope::new(
+            &response.id,
+            &response.result,
+            method,
+            None,
+            false,
+        )),
+    };
 
-    broadcast_recipient.unbounded_send(response)
+    broadcast_recipient.send(response)
 }
 
 pub fn thin_by_interval(
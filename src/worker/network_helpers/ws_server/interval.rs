@@ -1,4 +1,6 @@
-#[derive(Deserialize, Debug, Clone, Copy)]
+use schemars::JsonSchema;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum Interval {
     Second,
@@ -10,6 +12,15 @@ pub enum Interval {
 }
 
 impl Interval {
+    pub const ALL: [Self; 6] = [
+        Self::Second,
+        Self::Minute,
+        Self::Hour,
+        Self::Day,
+        Self::Week,
+        Self::Month,
+    ];
+
     pub fn into_seconds(self) -> u64 {
         match self {
             Self::Second => 1,
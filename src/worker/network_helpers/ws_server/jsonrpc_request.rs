@@ -12,5 +12,6 @@ pub enum JsonRpcId {
 pub struct JsonRpcRequest {
     pub id: Option<JsonRpcId>,
     pub method: WsChannelName,
+    #[serde(default)]
     pub params: serde_json::Value,
 }
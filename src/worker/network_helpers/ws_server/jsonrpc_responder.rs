@@ -0,0 +1,296 @@
+use crate::worker::network_helpers::ws_server::hepler_functions::ws_send_response;
+use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcId;
+use crate::worker::network_helpers::ws_server::outbound_queue::OutboundQueueSender;
+use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use crate::worker::network_helpers::ws_server::ws_channel_response::WsChannelResponse;
+use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+use schemars::JsonSchema;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+type Tx = OutboundQueueSender;
+
+/// Result of a successful subscription
+#[derive(Serialize, JsonSchema)]
+pub struct WsSubscribedResult<'a> {
+    pub method: WsChannelName,
+    /// Is sent in notifications of the subscription, can be used to unsubscribe
+    pub subscription_id: &'a str,
+    pub message: &'a str,
+}
+
+/// Responses to requests of one batch. They are sent together (as an array) when the batch is dropped,
+/// i.e. when all requests of the batch are processed.
+pub struct JsonRpcBatch {
+    broadcast_recipient: Tx,
+    responses: Mutex<Vec<serde_json::Value>>,
+}
+
+impl JsonRpcBatch {
+    pub fn new(broadcast_recipient: Tx) -> Arc<Self> {
+        Arc::new(Self {
+            broadcast_recipient,
+            responses: Mutex::new(Vec::new()),
+        })
+    }
+}
+
+impl Drop for JsonRpcBatch {
+    fn drop(&mut self) {
+        let responses = std::mem::take(self.responses.get_mut().unwrap());
+
+        // Batch of notifications gets no response
+        if !responses.is_empty() {
+            let encoding = self.broadcast_recipient.get_encoding();
+            let _ = self.broadcast_recipient.send(encoding.encode(&responses));
+        }
+    }
+}
+
+/// Sends the response to one request
+#[derive(Clone)]
+pub struct JsonRpcResponder {
+    broadcast_recipient: Tx,
+    id: Option<JsonRpcId>,
+    /// Notification (request without `id`) gets no response
+    is_notification: bool,
+    batch: Option<Arc<JsonRpcBatch>>,
+}
+
+impl JsonRpcResponder {
+    pub fn new(
+        broadcast_recipient: Tx,
+        id: Option<JsonRpcId>,
+        is_notification: bool,
+        batch: Option<Arc<JsonRpcBatch>>,
+    ) -> Self {
+        Self {
+            broadcast_recipient,
+            id,
+            is_notification,
+            batch,
+        }
+    }
+
+    pub fn get_broadcast_recipient(&self) -> &Tx {
+        &self.broadcast_recipient
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.broadcast_recipient.is_legacy_responses()
+    }
+
+    fn send(&self, response: serde_json::Value) {
+        if self.is_notification {
+            return;
+        }
+
+        match &self.batch {
+            Some(batch) => batch.responses.lock().unwrap().push(response),
+            None => {
+                let encoding = self.broadcast_recipient.get_encoding();
+                let _ = self.broadcast_recipient.send(encoding.encode(&response));
+            }
+        }
+    }
+
+    fn send_legacy(&self, result: WsChannelResponsePayload) {
+        let response = WsChannelResponse {
+            id: self.id.clone(),
+            result,
+        };
+        let _ = ws_send_response(&self.broadcast_recipient, response, None);
+    }
+
+    /// `Err` payload is sent as jsonrpc error (or inside `result` for legacy responses)
+    pub fn send_result(&self, result: WsChannelResponsePayload) {
+        if self.is_legacy() {
+            self.send_legacy(result);
+        } else if let WsChannelResponsePayload::Err { code, message, .. } = result {
+            self.send(json!({
+                "jsonrpc": "2.0",
+                "id": self.id,
+                "error": {
+                    "code": code,
+                    "message": message,
+                },
+            }));
+        } else {
+            self.send(json!({
+                "jsonrpc": "2.0",
+                "id": self.id,
+                "result": result,
+            }));
+        }
+    }
+
+    /// Successful subscription response. Isn't used for legacy responses
+    /// (successful subscription message is sent by every channel of the subscription).
+    pub fn send_subscribed(&self, method: WsChannelName, subscription_id: &str) {
+        self.send(json!({
+            "jsonrpc": "2.0",
+            "id": self.id,
+            "result": WsSubscribedResult {
+                method,
+                subscription_id,
+                message: "Successfully subscribed.",
+            },
+        }));
+    }
+
+    /// `method` is used only for legacy responses
+    pub fn send_error(&self, method: Option<WsChannelName>, code: i64, message: String) {
+        self.send_result(WsChannelResponsePayload::Err {
+            method,
+            code,
+            message,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcId;
+    use crate::worker::network_helpers::ws_server::jsonrpc_responder::{
+        JsonRpcBatch, JsonRpcResponder,
+    };
+    use crate::worker::network_helpers::ws_server::outbound_queue::{
+        outbound_queue, OutboundQueuePolicy, OutboundQueueReceiver,
+    };
+    use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+    use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+    use futures::{FutureExt, StreamExt};
+    use serde_json::json;
+
+    fn get_responses(rx: &mut OutboundQueueReceiver) -> Vec<serde_json::Value> {
+        let mut responses = Vec::new();
+        while let Some(Some(msg)) = rx.next().now_or_never() {
+            responses.push(serde_json::from_str(&msg.to_string()).unwrap());
+        }
+
+        responses
+    }
+
+    fn make_result() -> WsChannelResponsePayload {
+        WsChannelResponsePayload::SuccSub {
+            method: WsChannelName::Configure,
+            message: "Successfully configured.".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_send() {
+        let (tx, mut rx) = outbound_queue(100, OutboundQueuePolicy::DropOldest);
+
+        let responder = JsonRpcResponder::new(tx.clone(), Some(JsonRpcId::Int(1)), false, None);
+        responder.send_result(make_result());
+        responder.send_error(Some(WsChannelName::Configure), -32602, "Wrong.".to_string());
+        responder.send_subscribed(WsChannelName::CoinAveragePrice, "sub_id");
+
+        // Notification gets no response
+        let responder = JsonRpcResponder::new(tx, None, true, None);
+        responder.send_result(make_result());
+        responder.send_error(None, -32602, "Wrong.".to_string());
+
+        assert_eq!(
+            get_responses(&mut rx),
+            vec![
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "result": {"method": "configure", "message": "Successfully configured."},
+                }),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "error": {"code": -32602, "message": "Wrong."},
+                }),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "result": {
+                        "method": "coin_average_price",
+                        "subscription_id": "sub_id",
+                        "message": "Successfully subscribed.",
+                    },
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_send_legacy() {
+        let (tx, mut rx) = outbound_queue(100, OutboundQueuePolicy::DropOldest);
+        tx.set_legacy_responses(true);
+
+        let responder = JsonRpcResponder::new(tx, None, false, None);
+        responder.send_result(make_result());
+        responder.send_error(Some(WsChannelName::Configure), -32602, "Wrong.".to_string());
+
+        assert_eq!(
+            get_responses(&mut rx),
+            vec![
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "result": {"method": "configure", "message": "Successfully configured."},
+                }),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "result": {"method": "configure", "code": -32602, "message": "Wrong."},
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_batch() {
+        let (tx, mut rx) = outbound_queue(100, OutboundQueuePolicy::DropOldest);
+        let batch = JsonRpcBatch::new(tx.clone());
+
+        let responder_1 = JsonRpcResponder::new(
+            tx.clone(),
+            Some(JsonRpcId::Int(1)),
+            false,
+            Some(batch.clone()),
+        );
+        let responder_2 = JsonRpcResponder::new(tx.clone(), None, true, Some(batch.clone()));
+        let responder_3 = JsonRpcResponder::new(
+            tx.clone(),
+            Some(JsonRpcId::Str("3".to_string())),
+            false,
+            Some(batch),
+        );
+
+        responder_3.send_error(None, -32602, "Wrong.".to_string());
+        responder_2.send_result(make_result());
+        drop(responder_2);
+        drop(responder_3);
+        assert!(get_responses(&mut rx).is_empty());
+
+        // Responses are sent when all requests of the batch are processed
+        responder_1.send_result(make_result());
+        drop(responder_1);
+        assert_eq!(
+            get_responses(&mut rx),
+            vec![json!([
+                {
+                    "jsonrpc": "2.0",
+                    "id": "3",
+                    "error": {"code": -32602, "message": "Wrong."},
+                },
+                {
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "result": {"method": "configure", "message": "Successfully configured."},
+                },
+            ])]
+        );
+
+        // Batch of notifications gets no response
+        let batch = JsonRpcBatch::new(tx.clone());
+        JsonRpcResponder::new(tx, None, true, Some(batch)).send_result(make_result());
+        assert!(get_responses(&mut rx).is_empty());
+    }
+}
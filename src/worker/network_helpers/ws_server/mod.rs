@@ -1,16 +1,30 @@
 pub mod candles;
 pub mod channels;
+pub mod discovery;
 pub mod f64_snapshot;
 pub mod hepler_functions;
 pub mod interval;
 pub mod jsonrpc_request;
+pub mod jsonrpc_responder;
+pub mod openrpc;
+pub mod outbound_queue;
+pub mod permessage_deflate;
 pub mod requests;
 pub mod ser_date_into_timestamp;
+pub mod unix_socket;
+pub mod worker_pool;
+pub mod ws_auth;
 pub mod ws_channel_name;
 pub mod ws_channel_response;
 pub mod ws_channel_response_payload;
+pub mod ws_channel_response_payload_serialized;
 pub mod ws_channel_response_sender;
+pub mod ws_channel_subscription;
 pub mod ws_channels;
 pub mod ws_channels_holder;
+pub mod ws_encoding;
+pub mod ws_handshake;
+pub mod ws_limits;
 pub mod ws_request;
 pub mod ws_server;
+pub mod ws_tls;
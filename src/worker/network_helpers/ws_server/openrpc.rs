@@ -0,0 +1,221 @@
+use crate::worker::network_helpers::alerts::alert_rule::AlertRule;
+use crate::worker::network_helpers::ws_server::channels::market_channels::MarketChannels;
+use crate::worker::network_helpers::ws_server::channels::worker_channels::WorkerChannels;
+use crate::worker::network_helpers::ws_server::channels::ws_channel_unsubscribe::WsChannelUnsubscribe;
+use crate::worker::network_helpers::ws_server::jsonrpc_responder::WsSubscribedResult;
+use crate::worker::network_helpers::ws_server::requests::ws_alert_request::WsRemoveAlertRequest;
+use crate::worker::network_helpers::ws_server::requests::ws_auth_request::WsAuthRequest;
+use crate::worker::network_helpers::ws_server::requests::ws_configure_request::WsConfigureRequest;
+use crate::worker::network_helpers::ws_server::requests::ws_method_request::WsMethodRequest;
+use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+const OPENRPC_VERSION: &str = "1.2.6";
+/// Schema of `rpc.discover` result
+const OPENRPC_META_SCHEMA: &str =
+    "https://raw.githubusercontent.com/open-rpc/meta-schema/master/schema.json";
+
+/// Makes OpenRPC document of the websocket server API.
+/// Params and results of methods are generated from request and response types.
+pub fn make_openrpc_document() -> Value {
+    let mut gen = SchemaSettings::draft07()
+        .with(|settings| settings.definitions_path = "#/components/schemas/".to_string())
+        .into_generator();
+
+    let mut params = get_variants::<WorkerChannels>(&mut gen);
+    params.extend(get_variants::<MarketChannels>(&mut gen));
+    params.extend(get_variants::<WsMethodRequest>(&mut gen));
+    let results = get_titled_variants::<WsChannelResponsePayload>(&mut gen);
+    let subscribed = serde_json::to_value(gen.subschema_for::<WsSubscribedResult>()).unwrap();
+
+    let mut methods = Vec::new();
+    for method in WsChannelName::ALL {
+        let name = get_method_name(method);
+
+        let (method_params, result) = match method {
+            WsChannelName::CoinAveragePrice
+            | WsChannelName::CoinAveragePriceCandles
+            | WsChannelName::CoinExchangePrice
+            | WsChannelName::CoinExchangeVolume
+            | WsChannelName::Alerts => (params.get(&name).cloned(), subscribed.clone()),
+            WsChannelName::CoinAveragePriceHistorical
+            | WsChannelName::CoinAveragePriceCandlesHistorical => {
+                (params.get(&name).cloned(), results[&name].clone())
+            }
+            WsChannelName::AddAlert => (
+                Some(get_schema::<AlertRule>(&mut gen)),
+                results[&name].clone(),
+            ),
+            WsChannelName::RemoveAlert => (
+                Some(get_schema::<WsRemoveAlertRequest>(&mut gen)),
+                results["success"].clone(),
+            ),
+            WsChannelName::ListAlerts => (None, results[&name].clone()),
+            WsChannelName::Unsubscribe => (
+                Some(get_schema::<WsChannelUnsubscribe>(&mut gen)),
+                results["success"].clone(),
+            ),
+            WsChannelName::Configure => (
+                Some(get_schema::<WsConfigureRequest>(&mut gen)),
+                results["success"].clone(),
+            ),
+            WsChannelName::Auth => (
+                Some(get_schema::<WsAuthRequest>(&mut gen)),
+                results["success"].clone(),
+            ),
+            WsChannelName::ListCoins
+            | WsChannelName::ListExchanges
+            | WsChannelName::ListMethods
+            | WsChannelName::ListIntervals => (None, results[&name].clone()),
+            WsChannelName::RpcDiscover => (None, json!({ "$ref": OPENRPC_META_SCHEMA })),
+        };
+
+        let mut res = json!({
+            "name": name,
+            "paramStructure": "by-name",
+            "params": make_content_descriptors(method_params),
+            "result": {"name": "result", "schema": result},
+        });
+
+        if method.is_channel() {
+            res["x-notification"] = json!({
+                "name": "params",
+                "schema": {"allOf": [make_notification_params(), results[&name].clone()]},
+            });
+        }
+
+        methods.push(res);
+    }
+
+    json!({
+        "openrpc": OPENRPC_VERSION,
+        "info": {
+            "title": "ICEX websocket API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "methods": methods,
+        "components": {"schemas": gen.definitions()},
+    })
+}
+
+fn get_method_name(method: WsChannelName) -> String {
+    serde_json::to_value(method)
+        .unwrap()
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+fn get_schema<T: JsonSchema>(gen: &mut SchemaGenerator) -> Value {
+    serde_json::to_value(T::json_schema(gen)).unwrap()
+}
+
+/// Returns schemas of variants of externally tagged enum `T`, keyed by variant name
+fn get_variants<T: JsonSchema>(gen: &mut SchemaGenerator) -> Map<String, Value> {
+    get_schema::<T>(gen)["oneOf"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|variant| variant["properties"].as_object().unwrap().clone())
+        .collect()
+}
+
+/// Returns schemas of variants of untagged enum `T`, keyed by variant title
+fn get_titled_variants<T: JsonSchema>(gen: &mut SchemaGenerator) -> Map<String, Value> {
+    get_schema::<T>(gen)["anyOf"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|variant| Some((variant["title"].as_str()?.to_string(), variant.clone())))
+        .collect()
+}
+
+/// Makes OpenRPC content descriptors from properties of `params` object schema
+fn make_content_descriptors(params: Option<Value>) -> Vec<Value> {
+    let params = match params {
+        Some(params) => params,
+        None => return Vec::new(),
+    };
+    let required = params["required"].as_array().cloned().unwrap_or_default();
+
+    params["properties"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, schema)| {
+            json!({
+                "name": name,
+                "required": required.contains(&json!(name)),
+                "schema": schema,
+            })
+        })
+        .collect()
+}
+
+/// Fields, which are added to the payload of subscription notification
+fn make_notification_params() -> Value {
+    json!({
+        "type": "object",
+        "required": ["subscription_id"],
+        "properties": {
+            "subscription_id": {"type": "string"},
+            "snapshot": {
+                "description": "Current value, sent right after subscription",
+                "type": "boolean",
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::ws_server::openrpc::make_openrpc_document;
+    use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+    use serde_json::json;
+
+    #[test]
+    fn test_make_openrpc_document() {
+        let document = make_openrpc_document();
+        let methods = document["methods"].as_array().unwrap();
+        assert_eq!(methods.len(), WsChannelName::ALL.len());
+
+        let method = &methods[0];
+        assert_eq!(method["name"], "coin_average_price");
+        assert!(method["params"]
+            .as_array()
+            .unwrap()
+            .contains(&json!({"name": "coins", "required": true, "schema": {"type": "array", "items": {"type": "string"}}})));
+        assert_eq!(
+            method["result"]["schema"]["$ref"],
+            "#/components/schemas/WsSubscribedResult"
+        );
+        assert_eq!(
+            method["x-notification"]["schema"]["allOf"][1]["title"],
+            "coin_average_price"
+        );
+
+        for method in methods {
+            assert!(method["result"]["schema"].is_object(), "{}", method["name"]);
+        }
+    }
+
+    /// Fails when the API changes. Regenerate the document: `cargo run -- --openrpc > openrpc.json`.
+    #[test]
+    fn test_openrpc_document_is_up_to_date() {
+        let mut expected: serde_json::Value =
+            serde_json::from_str(include_str!("../../../../openrpc.json")).unwrap();
+        let mut document = make_openrpc_document();
+
+        // Package version is changed by releases, not by API changes
+        expected["info"]["version"] = serde_json::Value::Null;
+        document["info"]["version"] = serde_json::Value::Null;
+
+        assert!(
+            document == expected,
+            "API schema changed. Regenerate it: `cargo run -- --openrpc > openrpc.json`."
+        );
+    }
+}
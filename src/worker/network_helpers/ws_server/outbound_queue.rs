@@ -0,0 +1,439 @@
+use crate::metrics::metrics::{
+    METRICS, WS_SERVER_OUTBOUND_QUEUE_DROPPED, WS_SERVER_SLOW_CONSUMER_DISCONNECTS,
+};
+use crate::worker::network_helpers::ws_server::ws_encoding::WsEncoding;
+use async_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use async_tungstenite::tungstenite::protocol::{CloseFrame, Message};
+use futures::task::{Context, Poll, Waker};
+use futures::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// What to do with a new message if connection's outbound queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboundQueuePolicy {
+    /// Drop the oldest queued message
+    DropOldest,
+    /// Replace queued message of the same subscription, coin and exchange (else drop the oldest one)
+    Conflate,
+    /// Drop all queued messages and close the connection
+    Disconnect,
+}
+
+impl FromStr for OutboundQueuePolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop_oldest" => Ok(Self::DropOldest),
+            "conflate" => Ok(Self::Conflate),
+            "disconnect" => Ok(Self::Disconnect),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ToString for OutboundQueuePolicy {
+    fn to_string(&self) -> String {
+        match self {
+            Self::DropOldest => "drop_oldest".to_string(),
+            Self::Conflate => "conflate".to_string(),
+            Self::Disconnect => "disconnect".to_string(),
+        }
+    }
+}
+
+/// Message can't be queued: connection is closed (or is being closed)
+#[derive(Debug)]
+pub struct OutboundQueueClosed;
+
+struct QueuedMessage {
+    message: Message,
+    conflation_key: Option<String>,
+    /// Responses to requests aren't dropped by `DropOldest` and `Conflate` policies
+    is_response: bool,
+}
+
+struct OutboundQueueInner {
+    messages: VecDeque<QueuedMessage>,
+    capacity: usize,
+    policy: OutboundQueuePolicy,
+    /// Encoding of the connection's messages
+    encoding: WsEncoding,
+    /// Whether transport supports binary messages (e.g. newline-delimited JSON doesn't)
+    binary_allowed: bool,
+    /// Whether responses have the legacy shape (errors and subscription messages inside `result`)
+    legacy_responses: bool,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+impl OutboundQueueInner {
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Drops the oldest channel message. Returns `false` if there are only responses in the queue.
+    fn drop_oldest(&mut self) -> bool {
+        let position = self.messages.iter().position(|v| !v.is_response);
+
+        match position {
+            Some(position) => {
+                self.messages.remove(position);
+                self.inc_dropped();
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops all queued messages and closes the connection
+    fn disconnect(&mut self) {
+        let close_frame = CloseFrame {
+            code: CloseCode::Policy,
+            reason: "Slow consumer: outbound queue is full.".into(),
+        };
+
+        self.messages.clear();
+        self.messages.push_back(QueuedMessage {
+            message: Message::Close(Some(close_frame)),
+            conflation_key: None,
+            is_response: false,
+        });
+        self.closed = true;
+        self.wake();
+        METRICS.inc(WS_SERVER_SLOW_CONSUMER_DISCONNECTS, &[]);
+    }
+
+    fn inc_dropped(&self) {
+        METRICS.inc(
+            WS_SERVER_OUTBOUND_QUEUE_DROPPED,
+            &[("policy", &self.policy.to_string())],
+        );
+    }
+}
+
+/// Sending half of a connection's bounded outbound queue
+#[derive(Clone)]
+pub struct OutboundQueueSender(Arc<Mutex<OutboundQueueInner>>);
+
+/// Receiving half of a connection's bounded outbound queue. Is forwarded into websocket stream.
+pub struct OutboundQueueReceiver(Arc<Mutex<OutboundQueueInner>>);
+
+pub fn outbound_queue(
+    capacity: usize,
+    policy: OutboundQueuePolicy,
+) -> (OutboundQueueSender, OutboundQueueReceiver) {
+    let inner = Arc::new(Mutex::new(OutboundQueueInner {
+        messages: VecDeque::with_capacity(capacity),
+        capacity,
+        policy,
+        encoding: WsEncoding::default(),
+        binary_allowed: true,
+        legacy_responses: false,
+        closed: false,
+        waker: None,
+    }));
+
+    (
+        OutboundQueueSender(Arc::clone(&inner)),
+        OutboundQueueReceiver(inner),
+    )
+}
+
+impl OutboundQueueSender {
+    pub fn get_encoding(&self) -> WsEncoding {
+        self.0.lock().unwrap().encoding
+    }
+
+    /// Sets encoding of the subsequent messages
+    pub fn set_encoding(&self, encoding: WsEncoding) {
+        self.0.lock().unwrap().encoding = encoding;
+    }
+
+    pub fn is_binary_allowed(&self) -> bool {
+        self.0.lock().unwrap().binary_allowed
+    }
+
+    /// Is called for text-only transports
+    pub fn disallow_binary(&self) {
+        self.0.lock().unwrap().binary_allowed = false;
+    }
+
+    pub fn is_legacy_responses(&self) -> bool {
+        self.0.lock().unwrap().legacy_responses
+    }
+
+    pub fn set_legacy_responses(&self, legacy_responses: bool) {
+        self.0.lock().unwrap().legacy_responses = legacy_responses;
+    }
+
+    /// Sends response to a request. Responses aren't dropped by `DropOldest` and `Conflate` policies
+    /// (otherwise the request is never answered). If the queue is full of responses (the client
+    /// sends requests, but doesn't read responses), the connection is closed.
+    pub fn send(&self, message: Message) -> Result<(), OutboundQueueClosed> {
+        self.push(message, None, true)
+    }
+
+    /// Sends channel message. `conflation_key` identifies messages, which can replace each other
+    /// (see `OutboundQueuePolicy::Conflate`)
+    pub fn send_conflatable(
+        &self,
+        message: Message,
+        conflation_key: Option<String>,
+    ) -> Result<(), OutboundQueueClosed> {
+        self.push(message, conflation_key, false)
+    }
+
+    fn push(
+        &self,
+        message: Message,
+        conflation_key: Option<String>,
+        is_response: bool,
+    ) -> Result<(), OutboundQueueClosed> {
+        let mut inner = self.0.lock().unwrap();
+
+        if inner.closed {
+            return Err(OutboundQueueClosed);
+        }
+
+        let queued_message = QueuedMessage {
+            message,
+            conflation_key,
+            is_response,
+        };
+
+        if inner.messages.len() >= inner.capacity {
+            match inner.policy {
+                OutboundQueuePolicy::DropOldest | OutboundQueuePolicy::Conflate => {
+                    let position = match (&queued_message.conflation_key, inner.policy) {
+                        (Some(conflation_key), OutboundQueuePolicy::Conflate) => inner
+                            .messages
+                            .iter()
+                            .position(|v| v.conflation_key.as_ref() == Some(conflation_key)),
+                        _ => None,
+                    };
+
+                    if let Some(position) = position {
+                        inner.messages[position] = queued_message;
+                        inner.inc_dropped();
+
+                        return Ok(());
+                    }
+                    if !inner.drop_oldest() {
+                        // There are only responses in the queue
+                        if is_response {
+                            inner.disconnect();
+
+                            return Err(OutboundQueueClosed);
+                        }
+
+                        inner.inc_dropped();
+
+                        return Ok(());
+                    }
+                }
+                OutboundQueuePolicy::Disconnect => {
+                    inner.disconnect();
+
+                    return Err(OutboundQueueClosed);
+                }
+            }
+        }
+
+        inner.messages.push_back(queued_message);
+        inner.wake();
+
+        Ok(())
+    }
+}
+
+impl Stream for OutboundQueueReceiver {
+    type Item = Message;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut inner = self.0.lock().unwrap();
+
+        if let Some(queued_message) = inner.messages.pop_front() {
+            Poll::Ready(Some(queued_message.message))
+        } else if inner.closed {
+            Poll::Ready(None)
+        } else {
+            inner.waker = Some(cx.waker().clone());
+
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for OutboundQueueReceiver {
+    fn drop(&mut self) {
+        // The client is disconnected. Senders get an error and stop sending him messages.
+        let mut inner = self.0.lock().unwrap();
+        inner.closed = true;
+        inner.messages.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::ws_server::outbound_queue::{
+        outbound_queue, OutboundQueuePolicy, OutboundQueueSender,
+    };
+    use async_std::task;
+    use async_tungstenite::tungstenite::protocol::Message;
+    use futures::StreamExt;
+    use std::sync::{Arc, Mutex};
+    use std::{thread, time};
+
+    fn get_len(tx: &OutboundQueueSender) -> usize {
+        tx.0.lock().unwrap().messages.len()
+    }
+
+    fn get_texts(messages: Vec<Message>) -> Vec<String> {
+        messages.into_iter().map(|v| v.to_string()).collect()
+    }
+
+    /// Sends channel message (without conflation key)
+    fn send_update(tx: &OutboundQueueSender, text: &str) {
+        tx.send_conflatable(Message::text(text), None).unwrap();
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let (tx, rx) = outbound_queue(2, OutboundQueuePolicy::DropOldest);
+
+        for i in 0..5 {
+            send_update(&tx, &i.to_string());
+        }
+        assert_eq!(get_len(&tx), 2);
+
+        drop(tx);
+        let messages = task::block_on(rx.take(2).collect::<Vec<Message>>());
+        assert_eq!(get_texts(messages), vec!["3", "4"]);
+    }
+
+    #[test]
+    fn test_responses_are_not_dropped() {
+        for policy in [
+            OutboundQueuePolicy::DropOldest,
+            OutboundQueuePolicy::Conflate,
+        ] {
+            let (tx, rx) = outbound_queue(2, policy);
+
+            send_update(&tx, "update_1");
+            tx.send(Message::text("response_1")).unwrap();
+            send_update(&tx, "update_2");
+            tx.send(Message::text("response_2")).unwrap();
+            // Only responses are queued: the update is dropped
+            send_update(&tx, "update_3");
+            assert_eq!(get_len(&tx), 2);
+
+            drop(tx);
+            let messages = task::block_on(rx.take(2).collect::<Vec<Message>>());
+            assert_eq!(get_texts(messages), vec!["response_1", "response_2"]);
+        }
+    }
+
+    /// Client sends requests, but doesn't read responses: queue doesn't grow beyond capacity
+    #[test]
+    fn test_unread_responses() {
+        for policy in [
+            OutboundQueuePolicy::DropOldest,
+            OutboundQueuePolicy::Conflate,
+        ] {
+            let (tx, rx) = outbound_queue(2, policy);
+
+            assert!(tx.send(Message::text("1")).is_ok());
+            assert!(tx.send(Message::text("2")).is_ok());
+            assert!(tx.send(Message::text("3")).is_err());
+            assert!(tx.send(Message::text("4")).is_err());
+
+            let messages = task::block_on(rx.collect::<Vec<Message>>());
+            assert_eq!(messages.len(), 1);
+            assert!(matches!(&messages[0], Message::Close(Some(close_frame))
+                if close_frame.reason == "Slow consumer: outbound queue is full."));
+        }
+    }
+
+    #[test]
+    fn test_conflate() {
+        let (tx, rx) = outbound_queue(2, OutboundQueuePolicy::Conflate);
+
+        let btc = Some("sub_id:BTC".to_string());
+        let eth = Some("sub_id:ETH".to_string());
+        tx.send_conflatable(Message::text("btc_1"), btc.clone())
+            .unwrap();
+        tx.send_conflatable(Message::text("eth_1"), eth.clone())
+            .unwrap();
+        tx.send_conflatable(Message::text("btc_2"), btc).unwrap();
+        tx.send_conflatable(Message::text("eth_2"), eth).unwrap();
+        assert_eq!(get_len(&tx), 2);
+
+        let messages = task::block_on(rx.take(2).collect::<Vec<Message>>());
+        assert_eq!(get_texts(messages), vec!["btc_2", "eth_2"]);
+    }
+
+    #[test]
+    fn test_disconnect() {
+        let (tx, rx) = outbound_queue(2, OutboundQueuePolicy::Disconnect);
+
+        assert!(tx.send(Message::text("1")).is_ok());
+        assert!(tx.send(Message::text("2")).is_ok());
+        assert!(tx.send(Message::text("3")).is_err());
+        assert!(tx.send(Message::text("4")).is_err());
+
+        let messages = task::block_on(rx.collect::<Vec<Message>>());
+        assert_eq!(messages.len(), 1);
+        assert!(matches!(&messages[0], Message::Close(Some(close_frame))
+            if close_frame.reason == "Slow consumer: outbound queue is full."));
+    }
+
+    #[test]
+    fn test_sender_gets_error_after_receiver_dropped() {
+        let (tx, rx) = outbound_queue(2, OutboundQueuePolicy::DropOldest);
+        drop(rx);
+
+        assert!(tx.send(Message::text("1")).is_err());
+    }
+
+    /// Fast producer and a slow consumer: queue (memory) doesn't grow beyond capacity
+    #[test]
+    fn test_slow_consumer() {
+        let capacity = 100;
+        let (tx, mut rx) = outbound_queue(capacity, OutboundQueuePolicy::DropOldest);
+
+        let received = Arc::new(Mutex::new(0));
+        let received_2 = Arc::clone(&received);
+        let consumer = thread::spawn(move || {
+            while let Some(_message) = task::block_on(rx.next()) {
+                *received_2.lock().unwrap() += 1;
+                thread::sleep(time::Duration::from_millis(1));
+            }
+        });
+
+        let mut max_len = 0;
+        for i in 0..20000 {
+            send_update(&tx, &i.to_string());
+            max_len = max_len.max(get_len(&tx));
+        }
+        assert!(max_len <= capacity);
+
+        // Let the consumer empty the queue, then close it
+        while get_len(&tx) > 0 {
+            thread::sleep(time::Duration::from_millis(10));
+        }
+        tx.0.lock().unwrap().closed = true;
+        tx.0.lock().unwrap().wake();
+        consumer.join().unwrap();
+
+        let received = *received.lock().unwrap();
+        assert!(received >= capacity);
+        assert!(received < 20000);
+    }
+}
@@ -0,0 +1,639 @@
+use async_tungstenite::tungstenite::protocol::frame::coding::{Data, OpCode};
+use async_tungstenite::tungstenite::protocol::frame::FrameHeader;
+use flate2::write::{DeflateDecoder, DeflateEncoder};
+use flate2::Compression;
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::task::{Context, Poll};
+use std::io::{self, Cursor, Write};
+use std::pin::Pin;
+
+/// Extension, sent back to client if compression is accepted.
+/// Server resets compression context for every message (thus no per-connection compressor is kept).
+pub const PERMESSAGE_DEFLATE_RESPONSE: &str = "permessage-deflate; server_no_context_takeover";
+
+/// Max size of a decompressed client message (the same as `tungstenite` default max message size)
+const MAX_MESSAGE_SIZE: usize = 64 << 20;
+
+/// Compressed bytes are written to the socket in background. This is the max size of such buffer,
+/// after which writer has to wait.
+const MAX_WRITE_BUFFER_SIZE: usize = 64 << 10;
+
+/// Deflate tail, which is removed from compressed message (RFC 7692, section 7.2.1)
+const DEFLATE_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// End of the headers of client's handshake request
+const HANDSHAKE_END: &[u8] = b"\r\n\r\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PermessageDeflateConfig {
+    /// Compression level (0-9)
+    pub level: u32,
+    /// Messages, smaller than this (in bytes), are sent uncompressed
+    pub min_size: usize,
+}
+
+/// Checks client's offers (`Sec-WebSocket-Extensions` header value) and returns whether
+/// `permessage-deflate` can be accepted.
+/// Offers with `server_max_window_bits` less than 15 or with unknown params are declined.
+pub fn accept_permessage_deflate(extensions: &str) -> bool {
+    extensions.split(',').any(|offer| {
+        let mut params = offer.split(';').map(|v| v.trim());
+
+        params.next() == Some("permessage-deflate")
+            && params.all(|param| {
+                let (name, value) = match param.split_once('=') {
+                    Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                    None => (param, None),
+                };
+
+                match name {
+                    "server_no_context_takeover" | "client_no_context_takeover" => value.is_none(),
+                    "client_max_window_bits" => true,
+                    "server_max_window_bits" => value == Some("15"),
+                    _ => false,
+                }
+            })
+    })
+}
+
+fn invalid_data<E>(e: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+fn apply_mask(data: &mut [u8], mask: Option<[u8; 4]>) {
+    if let Some(mask) = mask {
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+}
+
+/// Compresses message payload (without context takeover)
+fn compress(data: &[u8], level: u32) -> io::Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::new(level));
+    encoder.write_all(data)?;
+    // Sync flush: ends with the deflate tail
+    encoder.flush()?;
+    let mut compressed = std::mem::take(encoder.get_mut());
+
+    if compressed.ends_with(&DEFLATE_TAIL) {
+        compressed.truncate(compressed.len() - DEFLATE_TAIL.len());
+    }
+
+    Ok(compressed)
+}
+
+/// Compressed client message, which is being received (maybe fragmented)
+struct CompressedMessage {
+    opcode: OpCode,
+    mask: Option<[u8; 4]>,
+    payload: Vec<u8>,
+}
+
+/// Stream adapter, which implements `permessage-deflate` websocket extension (RFC 7692)
+/// on the frame level, beneath `tungstenite` (which doesn't support extensions).
+/// Is passthrough until compression is enabled (after the handshake).
+/// Bytes after client's handshake request are held back until then, so frames, which came
+/// together with the request, aren't buffered by `tungstenite` bypassing decompression.
+pub struct DeflateStream<S> {
+    inner: S,
+    /// Compression config (`None` if compression is turned off in server config)
+    config: Option<PermessageDeflateConfig>,
+    enabled: bool,
+    /// Whether client's handshake request is read (the following bytes are frames)
+    handshake_read: bool,
+    /// Is kept for the whole connection, since client may use context takeover
+    decoder: DeflateDecoder<Vec<u8>>,
+    compressed_message: Option<CompressedMessage>,
+    /// Bytes, read from socket, but not processed yet (incomplete frame)
+    read_raw: Vec<u8>,
+    /// Processed bytes, which are ready to be read by `tungstenite`
+    read_ready: Vec<u8>,
+    read_pos: usize,
+    /// Bytes, written by `tungstenite`, but not processed yet (incomplete frame)
+    write_raw: Vec<u8>,
+    /// Processed bytes, which are ready to be written to socket
+    write_ready: Vec<u8>,
+    write_pos: usize,
+}
+
+impl<S> DeflateStream<S> {
+    pub fn new(inner: S, config: Option<PermessageDeflateConfig>) -> Self {
+        Self {
+            inner,
+            config,
+            enabled: false,
+            handshake_read: false,
+            decoder: DeflateDecoder::new(Vec::new()),
+            compressed_message: None,
+            read_raw: Vec::new(),
+            read_ready: Vec::new(),
+            read_pos: 0,
+            write_raw: Vec::new(),
+            write_ready: Vec::new(),
+            write_pos: 0,
+        }
+    }
+
+    pub fn is_compression_allowed(&self) -> bool {
+        self.config.is_some()
+    }
+
+    /// Must be called right after the handshake (before frames are read), if compression was accepted
+    pub fn enable(&mut self) {
+        self.enabled = self.config.is_some();
+        self.handshake_read = true;
+    }
+
+    /// Takes the next complete frame from `raw`: (header, header length, payload length)
+    fn parse_frame(raw: &[u8]) -> io::Result<Option<(FrameHeader, usize, usize)>> {
+        let mut cursor = Cursor::new(raw);
+
+        match FrameHeader::parse(&mut cursor).map_err(invalid_data)? {
+            Some((header, payload_len)) => {
+                let header_len = cursor.position() as usize;
+                let payload_len = payload_len as usize;
+
+                if payload_len > MAX_MESSAGE_SIZE {
+                    return Err(invalid_data("Frame is too big."));
+                }
+
+                if raw.len() >= header_len + payload_len {
+                    Ok(Some((header, header_len, payload_len)))
+                } else {
+                    Ok(None)
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn decompress(&mut self, payload: &[u8]) -> io::Result<Vec<u8>> {
+        for chunk in payload.chunks(4096).chain([&DEFLATE_TAIL[..]]) {
+            self.decoder.write_all(chunk)?;
+
+            if self.decoder.get_ref().len() > MAX_MESSAGE_SIZE {
+                return Err(invalid_data("Decompressed message is too big."));
+            }
+        }
+        self.decoder.flush()?;
+
+        Ok(std::mem::take(self.decoder.get_mut()))
+    }
+
+    /// Decompresses complete client frames from `read_raw` into `read_ready`
+    fn process_read(&mut self) -> io::Result<()> {
+        while let Some((header, header_len, payload_len)) = Self::parse_frame(&self.read_raw)? {
+            let frame: Vec<u8> = self.read_raw.drain(..header_len + payload_len).collect();
+            let mut payload = frame[header_len..].to_vec();
+
+            match header.opcode {
+                OpCode::Data(Data::Text) | OpCode::Data(Data::Binary) if header.rsv1 => {
+                    if self.compressed_message.is_some() {
+                        return Err(invalid_data(
+                            "New message before the end of fragmented one.",
+                        ));
+                    }
+
+                    apply_mask(&mut payload, header.mask);
+                    self.compressed_message = Some(CompressedMessage {
+                        opcode: header.opcode,
+                        mask: header.mask,
+                        payload,
+                    });
+                }
+                OpCode::Data(Data::Continue) if self.compressed_message.is_some() => {
+                    apply_mask(&mut payload, header.mask);
+                    let compressed_message = self.compressed_message.as_mut().unwrap();
+                    compressed_message.payload.extend(payload);
+
+                    if compressed_message.payload.len() > MAX_MESSAGE_SIZE {
+                        return Err(invalid_data("Message is too big."));
+                    }
+                }
+                _ => {
+                    // Uncompressed message or control frame
+                    self.read_ready.extend(frame);
+                    continue;
+                }
+            }
+
+            if header.is_final {
+                let compressed_message = self.compressed_message.take().unwrap();
+                let mut payload = self.decompress(&compressed_message.payload)?;
+                apply_mask(&mut payload, compressed_message.mask);
+
+                let header = FrameHeader {
+                    is_final: true,
+                    rsv1: false,
+                    rsv2: false,
+                    rsv3: false,
+                    opcode: compressed_message.opcode,
+                    mask: compressed_message.mask,
+                };
+                header
+                    .format(payload.len() as u64, &mut self.read_ready)
+                    .map_err(invalid_data)?;
+                self.read_ready.extend(payload);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compresses complete server frames from `write_raw` into `write_ready`
+    fn process_write(&mut self, config: PermessageDeflateConfig) -> io::Result<()> {
+        while let Some((mut header, header_len, payload_len)) = Self::parse_frame(&self.write_raw)?
+        {
+            let frame: Vec<u8> = self.write_raw.drain(..header_len + payload_len).collect();
+
+            let compress_frame = matches!(
+                header.opcode,
+                OpCode::Data(Data::Text) | OpCode::Data(Data::Binary)
+            ) && header.is_final
+                && payload_len >= config.min_size;
+
+            if compress_frame {
+                let payload = compress(&frame[header_len..], config.level)?;
+
+                header.rsv1 = true;
+                header
+                    .format(payload.len() as u64, &mut self.write_ready)
+                    .map_err(invalid_data)?;
+                self.write_ready.extend(payload);
+            } else {
+                // Small message, fragmented message or control frame
+                self.write_ready.extend(frame);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: AsyncWrite + Unpin> DeflateStream<S> {
+    /// Writes processed bytes to socket
+    fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.write_pos < self.write_ready.len() {
+            match Pin::new(&mut self.inner).poll_write(cx, &self.write_ready[self.write_pos..]) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(n)) => self.write_pos += n,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        self.write_ready.clear();
+        self.write_pos = 0;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + Unpin> DeflateStream<S> {
+    /// Copies processed bytes to `buf`. Returns `None` if there are no such bytes.
+    fn take_read_ready(&mut self, buf: &mut [u8]) -> Option<usize> {
+        if self.read_pos == self.read_ready.len() {
+            return None;
+        }
+
+        let n = buf.len().min(self.read_ready.len() - self.read_pos);
+        buf[..n].copy_from_slice(&self.read_ready[self.read_pos..self.read_pos + n]);
+        self.read_pos += n;
+
+        if self.read_pos == self.read_ready.len() {
+            self.read_ready.clear();
+            self.read_pos = 0;
+        }
+
+        Some(n)
+    }
+
+    /// Reads bytes from socket into `read_raw`. Returns `Ok(false)` on EOF.
+    fn poll_read_raw(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<bool>> {
+        let mut chunk = [0; 8192];
+
+        match Pin::new(&mut self.inner).poll_read(cx, &mut chunk) {
+            Poll::Ready(Ok(0)) => Poll::Ready(Ok(false)),
+            Poll::Ready(Ok(n)) => {
+                self.read_raw.extend(&chunk[..n]);
+                Poll::Ready(Ok(true))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    /// Passes through the handshake request. The following bytes are held back in `read_raw`.
+    fn process_handshake_read(&mut self) {
+        let end = self
+            .read_raw
+            .windows(HANDSHAKE_END.len())
+            .position(|v| v == HANDSHAKE_END)
+            .map(|position| position + HANDSHAKE_END.len());
+
+        match end {
+            Some(end) => {
+                self.read_ready.extend(self.read_raw.drain(..end));
+                self.handshake_read = true;
+            }
+            None => {
+                // The end of the headers can be split between reads
+                let end = self.read_raw.len().saturating_sub(HANDSHAKE_END.len() - 1);
+                self.read_ready.extend(self.read_raw.drain(..end));
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for DeflateStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(n) = this.take_read_ready(buf) {
+                return Poll::Ready(Ok(n));
+            }
+
+            if this.enabled {
+                // Frames, held back since the handshake, or the rest of incomplete frame
+                this.process_read()?;
+            } else if this.handshake_read {
+                // Compression isn't accepted: held back bytes are passed through
+                this.read_ready.append(&mut this.read_raw);
+            }
+            if this.read_pos < this.read_ready.len() {
+                continue;
+            }
+
+            match this.poll_read_raw(cx) {
+                Poll::Ready(Ok(true)) => {
+                    if !this.handshake_read {
+                        this.process_handshake_read();
+                    }
+                }
+                Poll::Ready(Ok(false)) => return Poll::Ready(Ok(0)),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for DeflateStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        let config = match this.config {
+            Some(config) if this.enabled => config,
+            _ => return Pin::new(&mut this.inner).poll_write(cx, buf),
+        };
+
+        if let Poll::Ready(Err(e)) = this.poll_write_ready(cx) {
+            return Poll::Ready(Err(e));
+        }
+        if this.write_ready.len() > MAX_WRITE_BUFFER_SIZE {
+            // Socket is busy. Waker is registered by `Self::poll_write_ready`.
+            return Poll::Pending;
+        }
+
+        this.write_raw.extend(buf);
+        this.process_write(config)?;
+        if let Poll::Ready(Err(e)) = this.poll_write_ready(cx) {
+            return Poll::Ready(Err(e));
+        }
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        match this.poll_write_ready(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_flush(cx),
+            other => other,
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        match this.poll_write_ready(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_close(cx),
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::ws_server::permessage_deflate::{
+        accept_permessage_deflate, apply_mask, compress, DeflateStream, PermessageDeflateConfig,
+        DEFLATE_TAIL,
+    };
+    use async_std::task;
+    use async_tungstenite::tungstenite::protocol::frame::coding::{Data, OpCode};
+    use async_tungstenite::tungstenite::protocol::frame::FrameHeader;
+    use async_tungstenite::tungstenite::protocol::{Message, Role};
+    use async_tungstenite::WebSocketStream;
+    use flate2::write::DeflateDecoder;
+    use futures::io::{AsyncRead, AsyncWrite, Cursor};
+    use futures::task::{Context, Poll};
+    use futures::{SinkExt, StreamExt};
+    use std::io::{self, Write};
+    use std::pin::Pin;
+
+    /// Socket mock: reads prepared bytes, keeps written bytes
+    struct MockStream {
+        read: Cursor<Vec<u8>>,
+        written: Vec<u8>,
+    }
+
+    impl AsyncRead for MockStream {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.read).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for MockStream {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.written).poll_write(cx, buf)
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Makes masked client frame
+    fn make_client_frame(payload: &[u8], opcode: Data, is_final: bool, rsv1: bool) -> Vec<u8> {
+        let mask = [1, 2, 3, 4];
+        let header = FrameHeader {
+            is_final,
+            rsv1,
+            rsv2: false,
+            rsv3: false,
+            opcode: OpCode::Data(opcode),
+            mask: Some(mask),
+        };
+
+        let mut payload = payload.to_vec();
+        apply_mask(&mut payload, Some(mask));
+
+        let mut frame = Vec::new();
+        header.format(payload.len() as u64, &mut frame).unwrap();
+        frame.extend(payload);
+
+        frame
+    }
+
+    fn decompress(payload: &[u8]) -> Vec<u8> {
+        let mut decoder = DeflateDecoder::new(Vec::new());
+        decoder.write_all(payload).unwrap();
+        decoder.write_all(&DEFLATE_TAIL).unwrap();
+        decoder.flush().unwrap();
+
+        decoder.get_ref().to_vec()
+    }
+
+    #[test]
+    fn test_accept_permessage_deflate() {
+        assert!(accept_permessage_deflate("permessage-deflate"));
+        assert!(accept_permessage_deflate(
+            "permessage-deflate; client_max_window_bits"
+        ));
+        assert!(accept_permessage_deflate(
+            "permessage-deflate; server_max_window_bits=10, permessage-deflate"
+        ));
+        assert!(!accept_permessage_deflate(
+            "permessage-deflate; server_max_window_bits=10"
+        ));
+        assert!(!accept_permessage_deflate(
+            "permessage-deflate; unknown_param"
+        ));
+        assert!(!accept_permessage_deflate("x-webkit-deflate-frame"));
+    }
+
+    #[test]
+    fn test_deflate_stream() {
+        let text = "{\"coin\":\"BTC\",\"value\":43500.5}".repeat(100);
+
+        // Client sends compressed message (fragmented) and uncompressed one
+        let compressed = compress(text.as_bytes(), 6).unwrap();
+        let (first, second) = compressed.split_at(compressed.len() / 2);
+        let mut client_frames = make_client_frame(first, Data::Text, false, true);
+        client_frames.extend(make_client_frame(second, Data::Continue, true, false));
+        client_frames.extend(make_client_frame(b"small", Data::Text, true, false));
+
+        let config = PermessageDeflateConfig {
+            level: 6,
+            min_size: 100,
+        };
+        let mut stream = DeflateStream::new(
+            MockStream {
+                read: Cursor::new(client_frames),
+                written: Vec::new(),
+            },
+            Some(config),
+        );
+        stream.enable();
+
+        task::block_on(async {
+            let mut ws_stream = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+
+            assert_eq!(
+                ws_stream.next().await.unwrap().unwrap(),
+                Message::text(&text)
+            );
+            assert_eq!(
+                ws_stream.next().await.unwrap().unwrap(),
+                Message::text("small")
+            );
+
+            ws_stream.send(Message::text(&text)).await.unwrap();
+            ws_stream.send(Message::text("small")).await.unwrap();
+
+            // Server sent big message compressed and small message uncompressed
+            let written = ws_stream.get_ref().inner.written.clone();
+
+            let (header, header_len, payload_len) =
+                DeflateStream::<MockStream>::parse_frame(&written)
+                    .unwrap()
+                    .unwrap();
+            assert!(header.rsv1);
+            assert!(payload_len < text.len());
+            let payload = &written[header_len..header_len + payload_len];
+            assert_eq!(decompress(payload), text.as_bytes());
+
+            let written = &written[header_len + payload_len..];
+            let (header, header_len, payload_len) =
+                DeflateStream::<MockStream>::parse_frame(written)
+                    .unwrap()
+                    .unwrap();
+            assert!(!header.rsv1);
+            assert_eq!(&written[header_len..header_len + payload_len], b"small");
+        });
+    }
+
+    #[test]
+    fn test_deflate_stream_frames_with_handshake() {
+        let text = "{\"coin\":\"BTC\",\"value\":43500.5}".repeat(100);
+
+        // Client sends compressed message right after the handshake request (in one read)
+        let mut client_bytes = b"GET / HTTP/1.1\r\n\
+            Host: localhost\r\n\
+            Connection: Upgrade\r\n\
+            Upgrade: websocket\r\n\
+            Sec-WebSocket-Version: 13\r\n\
+            Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+            Sec-WebSocket-Extensions: permessage-deflate\r\n\r\n"
+            .to_vec();
+        let compressed = compress(text.as_bytes(), 6).unwrap();
+        client_bytes.extend(make_client_frame(&compressed, Data::Text, true, true));
+
+        let config = PermessageDeflateConfig {
+            level: 6,
+            min_size: 100,
+        };
+        let stream = DeflateStream::new(
+            MockStream {
+                read: Cursor::new(client_bytes),
+                written: Vec::new(),
+            },
+            Some(config),
+        );
+
+        task::block_on(async {
+            let mut ws_stream = async_tungstenite::accept_async(stream).await.unwrap();
+            ws_stream.get_mut().enable();
+
+            assert_eq!(
+                ws_stream.next().await.unwrap().unwrap(),
+                Message::text(&text)
+            );
+        });
+    }
+}
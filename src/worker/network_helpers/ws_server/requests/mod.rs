@@ -1 +1,5 @@
+pub mod ws_alert_request;
+pub mod ws_auth_request;
+pub mod ws_configure_request;
+pub mod ws_discovery_request;
 pub mod ws_method_request;
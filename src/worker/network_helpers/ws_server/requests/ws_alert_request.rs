@@ -0,0 +1,27 @@
+use crate::worker::network_helpers::alerts::alert_rule::AlertRule;
+use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use schemars::JsonSchema;
+
+/// Manages alerts, which are pushed to `alerts` channel and posted to webhook
+#[derive(Debug, Clone)]
+pub enum WsAlertRequest {
+    Add(AlertRule),
+    Remove(WsRemoveAlertRequest),
+    List,
+}
+
+impl WsAlertRequest {
+    pub fn get_method(&self) -> WsChannelName {
+        match self {
+            Self::Add(..) => WsChannelName::AddAlert,
+            Self::Remove(..) => WsChannelName::RemoveAlert,
+            Self::List => WsChannelName::ListAlerts,
+        }
+    }
+}
+
+/// Removes alert by its id
+#[derive(Debug, Clone, JsonSchema)]
+pub struct WsRemoveAlertRequest {
+    pub id: String,
+}
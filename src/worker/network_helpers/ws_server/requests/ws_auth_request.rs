@@ -0,0 +1,7 @@
+use schemars::JsonSchema;
+
+/// Authenticates the connection with API key
+#[derive(Debug, Clone, JsonSchema)]
+pub struct WsAuthRequest {
+    pub token: String,
+}
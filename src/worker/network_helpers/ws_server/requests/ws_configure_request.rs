@@ -0,0 +1,8 @@
+use crate::worker::network_helpers::ws_server::ws_encoding::WsEncoding;
+use schemars::JsonSchema;
+
+/// Changes settings of the connection
+#[derive(Debug, Clone, JsonSchema)]
+pub struct WsConfigureRequest {
+    pub encoding: WsEncoding,
+}
@@ -0,0 +1,10 @@
+/// Describes what the server supports
+#[derive(Debug, Clone, Copy)]
+pub enum WsDiscoveryRequest {
+    Coins,
+    Exchanges,
+    Methods,
+    Intervals,
+    /// OpenRPC document (`rpc.discover`)
+    Schema,
+}
@@ -1,10 +1,15 @@
 use crate::worker::network_helpers::ws_server::interval::Interval;
 use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcId;
 use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use schemars::JsonSchema;
+use serde_json::json;
 
-#[derive(Debug, Clone)]
+/// Schema describes `params` of the request, keyed by method
+#[derive(Debug, Clone, JsonSchema)]
+#[schemars(rename_all = "snake_case")]
 pub enum WsMethodRequest {
     CoinAveragePriceHistorical {
+        #[schemars(skip)]
         id: Option<JsonRpcId>,
         coin: String,
         interval: Interval,
@@ -12,6 +17,7 @@ pub enum WsMethodRequest {
         to: Option<u64>,
     },
     CoinAveragePriceCandlesHistorical {
+        #[schemars(skip)]
         id: Option<JsonRpcId>,
         coin: String,
         interval: Interval,
@@ -21,6 +27,33 @@ pub enum WsMethodRequest {
 }
 
 impl WsMethodRequest {
+    /// Returns `params` of jsonrpc request
+    pub fn get_params(&self) -> serde_json::Value {
+        match self {
+            Self::CoinAveragePriceHistorical {
+                coin,
+                interval,
+                from,
+                to,
+                ..
+            }
+            | Self::CoinAveragePriceCandlesHistorical {
+                coin,
+                interval,
+                from,
+                to,
+                ..
+            } => {
+                let mut params = json!({"coin": coin, "interval": interval, "from": from});
+                if let Some(to) = to {
+                    params["to"] = json!(to);
+                }
+
+                params
+            }
+        }
+    }
+
     pub fn get_method(&self) -> WsChannelName {
         match self {
             Self::CoinAveragePriceHistorical { .. } => WsChannelName::CoinAveragePriceHistorical,
@@ -1,5 +1,5 @@
-use chrono::{DateTime, Utc};
-use serde::{self, Serializer};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{self, de, Deserialize, Deserializer, Serializer};
 
 pub fn serialize<S>(date: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -8,3 +8,13 @@ where
     let s = date.timestamp();
     serializer.serialize_i64(s)
 }
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = i64::deserialize(deserializer)?;
+    Utc.timestamp_opt(s, 0)
+        .single()
+        .ok_or_else(|| de::Error::custom(format!("Wrong timestamp: {}", s)))
+}
@@ -0,0 +1,196 @@
+use async_std::io::BufReader;
+use async_std::os::unix::net::UnixListener;
+use async_tungstenite::tungstenite::protocol::Message;
+use futures::{stream, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use futures::{Stream, StreamExt};
+use std::io;
+use std::os::unix::fs::FileTypeExt;
+use std::str::FromStr;
+
+/// Max length of newline-delimited JSON line, sent by client
+pub const MAX_NDJSON_LINE_LEN: usize = 1 << 20;
+
+/// Protocol, spoken over Unix domain socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnixSocketProtocol {
+    /// The same websocket JSON-RPC protocol, as over TCP
+    Ws,
+    /// Newline-delimited JSON: one JSON-RPC request/response per line
+    Ndjson,
+}
+
+impl FromStr for UnixSocketProtocol {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ws" => Ok(Self::Ws),
+            "ndjson" => Ok(Self::Ndjson),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ToString for UnixSocketProtocol {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Ws => "ws".to_string(),
+            Self::Ndjson => "ndjson".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnixSocketConfig {
+    pub path: String,
+    pub protocol: UnixSocketProtocol,
+}
+
+/// Removes socket file, left by the previous run (if any), then binds
+pub async fn bind_unix_socket(path: &str) -> io::Result<UnixListener> {
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if metadata.file_type().is_socket() {
+            std::fs::remove_file(path)?;
+        }
+    }
+
+    UnixListener::bind(path).await
+}
+
+/// Reads lines (without line endings). Stream ends with error on a line, longer than `max_len` bytes.
+pub fn read_ndjson<R: AsyncRead + Unpin>(
+    reader: R,
+    max_len: usize,
+) -> impl Stream<Item = io::Result<String>> {
+    stream::try_unfold(BufReader::new(reader), move |mut reader| async move {
+        let mut line = Vec::new();
+        (&mut reader)
+            .take(max_len as u64 + 1)
+            .read_until(b'\n', &mut line)
+            .await?;
+
+        if line.last() == Some(&b'\n') {
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+        } else if line.is_empty() {
+            return Ok(None);
+        } else if line.len() > max_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Line is too long.",
+            ));
+        }
+
+        String::from_utf8(line)
+            .map(|line| Some((line, reader)))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    })
+}
+
+/// Writes text messages as lines. Function ends when `messages` ends, or on close message, or on write error.
+pub async fn write_ndjson<W, M>(mut writer: W, mut messages: M)
+where
+    W: AsyncWrite + Unpin,
+    M: Stream<Item = Message> + Unpin,
+{
+    while let Some(message) = messages.next().await {
+        match message {
+            Message::Text(mut text) => {
+                text.push('\n');
+
+                if writer.write_all(text.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+            Message::Close(_) => break,
+            // Binary messages are not allowed for text-only transports
+            message => error!("Unexpected message for ndjson transport: {:?}", message),
+        }
+    }
+
+    let _ = writer.close().await;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::ws_server::unix_socket::{
+        bind_unix_socket, read_ndjson, write_ndjson, UnixSocketProtocol,
+    };
+    use async_std::task;
+    use async_tungstenite::tungstenite::protocol::Message;
+    use futures::{stream, StreamExt};
+
+    #[test]
+    fn test_protocol_from_str() {
+        assert_eq!("ws".parse(), Ok(UnixSocketProtocol::Ws));
+        assert_eq!("ndjson".parse(), Ok(UnixSocketProtocol::Ndjson));
+        assert_eq!("json".parse::<UnixSocketProtocol>(), Err(()));
+    }
+
+    #[test]
+    fn test_bind_unix_socket() {
+        let path = std::env::temp_dir().join("index_daemon_test_bind.sock");
+        let path = path.to_str().unwrap();
+
+        task::block_on(async {
+            let listener = bind_unix_socket(path).await.unwrap();
+            drop(listener);
+
+            // Socket file is left after the previous run
+            let _listener = bind_unix_socket(path).await.unwrap();
+        });
+
+        // Not a socket file is not removed
+        std::fs::remove_file(path).unwrap();
+        std::fs::write(path, "").unwrap();
+        assert!(task::block_on(bind_unix_socket(path)).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_write_ndjson() {
+        let messages = vec![
+            Message::Text("{\"a\":1}".to_string()),
+            Message::Text("{\"b\":2}".to_string()),
+            Message::Close(None),
+            Message::Text("{\"c\":3}".to_string()),
+        ];
+        let mut written = Vec::new();
+
+        task::block_on(write_ndjson(&mut written, stream::iter(messages)));
+
+        assert_eq!(
+            String::from_utf8(written).unwrap(),
+            "{\"a\":1}\n{\"b\":2}\n"
+        );
+    }
+
+    #[test]
+    fn test_read_ndjson() {
+        let read = |input: &str| -> Vec<Result<String, ()>> {
+            task::block_on(
+                read_ndjson(input.as_bytes(), 10)
+                    .map(|v| v.map_err(|_| ()))
+                    .collect(),
+            )
+        };
+
+        assert_eq!(
+            read("{\"a\":1}\r\n\n{\"b\":22}\n{\"c\":3}"),
+            vec![
+                Ok("{\"a\":1}".to_string()),
+                Ok("".to_string()),
+                Ok("{\"b\":22}".to_string()),
+                Ok("{\"c\":3}".to_string()),
+            ]
+        );
+
+        // Stream ends with error on a too long line
+        assert_eq!(
+            read("{\"a\":1}\n{\"b\":\"222\"}\n{\"c\":3}\n"),
+            vec![Ok("{\"a\":1}".to_string()), Err(())]
+        );
+    }
+}
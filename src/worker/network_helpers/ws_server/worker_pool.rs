@@ -0,0 +1,86 @@
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Fixed number of threads, processing jobs from a bounded queue
+#[derive(Clone)]
+pub struct WorkerPool {
+    sender: SyncSender<Job>,
+}
+
+impl WorkerPool {
+    pub fn new(name: &str, workers: usize, queue_size: usize) -> Self {
+        let (sender, receiver) = sync_channel::<Job>(queue_size);
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for i in 0..workers {
+            let receiver = Arc::clone(&receiver);
+
+            thread::Builder::new()
+                .name(format!("{}: {}", name, i))
+                .spawn(move || Self::work(receiver))
+                .unwrap();
+        }
+
+        Self { sender }
+    }
+
+    /// Function ends when all senders are dropped
+    fn work(receiver: Arc<Mutex<Receiver<Job>>>) {
+        loop {
+            let job = receiver.lock().unwrap().recv();
+
+            match job {
+                Ok(job) => job(),
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Returns `false` if queue is full
+    pub fn try_execute<F: FnOnce() + Send + 'static>(&self, job: F) -> bool {
+        match self.sender.try_send(Box::new(job)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => false,
+            Err(TrySendError::Disconnected(_)) => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::ws_server::worker_pool::WorkerPool;
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Barrier};
+
+    #[test]
+    fn test_worker_pool() {
+        let pool = WorkerPool::new("test_worker_pool", 2, 1);
+        let barrier = Arc::new(Barrier::new(3));
+        let (tx, rx) = channel();
+
+        // Both workers are busy
+        for _ in 0..2 {
+            let barrier = Arc::clone(&barrier);
+            let tx = tx.clone();
+
+            let job = move || {
+                barrier.wait();
+                tx.send(()).unwrap();
+            };
+
+            while !pool.try_execute(job.clone()) {}
+        }
+        // Wait until both jobs are taken from the queue
+        while !pool.try_execute(|| {}) {}
+
+        // Queue is full
+        assert!(!pool.try_execute(|| {}));
+
+        barrier.wait();
+        rx.recv().unwrap();
+        rx.recv().unwrap();
+    }
+}
@@ -0,0 +1,397 @@
+use crate::worker::network_helpers::ws_server::channels::ws_channel_action::WsChannelAction;
+use crate::worker::network_helpers::ws_server::requests::ws_alert_request::WsAlertRequest;
+use crate::worker::network_helpers::ws_server::requests::ws_method_request::WsMethodRequest;
+use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use crate::worker::network_helpers::ws_server::ws_request::WsRequest;
+use crate::worker::network_helpers::ws_server::ws_server::{
+    JSONRPC_ERROR_FORBIDDEN, JSONRPC_ERROR_UNAUTHORIZED,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// API key with its permissions. `None` means "no restriction".
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKey {
+    pub key: String,
+    pub methods: Option<Vec<WsChannelName>>,
+    pub coins: Option<Vec<String>>,
+    pub max_subscriptions: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct ApiKeysFile {
+    keys: Vec<ApiKey>,
+}
+
+pub struct ApiKeys {
+    keys: HashMap<String, ApiKey>,
+    /// Number of subscriptions of every key (of all connections and transports)
+    subscriptions: Mutex<HashMap<String, usize>>,
+}
+
+impl ApiKeys {
+    /// Loads keys from file (supports the same formats as config files, e.g. _yaml_ and _toml_)
+    pub fn load(path: &str) -> Self {
+        let mut file = config::Config::default();
+        file.merge(config::File::with_name(path))
+            .and_then(|file| file.clone().try_into::<ApiKeysFile>())
+            .map(|file| Self::new(file.keys))
+            .unwrap_or_else(|e| {
+                panic!(
+                    "Got wrong config value. service_config: ws_auth_keys_file={}. Error: {}",
+                    path, e
+                )
+            })
+    }
+
+    pub fn new(keys: Vec<ApiKey>) -> Self {
+        Self {
+            keys: keys.into_iter().map(|v| (v.key.clone(), v)).collect(),
+            subscriptions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, token: &str) -> Option<&ApiKey> {
+        self.keys.get(token)
+    }
+
+    /// Counts a new subscription of the key, if key's `max_subscriptions` isn't reached.
+    /// Returns error code and message.
+    fn reserve_subscription(
+        self: &Arc<Self>,
+        api_key: &ApiKey,
+    ) -> Result<ApiKeySubscriptionSlot, (i64, String)> {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let count = subscriptions.entry(api_key.key.clone()).or_insert(0);
+
+        match api_key.max_subscriptions {
+            Some(max_subscriptions) if *count >= max_subscriptions => Err((
+                JSONRPC_ERROR_FORBIDDEN,
+                "Forbidden. Max number of subscriptions is reached.".to_string(),
+            )),
+            _ => {
+                *count += 1;
+
+                Ok(ApiKeySubscriptionSlot {
+                    api_keys: Arc::clone(self),
+                    key: api_key.key.clone(),
+                })
+            }
+        }
+    }
+}
+
+/// Subscription, which is counted against API key's `max_subscriptions` until it's dropped
+pub struct ApiKeySubscriptionSlot {
+    api_keys: Arc<ApiKeys>,
+    key: String,
+}
+
+impl Drop for ApiKeySubscriptionSlot {
+    fn drop(&mut self) {
+        let mut subscriptions = self.api_keys.subscriptions.lock().unwrap();
+
+        if let Some(count) = subscriptions.get_mut(&self.key) {
+            *count -= 1;
+            if *count == 0 {
+                subscriptions.remove(&self.key);
+            }
+        }
+    }
+}
+
+/// Authentication state of a connection
+#[derive(Clone)]
+pub struct WsAuth {
+    /// `None` if authentication is turned off
+    api_keys: Option<Arc<ApiKeys>>,
+    api_key: Arc<Mutex<Option<ApiKey>>>,
+}
+
+impl WsAuth {
+    pub fn new(api_keys: Option<Arc<ApiKeys>>) -> Self {
+        Self {
+            api_keys,
+            api_key: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Returns `false` if token is invalid. Always succeeds if authentication is turned off.
+    pub fn authenticate(&self, token: &str) -> bool {
+        match &self.api_keys {
+            Some(api_keys) => match api_keys.get(token) {
+                Some(api_key) => {
+                    *self.api_key.lock().unwrap() = Some(api_key.clone());
+
+                    true
+                }
+                None => false,
+            },
+            None => true,
+        }
+    }
+
+    /// Returns API key of the authenticated connection
+    pub fn get_key(&self) -> Option<String> {
+        self.api_key.lock().unwrap().as_ref().map(|v| v.key.clone())
+    }
+
+    fn check_method(api_key: &ApiKey, method: WsChannelName) -> Result<(), (i64, String)> {
+        match &api_key.methods {
+            Some(methods) if !methods.contains(&method) => Err((
+                JSONRPC_ERROR_FORBIDDEN,
+                "Forbidden. Method is not allowed.".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_coins(api_key: &ApiKey, coins: &[String]) -> Result<(), (i64, String)> {
+        match &api_key.coins {
+            Some(allowed_coins) => match coins.iter().find(|v| !allowed_coins.contains(v)) {
+                Some(coin) => Err((
+                    JSONRPC_ERROR_FORBIDDEN,
+                    format!("Forbidden. Coin {} is not allowed.", coin),
+                )),
+                None => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+
+    fn make_unauthorized_error() -> (i64, String) {
+        (
+            JSONRPC_ERROR_UNAUTHORIZED,
+            "Unauthorized. Authentication required.".to_string(),
+        )
+    }
+
+    /// Checks whether the connection is allowed to make `request`.
+    /// Number of subscriptions is checked by `Self::reserve_subscription`.
+    /// Returns error code and message.
+    pub fn authorize(&self, request: &WsRequest) -> Result<(), (i64, String)> {
+        if self.api_keys.is_none() {
+            return Ok(());
+        }
+
+        let api_key = self.api_key.lock().unwrap();
+        let api_key = api_key.as_ref().ok_or_else(Self::make_unauthorized_error)?;
+
+        match request {
+            WsRequest::Channel(WsChannelAction::Subscribe(request)) => {
+                Self::check_method(api_key, request.get_method())?;
+                Self::check_coins(api_key, request.get_coins())
+            }
+            WsRequest::Method(request) => {
+                Self::check_method(api_key, request.get_method())?;
+
+                match request {
+                    WsMethodRequest::CoinAveragePriceHistorical { coin, .. }
+                    | WsMethodRequest::CoinAveragePriceCandlesHistorical { coin, .. } => {
+                        Self::check_coins(api_key, &[coin.to_string()])
+                    }
+                }
+            }
+            WsRequest::Alert(request) => {
+                Self::check_method(api_key, request.get_method())?;
+
+                match request {
+                    WsAlertRequest::Add(rule) => {
+                        Self::check_coins(api_key, std::slice::from_ref(&rule.coin))
+                    }
+                    WsAlertRequest::Remove(..) | WsAlertRequest::List => Ok(()),
+                }
+            }
+            WsRequest::Channel(WsChannelAction::Unsubscribe(_))
+            | WsRequest::Configure(_)
+            | WsRequest::Auth(_)
+            | WsRequest::Discovery(_) => Ok(()),
+        }
+    }
+
+    /// Counts a new subscription against API key's `max_subscriptions`, which is shared
+    /// by all connections (and transports) of the key. Subscription is counted until
+    /// the returned slot is dropped. Returns `None` if authentication is turned off.
+    pub fn reserve_subscription(&self) -> Result<Option<ApiKeySubscriptionSlot>, (i64, String)> {
+        let api_keys = match &self.api_keys {
+            Some(api_keys) => api_keys,
+            None => return Ok(None),
+        };
+
+        let api_key = self.api_key.lock().unwrap();
+        let api_key = api_key.as_ref().ok_or_else(Self::make_unauthorized_error)?;
+
+        api_keys.reserve_subscription(api_key).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcRequest;
+    use crate::worker::network_helpers::ws_server::ws_auth::{ApiKey, ApiKeys, WsAuth};
+    use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+    use crate::worker::network_helpers::ws_server::ws_request::WsRequest;
+    use crate::worker::network_helpers::ws_server::ws_server::{
+        JSONRPC_ERROR_FORBIDDEN, JSONRPC_ERROR_UNAUTHORIZED,
+    };
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn make_request(request: serde_json::Value) -> WsRequest {
+        let request: JsonRpcRequest = serde_json::from_value(request).unwrap();
+
+        request.try_into().unwrap()
+    }
+
+    fn subscribe(coins: &[&str]) -> WsRequest {
+        make_request(json!({
+            "id": null,
+            "jsonrpc": "2.0",
+            "method": "coin_average_price",
+            "params": {"coins": coins}
+        }))
+    }
+
+    fn get_error_code(result: Result<(), (i64, String)>) -> i64 {
+        result.unwrap_err().0
+    }
+
+    #[test]
+    fn test_authorize() {
+        let api_keys = ApiKeys::new(vec![
+            ApiKey {
+                key: "full_access".to_string(),
+                methods: None,
+                coins: None,
+                max_subscriptions: None,
+            },
+            ApiKey {
+                key: "restricted".to_string(),
+                methods: Some(vec![WsChannelName::CoinAveragePrice]),
+                coins: Some(vec!["BTC".to_string()]),
+                max_subscriptions: Some(2),
+            },
+        ]);
+        let api_keys = Some(Arc::new(api_keys));
+        let historical = make_request(json!({
+            "id": null,
+            "jsonrpc": "2.0",
+            "method": "coin_average_price_historical",
+            "params": {"coin": "BTC", "interval": "day", "from": 1643835600}
+        }));
+        let add_alert = make_request(json!({
+            "id": null,
+            "jsonrpc": "2.0",
+            "method": "add_alert",
+            "params": {"coin": "ETH", "condition": {"kind": "cross", "price": 2000}}
+        }));
+
+        // Not authenticated
+        let auth = WsAuth::new(api_keys.clone());
+        assert_eq!(
+            get_error_code(auth.authorize(&subscribe(&["BTC"]))),
+            JSONRPC_ERROR_UNAUTHORIZED
+        );
+        assert!(!auth.authenticate("wrong_key"));
+        assert_eq!(
+            get_error_code(auth.authorize(&subscribe(&["BTC"]))),
+            JSONRPC_ERROR_UNAUTHORIZED
+        );
+
+        // Full access
+        assert!(auth.authenticate("full_access"));
+        assert!(auth.authorize(&subscribe(&["BTC", "ETH"])).is_ok());
+        assert!(auth.authorize(&historical).is_ok());
+        assert!(auth.authorize(&add_alert).is_ok());
+
+        // Restricted access
+        let auth = WsAuth::new(api_keys);
+        assert!(auth.authenticate("restricted"));
+        assert!(auth.authorize(&subscribe(&["BTC"])).is_ok());
+        assert_eq!(
+            get_error_code(auth.authorize(&subscribe(&["BTC", "ETH"]))),
+            JSONRPC_ERROR_FORBIDDEN
+        );
+        assert_eq!(
+            get_error_code(auth.authorize(&historical)),
+            JSONRPC_ERROR_FORBIDDEN
+        );
+        assert_eq!(
+            get_error_code(auth.authorize(&add_alert)),
+            JSONRPC_ERROR_FORBIDDEN
+        );
+        // Discovery is not restricted by the key
+        let list_coins =
+            make_request(json!({"id": null, "jsonrpc": "2.0", "method": "list_coins"}));
+        assert!(auth.authorize(&list_coins).is_ok());
+
+        // Authentication is turned off
+        let auth = WsAuth::new(None);
+        assert!(auth.authorize(&subscribe(&["BTC", "ETH"])).is_ok());
+        assert!(auth.authenticate("any_key"));
+    }
+
+    #[test]
+    fn test_reserve_subscription() {
+        let api_keys = Some(Arc::new(ApiKeys::new(vec![ApiKey {
+            key: "restricted".to_string(),
+            methods: None,
+            coins: None,
+            max_subscriptions: Some(2),
+        }])));
+
+        let auth = WsAuth::new(api_keys.clone());
+        assert_eq!(
+            auth.reserve_subscription().err().unwrap().0,
+            JSONRPC_ERROR_UNAUTHORIZED
+        );
+        assert!(auth.authenticate("restricted"));
+        let slot_1 = auth.reserve_subscription().unwrap();
+        assert!(slot_1.is_some());
+
+        // Subscriptions of all connections of the key are counted
+        let auth_2 = WsAuth::new(api_keys);
+        assert!(auth_2.authenticate("restricted"));
+        let _slot_2 = auth_2.reserve_subscription().unwrap();
+        assert_eq!(
+            auth_2.reserve_subscription().err().unwrap().0,
+            JSONRPC_ERROR_FORBIDDEN
+        );
+        assert_eq!(
+            auth.reserve_subscription().err().unwrap().0,
+            JSONRPC_ERROR_FORBIDDEN
+        );
+
+        // Slot is released on drop
+        drop(slot_1);
+        assert!(auth_2.reserve_subscription().is_ok());
+
+        // Authentication is turned off
+        assert!(WsAuth::new(None).reserve_subscription().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join("index_daemon_test_api_keys.yaml");
+        std::fs::write(
+            &path,
+            "keys:\n  - key: some_key\n    methods: [coin_average_price]\n    coins: [BTC]\n    max_subscriptions: 5\n  - key: other_key\n",
+        )
+        .unwrap();
+
+        let api_keys = ApiKeys::load(path.to_str().unwrap());
+        std::fs::remove_file(path).unwrap();
+
+        let api_key = api_keys.get("some_key").unwrap();
+        assert_eq!(api_key.methods, Some(vec![WsChannelName::CoinAveragePrice]));
+        assert_eq!(api_key.coins, Some(vec!["BTC".to_string()]));
+        assert_eq!(api_key.max_subscriptions, Some(5));
+
+        let api_key = api_keys.get("other_key").unwrap();
+        assert!(api_key.methods.is_none());
+        assert!(api_key.coins.is_none());
+        assert!(api_key.max_subscriptions.is_none());
+
+        assert!(api_keys.get("unknown_key").is_none());
+    }
+}
@@ -1,27 +1,86 @@
 use crate::worker::market_helpers::market_value::MarketValue;
+use schemars::JsonSchema;
 use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, Eq, PartialEq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum WsChannelName {
     CoinAveragePrice,
     CoinAveragePriceCandles,
     CoinExchangePrice,
     CoinExchangeVolume,
+    // Triggered alerts. Subscriptions are kept in the channels of coin average price.
+    Alerts,
     CoinAveragePriceHistorical,
     CoinAveragePriceCandlesHistorical,
+    AddAlert,
+    RemoveAlert,
+    ListAlerts,
     Unsubscribe,
+    Configure,
+    Auth,
+    ListCoins,
+    ListExchanges,
+    ListMethods,
+    ListIntervals,
+    #[serde(rename = "rpc.discover")]
+    RpcDiscover,
 }
 
 impl WsChannelName {
+    pub const ALL: [Self; 18] = [
+        Self::CoinAveragePrice,
+        Self::CoinAveragePriceCandles,
+        Self::CoinExchangePrice,
+        Self::CoinExchangeVolume,
+        Self::Alerts,
+        Self::CoinAveragePriceHistorical,
+        Self::CoinAveragePriceCandlesHistorical,
+        Self::AddAlert,
+        Self::RemoveAlert,
+        Self::ListAlerts,
+        Self::Unsubscribe,
+        Self::Configure,
+        Self::Auth,
+        Self::ListCoins,
+        Self::ListExchanges,
+        Self::ListMethods,
+        Self::ListIntervals,
+        Self::RpcDiscover,
+    ];
+
+    /// Whether method is a channel (subscription), not a request
+    pub fn is_channel(&self) -> bool {
+        matches!(
+            self,
+            Self::CoinAveragePrice
+                | Self::CoinAveragePriceCandles
+                | Self::CoinExchangePrice
+                | Self::CoinExchangeVolume
+                | Self::Alerts
+        )
+    }
+
     pub fn is_worker_channel(&self) -> bool {
         match self {
-            Self::CoinAveragePrice { .. } | Self::CoinAveragePriceCandles { .. } => true,
+            Self::CoinAveragePrice { .. }
+            | Self::CoinAveragePriceCandles { .. }
+            | Self::Alerts { .. } => true,
             Self::CoinExchangePrice { .. }
             | Self::CoinExchangeVolume { .. }
             | Self::CoinAveragePriceHistorical { .. }
             | Self::CoinAveragePriceCandlesHistorical { .. } => false,
-            Self::Unsubscribe => unreachable!(),
+            Self::AddAlert
+            | Self::RemoveAlert
+            | Self::ListAlerts
+            | Self::Unsubscribe
+            | Self::Configure
+            | Self::Auth
+            | Self::ListCoins
+            | Self::ListExchanges
+            | Self::ListMethods
+            | Self::ListIntervals
+            | Self::RpcDiscover => unreachable!(),
         }
     }
 
@@ -30,10 +89,21 @@ impl WsChannelName {
             Self::CoinAveragePrice { .. }
             | Self::CoinAveragePriceHistorical { .. }
             | Self::CoinAveragePriceCandles { .. }
-            | Self::CoinAveragePriceCandlesHistorical { .. } => MarketValue::PairAveragePrice,
+            | Self::CoinAveragePriceCandlesHistorical { .. }
+            | Self::Alerts { .. } => MarketValue::PairAveragePrice,
             Self::CoinExchangePrice { .. } => MarketValue::PairExchangePrice,
             Self::CoinExchangeVolume { .. } => MarketValue::PairExchangeVolume,
-            Self::Unsubscribe { .. } => unreachable!(),
+            Self::AddAlert
+            | Self::RemoveAlert
+            | Self::ListAlerts
+            | Self::Unsubscribe
+            | Self::Configure
+            | Self::Auth
+            | Self::ListCoins
+            | Self::ListExchanges
+            | Self::ListMethods
+            | Self::ListIntervals
+            | Self::RpcDiscover => unreachable!(),
         }
     }
 }
@@ -47,6 +117,7 @@ impl FromStr for WsChannelName {
             "coin_average_price_candles" => Ok(Self::CoinAveragePriceCandles),
             "coin_exchange_price" => Ok(Self::CoinExchangePrice),
             "coin_exchange_volume" => Ok(Self::CoinExchangeVolume),
+            "alerts" => Ok(Self::Alerts),
             "coin_average_price_historical" => Ok(Self::CoinAveragePriceHistorical),
             "coin_average_price_candles_historical" => Ok(Self::CoinAveragePriceCandlesHistorical),
             _ => Err(()),
@@ -61,11 +132,22 @@ impl ToString for WsChannelName {
             Self::CoinAveragePriceCandles { .. } => "coin_average_price_candles".to_string(),
             Self::CoinExchangePrice { .. } => "coin_exchange_price".to_string(),
             Self::CoinExchangeVolume { .. } => "coin_exchange_volume".to_string(),
+            Self::Alerts { .. } => "alerts".to_string(),
             Self::CoinAveragePriceHistorical { .. } => "coin_average_price_historical".to_string(),
             Self::CoinAveragePriceCandlesHistorical { .. } => {
                 "coin_average_price_candles_historical".to_string()
             }
-            Self::Unsubscribe => unreachable!(),
+            Self::AddAlert
+            | Self::RemoveAlert
+            | Self::ListAlerts
+            | Self::Unsubscribe
+            | Self::Configure
+            | Self::Auth
+            | Self::ListCoins
+            | Self::ListExchanges
+            | Self::ListMethods
+            | Self::ListIntervals
+            | Self::RpcDiscover => unreachable!(),
         }
     }
 }
@@ -1,4 +1,5 @@
 use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcId;
+use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
 use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
 
 #[derive(Serialize, Clone)]
@@ -6,3 +7,86 @@ pub struct WsChannelResponse {
     pub id: Option<JsonRpcId>,
     pub result: WsChannelResponsePayload,
 }
+
+/// Jsonrpc response with additional `result` fields. Is used for binary encodings.
+#[derive(Serialize)]
+pub struct WsChannelResponseEnvelope<'a> {
+    jsonrpc: &'static str,
+    id: &'a Option<JsonRpcId>,
+    result: WsChannelResponseResultEnvelope<'a>,
+}
+
+#[derive(Serialize)]
+struct WsChannelResponseResultEnvelope<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    method: Option<WsChannelName>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subscription_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    snapshot: bool,
+    #[serde(flatten)]
+    payload: &'a WsChannelResponsePayload,
+}
+
+impl<'a> WsChannelResponseEnvelope<'a> {
+    pub fn new(
+        id: &'a Option<JsonRpcId>,
+        payload: &'a WsChannelResponsePayload,
+        method: Option<WsChannelName>,
+        subscription_id: Option<&'a str>,
+        snapshot: bool,
+    ) -> Self {
+        // `SuccSub` and `Err` payloads already have `method` field
+        let method = match payload {
+            WsChannelResponsePayload::SuccSub { .. } | WsChannelResponsePayload::Err { .. } => None,
+            _ => method,
+        };
+
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: WsChannelResponseResultEnvelope {
+                method,
+                subscription_id,
+                snapshot,
+                payload,
+            },
+        }
+    }
+}
+
+/// Jsonrpc notification of a subscription. Is used for binary encodings.
+#[derive(Serialize)]
+pub struct WsChannelNotificationEnvelope<'a> {
+    jsonrpc: &'static str,
+    method: WsChannelName,
+    params: WsChannelNotificationParamsEnvelope<'a>,
+}
+
+#[derive(Serialize)]
+struct WsChannelNotificationParamsEnvelope<'a> {
+    subscription_id: &'a str,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    snapshot: bool,
+    #[serde(flatten)]
+    payload: &'a WsChannelResponsePayload,
+}
+
+impl<'a> WsChannelNotificationEnvelope<'a> {
+    pub fn new(
+        payload: &'a WsChannelResponsePayload,
+        method: WsChannelName,
+        subscription_id: &'a str,
+        snapshot: bool,
+    ) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            method,
+            params: WsChannelNotificationParamsEnvelope {
+                subscription_id,
+                snapshot,
+                payload,
+            },
+        }
+    }
+}
@@ -1,61 +1,165 @@
+use crate::worker::network_helpers::alerts::alert_rule::{AlertNotification, AlertRule};
 use crate::worker::network_helpers::ws_server::candles::{Candle, Candles};
+use crate::worker::network_helpers::ws_server::discovery::{
+    ExchangeInfo, IntervalInfo, MethodInfo,
+};
 use crate::worker::network_helpers::ws_server::f64_snapshot::F64Snapshots;
 use crate::worker::network_helpers::ws_server::ser_date_into_timestamp;
 use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeZone, Utc};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
 
-#[derive(Serialize, Clone)]
+/// Variants of the schema are titled by methods, which return them
+#[derive(Serialize, JsonSchema, Clone)]
 #[serde(untagged)]
 pub enum WsChannelResponsePayload {
+    #[schemars(title = "success")]
     SuccSub {
         method: WsChannelName,
         message: String,
     },
+    #[schemars(title = "error")]
     Err {
         method: Option<WsChannelName>,
         code: i64,
         message: String,
     },
+    #[schemars(title = "coin_average_price")]
     CoinAveragePrice {
         coin: String,
         value: f64,
         #[serde(with = "ser_date_into_timestamp")]
+        #[schemars(with = "i64")]
         timestamp: DateTime<Utc>,
     },
+    #[schemars(title = "coin_exchange_price")]
     CoinExchangePrice {
         coin: String,
         exchange: String,
         value: f64,
         #[serde(with = "ser_date_into_timestamp")]
+        #[schemars(with = "i64")]
         timestamp: DateTime<Utc>,
     },
+    #[schemars(title = "coin_exchange_volume")]
     CoinExchangeVolume {
         coin: String,
         exchange: String,
         value: f64,
         #[serde(with = "ser_date_into_timestamp")]
+        #[schemars(with = "i64")]
         timestamp: DateTime<Utc>,
     },
-    CoinAveragePriceHistorical {
-        coin: String,
-        values: F64Snapshots,
-    },
-    CoinAveragePriceCandles {
-        coin: String,
-        value: Candle,
-    },
-    CoinAveragePriceCandlesHistorical {
-        coin: String,
-        values: Candles,
-    },
+    #[schemars(title = "alerts")]
+    Alert(AlertNotification),
+    #[schemars(title = "coin_average_price_historical")]
+    CoinAveragePriceHistorical { coin: String, values: F64Snapshots },
+    #[schemars(title = "coin_average_price_candles")]
+    CoinAveragePriceCandles { coin: String, value: Candle },
+    #[schemars(title = "coin_average_price_candles_historical")]
+    CoinAveragePriceCandlesHistorical { coin: String, values: Candles },
+    #[schemars(title = "add_alert")]
+    AlertAdded { alert: AlertRule },
+    #[schemars(title = "list_alerts")]
+    Alerts { alerts: Vec<AlertRule> },
+    #[schemars(title = "list_coins")]
+    Coins { coins: Vec<String> },
+    #[schemars(title = "list_exchanges")]
+    Exchanges { exchanges: Vec<ExchangeInfo> },
+    #[schemars(title = "list_methods")]
+    Methods { methods: Vec<MethodInfo> },
+    #[schemars(title = "list_intervals")]
+    Intervals { intervals: Vec<IntervalInfo> },
+    /// OpenRPC document
+    #[schemars(skip)]
+    Schema(serde_json::Value),
 }
 
 impl WsChannelResponsePayload {
+    /// Parses `result` of a successful response or `params` of a notification of `method`.
+    /// Variants can't be told apart by fields only, so `method` is needed.
+    pub fn from_value(method: WsChannelName, value: Value) -> Result<Self, String> {
+        let mut object = match value {
+            Value::Object(object) => object,
+            _ => return Err("Payload must be an object.".to_string()),
+        };
+        let object = &mut object;
+
+        let res = match method {
+            WsChannelName::CoinAveragePrice => Self::CoinAveragePrice {
+                coin: take_field(object, "coin")?,
+                value: take_field(object, "value")?,
+                timestamp: take_timestamp(object)?,
+            },
+            WsChannelName::CoinExchangePrice => Self::CoinExchangePrice {
+                coin: take_field(object, "coin")?,
+                exchange: take_field(object, "exchange")?,
+                value: take_field(object, "value")?,
+                timestamp: take_timestamp(object)?,
+            },
+            WsChannelName::CoinExchangeVolume => Self::CoinExchangeVolume {
+                coin: take_field(object, "coin")?,
+                exchange: take_field(object, "exchange")?,
+                value: take_field(object, "value")?,
+                timestamp: take_timestamp(object)?,
+            },
+            WsChannelName::Alerts => Self::Alert(
+                serde_json::from_value(Value::Object(std::mem::take(object)))
+                    .map_err(|e| format!("Wrong alert. {}", e))?,
+            ),
+            WsChannelName::CoinAveragePriceHistorical => Self::CoinAveragePriceHistorical {
+                coin: take_field(object, "coin")?,
+                values: take_field(object, "values")?,
+            },
+            WsChannelName::CoinAveragePriceCandles => Self::CoinAveragePriceCandles {
+                coin: take_field(object, "coin")?,
+                value: take_field(object, "value")?,
+            },
+            WsChannelName::CoinAveragePriceCandlesHistorical => {
+                Self::CoinAveragePriceCandlesHistorical {
+                    coin: take_field(object, "coin")?,
+                    values: take_field(object, "values")?,
+                }
+            }
+            WsChannelName::AddAlert => Self::AlertAdded {
+                alert: take_field(object, "alert")?,
+            },
+            WsChannelName::ListAlerts => Self::Alerts {
+                alerts: take_field(object, "alerts")?,
+            },
+            WsChannelName::RemoveAlert
+            | WsChannelName::Unsubscribe
+            | WsChannelName::Configure
+            | WsChannelName::Auth => Self::SuccSub {
+                method: take_field(object, "method")?,
+                message: take_field(object, "message")?,
+            },
+            WsChannelName::ListCoins => Self::Coins {
+                coins: take_field(object, "coins")?,
+            },
+            WsChannelName::ListExchanges => Self::Exchanges {
+                exchanges: take_field(object, "exchanges")?,
+            },
+            WsChannelName::ListMethods => Self::Methods {
+                methods: take_field(object, "methods")?,
+            },
+            WsChannelName::ListIntervals => Self::Intervals {
+                intervals: take_field(object, "intervals")?,
+            },
+            WsChannelName::RpcDiscover => Self::Schema(Value::Object(std::mem::take(object))),
+        };
+
+        Ok(res)
+    }
+
     pub fn get_method(&self) -> Option<WsChannelName> {
         match self {
             Self::CoinAveragePrice { .. } => Some(WsChannelName::CoinAveragePrice),
             Self::CoinExchangePrice { .. } => Some(WsChannelName::CoinExchangePrice),
             Self::CoinExchangeVolume { .. } => Some(WsChannelName::CoinExchangeVolume),
+            Self::Alert(..) => Some(WsChannelName::Alerts),
             Self::CoinAveragePriceHistorical { .. } => {
                 Some(WsChannelName::CoinAveragePriceHistorical)
             }
@@ -63,6 +167,13 @@ impl WsChannelResponsePayload {
             Self::CoinAveragePriceCandlesHistorical { .. } => {
                 Some(WsChannelName::CoinAveragePriceCandlesHistorical)
             }
+            Self::AlertAdded { .. } => Some(WsChannelName::AddAlert),
+            Self::Alerts { .. } => Some(WsChannelName::ListAlerts),
+            Self::Coins { .. } => Some(WsChannelName::ListCoins),
+            Self::Exchanges { .. } => Some(WsChannelName::ListExchanges),
+            Self::Methods { .. } => Some(WsChannelName::ListMethods),
+            Self::Intervals { .. } => Some(WsChannelName::ListIntervals),
+            Self::Schema(..) => Some(WsChannelName::RpcDiscover),
             Self::SuccSub { method, .. } => Some(*method),
             Self::Err { method, .. } => *method,
         }
@@ -76,24 +187,83 @@ impl WsChannelResponsePayload {
             | Self::CoinAveragePriceHistorical { coin, .. }
             | Self::CoinAveragePriceCandles { coin, .. }
             | Self::CoinAveragePriceCandlesHistorical { coin, .. } => coin.to_string(),
-            Self::SuccSub { .. } | Self::Err { .. } => {
+            Self::Alert(notification) => notification.coin.to_string(),
+            Self::SuccSub { .. }
+            | Self::AlertAdded { .. }
+            | Self::Alerts { .. }
+            | Self::Err { .. }
+            | Self::Coins { .. }
+            | Self::Exchanges { .. }
+            | Self::Methods { .. }
+            | Self::Intervals { .. }
+            | Self::Schema(..) => {
                 unreachable!()
             }
         }
     }
 
+    /// Messages with the same key (within one subscription) can replace each other
+    pub fn get_conflation_key(&self) -> Option<String> {
+        match self {
+            Self::CoinAveragePrice { coin, .. } | Self::CoinAveragePriceCandles { coin, .. } => {
+                Some(coin.to_string())
+            }
+            Self::CoinExchangePrice { coin, exchange, .. }
+            | Self::CoinExchangeVolume { coin, exchange, .. } => {
+                Some(format!("{}:{}", coin, exchange))
+            }
+            Self::Alert(..)
+            | Self::CoinAveragePriceHistorical { .. }
+            | Self::CoinAveragePriceCandlesHistorical { .. }
+            | Self::SuccSub { .. }
+            | Self::Err { .. }
+            | Self::AlertAdded { .. }
+            | Self::Alerts { .. }
+            | Self::Coins { .. }
+            | Self::Exchanges { .. }
+            | Self::Methods { .. }
+            | Self::Intervals { .. }
+            | Self::Schema(..) => None,
+        }
+    }
+
     pub fn get_timestamp(&self) -> DateTime<Utc> {
         match self {
             Self::CoinAveragePrice { timestamp, .. }
             | Self::CoinExchangePrice { timestamp, .. }
             | Self::CoinExchangeVolume { timestamp, .. } => *timestamp,
             Self::CoinAveragePriceCandles { value, .. } => value.timestamp,
+            Self::Alert(notification) => notification.timestamp,
             Self::CoinAveragePriceHistorical { .. }
             | Self::CoinAveragePriceCandlesHistorical { .. }
             | Self::SuccSub { .. }
-            | Self::Err { .. } => {
+            | Self::Err { .. }
+            | Self::AlertAdded { .. }
+            | Self::Alerts { .. }
+            | Self::Coins { .. }
+            | Self::Exchanges { .. }
+            | Self::Methods { .. }
+            | Self::Intervals { .. }
+            | Self::Schema(..) => {
                 unreachable!()
             }
         }
     }
 }
+
+fn take_field<T: DeserializeOwned>(
+    object: &mut Map<String, Value>,
+    key: &str,
+) -> Result<T, String> {
+    let value = object.remove(key).unwrap_or(Value::Null);
+
+    serde_json::from_value(value).map_err(|e| format!("Wrong field: {}. {}", key, e))
+}
+
+fn take_timestamp(object: &mut Map<String, Value>) -> Result<DateTime<Utc>, String> {
+    let timestamp: i64 = take_field(object, "timestamp")?;
+
+    Utc.timestamp_opt(timestamp, 0)
+        .single()
+        .ok_or_else(|| "Wrong field: timestamp.".to_string())
+}
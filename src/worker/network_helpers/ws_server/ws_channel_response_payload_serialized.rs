@@ -0,0 +1,380 @@
+use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcId;
+use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use crate::worker::network_helpers::ws_server::ws_channel_response::{
+    WsChannelNotificationEnvelope, WsChannelResponseEnvelope,
+};
+use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+use crate::worker::network_helpers::ws_server::ws_encoding::WsEncoding;
+use async_tungstenite::tungstenite::protocol::Message;
+
+/// Payload, serialized once (into JSON) and shared by all subscribers.
+/// Only the per-subscriber envelope is serialized for each of them.
+pub struct WsChannelResponsePayloadSerialized {
+    pub payload: WsChannelResponsePayload,
+    json: String,
+}
+
+impl WsChannelResponsePayloadSerialized {
+    pub fn new(payload: WsChannelResponsePayload) -> Self {
+        let json = serde_json::to_string(&payload).unwrap();
+
+        Self { payload, json }
+    }
+
+    fn push_field(response: &mut String, key: &str, value: &str) {
+        if !response.ends_with('{') {
+            response.push(',');
+        }
+        response.push('"');
+        response.push_str(key);
+        response.push_str("\":");
+        response.push_str(value);
+    }
+
+    /// Makes jsonrpc response: envelope (`jsonrpc`, `id`) with `method`, `subscription_id` and `snapshot` marker
+    /// spliced into serialized payload (`result`)
+    pub fn make_response(
+        &self,
+        id: &Option<JsonRpcId>,
+        method: WsChannelName,
+        subscription_id: &str,
+        snapshot: bool,
+    ) -> String {
+        let mut response = String::with_capacity(self.json.len() + 128);
+        response.push_str("{\"jsonrpc\":\"2.0\",\"id\":");
+        response.push_str(&serde_json::to_string(id).unwrap());
+        response.push_str(",\"result\":{");
+
+        // `SuccSub` and `Err` payloads already have `method` field
+        if !matches!(
+            self.payload,
+            WsChannelResponsePayload::SuccSub { .. } | WsChannelResponsePayload::Err { .. }
+        ) {
+            Self::push_field(
+                &mut response,
+                "method",
+                &serde_json::to_string(&method).unwrap(),
+            );
+        }
+        Self::push_field(
+            &mut response,
+            "subscription_id",
+            &serde_json::to_string(subscription_id).unwrap(),
+        );
+        if snapshot {
+            Self::push_field(&mut response, "snapshot", "true");
+        }
+        self.push_payload_fields(&mut response);
+        response.push('}');
+
+        response
+    }
+
+    /// Appends payload fields and the closing brace of the object
+    fn push_payload_fields(&self, response: &mut String) {
+        // Payload fields (without the opening brace)
+        let fields = &self.json[1..];
+        if fields != "}" {
+            response.push(',');
+        }
+        response.push_str(fields);
+    }
+
+    /// Makes jsonrpc notification: `method` and `params` with `subscription_id` and `snapshot` marker
+    /// spliced into serialized payload
+    pub fn make_notification(
+        &self,
+        method: WsChannelName,
+        subscription_id: &str,
+        snapshot: bool,
+    ) -> String {
+        let mut notification = String::with_capacity(self.json.len() + 128);
+        notification.push_str("{\"jsonrpc\":\"2.0\",\"method\":");
+        notification.push_str(&serde_json::to_string(&method).unwrap());
+        notification.push_str(",\"params\":{");
+
+        Self::push_field(
+            &mut notification,
+            "subscription_id",
+            &serde_json::to_string(subscription_id).unwrap(),
+        );
+        if snapshot {
+            Self::push_field(&mut notification, "snapshot", "true");
+        }
+        self.push_payload_fields(&mut notification);
+        notification.push('}');
+
+        notification
+    }
+
+    /// Makes message of a subscription in the connection's encoding:
+    /// jsonrpc notification or (if `legacy`) jsonrpc response with request's `id`
+    pub fn make_message(
+        &self,
+        encoding: WsEncoding,
+        legacy: bool,
+        id: &Option<JsonRpcId>,
+        method: WsChannelName,
+        subscription_id: &str,
+        snapshot: bool,
+    ) -> Message {
+        match (encoding, legacy) {
+            (WsEncoding::Json, true) => {
+                Message::Text(self.make_response(id, method, subscription_id, snapshot))
+            }
+            (WsEncoding::Json, false) => {
+                Message::Text(self.make_notification(method, subscription_id, snapshot))
+            }
+            (encoding, true) => encoding.encode(&WsChannelResponseEnvelope::new(
+                id,
+                &self.payload,
+                Some(method),
+                Some(subscription_id),
+                snapshot,
+            )),
+            (encoding, false) => encoding.encode(&WsChannelNotificationEnvelope::new(
+                &self.payload,
+                method,
+                subscription_id,
+                snapshot,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::ws_server::candles::Candle;
+    use crate::worker::network_helpers::ws_server::hepler_functions::add_jsonrpc_version_and_method;
+    use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcId;
+    use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+    use crate::worker::network_helpers::ws_server::ws_channel_response::WsChannelResponse;
+    use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+    use crate::worker::network_helpers::ws_server::ws_channel_response_payload_serialized::WsChannelResponsePayloadSerialized;
+    use crate::worker::network_helpers::ws_server::ws_encoding::WsEncoding;
+    use async_tungstenite::tungstenite::protocol::Message;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    fn make_response(
+        payload: WsChannelResponsePayload,
+        id: Option<JsonRpcId>,
+        snapshot: bool,
+    ) -> serde_json::Value {
+        let payload = WsChannelResponsePayloadSerialized::new(payload);
+        let response = payload.make_response(
+            &id,
+            WsChannelName::CoinExchangePrice,
+            "some \"sub\" id",
+            snapshot,
+        );
+
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn test_make_response() {
+        let payload = WsChannelResponsePayload::CoinExchangePrice {
+            coin: "BTC".to_string(),
+            exchange: "binance".to_string(),
+            value: 43500.5,
+            timestamp: Utc.timestamp(1644440400, 0),
+        };
+
+        assert_eq!(
+            make_response(payload.clone(), Some(JsonRpcId::Int(1)), false),
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "method": "coin_exchange_price",
+                    "subscription_id": "some \"sub\" id",
+                    "coin": "BTC",
+                    "exchange": "binance",
+                    "value": 43500.5,
+                    "timestamp": 1644440400,
+                }
+            })
+        );
+
+        assert_eq!(
+            make_response(payload, None, true),
+            json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "result": {
+                    "method": "coin_exchange_price",
+                    "subscription_id": "some \"sub\" id",
+                    "snapshot": true,
+                    "coin": "BTC",
+                    "exchange": "binance",
+                    "value": 43500.5,
+                    "timestamp": 1644440400,
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_make_response_succ_sub() {
+        let payload = WsChannelResponsePayload::SuccSub {
+            method: WsChannelName::CoinExchangePrice,
+            message: "Successfully subscribed.".to_string(),
+        };
+
+        assert_eq!(
+            make_response(payload, Some(JsonRpcId::Str("id".to_string())), false),
+            json!({
+                "jsonrpc": "2.0",
+                "id": "id",
+                "result": {
+                    "method": "coin_exchange_price",
+                    "subscription_id": "some \"sub\" id",
+                    "message": "Successfully subscribed.",
+                }
+            })
+        );
+    }
+
+    /// Spliced response is the same as the one made by serializing the whole response
+    #[test]
+    fn test_make_response_is_equal_to_full_serialization() {
+        let timestamp = Utc.timestamp(1644440400, 0);
+        let payloads = vec![
+            (
+                WsChannelName::CoinAveragePrice,
+                WsChannelResponsePayload::CoinAveragePrice {
+                    coin: "BTC".to_string(),
+                    value: 43500.5,
+                    timestamp,
+                },
+            ),
+            (
+                WsChannelName::CoinExchangeVolume,
+                WsChannelResponsePayload::CoinExchangeVolume {
+                    coin: "ETH".to_string(),
+                    exchange: "coinbase".to_string(),
+                    value: 1000.0,
+                    timestamp,
+                },
+            ),
+            (
+                WsChannelName::CoinAveragePriceCandles,
+                WsChannelResponsePayload::CoinAveragePriceCandles {
+                    coin: "BTC".to_string(),
+                    value: Candle::calculate(vec![(timestamp, 1.0), (timestamp, 2.0)], timestamp)
+                        .unwrap(),
+                },
+            ),
+        ];
+
+        for (method, payload) in payloads {
+            for id in [
+                None,
+                Some(JsonRpcId::Int(1)),
+                Some(JsonRpcId::Str("id".to_string())),
+            ] {
+                let response = WsChannelResponse {
+                    id: id.clone(),
+                    result: payload.clone(),
+                };
+                let mut expected = serde_json::to_string(&response).unwrap();
+                add_jsonrpc_version_and_method(&mut expected, Some(method));
+                let mut expected: serde_json::Value = serde_json::from_str(&expected).unwrap();
+                expected["result"]["subscription_id"] = serde_json::Value::from("sub_id");
+
+                let real = WsChannelResponsePayloadSerialized::new(payload.clone())
+                    .make_response(&id, method, "sub_id", false);
+                let real: serde_json::Value = serde_json::from_str(&real).unwrap();
+
+                assert_eq!(real, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_make_notification() {
+        let payload = WsChannelResponsePayload::CoinExchangePrice {
+            coin: "BTC".to_string(),
+            exchange: "binance".to_string(),
+            value: 43500.5,
+            timestamp: Utc.timestamp(1644440400, 0),
+        };
+        let payload = WsChannelResponsePayloadSerialized::new(payload);
+
+        let notification =
+            payload.make_notification(WsChannelName::CoinExchangePrice, "some \"sub\" id", true);
+        let notification: serde_json::Value = serde_json::from_str(&notification).unwrap();
+
+        assert_eq!(
+            notification,
+            json!({
+                "jsonrpc": "2.0",
+                "method": "coin_exchange_price",
+                "params": {
+                    "subscription_id": "some \"sub\" id",
+                    "snapshot": true,
+                    "coin": "BTC",
+                    "exchange": "binance",
+                    "value": 43500.5,
+                    "timestamp": 1644440400,
+                }
+            })
+        );
+    }
+
+    /// Binary encodings have the same content as JSON
+    #[test]
+    fn test_make_message_binary() {
+        let timestamp = Utc.timestamp(1644440400, 0);
+        let payloads = vec![
+            WsChannelResponsePayload::CoinExchangePrice {
+                coin: "BTC".to_string(),
+                exchange: "binance".to_string(),
+                value: 43500.5,
+                timestamp,
+            },
+            WsChannelResponsePayload::SuccSub {
+                method: WsChannelName::CoinExchangePrice,
+                message: "Successfully subscribed.".to_string(),
+            },
+        ];
+        let id = Some(JsonRpcId::Str("id".to_string()));
+
+        for payload in payloads {
+            let payload = WsChannelResponsePayloadSerialized::new(payload);
+
+            for (snapshot, legacy) in [(false, false), (true, false), (false, true), (true, true)] {
+                let make_message = |encoding| {
+                    payload.make_message(
+                        encoding,
+                        legacy,
+                        &id,
+                        WsChannelName::CoinExchangePrice,
+                        "sub_id",
+                        snapshot,
+                    )
+                };
+
+                let expected = match make_message(WsEncoding::Json) {
+                    Message::Text(text) => {
+                        serde_json::from_str::<serde_json::Value>(&text).unwrap()
+                    }
+                    _ => panic!("Text message expected."),
+                };
+
+                let real: serde_json::Value = match make_message(WsEncoding::Msgpack) {
+                    Message::Binary(bytes) => rmp_serde::from_slice(&bytes).unwrap(),
+                    _ => panic!("Binary message expected."),
+                };
+                assert_eq!(real, expected);
+
+                let real: serde_json::Value = match make_message(WsEncoding::Cbor) {
+                    Message::Binary(bytes) => ciborium::de::from_reader(bytes.as_slice()).unwrap(),
+                    _ => panic!("Binary message expected."),
+                };
+                assert_eq!(real, expected);
+            }
+        }
+    }
+}
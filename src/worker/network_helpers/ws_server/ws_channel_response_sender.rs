@@ -1,79 +1,254 @@
+use crate::metrics::metrics::{METRICS, WS_SERVER_MESSAGES_DROPPED, WS_SERVER_SUBSCRIPTIONS};
 use crate::worker::network_helpers::ws_server::channels::ws_channel_subscription_request::WsChannelSubscriptionRequest;
-use crate::worker::network_helpers::ws_server::hepler_functions::ws_send_response;
-use crate::worker::network_helpers::ws_server::ws_channel_response::WsChannelResponse;
+use crate::worker::network_helpers::ws_server::outbound_queue::{
+    OutboundQueueClosed, OutboundQueueSender,
+};
+use crate::worker::network_helpers::ws_server::ws_auth::ApiKeySubscriptionSlot;
 use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
-use async_tungstenite::tungstenite::protocol::Message;
+use crate::worker::network_helpers::ws_server::ws_channel_response_payload_serialized::WsChannelResponsePayloadSerialized;
 use chrono::{DateTime, Utc, MIN_DATETIME};
-use futures::channel::mpsc::{TrySendError, UnboundedSender};
 use std::cmp;
+use std::sync::Arc;
 
-type Tx = UnboundedSender<Message>;
+type Tx = OutboundQueueSender;
+
+/// Counts the subscription in `WS_SERVER_SUBSCRIPTIONS` while it exists
+struct SubscriptionGauge {
+    method: String,
+}
+
+impl SubscriptionGauge {
+    fn new(method: String) -> Self {
+        METRICS.inc(WS_SERVER_SUBSCRIPTIONS, &[("method", &method)]);
+
+        Self { method }
+    }
+}
+
+impl Drop for SubscriptionGauge {
+    fn drop(&mut self) {
+        METRICS.dec(WS_SERVER_SUBSCRIPTIONS, &[("method", &self.method)]);
+    }
+}
 
 #[derive(Clone)]
 pub struct WsChannelResponseSender {
     broadcast_recipient: Tx,
+    pub subscription_id: String,
     pub request: WsChannelSubscriptionRequest,
     last_send_timestamp: DateTime<Utc>,
+    /// The latest response, which came too early (see `frequency_ms`). It is sent by `Self::flush`.
+    pending: Option<Arc<WsChannelResponsePayloadSerialized>>,
+    /// Whether any update came after subscription (then snapshot isn't newer and isn't sent)
+    is_updated: bool,
+    /// Shared by clones of the sender (one per coin and exchange of the subscription),
+    /// so the subscription is counted once
+    _subscription_gauge: Arc<SubscriptionGauge>,
+    /// Counts the subscription against API key's limit, while any clone of the sender exists
+    _api_key_slot: Option<Arc<ApiKeySubscriptionSlot>>,
 }
 
 impl WsChannelResponseSender {
     pub fn new(
         broadcast_recipient: Tx,
+        subscription_id: String,
         mut request: WsChannelSubscriptionRequest,
         ws_answer_timeout_ms: u64,
     ) -> Self {
         let frequency_ms = request.get_frequency_ms().unwrap_or(ws_answer_timeout_ms);
         let frequency_ms = cmp::max(ws_answer_timeout_ms, frequency_ms);
         request.set_frequency_ms(frequency_ms);
+        let subscription_gauge = SubscriptionGauge::new(request.get_method().to_string());
 
         Self {
             broadcast_recipient,
+            subscription_id,
             request,
             last_send_timestamp: MIN_DATETIME,
+            pending: None,
+            is_updated: false,
+            _subscription_gauge: Arc::new(subscription_gauge),
+            _api_key_slot: None,
         }
     }
 
-    fn send_inner(&self, response: WsChannelResponse) -> Result<(), TrySendError<Message>> {
-        ws_send_response(
-            &self.broadcast_recipient,
-            response,
-            Some(self.request.get_method()),
-        )
+    pub fn set_api_key_slot(&mut self, api_key_slot: Option<ApiKeySubscriptionSlot>) {
+        self._api_key_slot = api_key_slot.map(Arc::new);
+    }
+
+    fn send_inner(
+        &self,
+        response_payload: &WsChannelResponsePayloadSerialized,
+        snapshot: bool,
+    ) -> Result<(), OutboundQueueClosed> {
+        let response = response_payload.make_message(
+            self.broadcast_recipient.get_encoding(),
+            self.broadcast_recipient.is_legacy_responses(),
+            &self.request.get_id(),
+            self.request.get_method(),
+            &self.subscription_id,
+            snapshot,
+        );
+        let conflation_key = response_payload
+            .payload
+            .get_conflation_key()
+            .map(|v| format!("{}:{}", self.subscription_id, v));
+
+        self.broadcast_recipient
+            .send_conflatable(response, conflation_key)
     }
 
-    pub fn send_succ_sub_notif(&self) -> Result<(), TrySendError<Message>> {
+    /// Is sent only for legacy responses. Otherwise successful subscription is a response to the request
+    /// (one subscription is added to many channels, but the request gets only one response).
+    pub fn send_succ_sub_notif(&self) -> Result<(), OutboundQueueClosed> {
+        if !self.broadcast_recipient.is_legacy_responses() {
+            return Ok(());
+        }
+
         let response_payload = WsChannelResponsePayload::SuccSub {
             method: self.request.get_method(),
             message: "Successfully subscribed.".to_string(),
         };
-        let response = WsChannelResponse {
-            id: self.request.get_id(),
-            result: response_payload,
-        };
+        let response_payload = WsChannelResponsePayloadSerialized::new(response_payload);
 
-        self.send_inner(response)
+        self.send_inner(&response_payload, false)
     }
 
+    /// Sends the current value right after subscription. Doesn't depend on `frequency_ms`.
+    /// Snapshot is taken after the subscription is added, so no update is lost. If an update
+    /// already came, snapshot isn't sent (it may be older than the update) and `None` is returned.
+    pub fn send_snapshot(
+        &self,
+        response_payload: WsChannelResponsePayload,
+    ) -> Option<Result<(), OutboundQueueClosed>> {
+        if self.is_updated {
+            return None;
+        }
+        let response_payload = WsChannelResponsePayloadSerialized::new(response_payload);
+
+        Some(self.send_inner(&response_payload, true))
+    }
+
+    fn is_enough_time_passed(&self, timestamp: DateTime<Utc>) -> bool {
+        if let Some(frequency_ms) = self.request.get_frequency_ms() {
+            (timestamp - self.last_send_timestamp).num_milliseconds() as u64 > frequency_ms
+        } else {
+            unreachable!("Request's frequency_ms must be set in fn WsChannelResponseSender::new()")
+        }
+    }
+
+    fn send_now(
+        &mut self,
+        response_payload: Arc<WsChannelResponsePayloadSerialized>,
+    ) -> Result<(), OutboundQueueClosed> {
+        self.last_send_timestamp = Utc::now();
+        self.pending = None;
+
+        self.send_inner(&response_payload, false)
+    }
+
+    /// Sends response if enough time passed since the last dispatch (see `frequency_ms`).
+    /// Otherwise response is kept as pending (replaces previous pending response)
+    /// and `None` is returned. Responses, which can't be conflated (alerts), are sent at once.
+    /// Payload is serialized once by caller and shared by all subscribers.
     pub fn send(
         &mut self,
-        response: WsChannelResponse,
-    ) -> Option<Result<(), TrySendError<Message>>> {
-        let timestamp = response.result.get_timestamp();
+        response_payload: Arc<WsChannelResponsePayloadSerialized>,
+    ) -> Option<Result<(), OutboundQueueClosed>> {
+        self.is_updated = true;
 
-        if let Some(frequency_ms) = self.request.get_frequency_ms() {
-            if (timestamp - self.last_send_timestamp).num_milliseconds() as u64 > frequency_ms {
-                // Enough time passed
-                self.last_send_timestamp = Utc::now();
+        if response_payload.payload.get_conflation_key().is_none() {
+            return Some(self.send_inner(&response_payload, false));
+        }
 
-                let send_msg_result = self.send_inner(response);
+        let timestamp = response_payload.payload.get_timestamp();
 
-                Some(send_msg_result)
-            } else {
-                // Too early
-                None
+        if self.is_enough_time_passed(timestamp) {
+            Some(self.send_now(response_payload))
+        } else {
+            // Too early
+            if self.pending.replace(response_payload).is_some() {
+                let method = self.request.get_method().to_string();
+                METRICS.inc(
+                    WS_SERVER_MESSAGES_DROPPED,
+                    &[("method", &method), ("reason", "conflated")],
+                );
             }
+
+            None
+        }
+    }
+
+    /// Sends pending response if its time has come
+    pub fn flush(&mut self) -> Option<Result<(), OutboundQueueClosed>> {
+        if self.pending.is_some() && self.is_enough_time_passed(Utc::now()) {
+            let response_payload = self.pending.take().unwrap();
+
+            Some(self.send_now(response_payload))
         } else {
-            unreachable!("Request's frequency_ms must be set in fn WsChannelResponseSender::new()")
+            None
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::ws_server::channels::worker_channels::WorkerChannels;
+    use crate::worker::network_helpers::ws_server::channels::ws_channel_subscription_request::WsChannelSubscriptionRequest;
+    use crate::worker::network_helpers::ws_server::outbound_queue::{
+        outbound_queue, OutboundQueuePolicy, OutboundQueueReceiver,
+    };
+    use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+    use crate::worker::network_helpers::ws_server::ws_channel_response_payload_serialized::WsChannelResponsePayloadSerialized;
+    use crate::worker::network_helpers::ws_server::ws_channel_response_sender::WsChannelResponseSender;
+    use chrono::Utc;
+    use futures::{FutureExt, StreamExt};
+    use std::sync::Arc;
+    use std::{thread, time};
+
+    fn make_response(value: f64) -> Arc<WsChannelResponsePayloadSerialized> {
+        Arc::new(WsChannelResponsePayloadSerialized::new(
+            WsChannelResponsePayload::CoinAveragePrice {
+                coin: "BTC".to_string(),
+                value,
+                timestamp: Utc::now(),
+            },
+        ))
+    }
+
+    fn get_values(rx: &mut OutboundQueueReceiver) -> Vec<f64> {
+        let mut values = Vec::new();
+        while let Some(Some(msg)) = rx.next().now_or_never() {
+            let msg: serde_json::Value = serde_json::from_str(&msg.to_string()).unwrap();
+            values.push(msg["params"]["value"].as_f64().unwrap());
+        }
+
+        values
+    }
+
+    #[test]
+    fn test_conflation() {
+        let request =
+            WsChannelSubscriptionRequest::WorkerChannels(WorkerChannels::CoinAveragePrice {
+                id: None,
+                coins: vec!["BTC".to_string()],
+                frequency_ms: Some(200),
+            });
+        let (tx, mut rx) = outbound_queue(100, OutboundQueuePolicy::DropOldest);
+        let mut sender = WsChannelResponseSender::new(tx, "sub_id".to_string(), request, 100);
+
+        assert!(sender.send(make_response(1.0)).is_some());
+        // Too early: values are conflated
+        assert!(sender.send(make_response(2.0)).is_none());
+        assert!(sender.send(make_response(3.0)).is_none());
+        assert!(sender.flush().is_none());
+        assert_eq!(get_values(&mut rx), vec![1.0]);
+
+        thread::sleep(time::Duration::from_millis(300));
+
+        // The latest value is delivered without new updates
+        assert!(sender.flush().is_some());
+        assert!(sender.flush().is_none());
+        assert_eq!(get_values(&mut rx), vec![3.0]);
+    }
+}
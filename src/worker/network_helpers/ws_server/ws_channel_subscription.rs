@@ -0,0 +1,80 @@
+use crate::worker::network_helpers::ws_server::channels::ws_channel_subscription_request::WsChannelSubscriptionRequest;
+use crate::worker::network_helpers::ws_server::outbound_queue::{
+    outbound_queue, OutboundQueuePolicy, OutboundQueueReceiver,
+};
+use crate::worker::network_helpers::ws_server::ws_auth::ApiKeySubscriptionSlot;
+use crate::worker::network_helpers::ws_server::ws_channel_response_sender::WsChannelResponseSender;
+use crate::worker::network_helpers::ws_server::ws_channels_holder::WsChannelsHolder;
+use crate::worker::network_helpers::ws_server::ws_server::{
+    WsServer, JSONRPC_ERROR_INVALID_PARAMS,
+};
+use async_tungstenite::tungstenite::protocol::Message;
+use futures::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use uuid::Uuid;
+
+/// Subscription of a transport with one subscription per connection (event stream, gRPC stream).
+/// It's a stream of the same messages as websocket notifications (json encoding).
+/// Subscription is removed from channels after it's dropped.
+pub struct WsChannelSubscription {
+    ws_channels_holder: WsChannelsHolder,
+    conn_id: String,
+    messages: OutboundQueueReceiver,
+}
+
+impl WsChannelSubscription {
+    /// Adds subscription to the channels (and queues snapshot).
+    /// `api_key_slot` - counts the subscription against API key's limit, while it exists.
+    /// Returns error code and message if some of the channels don't exist.
+    pub fn new(
+        ws_channels_holder: &WsChannelsHolder,
+        request: WsChannelSubscriptionRequest,
+        api_key_slot: Option<ApiKeySubscriptionSlot>,
+        ws_answer_timeout_ms: u64,
+        ws_outbound_queue_size: usize,
+        ws_outbound_queue_policy: OutboundQueuePolicy,
+    ) -> Result<Self, (i64, String)> {
+        let (keys, error_msg) = WsServer::make_channel_keys(&request);
+        if !keys.iter().all(|key| ws_channels_holder.contains_key(key)) {
+            return Err((JSONRPC_ERROR_INVALID_PARAMS, error_msg.to_string()));
+        }
+
+        let (tx, rx) = outbound_queue(ws_outbound_queue_size, ws_outbound_queue_policy);
+        tx.disallow_binary();
+
+        let conn_id = Uuid::new_v4().to_string();
+        let subscription_id = Uuid::new_v4().to_string();
+        let mut response_sender =
+            WsChannelResponseSender::new(tx, subscription_id, request, ws_answer_timeout_ms);
+        response_sender.set_api_key_slot(api_key_slot);
+        for key in keys {
+            ws_channels_holder.add(&key, (conn_id.clone(), response_sender.clone()));
+        }
+
+        Ok(Self {
+            ws_channels_holder: ws_channels_holder.clone(),
+            conn_id,
+            messages: rx,
+        })
+    }
+
+    pub fn get_conn_id(&self) -> &str {
+        &self.conn_id
+    }
+}
+
+impl Stream for WsChannelSubscription {
+    type Item = Message;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.messages).poll_next(cx)
+    }
+}
+
+impl Drop for WsChannelSubscription {
+    fn drop(&mut self) {
+        // The client is disconnected
+        self.ws_channels_holder.remove_connection(&self.conn_id);
+    }
+}
@@ -1,11 +1,18 @@
+use crate::metrics::metrics::{METRICS, WS_SERVER_MESSAGES_DROPPED, WS_SERVER_MESSAGES_SENT};
 use crate::worker::network_helpers::ws_server::channels::ws_channel_subscription_request::WsChannelSubscriptionRequest;
+use crate::worker::network_helpers::ws_server::channels::ws_channel_unsubscribe::WsChannelUnsubscribe;
+use crate::worker::network_helpers::ws_server::outbound_queue::OutboundQueueClosed;
 use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
-use crate::worker::network_helpers::ws_server::ws_channel_response::WsChannelResponse;
 use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+use crate::worker::network_helpers::ws_server::ws_channel_response_payload_serialized::WsChannelResponsePayloadSerialized;
 use crate::worker::network_helpers::ws_server::ws_channel_response_sender::WsChannelResponseSender;
 use std::collections::HashMap;
+use std::sync::Arc;
 
-pub struct WsChannels(HashMap<(String, WsChannelName), WsChannelResponseSender>);
+/// (conn_id, subscription_id)
+pub type WsChannelsKey = (String, String);
+
+pub struct WsChannels(HashMap<WsChannelsKey, WsChannelResponseSender>);
 
 impl WsChannels {
     pub fn new() -> Self {
@@ -15,47 +22,78 @@ impl WsChannels {
     pub fn get_channels_by_method(
         &self,
         method: WsChannelName,
-    ) -> HashMap<&(String, WsChannelName), &WsChannelSubscriptionRequest> {
+    ) -> HashMap<&WsChannelsKey, &WsChannelSubscriptionRequest> {
         self.0
             .iter()
-            .filter(|(k, _)| k.1 == method)
+            .filter(|(_, v)| v.request.get_method() == method)
             .map(|(k, v)| (k, &v.request))
             .collect()
     }
 
-    fn send_inner(
-        sender: &mut WsChannelResponseSender,
-        response_payload: WsChannelResponsePayload,
+    fn handle_send_result(
+        sender: &WsChannelResponseSender,
+        send_msg_result: Option<Result<(), OutboundQueueClosed>>,
     ) -> Result<(), ()> {
-        let response = WsChannelResponse {
-            id: sender.request.get_id(),
-            result: response_payload,
-        };
+        let method = sender.request.get_method().to_string();
 
-        if let Some(send_msg_result) = sender.send(response) {
-            if send_msg_result.is_err() {
+        match send_msg_result {
+            Some(Err(_)) => {
                 // Send msg error. The client is likely disconnected. We stop sending him messages.
+                METRICS.inc(
+                    WS_SERVER_MESSAGES_DROPPED,
+                    &[("method", &method), ("reason", "disconnected")],
+                );
 
                 Err(())
-            } else {
+            }
+            Some(Ok(())) => {
+                METRICS.inc(WS_SERVER_MESSAGES_SENT, &[("method", &method)]);
+
                 Ok(())
             }
-        } else {
-            // Message wasn't sent because of frequency_ms (not enough time has passed since last dispatch)
+            None => {
+                // Message wasn't sent because of frequency_ms (not enough time has passed since last dispatch).
+                // It is kept as pending and will be sent by `Self::flush_pending`.
 
-            Ok(())
+                Ok(())
+            }
         }
     }
 
-    pub fn send_individual(
-        &mut self,
-        responses: HashMap<(String, WsChannelName), WsChannelResponsePayload>,
-    ) {
+    fn send_inner(
+        sender: &mut WsChannelResponseSender,
+        response_payload: Arc<WsChannelResponsePayloadSerialized>,
+    ) -> Result<(), ()> {
+        let send_msg_result = sender.send(response_payload);
+
+        Self::handle_send_result(sender, send_msg_result)
+    }
+
+    /// Sends pending (conflated) responses, whose time has come
+    pub fn flush_pending(&mut self) {
+        let mut keys_to_remove = Vec::new();
+
+        for (key, sender) in self.0.iter_mut() {
+            let send_msg_result = sender.flush();
+
+            if Self::handle_send_result(sender, send_msg_result).is_err() {
+                keys_to_remove.push(key.clone());
+            }
+        }
+
+        for key in keys_to_remove {
+            self.remove_channel(&key);
+        }
+    }
+
+    pub fn send_individual(&mut self, responses: HashMap<WsChannelsKey, WsChannelResponsePayload>) {
         let mut keys_to_remove = Vec::new();
 
         for (key, response_payload) in responses {
             if let Some(sender) = self.0.get_mut(&key) {
-                let send_result = Self::send_inner(sender, response_payload.clone());
+                let response_payload =
+                    Arc::new(WsChannelResponsePayloadSerialized::new(response_payload));
+                let send_result = Self::send_inner(sender, response_payload);
 
                 if send_result.is_err() {
                     // Send msg error. The client is likely disconnected. We stop sending him messages.
@@ -66,14 +104,14 @@ impl WsChannels {
         }
 
         for key in keys_to_remove {
-            self.0.remove(&key);
+            self.remove_channel(&key);
         }
     }
 
     pub fn send_general(&mut self, response_payload: WsChannelResponsePayload) {
         let coin = response_payload.get_coin();
 
-        let senders: HashMap<&(String, WsChannelName), &mut WsChannelResponseSender> = self
+        let senders: HashMap<&WsChannelsKey, &mut WsChannelResponseSender> = self
             .0
             .iter_mut()
             .filter(|(_, v)| v.request.get_coins().contains(&coin))
@@ -82,9 +120,13 @@ impl WsChannels {
         let mut keys_to_remove = Vec::new();
 
         if let Some(response_method) = response_payload.get_method() {
+            // Payload is serialized once and shared by all subscribers
+            let response_payload =
+                Arc::new(WsChannelResponsePayloadSerialized::new(response_payload));
+
             for (key, sender) in senders {
-                if key.1 == response_method {
-                    let send_result = Self::send_inner(sender, response_payload.clone());
+                if sender.request.get_method() == response_method {
+                    let send_result = Self::send_inner(sender, Arc::clone(&response_payload));
 
                     if send_result.is_err() {
                         // Send msg error. The client is likely disconnected. We stop sending him messages.
@@ -96,30 +138,86 @@ impl WsChannels {
         }
 
         for key in keys_to_remove {
-            self.0.remove(&key);
+            self.remove_channel(&key);
         }
     }
 
+    /// Adds subscription. Its snapshot is sent later by `Self::send_snapshot`
+    /// (snapshot can't be taken while `WsChannels` is locked).
     pub fn add_channel(&mut self, conn_id: String, channel: WsChannelResponseSender) {
-        let method = channel.request.get_method();
-
         if channel.send_succ_sub_notif().is_ok() {
-            self.0.insert((conn_id, method), channel);
+            let key = (conn_id, channel.subscription_id.clone());
+            self.0.insert(key, channel);
         } else {
             // Send msg error. The client is likely disconnected. Thus, we don't even establish subscription.
         }
     }
 
-    pub fn remove_channel(&mut self, key: &(String, WsChannelName)) {
+    pub fn send_snapshot(&mut self, key: &WsChannelsKey, snapshot: WsChannelResponsePayload) {
+        if let Some(sender) = self.0.get(key) {
+            let send_msg_result = sender.send_snapshot(snapshot);
+
+            if Self::handle_send_result(sender, send_msg_result).is_err() {
+                self.remove_channel(key);
+            }
+        }
+    }
+
+    pub fn remove_channel(&mut self, key: &WsChannelsKey) {
         self.0.remove(key);
     }
+
+    pub fn get_subscription_ids<'a>(
+        &'a self,
+        conn_id: &'a str,
+    ) -> impl Iterator<Item = &'a String> {
+        self.0
+            .keys()
+            .filter(move |(k_conn_id, _)| k_conn_id == conn_id)
+            .map(|(_, subscription_id)| subscription_id)
+    }
+
+    /// Removes subscriptions of the connection, which match unsubscribe request
+    pub fn remove_channels(&mut self, conn_id: &str, request: &WsChannelUnsubscribe) {
+        let keys_to_remove: Vec<WsChannelsKey> = self
+            .0
+            .iter()
+            .filter(|(k, v)| k.0 == conn_id && request.matches(v))
+            .map(|(k, _)| k.clone())
+            .collect();
+
+        for key in keys_to_remove {
+            self.remove_channel(&key);
+        }
+    }
+}
+
+impl Default for WsChannels {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
 pub mod test {
+    use crate::worker::market_helpers::stored_and_ws_transmissible_f64::StoredAndWsTransmissibleF64;
+    use crate::worker::network_helpers::ws_server::channels::worker_channels::WorkerChannels;
+    use crate::worker::network_helpers::ws_server::channels::ws_channel_subscription_request::WsChannelSubscriptionRequest;
+    use crate::worker::network_helpers::ws_server::channels::ws_channel_unsubscribe::WsChannelUnsubscribe;
+    use crate::worker::network_helpers::ws_server::hepler_functions::ws_send_response;
     use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcId;
+    use crate::worker::network_helpers::ws_server::outbound_queue::{
+        outbound_queue, OutboundQueuePolicy,
+    };
     use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
-    use crate::worker::network_helpers::ws_server::ws_channels::WsChannels;
+    use crate::worker::network_helpers::ws_server::ws_channel_response::WsChannelResponse;
+    use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+    use crate::worker::network_helpers::ws_server::ws_channel_response_sender::WsChannelResponseSender;
+    use crate::worker::network_helpers::ws_server::ws_channels::{WsChannels, WsChannelsKey};
+    use chrono::{Duration, Utc};
+    use futures::{FutureExt, StreamExt};
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
 
     pub fn check_subscriptions(
         ws_channels: &WsChannels,
@@ -128,10 +226,11 @@ pub mod test {
         assert_eq!(subscriptions.len(), ws_channels.0.len());
 
         for (sub_id, method, coins) in subscriptions {
-            let keys: Vec<&(String, WsChannelName)> = ws_channels
+            let keys: Vec<&WsChannelsKey> = ws_channels
                 .0
-                .keys()
-                .filter(|(_conn_id, method_inner)| method_inner == method)
+                .iter()
+                .filter(|(_, v)| &v.request.get_method() == method)
+                .map(|(k, _)| k)
                 .collect();
             assert_eq!(keys.len(), 1);
 
@@ -147,4 +246,269 @@ pub mod test {
             assert_eq!(channel.request.get_coins(), coins);
         }
     }
+
+    #[test]
+    fn test_add_channel_sends_snapshot() {
+        let request =
+            WsChannelSubscriptionRequest::WorkerChannels(WorkerChannels::CoinAveragePrice {
+                id: Some(JsonRpcId::Str("some_id".to_string())),
+                coins: vec!["BTC".to_string()],
+                frequency_ms: None,
+            });
+
+        let mut value = StoredAndWsTransmissibleF64::new(
+            None,
+            vec![WsChannelName::CoinAveragePrice],
+            None,
+            ("BTC".to_string(), "USD".to_string()),
+            Arc::new(Mutex::new(WsChannels::new())),
+            Vec::new(),
+        );
+        assert!(value.get_snapshot(&request).is_none());
+
+        value.set_new_value(100.0);
+        let snapshot = value.get_snapshot(&request);
+        assert!(snapshot.is_some());
+        let key = ("conn_id".to_string(), "subscription_id".to_string());
+
+        // Notification
+        let (tx, mut rx) = outbound_queue(100, OutboundQueuePolicy::DropOldest);
+        let mut ws_channels = WsChannels::new();
+        ws_channels.add_channel(
+            "conn_id".to_string(),
+            WsChannelResponseSender::new(tx, "subscription_id".to_string(), request.clone(), 100),
+        );
+        ws_channels.send_snapshot(&key, snapshot.clone().unwrap());
+
+        let notification: serde_json::Value =
+            serde_json::from_str(&rx.next().now_or_never().unwrap().unwrap().to_string()).unwrap();
+        assert!(notification.get("id").is_none());
+        assert_eq!(notification["method"], "coin_average_price");
+        assert_eq!(notification["params"]["subscription_id"], "subscription_id");
+        assert_eq!(notification["params"]["coin"], "BTC");
+        assert_eq!(notification["params"]["value"], 100.0);
+        assert_eq!(
+            notification["params"]["timestamp"],
+            value.get_timestamp().timestamp()
+        );
+        assert_eq!(notification["params"]["snapshot"], true);
+        assert!(rx.next().now_or_never().is_none());
+
+        // Legacy response
+        let (tx, mut rx) = outbound_queue(100, OutboundQueuePolicy::DropOldest);
+        tx.set_legacy_responses(true);
+        let mut ws_channels = WsChannels::new();
+        ws_channels.add_channel(
+            "conn_id".to_string(),
+            WsChannelResponseSender::new(tx, "subscription_id".to_string(), request.clone(), 100),
+        );
+        ws_channels.send_snapshot(&key, snapshot.clone().unwrap());
+
+        let succ_sub: serde_json::Value =
+            serde_json::from_str(&rx.next().now_or_never().unwrap().unwrap().to_string()).unwrap();
+        assert_eq!(succ_sub["result"]["message"], "Successfully subscribed.");
+        assert!(succ_sub["result"].get("snapshot").is_none());
+
+        let snapshot: serde_json::Value =
+            serde_json::from_str(&rx.next().now_or_never().unwrap().unwrap().to_string()).unwrap();
+        assert_eq!(snapshot["id"], "some_id");
+        assert_eq!(snapshot["result"]["method"], "coin_average_price");
+        assert_eq!(snapshot["result"]["coin"], "BTC");
+        assert_eq!(snapshot["result"]["value"], 100.0);
+        assert_eq!(
+            snapshot["result"]["timestamp"],
+            value.get_timestamp().timestamp()
+        );
+        assert_eq!(snapshot["result"]["snapshot"], true);
+
+        // Update came between subscription and snapshot: snapshot may be older, so it isn't sent
+        let snapshot = value.get_snapshot(&request).unwrap();
+        let (tx, mut rx) = outbound_queue(100, OutboundQueuePolicy::DropOldest);
+        let mut ws_channels = WsChannels::new();
+        ws_channels.add_channel(
+            "conn_id".to_string(),
+            WsChannelResponseSender::new(tx, "subscription_id".to_string(), request, 100),
+        );
+        ws_channels.send_general(WsChannelResponsePayload::CoinAveragePrice {
+            coin: "BTC".to_string(),
+            value: 101.0,
+            timestamp: Utc::now(),
+        });
+        ws_channels.send_snapshot(&key, snapshot);
+
+        let notification: serde_json::Value =
+            serde_json::from_str(&rx.next().now_or_never().unwrap().unwrap().to_string()).unwrap();
+        assert_eq!(notification["params"]["value"], 101.0);
+        assert!(notification["params"].get("snapshot").is_none());
+        assert!(rx.next().now_or_never().is_none());
+    }
+
+    fn make_coin_average_price_request(
+        coins: &[&str],
+        frequency_ms: u64,
+    ) -> WsChannelSubscriptionRequest {
+        WsChannelSubscriptionRequest::WorkerChannels(WorkerChannels::CoinAveragePrice {
+            id: None,
+            coins: coins.iter().map(|v| v.to_string()).collect(),
+            frequency_ms: Some(frequency_ms),
+        })
+    }
+
+    #[test]
+    fn test_multiple_subscriptions_per_channel() {
+        let (tx, mut rx) = outbound_queue(100, OutboundQueuePolicy::DropOldest);
+        let conn_id = "conn_id".to_string();
+
+        let mut ws_channels = WsChannels::new();
+        let requests = [
+            ("sub_1", make_coin_average_price_request(&["BTC"], 100)),
+            (
+                "sub_2",
+                make_coin_average_price_request(&["BTC", "ETH"], 1000),
+            ),
+            ("sub_3", make_coin_average_price_request(&["ETH"], 100)),
+        ];
+        for (subscription_id, request) in requests {
+            ws_channels.add_channel(
+                conn_id.clone(),
+                WsChannelResponseSender::new(tx.clone(), subscription_id.to_string(), request, 100),
+            );
+        }
+        assert_eq!(ws_channels.0.len(), 3);
+        assert!(rx.next().now_or_never().is_none());
+
+        ws_channels.send_general(WsChannelResponsePayload::CoinAveragePrice {
+            coin: "BTC".to_string(),
+            value: 100.0,
+            timestamp: Utc::now(),
+        });
+        let mut subscription_ids = Vec::new();
+        while let Some(Some(msg)) = rx.next().now_or_never() {
+            let msg: serde_json::Value = serde_json::from_str(&msg.to_string()).unwrap();
+            subscription_ids.push(
+                msg["params"]["subscription_id"]
+                    .as_str()
+                    .unwrap()
+                    .to_string(),
+            );
+        }
+        subscription_ids.sort();
+        assert_eq!(subscription_ids, vec!["sub_1", "sub_2"]);
+
+        // Unsubscribe by subscription id
+        ws_channels.remove_channels(
+            &conn_id,
+            &WsChannelUnsubscribe {
+                id: None,
+                method: None,
+                subscription_id: Some("sub_2".to_string()),
+            },
+        );
+        let mut keys: Vec<&WsChannelsKey> = ws_channels.0.keys().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec![
+                &(conn_id.clone(), "sub_1".to_string()),
+                &(conn_id.clone(), "sub_3".to_string())
+            ]
+        );
+
+        // Other connection's subscriptions are untouched
+        let unsubscribe_by_method = WsChannelUnsubscribe {
+            id: None,
+            method: Some(WsChannelName::CoinAveragePrice),
+            subscription_id: None,
+        };
+        ws_channels.remove_channels("other_conn_id", &unsubscribe_by_method);
+        assert_eq!(ws_channels.0.len(), 2);
+
+        // Unsubscribe by method
+        ws_channels.remove_channels(&conn_id, &unsubscribe_by_method);
+        assert!(ws_channels.0.is_empty());
+    }
+
+    /// Fan-out cost of one update: payload serialized once vs response sent by `ws_send_response`
+    /// for every subscriber (the former way).
+    /// Run with `cargo test --release bench_send_general -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_send_general() {
+        let iterations = 10;
+
+        for subscribers_count in [1000, 10000] {
+            let mut ws_channels = WsChannels::new();
+            let mut receivers = Vec::new();
+            for i in 0..subscribers_count {
+                let (tx, rx) = outbound_queue(iterations, OutboundQueuePolicy::DropOldest);
+                ws_channels.add_channel(
+                    format!("conn_{}", i),
+                    WsChannelResponseSender::new(
+                        tx,
+                        format!("sub_{}", i),
+                        make_coin_average_price_request(&["BTC"], 0),
+                        0,
+                    ),
+                );
+                receivers.push(rx);
+            }
+
+            let start = Instant::now();
+            for i in 0..iterations {
+                // Each update is far enough in time from the previous one to be sent right away
+                ws_channels.send_general(WsChannelResponsePayload::CoinAveragePrice {
+                    coin: "BTC".to_string(),
+                    value: i as f64,
+                    timestamp: Utc::now() + Duration::seconds(i as i64 + 1),
+                });
+            }
+            let serialized_once = start.elapsed() / iterations as u32;
+            for mut rx in receivers {
+                let mut count = 0;
+                while let Some(Some(_msg)) = rx.next().now_or_never() {
+                    count += 1;
+                }
+                assert_eq!(count, iterations);
+            }
+
+            // The former way: the response is cloned and sent by `ws_send_response`
+            // for every subscriber (serialized, parsed and serialized again)
+            let mut senders = Vec::new();
+            let mut receivers = Vec::new();
+            for i in 0..subscribers_count {
+                let (tx, rx) = outbound_queue(iterations, OutboundQueuePolicy::DropOldest);
+                senders.push((tx, Some(JsonRpcId::Str(format!("sub_{}", i)))));
+                receivers.push(rx);
+            }
+
+            let start = Instant::now();
+            for i in 0..iterations {
+                let payload = WsChannelResponsePayload::CoinAveragePrice {
+                    coin: "BTC".to_string(),
+                    value: i as f64,
+                    timestamp: Utc::now(),
+                };
+                for (tx, id) in &senders {
+                    let response = WsChannelResponse {
+                        id: id.clone(),
+                        result: payload.clone(),
+                    };
+                    ws_send_response(tx, response, Some(WsChannelName::CoinAveragePrice)).unwrap();
+                }
+            }
+            let serialized_per_subscriber = start.elapsed() / iterations as u32;
+            for mut rx in receivers {
+                let mut count = 0;
+                while let Some(Some(_msg)) = rx.next().now_or_never() {
+                    count += 1;
+                }
+                assert_eq!(count, iterations);
+            }
+
+            println!(
+                "{} subscribers: serialized once: {:?} per update, ws_send_response per subscriber: {:?} per update",
+                subscribers_count, serialized_once, serialized_per_subscriber,
+            );
+        }
+    }
 }
@@ -1,9 +1,17 @@
 use crate::config_scheme::market_config::MarketConfig;
+use crate::worker::market_helpers::hepler_functions::{
+    find_exchange_pair_info, get_live_markets_by_coin, LIVE_MARKET_MAX_AGE_SEC,
+};
+use crate::worker::market_helpers::market::MarketsHashMap;
 use crate::worker::market_helpers::market_value::MarketValue;
-use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+use crate::worker::market_helpers::pair_average_price::PairAveragePriceType;
+use crate::worker::network_helpers::ws_server::channels::ws_channel_subscription_request::WsChannelSubscriptionRequest;
+use crate::worker::network_helpers::ws_server::channels::ws_channel_unsubscribe::WsChannelUnsubscribe;
+use crate::worker::network_helpers::ws_server::discovery::{ExchangeCoinInfo, ExchangeInfo};
+use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
 use crate::worker::network_helpers::ws_server::ws_channel_response_sender::WsChannelResponseSender;
 use crate::worker::network_helpers::ws_server::ws_channels::WsChannels;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::option::Option::Some;
 use std::sync::{Arc, Mutex};
 
@@ -11,11 +19,24 @@ pub type WsChannelsHolderKey = (String, MarketValue, (String, String));
 pub type WsChannelsHolderHashMap = HashMap<WsChannelsHolderKey, Arc<Mutex<WsChannels>>>;
 
 #[derive(Clone)]
-pub struct WsChannelsHolder(WsChannelsHolderHashMap);
+pub struct WsChannelsHolder {
+    ws_channels: WsChannelsHolderHashMap,
+    /// Sources of current values (for snapshots sent right after subscription)
+    markets: MarketsHashMap,
+    pair_average_price: PairAveragePriceType,
+}
 
 impl WsChannelsHolder {
-    pub fn new(ws_channels_holder: WsChannelsHolderHashMap) -> Self {
-        Self(ws_channels_holder)
+    pub fn new(
+        ws_channels: WsChannelsHolderHashMap,
+        markets: MarketsHashMap,
+        pair_average_price: PairAveragePriceType,
+    ) -> Self {
+        Self {
+            ws_channels,
+            markets,
+            pair_average_price,
+        }
     }
 
     pub fn make_hashmap(market_config: &MarketConfig) -> WsChannelsHolderHashMap {
@@ -51,23 +72,201 @@ impl WsChannelsHolder {
     }
 
     pub fn contains_key(&self, key: &WsChannelsHolderKey) -> bool {
-        self.0.contains_key(key)
+        self.ws_channels.contains_key(key)
+    }
+
+    /// Returns sorted coins, which can be subscribed to
+    pub fn get_coins(&self) -> Vec<String> {
+        let coins: BTreeSet<&String> = self
+            .ws_channels
+            .keys()
+            .map(|(_, _, pair)| &pair.0)
+            .collect();
+
+        coins.into_iter().cloned().collect()
+    }
+
+    /// Returns sorted exchanges with their coins. Coin is live if the exchange updated its values recently.
+    pub fn get_exchanges(&self) -> Vec<ExchangeInfo> {
+        let mut exchanges: BTreeMap<&String, BTreeSet<&String>> = BTreeMap::new();
+        for (market_name, _, pair) in self.ws_channels.keys() {
+            if market_name != "worker" {
+                exchanges.entry(market_name).or_default().insert(&pair.0);
+            }
+        }
+
+        let live_markets = get_live_markets_by_coin(&self.markets, LIVE_MARKET_MAX_AGE_SEC);
+
+        exchanges
+            .into_iter()
+            .map(|(exchange, coins)| {
+                let coins: Vec<ExchangeCoinInfo> = coins
+                    .into_iter()
+                    .map(|coin| ExchangeCoinInfo {
+                        coin: coin.to_string(),
+                        live: live_markets
+                            .get(coin)
+                            .is_some_and(|market_names| market_names.contains(exchange)),
+                    })
+                    .collect();
+
+                ExchangeInfo {
+                    exchange: exchange.to_string(),
+                    live: coins.iter().any(|coin| coin.live),
+                    coins,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the current value of `holder_key` in the format of `request` channel.
+    /// Must not be called while `WsChannels` is locked (market locks `WsChannels` while updating values).
+    fn get_snapshot(
+        &self,
+        holder_key: &WsChannelsHolderKey,
+        request: &WsChannelSubscriptionRequest,
+    ) -> Option<WsChannelResponsePayload> {
+        let (market_name, market_value, pair) = holder_key;
+
+        match market_value {
+            MarketValue::PairAveragePrice => self
+                .pair_average_price
+                .get(pair)?
+                .lock()
+                .unwrap()
+                .get_snapshot(request),
+            MarketValue::PairExchangePrice | MarketValue::PairExchangeVolume => {
+                let market = self.markets.get(market_name)?.lock().unwrap();
+                let exchange_pair_info = find_exchange_pair_info(market.get_spine(), pair)?;
+
+                match market_value {
+                    MarketValue::PairExchangePrice => {
+                        exchange_pair_info.last_trade_price.get_snapshot(request)
+                    }
+                    MarketValue::PairExchangeVolume => {
+                        exchange_pair_info.total_volume.get_snapshot(request)
+                    }
+                    MarketValue::PairAveragePrice => unreachable!(),
+                }
+            }
+        }
     }
 
     pub fn add(&self, holder_key: &WsChannelsHolderKey, value: (String, WsChannelResponseSender)) {
-        if let Some(ws_channels) = self.0.get(holder_key) {
+        if let Some(ws_channels) = self.ws_channels.get(holder_key) {
             let (conn_id, response_sender) = value;
+            let key = (conn_id, response_sender.subscription_id.clone());
+            let request = response_sender.request.clone();
 
+            // Subscription is added before the snapshot is taken, so updates in between aren't lost
             ws_channels
                 .lock()
                 .unwrap()
-                .add_channel(conn_id, response_sender);
+                .add_channel(key.0.clone(), response_sender);
+
+            if let Some(snapshot) = self.get_snapshot(holder_key, &request) {
+                ws_channels.lock().unwrap().send_snapshot(&key, snapshot);
+            }
         }
     }
 
-    pub fn remove(&self, ws_channels_key: &(String, WsChannelName)) {
-        for ws_channels in self.0.values() {
-            ws_channels.lock().unwrap().remove_channel(ws_channels_key);
+    pub fn flush_pending(&self) {
+        for ws_channels in self.ws_channels.values() {
+            ws_channels.lock().unwrap().flush_pending();
+        }
+    }
+
+    /// Returns number of the connection's subscriptions
+    /// (one subscription can be stored in many `WsChannels`, one per coin and exchange)
+    pub fn count_subscriptions(&self, conn_id: &str) -> usize {
+        let mut subscription_ids = HashSet::new();
+
+        for ws_channels in self.ws_channels.values() {
+            subscription_ids.extend(
+                ws_channels
+                    .lock()
+                    .unwrap()
+                    .get_subscription_ids(conn_id)
+                    .cloned(),
+            );
+        }
+
+        subscription_ids.len()
+    }
+
+    pub fn remove(&self, conn_id: &str, request: &WsChannelUnsubscribe) {
+        for ws_channels in self.ws_channels.values() {
+            ws_channels
+                .lock()
+                .unwrap()
+                .remove_channels(conn_id, request);
+        }
+    }
+
+    /// Removes all subscriptions of the connection (the client is disconnected)
+    pub fn remove_connection(&self, conn_id: &str) {
+        self.remove(
+            conn_id,
+            &WsChannelUnsubscribe {
+                id: None,
+                method: None,
+                subscription_id: None,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config_scheme::market_config::MarketConfig;
+    use crate::worker::network_helpers::ws_server::ws_channels_holder::WsChannelsHolder;
+    use std::collections::HashMap;
+
+    fn make_holder(market_config: &MarketConfig) -> WsChannelsHolder {
+        WsChannelsHolder::new(
+            WsChannelsHolder::make_hashmap(market_config),
+            HashMap::new(),
+            HashMap::new(),
+        )
+    }
+
+    #[test]
+    fn test_get_coins() {
+        let market_config = MarketConfig::default();
+        let holder = make_holder(&market_config);
+
+        let mut expected: Vec<String> = market_config
+            .exchange_pairs
+            .iter()
+            .map(|v| v.pair.0.clone())
+            .collect();
+        expected.sort();
+        expected.dedup();
+
+        assert_eq!(holder.get_coins(), expected);
+    }
+
+    #[test]
+    fn test_get_exchanges() {
+        let market_config = MarketConfig::default();
+        let holder = make_holder(&market_config);
+        let exchanges = holder.get_exchanges();
+
+        let mut expected: Vec<String> = market_config.markets.clone();
+        expected.sort();
+
+        assert_eq!(
+            exchanges
+                .iter()
+                .map(|v| v.exchange.clone())
+                .collect::<Vec<_>>(),
+            expected
+        );
+        for exchange in exchanges {
+            // No market values were received
+            assert!(!exchange.live);
+            assert_eq!(exchange.coins.len(), holder.get_coins().len());
+            assert!(exchange.coins.iter().all(|coin| !coin.live));
         }
     }
 }
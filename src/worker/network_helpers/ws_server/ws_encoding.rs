@@ -0,0 +1,133 @@
+use async_tungstenite::tungstenite::protocol::Message;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Encoding of messages, sent to websocket client.
+/// Is negotiated per connection (via subprotocol or `configure` request).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WsEncoding {
+    /// Text frames
+    #[default]
+    Json,
+    /// Binary frames
+    Msgpack,
+    /// Binary frames
+    Cbor,
+}
+
+impl WsEncoding {
+    /// Picks the first supported encoding from `Sec-WebSocket-Protocol` header value
+    pub fn from_subprotocols(subprotocols: &str) -> Option<Self> {
+        subprotocols
+            .split(',')
+            .find_map(|subprotocol| subprotocol.trim().parse().ok())
+    }
+
+    pub fn encode<T: Serialize>(&self, value: &T) -> Message {
+        match self {
+            Self::Json => Message::Text(serde_json::to_string(value).unwrap()),
+            // Structs are encoded as maps (with field names), as in JSON
+            Self::Msgpack => Message::Binary(rmp_serde::to_vec_named(value).unwrap()),
+            Self::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::ser::into_writer(value, &mut bytes).unwrap();
+
+                Message::Binary(bytes)
+            }
+        }
+    }
+}
+
+impl FromStr for WsEncoding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "msgpack" => Ok(Self::Msgpack),
+            "cbor" => Ok(Self::Cbor),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ToString for WsEncoding {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Json => "json".to_string(),
+            Self::Msgpack => "msgpack".to_string(),
+            Self::Cbor => "cbor".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::ws_server::hepler_functions::ws_send_response;
+    use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcId;
+    use crate::worker::network_helpers::ws_server::outbound_queue::{
+        outbound_queue, OutboundQueuePolicy,
+    };
+    use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
+    use crate::worker::network_helpers::ws_server::ws_channel_response::WsChannelResponse;
+    use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
+    use crate::worker::network_helpers::ws_server::ws_encoding::WsEncoding;
+    use async_tungstenite::tungstenite::protocol::Message;
+    use futures::{FutureExt, StreamExt};
+    use serde_json::json;
+
+    #[test]
+    fn test_from_subprotocols() {
+        assert_eq!(
+            WsEncoding::from_subprotocols("msgpack"),
+            Some(WsEncoding::Msgpack)
+        );
+        assert_eq!(
+            WsEncoding::from_subprotocols("some_protocol, cbor, msgpack"),
+            Some(WsEncoding::Cbor)
+        );
+        assert_eq!(WsEncoding::from_subprotocols("some_protocol"), None);
+        assert_eq!(WsEncoding::from_subprotocols(""), None);
+    }
+
+    #[test]
+    fn test_connection_encoding() {
+        let (tx, mut rx) = outbound_queue(100, OutboundQueuePolicy::DropOldest);
+        let response = WsChannelResponse {
+            id: Some(JsonRpcId::Int(1)),
+            result: WsChannelResponsePayload::SuccSub {
+                method: WsChannelName::Configure,
+                message: "Successfully configured.".to_string(),
+            },
+        };
+        let expected = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {
+                "method": "configure",
+                "message": "Successfully configured.",
+            }
+        });
+
+        ws_send_response(&tx, response.clone(), None).unwrap();
+        match rx.next().now_or_never().unwrap().unwrap() {
+            Message::Text(text) => {
+                let real: serde_json::Value = serde_json::from_str(&text).unwrap();
+                assert_eq!(real, expected);
+            }
+            _ => panic!("Text message expected."),
+        }
+
+        tx.set_encoding(WsEncoding::Msgpack);
+        ws_send_response(&tx, response, None).unwrap();
+        match rx.next().now_or_never().unwrap().unwrap() {
+            Message::Binary(bytes) => {
+                let real: serde_json::Value = rmp_serde::from_slice(&bytes).unwrap();
+                assert_eq!(real, expected);
+            }
+            _ => panic!("Binary message expected."),
+        }
+    }
+}
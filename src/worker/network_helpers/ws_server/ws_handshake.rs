@@ -0,0 +1,260 @@
+use crate::worker::network_helpers::http_server::http_request::HttpRequest;
+use crate::worker::network_helpers::ws_server::outbound_queue::OutboundQueueSender;
+use crate::worker::network_helpers::ws_server::permessage_deflate::{
+    accept_permessage_deflate, PERMESSAGE_DEFLATE_RESPONSE,
+};
+use crate::worker::network_helpers::ws_server::ws_auth::WsAuth;
+use crate::worker::network_helpers::ws_server::ws_encoding::WsEncoding;
+use async_tungstenite::tungstenite::handshake::server::{
+    Callback, ErrorResponse, Request, Response,
+};
+use async_tungstenite::tungstenite::http::{HeaderValue, StatusCode};
+use std::sync::{Arc, Mutex};
+
+/// Websocket handshake callback. Negotiates connection's settings:
+/// - encoding, requested via subprotocol (`Sec-WebSocket-Protocol` header)
+/// - compression, requested via extension (`Sec-WebSocket-Extensions` header)
+/// - authentication, requested via `token` query param or `Authorization: Bearer` header
+pub struct WsHandshake {
+    pub broadcast_recipient: OutboundQueueSender,
+    pub auth: WsAuth,
+    /// Is set if connection must be rejected (e.g. connection quota is exceeded)
+    pub rejection: Option<StatusCode>,
+    /// Whether compression is turned on in server config
+    pub compression: bool,
+    /// Is set to `true` if compression is accepted
+    pub compression_accepted: Arc<Mutex<bool>>,
+}
+
+impl WsHandshake {
+    fn get_header<'a>(request: &'a Request, key: &str) -> Option<&'a str> {
+        request.headers().get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn make_error_response(status: StatusCode) -> ErrorResponse {
+        let mut error_response = ErrorResponse::new(status.canonical_reason().map(String::from));
+        *error_response.status_mut() = status;
+
+        error_response
+    }
+
+    fn get_token(request: &Request) -> Option<String> {
+        let from_query = request.uri().query().and_then(|query| {
+            query
+                .split('&')
+                .find_map(|param| param.strip_prefix("token="))
+                .map(HttpRequest::decode_url_component)
+        });
+
+        from_query.or_else(|| {
+            Self::get_header(request, "Authorization")
+                .and_then(|v| v.strip_prefix("Bearer "))
+                .map(String::from)
+        })
+    }
+}
+
+impl Callback for WsHandshake {
+    fn on_request(
+        self,
+        request: &Request,
+        mut response: Response,
+    ) -> Result<Response, ErrorResponse> {
+        if let Some(status) = self.rejection {
+            return Err(Self::make_error_response(status));
+        }
+
+        if let Some(token) = Self::get_token(request) {
+            if !self.auth.authenticate(&token) {
+                return Err(Self::make_error_response(StatusCode::UNAUTHORIZED));
+            }
+        }
+
+        let encoding = Self::get_header(request, "Sec-WebSocket-Protocol")
+            .and_then(WsEncoding::from_subprotocols);
+        if let Some(encoding) = encoding {
+            self.broadcast_recipient.set_encoding(encoding);
+            response.headers_mut().insert(
+                "Sec-WebSocket-Protocol",
+                HeaderValue::from_str(&encoding.to_string()).unwrap(),
+            );
+        }
+
+        let compression = self.compression
+            && Self::get_header(request, "Sec-WebSocket-Extensions")
+                .map(accept_permessage_deflate)
+                .unwrap_or(false);
+        if compression {
+            *self.compression_accepted.lock().unwrap() = true;
+            response.headers_mut().insert(
+                "Sec-WebSocket-Extensions",
+                HeaderValue::from_static(PERMESSAGE_DEFLATE_RESPONSE),
+            );
+        }
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcRequest;
+    use crate::worker::network_helpers::ws_server::outbound_queue::{
+        outbound_queue, OutboundQueuePolicy,
+    };
+    use crate::worker::network_helpers::ws_server::ws_auth::{ApiKey, ApiKeys, WsAuth};
+    use crate::worker::network_helpers::ws_server::ws_encoding::WsEncoding;
+    use crate::worker::network_helpers::ws_server::ws_handshake::WsHandshake;
+    use crate::worker::network_helpers::ws_server::ws_request::WsRequest;
+    use crate::worker::network_helpers::ws_server::ws_server::JSONRPC_ERROR_UNAUTHORIZED;
+    use async_tungstenite::tungstenite::handshake::server::{Callback, Request, Response};
+    use async_tungstenite::tungstenite::http::StatusCode;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    fn handshake_with_auth(
+        uri: &str,
+        headers: &[(&str, &str)],
+        compression: bool,
+        auth: WsAuth,
+        rejection: Option<StatusCode>,
+    ) -> Result<(WsEncoding, bool, Response), StatusCode> {
+        let (tx, _rx) = outbound_queue(100, OutboundQueuePolicy::DropOldest);
+        let compression_accepted = Arc::new(Mutex::new(false));
+
+        let mut request = Request::builder().uri(uri);
+        for (key, value) in headers {
+            request = request.header(*key, *value);
+        }
+        let request = request.body(()).unwrap();
+
+        let response = WsHandshake {
+            broadcast_recipient: tx.clone(),
+            auth,
+            rejection,
+            compression,
+            compression_accepted: Arc::clone(&compression_accepted),
+        }
+        .on_request(&request, Response::default())
+        .map_err(|e| e.status())?;
+
+        let compression_accepted = *compression_accepted.lock().unwrap();
+
+        Ok((tx.get_encoding(), compression_accepted, response))
+    }
+
+    fn handshake(headers: &[(&str, &str)], compression: bool) -> (WsEncoding, bool, Response) {
+        handshake_with_auth("/", headers, compression, WsAuth::new(None), None).unwrap()
+    }
+
+    #[test]
+    fn test_encoding_negotiation() {
+        let (encoding, _, response) = handshake(&[("Sec-WebSocket-Protocol", "cbor")], false);
+        assert_eq!(encoding, WsEncoding::Cbor);
+        assert_eq!(
+            response.headers().get("Sec-WebSocket-Protocol").unwrap(),
+            "cbor"
+        );
+
+        // Without subprotocol: default encoding
+        let (encoding, _, response) = handshake(&[], false);
+        assert_eq!(encoding, WsEncoding::Json);
+        assert!(response.headers().get("Sec-WebSocket-Protocol").is_none());
+    }
+
+    #[test]
+    fn test_compression_negotiation() {
+        let offer = [(
+            "Sec-WebSocket-Extensions",
+            "permessage-deflate; client_max_window_bits",
+        )];
+
+        let (_, compression_accepted, response) = handshake(&offer, true);
+        assert!(compression_accepted);
+        assert_eq!(
+            response.headers().get("Sec-WebSocket-Extensions").unwrap(),
+            "permessage-deflate; server_no_context_takeover"
+        );
+
+        // Compression is turned off in config
+        let (_, compression_accepted, response) = handshake(&offer, false);
+        assert!(!compression_accepted);
+        assert!(response.headers().get("Sec-WebSocket-Extensions").is_none());
+
+        // Client didn't offer compression
+        let (_, compression_accepted, _) = handshake(&[], true);
+        assert!(!compression_accepted);
+    }
+
+    #[test]
+    fn test_authentication() {
+        let api_keys = ApiKeys::new(vec![
+            ApiKey {
+                key: "some_key".to_string(),
+                methods: None,
+                coins: None,
+                max_subscriptions: None,
+            },
+            ApiKey {
+                key: "some key/+".to_string(),
+                methods: None,
+                coins: None,
+                max_subscriptions: None,
+            },
+        ]);
+        let api_keys = Some(Arc::new(api_keys));
+        let request: JsonRpcRequest = serde_json::from_value(json!({
+            "id": null,
+            "jsonrpc": "2.0",
+            "method": "coin_average_price",
+            "params": {"coins": ["BTC"]}
+        }))
+        .unwrap();
+        let request: WsRequest = request.try_into().unwrap();
+
+        // Token in query param
+        let auth = WsAuth::new(api_keys.clone());
+        assert!(
+            handshake_with_auth("/?a=1&token=some_key", &[], false, auth.clone(), None).is_ok()
+        );
+        assert!(auth.authorize(&request).is_ok());
+
+        // Percent-encoded token in query param
+        let auth = WsAuth::new(api_keys.clone());
+        let uri = "/?token=some%20key%2F%2B";
+        assert!(handshake_with_auth(uri, &[], false, auth.clone(), None).is_ok());
+        assert!(auth.authorize(&request).is_ok());
+
+        // Token in header
+        let auth = WsAuth::new(api_keys.clone());
+        let headers = [("Authorization", "Bearer some_key")];
+        assert!(handshake_with_auth("/", &headers, false, auth.clone(), None).is_ok());
+        assert!(auth.authorize(&request).is_ok());
+
+        // Wrong token
+        let auth = WsAuth::new(api_keys.clone());
+        let status = handshake_with_auth("/?token=wrong_key", &[], false, auth, None).unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // Without token: connection is established, but requests are not authorized
+        let auth = WsAuth::new(api_keys);
+        assert!(handshake_with_auth("/", &[], false, auth.clone(), None).is_ok());
+        assert_eq!(
+            auth.authorize(&request).unwrap_err().0,
+            JSONRPC_ERROR_UNAUTHORIZED
+        );
+    }
+
+    #[test]
+    fn test_rejection() {
+        let status = handshake_with_auth(
+            "/",
+            &[],
+            false,
+            WsAuth::new(None),
+            Some(StatusCode::TOO_MANY_REQUESTS),
+        )
+        .unwrap_err();
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    }
+}
@@ -0,0 +1,376 @@
+use crate::metrics::metrics::{METRICS, WS_SERVER_REJECTED};
+use crate::worker::network_helpers::ws_server::ws_server::JSONRPC_ERROR_LIMIT_EXCEEDED;
+use async_tungstenite::tungstenite::http::StatusCode;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// State of IP without connections is kept for this time, so reconnecting doesn't reset its rate limit
+const IP_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy)]
+pub struct WsLimitsConfig {
+    pub max_connections: usize,
+    pub max_connections_per_ip: usize,
+    /// Max number of subscriptions per connection
+    pub max_subscriptions: usize,
+    /// Requests per second per connection
+    pub requests_per_sec: u32,
+    /// Requests per second per IP (all connections of the IP)
+    pub requests_per_sec_per_ip: u32,
+}
+
+/// Allows bursts of up to `rate` requests, then `rate` requests per second
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate: u32) -> Self {
+        Self {
+            capacity: rate as f64,
+            tokens: rate as f64,
+            updated_at: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * self.capacity).min(self.capacity);
+        self.updated_at = now;
+    }
+
+    pub fn try_take_at(&mut self, now: Instant) -> bool {
+        self.refill(now);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn try_take(&mut self) -> bool {
+        self.try_take_at(Instant::now())
+    }
+}
+
+struct IpState {
+    connections: usize,
+    bucket: TokenBucket,
+    /// When the last connection of IP was released
+    released_at: Instant,
+}
+
+struct WsLimitsState {
+    connections: usize,
+    ips: HashMap<IpAddr, IpState>,
+}
+
+/// Limits, shared by all connections of the server
+#[derive(Clone)]
+pub struct WsLimits {
+    config: WsLimitsConfig,
+    state: Arc<Mutex<WsLimitsState>>,
+}
+
+impl WsLimits {
+    pub fn new(config: WsLimitsConfig) -> Self {
+        Self {
+            config,
+            state: Arc::new(Mutex::new(WsLimitsState {
+                connections: 0,
+                ips: HashMap::new(),
+            })),
+        }
+    }
+
+    fn reject(reason: &str) {
+        METRICS.inc(WS_SERVER_REJECTED, &[("reason", reason)]);
+    }
+
+    /// Removes states of IPs, which have no connections for `IP_IDLE_TIMEOUT`
+    fn evict_idle_ips(state: &mut WsLimitsState, now: Instant) {
+        state.ips.retain(|_, v| {
+            v.connections > 0 || now.duration_since(v.released_at) < IP_IDLE_TIMEOUT
+        });
+    }
+
+    /// Returns HTTP status to reject the connection with, if connection quota is exceeded.
+    /// Connection is counted until returned value is dropped.
+    pub fn acquire_connection(&self, ip: IpAddr) -> Result<WsConnectionLimits, StatusCode> {
+        self.acquire_connection_at(ip, Instant::now())
+    }
+
+    fn acquire_connection_at(
+        &self,
+        ip: IpAddr,
+        now: Instant,
+    ) -> Result<WsConnectionLimits, StatusCode> {
+        let mut state = self.state.lock().unwrap();
+        Self::evict_idle_ips(&mut state, now);
+
+        if state.connections >= self.config.max_connections {
+            Self::reject("max_connections");
+            return Err(StatusCode::SERVICE_UNAVAILABLE);
+        }
+
+        let requests_per_sec_per_ip = self.config.requests_per_sec_per_ip;
+        let ip_state = state.ips.entry(ip).or_insert_with(|| IpState {
+            connections: 0,
+            bucket: TokenBucket::new(requests_per_sec_per_ip),
+            released_at: now,
+        });
+        if ip_state.connections >= self.config.max_connections_per_ip {
+            Self::reject("max_connections_per_ip");
+            return Err(StatusCode::TOO_MANY_REQUESTS);
+        }
+
+        ip_state.connections += 1;
+        state.connections += 1;
+
+        Ok(WsConnectionLimits {
+            limits: self.clone(),
+            ip,
+            bucket: Mutex::new(TokenBucket::new(self.config.requests_per_sec)),
+            reserved_subscriptions: Arc::new(Mutex::new(0)),
+        })
+    }
+
+    fn release_connection(&self, ip: IpAddr) {
+        let mut state = self.state.lock().unwrap();
+
+        state.connections -= 1;
+        if let Some(ip_state) = state.ips.get_mut(&ip) {
+            ip_state.connections -= 1;
+            ip_state.released_at = Instant::now();
+        }
+    }
+
+    fn try_take_ip(&self, ip: IpAddr) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .ips
+            .get_mut(&ip)
+            .map(|v| v.bucket.try_take())
+            .unwrap_or(false)
+    }
+}
+
+/// Limits of one connection
+pub struct WsConnectionLimits {
+    limits: WsLimits,
+    ip: IpAddr,
+    bucket: Mutex<TokenBucket>,
+    /// Number of subscriptions, which are checked, but not added yet
+    reserved_subscriptions: Arc<Mutex<usize>>,
+}
+
+/// Slot of a subscription, which is being added. Slot is released on drop
+/// (when subscription is added, so it's counted by `subscriptions_count`, or failed).
+pub struct WsSubscriptionSlot {
+    reserved_subscriptions: Arc<Mutex<usize>>,
+}
+
+impl Drop for WsSubscriptionSlot {
+    fn drop(&mut self) {
+        *self.reserved_subscriptions.lock().unwrap() -= 1;
+    }
+}
+
+impl WsConnectionLimits {
+    /// Must be called for every request. Returns error code and message.
+    pub fn check_request(&self) -> Result<(), (i64, String)> {
+        if !self.bucket.lock().unwrap().try_take() {
+            WsLimits::reject("requests_per_sec");
+
+            Err((
+                JSONRPC_ERROR_LIMIT_EXCEEDED,
+                "Limit exceeded. Too many requests per connection.".to_string(),
+            ))
+        } else if !self.limits.try_take_ip(self.ip) {
+            WsLimits::reject("requests_per_sec_per_ip");
+
+            Err((
+                JSONRPC_ERROR_LIMIT_EXCEEDED,
+                "Limit exceeded. Too many requests per IP.".to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Reserves a slot for a new subscription, if max number of subscriptions isn't reached.
+    /// `subscriptions_count` - returns number of the connection's added subscriptions,
+    /// it's called under the lock, so subscriptions being added are counted exactly once.
+    pub fn reserve_subscription<F: FnOnce() -> usize>(
+        &self,
+        subscriptions_count: F,
+    ) -> Result<WsSubscriptionSlot, (i64, String)> {
+        let mut reserved_subscriptions = self.reserved_subscriptions.lock().unwrap();
+        let subscriptions_count = subscriptions_count() + *reserved_subscriptions;
+
+        if subscriptions_count >= self.limits.config.max_subscriptions {
+            WsLimits::reject("max_subscriptions");
+
+            Err((
+                JSONRPC_ERROR_LIMIT_EXCEEDED,
+                "Limit exceeded. Max number of subscriptions is reached.".to_string(),
+            ))
+        } else {
+            *reserved_subscriptions += 1;
+
+            Ok(WsSubscriptionSlot {
+                reserved_subscriptions: Arc::clone(&self.reserved_subscriptions),
+            })
+        }
+    }
+}
+
+impl Drop for WsConnectionLimits {
+    fn drop(&mut self) {
+        self.limits.release_connection(self.ip);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::ws_server::ws_limits::{
+        TokenBucket, WsLimits, WsLimitsConfig, IP_IDLE_TIMEOUT,
+    };
+    use async_tungstenite::tungstenite::http::StatusCode;
+    use std::net::IpAddr;
+    use std::time::{Duration, Instant};
+
+    fn make_limits() -> WsLimits {
+        WsLimits::new(WsLimitsConfig {
+            max_connections: 3,
+            max_connections_per_ip: 2,
+            max_subscriptions: 2,
+            requests_per_sec: 2,
+            requests_per_sec_per_ip: 3,
+        })
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(2);
+
+        // Burst
+        assert!(bucket.try_take_at(now));
+        assert!(bucket.try_take_at(now));
+        assert!(!bucket.try_take_at(now));
+
+        // Refill
+        let now = now + Duration::from_millis(500);
+        assert!(bucket.try_take_at(now));
+        assert!(!bucket.try_take_at(now));
+
+        // Refill is capped by capacity
+        let now = now + Duration::from_secs(10);
+        assert!(bucket.try_take_at(now));
+        assert!(bucket.try_take_at(now));
+        assert!(!bucket.try_take_at(now));
+    }
+
+    #[test]
+    fn test_connection_quota() {
+        let limits = make_limits();
+        let ip_1: IpAddr = "127.0.0.1".parse().unwrap();
+        let ip_2: IpAddr = "127.0.0.2".parse().unwrap();
+
+        let conn_1 = limits.acquire_connection(ip_1).unwrap();
+        let conn_2 = limits.acquire_connection(ip_1).unwrap();
+        assert_eq!(
+            limits.acquire_connection(ip_1).err(),
+            Some(StatusCode::TOO_MANY_REQUESTS)
+        );
+
+        let _conn_3 = limits.acquire_connection(ip_2).unwrap();
+        assert_eq!(
+            limits.acquire_connection(ip_2).err(),
+            Some(StatusCode::SERVICE_UNAVAILABLE)
+        );
+
+        // Connection is released on drop
+        drop(conn_1);
+        let _conn_4 = limits.acquire_connection(ip_1).unwrap();
+
+        drop(conn_2);
+        drop(_conn_4);
+        assert!(limits.state.lock().unwrap().ips.contains_key(&ip_1));
+    }
+
+    #[test]
+    fn test_ip_state_is_kept_after_reconnect() {
+        let limits = make_limits();
+        let ip: IpAddr = "127.0.0.1".parse().unwrap();
+        let now = Instant::now();
+
+        let conn_1 = limits.acquire_connection_at(ip, now).unwrap();
+        assert!(conn_1.check_request().is_ok());
+        assert!(conn_1.check_request().is_ok());
+        drop(conn_1);
+
+        // Reconnecting doesn't reset the rate limit of IP
+        let conn_2 = limits.acquire_connection_at(ip, now).unwrap();
+        assert!(conn_2.check_request().is_ok());
+        assert!(conn_2.check_request().is_err());
+        drop(conn_2);
+
+        // State of idle IP is evicted
+        let ip_2: IpAddr = "127.0.0.2".parse().unwrap();
+        let _conn_3 = limits
+            .acquire_connection_at(ip_2, Instant::now() + IP_IDLE_TIMEOUT)
+            .unwrap();
+        let state = limits.state.lock().unwrap();
+        assert!(!state.ips.contains_key(&ip));
+        assert!(state.ips.contains_key(&ip_2));
+    }
+
+    #[test]
+    fn test_check_request() {
+        let limits = make_limits();
+        let ip: IpAddr = "127.0.0.1".parse().unwrap();
+
+        // Per connection
+        let conn_1 = limits.acquire_connection(ip).unwrap();
+        assert!(conn_1.check_request().is_ok());
+        assert!(conn_1.check_request().is_ok());
+        assert!(conn_1.check_request().is_err());
+
+        // Per IP
+        let conn_2 = limits.acquire_connection(ip).unwrap();
+        assert!(conn_2.check_request().is_ok());
+        assert!(conn_2.check_request().is_err());
+    }
+
+    #[test]
+    fn test_reserve_subscription() {
+        let limits = make_limits();
+        let conn = limits
+            .acquire_connection("127.0.0.1".parse().unwrap())
+            .unwrap();
+
+        assert!(conn.reserve_subscription(|| 2).is_err());
+
+        // Reserved slots are counted until they are released
+        let slot = conn.reserve_subscription(|| 0).unwrap();
+        assert!(conn.reserve_subscription(|| 1).is_err());
+
+        let _slot_2 = conn.reserve_subscription(|| 0).unwrap();
+        assert!(conn.reserve_subscription(|| 0).is_err());
+
+        drop(slot);
+        assert!(conn.reserve_subscription(|| 0).is_ok());
+    }
+}
@@ -4,6 +4,12 @@ use crate::worker::network_helpers::ws_server::channels::ws_channel_action::WsCh
 use crate::worker::network_helpers::ws_server::channels::ws_channel_subscription_request::WsChannelSubscriptionRequest;
 use crate::worker::network_helpers::ws_server::channels::ws_channel_unsubscribe::WsChannelUnsubscribe;
 use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcRequest;
+use crate::worker::network_helpers::ws_server::requests::ws_alert_request::{
+    WsAlertRequest, WsRemoveAlertRequest,
+};
+use crate::worker::network_helpers::ws_server::requests::ws_auth_request::WsAuthRequest;
+use crate::worker::network_helpers::ws_server::requests::ws_configure_request::WsConfigureRequest;
+use crate::worker::network_helpers::ws_server::requests::ws_discovery_request::WsDiscoveryRequest;
 use crate::worker::network_helpers::ws_server::requests::ws_method_request::WsMethodRequest;
 use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
 use serde_json::Map;
@@ -12,6 +18,10 @@ use serde_json::Map;
 pub enum WsRequest {
     Channel(WsChannelAction),
     Method(WsMethodRequest),
+    Configure(WsConfigureRequest),
+    Auth(WsAuthRequest),
+    Discovery(WsDiscoveryRequest),
+    Alert(WsAlertRequest),
 }
 
 impl WsRequest {
@@ -35,7 +45,12 @@ impl TryFrom<JsonRpcRequest> for WsRequest {
     fn try_from(request: JsonRpcRequest) -> Result<Self, Self::Error> {
         let e = "Wrong params.";
         let id = request.id.clone();
-        let object = request.params.as_object().ok_or(e)?;
+        // `params` may be omitted
+        let empty = Map::new();
+        let object = match &request.params {
+            serde_json::Value::Null => &empty,
+            params => params.as_object().ok_or(e)?,
+        };
         let coins = Self::parse_vec_of_str(object, "coins").ok_or(e);
         let frequency_ms = Self::parse_u64(object, "frequency_ms");
         let interval = object
@@ -45,7 +60,9 @@ impl TryFrom<JsonRpcRequest> for WsRequest {
             .map(|v| serde_json::from_value(v).map_err(|_| e));
 
         match request.method {
-            WsChannelName::CoinAveragePrice | WsChannelName::CoinAveragePriceCandles => {
+            WsChannelName::CoinAveragePrice
+            | WsChannelName::CoinAveragePriceCandles
+            | WsChannelName::Alerts => {
                 let coins = coins?;
 
                 let res = match request.method {
@@ -64,6 +81,7 @@ impl TryFrom<JsonRpcRequest> for WsRequest {
                             interval,
                         }
                     }
+                    WsChannelName::Alerts => WorkerChannels::Alerts { id, coins },
                     _ => unreachable!(),
                 };
 
@@ -96,14 +114,61 @@ impl TryFrom<JsonRpcRequest> for WsRequest {
                 )))
             }
             WsChannelName::Unsubscribe => {
-                let method = object.get("method").ok_or(e)?;
-                let method = method.as_str().ok_or(e)?.to_string();
-                let method = method.parse().map_err(|_| e)?;
+                let method = match object.get("method") {
+                    Some(method) => Some(method.as_str().ok_or(e)?.parse().map_err(|_| e)?),
+                    None => None,
+                };
+                let subscription_id = match object.get("subscription_id") {
+                    Some(subscription_id) => Some(subscription_id.as_str().ok_or(e)?.to_string()),
+                    None => None,
+                };
+
+                if method.is_none() && subscription_id.is_none() {
+                    return Err(e.to_string());
+                }
 
                 Ok(Self::Channel(WsChannelAction::Unsubscribe(
-                    WsChannelUnsubscribe { id, method },
+                    WsChannelUnsubscribe {
+                        id,
+                        method,
+                        subscription_id,
+                    },
                 )))
             }
+            WsChannelName::Configure => {
+                let encoding = object
+                    .get("encoding")
+                    .ok_or(e)?
+                    .as_str()
+                    .ok_or(e)?
+                    .parse()
+                    .map_err(|_| e)?;
+
+                Ok(Self::Configure(WsConfigureRequest { encoding }))
+            }
+            WsChannelName::Auth => {
+                let token = object.get("token").ok_or(e)?.as_str().ok_or(e)?.to_string();
+
+                Ok(Self::Auth(WsAuthRequest { token }))
+            }
+            WsChannelName::AddAlert => {
+                let rule = serde_json::from_value(request.params).map_err(|_| e)?;
+
+                Ok(Self::Alert(WsAlertRequest::Add(rule)))
+            }
+            WsChannelName::RemoveAlert => {
+                let id = object.get("id").ok_or(e)?.as_str().ok_or(e)?.to_string();
+
+                Ok(Self::Alert(WsAlertRequest::Remove(WsRemoveAlertRequest {
+                    id,
+                })))
+            }
+            WsChannelName::ListAlerts => Ok(Self::Alert(WsAlertRequest::List)),
+            WsChannelName::ListCoins => Ok(Self::Discovery(WsDiscoveryRequest::Coins)),
+            WsChannelName::ListExchanges => Ok(Self::Discovery(WsDiscoveryRequest::Exchanges)),
+            WsChannelName::ListMethods => Ok(Self::Discovery(WsDiscoveryRequest::Methods)),
+            WsChannelName::ListIntervals => Ok(Self::Discovery(WsDiscoveryRequest::Intervals)),
+            WsChannelName::RpcDiscover => Ok(Self::Discovery(WsDiscoveryRequest::Schema)),
             WsChannelName::CoinAveragePriceHistorical
             | WsChannelName::CoinAveragePriceCandlesHistorical => {
                 let coin = object.get("coin").ok_or(e)?.as_str().ok_or(e)?.to_string();
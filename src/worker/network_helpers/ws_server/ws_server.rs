@@ -1,13 +1,37 @@
+use crate::metrics::metrics::{METRICS, WS_SERVER_CONNECTIONS, WS_SERVER_REJECTED};
 use crate::repository::repositories::WorkerRepositoriesByPairTuple;
 use crate::worker::helper_functions::date_time_from_timestamp_sec;
+use crate::worker::market_helpers::market_value::MarketValue;
+use crate::worker::network_helpers::alerts::alert_registry::AlertRegistry;
+use crate::worker::network_helpers::alerts::alert_rule::{AlertCondition, AlertRule};
 use crate::worker::network_helpers::ws_server::candles::Candles;
 use crate::worker::network_helpers::ws_server::channels::ws_channel_action::WsChannelAction;
 use crate::worker::network_helpers::ws_server::channels::ws_channel_subscription_request::WsChannelSubscriptionRequest;
 use crate::worker::network_helpers::ws_server::channels::ws_channel_unsubscribe::WsChannelUnsubscribe;
+use crate::worker::network_helpers::ws_server::discovery::{list_intervals, list_methods};
 use crate::worker::network_helpers::ws_server::f64_snapshot::F64Snapshots;
-use crate::worker::network_helpers::ws_server::hepler_functions::ws_send_response;
-use crate::worker::network_helpers::ws_server::jsonrpc_request::{JsonRpcId, JsonRpcRequest};
+use crate::worker::network_helpers::ws_server::jsonrpc_request::JsonRpcRequest;
+use crate::worker::network_helpers::ws_server::jsonrpc_responder::{
+    JsonRpcBatch, JsonRpcResponder,
+};
+use crate::worker::network_helpers::ws_server::openrpc::make_openrpc_document;
+use crate::worker::network_helpers::ws_server::outbound_queue::{
+    outbound_queue, OutboundQueuePolicy, OutboundQueueReceiver, OutboundQueueSender,
+};
+use crate::worker::network_helpers::ws_server::permessage_deflate::{
+    DeflateStream, PermessageDeflateConfig,
+};
+use crate::worker::network_helpers::ws_server::requests::ws_alert_request::WsAlertRequest;
+use crate::worker::network_helpers::ws_server::requests::ws_auth_request::WsAuthRequest;
+use crate::worker::network_helpers::ws_server::requests::ws_configure_request::WsConfigureRequest;
+use crate::worker::network_helpers::ws_server::requests::ws_discovery_request::WsDiscoveryRequest;
 use crate::worker::network_helpers::ws_server::requests::ws_method_request::WsMethodRequest;
+use crate::worker::network_helpers::ws_server::unix_socket::{
+    bind_unix_socket, read_ndjson, write_ndjson, UnixSocketConfig, UnixSocketProtocol,
+    MAX_NDJSON_LINE_LEN,
+};
+use crate::worker::network_helpers::ws_server::worker_pool::WorkerPool;
+use crate::worker::network_helpers::ws_server::ws_auth::{ApiKeySubscriptionSlot, ApiKeys, WsAuth};
 use crate::worker::network_helpers::ws_server::ws_channel_name::WsChannelName;
 use crate::worker::network_helpers::ws_server::ws_channel_response::WsChannelResponse;
 use crate::worker::network_helpers::ws_server::ws_channel_response_payload::WsChannelResponsePayload;
@@ -15,33 +39,84 @@ use crate::worker::network_helpers::ws_server::ws_channel_response_sender::WsCha
 use crate::worker::network_helpers::ws_server::ws_channels_holder::{
     WsChannelsHolder, WsChannelsHolderKey,
 };
+use crate::worker::network_helpers::ws_server::ws_encoding::WsEncoding;
+use crate::worker::network_helpers::ws_server::ws_handshake::WsHandshake;
+use crate::worker::network_helpers::ws_server::ws_limits::{WsConnectionLimits, WsLimits};
 use crate::worker::network_helpers::ws_server::ws_request::WsRequest;
-use async_std::{
-    net::{TcpListener, TcpStream},
-    task,
-};
-use async_tungstenite::tungstenite::protocol::Message;
+use crate::worker::network_helpers::ws_server::ws_tls::{WsTls, WsTlsConfig};
+use async_std::{net::TcpListener, task};
+use async_tungstenite::stream::Stream;
+use async_tungstenite::tungstenite::http::StatusCode;
 use chrono::Utc;
-use futures::{
-    channel::mpsc::{unbounded, UnboundedSender},
-    future, pin_mut,
-    prelude::*,
-};
-use std::{collections::HashMap, io, net::SocketAddr, sync::Arc, sync::Mutex, thread, time};
+use futures::{future, pin_mut, prelude::*};
+use std::net::{IpAddr, Ipv4Addr};
+use std::{collections::HashMap, io, sync::Arc, sync::Mutex, time};
 use uuid::Uuid;
 
-type Tx = UnboundedSender<Message>;
-type PeerMap = Arc<Mutex<HashMap<SocketAddr, Tx>>>;
+type Tx = OutboundQueueSender;
+/// Key - `conn_id`
+type PeerMap = Arc<Mutex<HashMap<String, Tx>>>;
+/// Request (or error code and message) with its responder
+type ParsedRequest = (JsonRpcResponder, Result<JsonRpcRequest, (i64, String)>);
+
+/// Connection's state, which is needed to process its requests
+#[derive(Clone)]
+struct WsConnectionContext {
+    ws_channels_holder: WsChannelsHolder,
+    peer_map: PeerMap,
+    client_addr: String,
+    conn_id: String,
+    auth: WsAuth,
+    worker_pool: WorkerPool,
+    ws_answer_timeout_ms: u64,
+    pair_average_price_repositories: Option<WorkerRepositoriesByPairTuple>,
+    alerts: Option<AlertRegistry>,
+}
+
+/// State, shared by all connections of the server
+struct WsServerState {
+    peer_map: PeerMap,
+    limits: WsLimits,
+    worker_pool: WorkerPool,
+}
 
-const JSONRPC_ERROR_INVALID_REQUEST: i64 = -32600;
-const JSONRPC_ERROR_INVALID_PARAMS: i64 = -32602;
-const JSONRPC_ERROR_INTERNAL_ERROR: i64 = -32603;
+pub const JSONRPC_ERROR_UNAUTHORIZED: i64 = -32001;
+pub const JSONRPC_ERROR_FORBIDDEN: i64 = -32003;
+pub const JSONRPC_ERROR_LIMIT_EXCEEDED: i64 = -32005;
+pub const JSONRPC_ERROR_INVALID_REQUEST: i64 = -32600;
+pub const JSONRPC_ERROR_METHOD_NOT_FOUND: i64 = -32601;
+pub const JSONRPC_ERROR_INVALID_PARAMS: i64 = -32602;
+pub const JSONRPC_ERROR_INTERNAL_ERROR: i64 = -32603;
+pub const JSONRPC_ERROR_PARSE_ERROR: i64 = -32700;
 
 pub struct WsServer {
     pub ws_channels_holder: WsChannelsHolder,
+    /// Whether to listen on `ws_addr` (TCP)
+    pub ws_tcp: bool,
     pub ws_addr: String,
+    /// Unix domain socket config (`None` if Unix domain socket is turned off)
+    pub ws_unix_socket: Option<UnixSocketConfig>,
     pub ws_answer_timeout_ms: u64,
+    pub ws_outbound_queue_size: usize,
+    pub ws_outbound_queue_policy: OutboundQueuePolicy,
+    /// `permessage-deflate` compression config (`None` if compression is turned off)
+    pub ws_compression: Option<PermessageDeflateConfig>,
+    /// API keys (`None` if authentication is turned off)
+    pub ws_api_keys: Option<Arc<ApiKeys>>,
+    /// TLS config (`None` if TLS is turned off)
+    pub ws_tls: Option<WsTlsConfig>,
+    /// Limits, shared with event streams and grpc streams
+    pub ws_limits: WsLimits,
+    /// Number of threads, processing requests
+    pub ws_workers: usize,
+    /// Max number of requests, waiting for a free thread
+    pub ws_workers_queue_size: usize,
+    /// Whether responses have the legacy shape (errors and subscription messages inside `result`)
+    pub ws_legacy_responses: bool,
     pub pair_average_price_repositories: Option<WorkerRepositoriesByPairTuple>,
+    /// Registered alerts (`None` if alerts are turned off)
+    pub alerts: Option<AlertRegistry>,
+    pub ws_listener_bound: Arc<Mutex<bool>>,
     pub graceful_shutdown: Arc<Mutex<bool>>,
 }
 
@@ -54,67 +129,131 @@ impl WsServer {
         serde_json::from_str(request)
     }
 
-    fn parse_ws_request(request: JsonRpcRequest) -> Result<WsRequest, String> {
-        request.try_into()
-    }
-
-    fn send_error(
+    /// Parses request object of JSON-RPC 2.0. Request without `id` is a notification (gets no response).
+    fn parse_jsonrpc_value(
+        value: serde_json::Value,
         broadcast_recipient: &Tx,
-        id: Option<JsonRpcId>,
-        method: Option<WsChannelName>,
-        code: i64,
-        message: String,
-    ) {
-        let response = WsChannelResponse {
-            id,
-            result: WsChannelResponsePayload::Err {
-                method,
-                code,
-                message,
-            },
+        batch: Option<Arc<JsonRpcBatch>>,
+    ) -> ParsedRequest {
+        let make_responder = |id, is_notification, batch| {
+            JsonRpcResponder::new(broadcast_recipient.clone(), id, is_notification, batch)
         };
-        let _ = ws_send_response(broadcast_recipient, response, None);
+        let invalid_request = |message: &str| {
+            Err((
+                JSONRPC_ERROR_INVALID_REQUEST,
+                format!("Invalid request. {}", message),
+            ))
+        };
+
+        let object = match value.as_object() {
+            Some(object) => object,
+            None => {
+                return (
+                    make_responder(None, false, batch),
+                    invalid_request("Request must be an object."),
+                )
+            }
+        };
+
+        let (id, is_notification) = match object.get("id").cloned().map(serde_json::from_value) {
+            Some(Ok(id)) => (id, false),
+            Some(Err(_)) => {
+                return (
+                    make_responder(None, false, batch),
+                    invalid_request("Wrong id."),
+                )
+            }
+            None => (None, true),
+        };
+        let responder = make_responder(id, is_notification, batch);
+
+        if object.get("jsonrpc").is_some_and(|v| v != "2.0") {
+            return (responder, invalid_request("Wrong jsonrpc version."));
+        }
+
+        let method = match object.get("method") {
+            Some(serde_json::Value::String(method)) => method,
+            _ => return (responder, invalid_request("Wrong method.")),
+        };
+        let method_value = serde_json::Value::String(method.to_string());
+        if serde_json::from_value::<WsChannelName>(method_value).is_err() {
+            return (
+                responder,
+                Err((
+                    JSONRPC_ERROR_METHOD_NOT_FOUND,
+                    format!("Method not found: {}.", method),
+                )),
+            );
+        }
+
+        let request = serde_json::from_value(value)
+            .map_err(|e| (JSONRPC_ERROR_INVALID_REQUEST, e.to_string()));
+
+        (responder, request)
+    }
+
+    /// Parses message into requests: one request, or many (if message is a batch).
+    /// Every request gets its own responder.
+    fn parse_jsonrpc_message(message: &str, broadcast_recipient: &Tx) -> Vec<ParsedRequest> {
+        if broadcast_recipient.is_legacy_responses() {
+            let request = Self::parse_jsonrpc_request(message)
+                .map_err(|e| (JSONRPC_ERROR_INVALID_REQUEST, e.to_string()));
+            let id = request.as_ref().ok().and_then(|v| v.id.clone());
+            let responder = JsonRpcResponder::new(broadcast_recipient.clone(), id, false, None);
+
+            return vec![(responder, request)];
+        }
+
+        match serde_json::from_str(message) {
+            Ok(serde_json::Value::Array(values)) if !values.is_empty() => {
+                let batch = JsonRpcBatch::new(broadcast_recipient.clone());
+
+                values
+                    .into_iter()
+                    .map(|v| Self::parse_jsonrpc_value(v, broadcast_recipient, Some(batch.clone())))
+                    .collect()
+            }
+            Ok(value) => vec![Self::parse_jsonrpc_value(value, broadcast_recipient, None)],
+            Err(e) => {
+                let responder =
+                    JsonRpcResponder::new(broadcast_recipient.clone(), None, false, None);
+
+                vec![(
+                    responder,
+                    Err((JSONRPC_ERROR_PARSE_ERROR, format!("Parse error. {}", e))),
+                )]
+            }
+        }
+    }
+
+    fn parse_ws_request(request: JsonRpcRequest) -> Result<WsRequest, String> {
+        request.try_into()
     }
 
     fn subscribe_stage_2(
         ws_channels_holder: &mut WsChannelsHolder,
-        broadcast_recipient: &Tx,
+        responder: &JsonRpcResponder,
         conn_id: String,
-        request: WsChannelSubscriptionRequest,
-        ws_answer_timeout_ms: u64,
+        response_sender: WsChannelResponseSender,
         key: WsChannelsHolderKey,
         error_msg: String,
     ) {
-        let sub_id = request.get_id();
-        let method = request.get_method();
+        let method = response_sender.request.get_method();
 
         if ws_channels_holder.contains_key(&key) {
-            let response_sender = WsChannelResponseSender::new(
-                broadcast_recipient.clone(),
-                request,
-                ws_answer_timeout_ms,
-            );
-
             ws_channels_holder.add(&key, (conn_id, response_sender));
         } else {
-            Self::send_error(
-                broadcast_recipient,
-                sub_id,
-                Some(method),
-                JSONRPC_ERROR_INVALID_PARAMS,
-                error_msg,
-            );
+            responder.send_error(Some(method), JSONRPC_ERROR_INVALID_PARAMS, error_msg);
         }
     }
 
-    fn subscribe_stage_1(
-        ws_channels_holder: &mut WsChannelsHolder,
-        broadcast_recipient: &Tx,
-        conn_id: String,
-        request: WsChannelSubscriptionRequest,
-        ws_answer_timeout_ms: u64,
-    ) {
-        let (exchanges, error_msg) = match &request {
+    /// Returns keys of channels, which subscription is added to (one per coin and exchange),
+    /// and error message for the case when some of them don't exist.
+    /// Used by both websocket server and http server.
+    pub fn make_channel_keys(
+        request: &WsChannelSubscriptionRequest,
+    ) -> (Vec<WsChannelsHolderKey>, &'static str) {
+        let (exchanges, error_msg) = match request {
             WsChannelSubscriptionRequest::WorkerChannels(..) => (
                 vec!["worker".to_string()],
                 "Parameter value is wrong: coin.",
@@ -125,78 +264,120 @@ impl WsServer {
             ),
         };
 
-        let market_value = request.get_method().get_market_value();
-        let pairs: Vec<(String, String)> = request
-            .get_coins()
-            .iter()
-            .map(|v| (v.to_string(), "USD".to_string()))
-            .collect();
+        let method = request.get_method();
+        let market_value = method.get_market_value();
+        let mut keys: Vec<WsChannelsHolderKey> = Vec::new();
+        for exchange in exchanges {
+            for coin in request.get_coins() {
+                let pair = (coin.to_string(), "USD".to_string());
+
+                keys.push((exchange.to_string(), market_value, pair));
+            }
+        }
 
-        Self::unsubscribe(ws_channels_holder, conn_id.clone(), request.clone().into());
+        (keys, error_msg)
+    }
 
-        for exchange in exchanges {
-            for pair in pairs.clone() {
-                let key = (exchange.to_string(), market_value, pair);
-
-                Self::subscribe_stage_2(
-                    ws_channels_holder,
-                    broadcast_recipient,
-                    conn_id.clone(),
-                    request.clone(),
-                    ws_answer_timeout_ms,
-                    key,
+    fn subscribe_stage_1(
+        ws_channels_holder: &mut WsChannelsHolder,
+        responder: &JsonRpcResponder,
+        conn_id: String,
+        request: WsChannelSubscriptionRequest,
+        api_key_slot: Option<ApiKeySubscriptionSlot>,
+        ws_answer_timeout_ms: u64,
+    ) {
+        let method = request.get_method();
+        let (keys, error_msg) = Self::make_channel_keys(&request);
+
+        // Subscription is identified by its own id, so there can be many subscriptions per channel
+        let subscription_id = Uuid::new_v4().to_string();
+
+        if !responder.is_legacy() {
+            // Request gets one response, so subscription is added only if all its channels exist
+            if !keys.iter().all(|key| ws_channels_holder.contains_key(key)) {
+                responder.send_error(
+                    Some(method),
+                    JSONRPC_ERROR_INVALID_PARAMS,
                     error_msg.to_string(),
                 );
-
-                // To prevent DDoS attack on a client
-                thread::sleep(time::Duration::from_millis(100));
+                return;
             }
+
+            responder.send_subscribed(method, &subscription_id);
+        }
+
+        // Clones of the sender are added to the channels (one per coin and exchange)
+        let mut response_sender = WsChannelResponseSender::new(
+            responder.get_broadcast_recipient().clone(),
+            subscription_id,
+            request,
+            ws_answer_timeout_ms,
+        );
+        response_sender.set_api_key_slot(api_key_slot);
+        for key in keys {
+            Self::subscribe_stage_2(
+                ws_channels_holder,
+                responder,
+                conn_id.clone(),
+                response_sender.clone(),
+                key,
+                error_msg.to_string(),
+            );
         }
     }
 
     fn unsubscribe(
         ws_channels_holder: &mut WsChannelsHolder,
+        responder: &JsonRpcResponder,
         conn_id: String,
         request: WsChannelUnsubscribe,
     ) {
-        let method = request.method;
-        let key = (conn_id, method);
+        ws_channels_holder.remove(&conn_id, &request);
 
-        ws_channels_holder.remove(&key);
+        // Legacy responses have no response to unsubscribe request
+        if !responder.is_legacy() {
+            responder.send_result(WsChannelResponsePayload::SuccSub {
+                method: WsChannelName::Unsubscribe,
+                message: "Successfully unsubscribed.".to_string(),
+            });
+        }
     }
 
     /// Function adds new channel or removes existing channel (depends on `action`)
     fn process_channel_action_request(
         mut ws_channels_holder: WsChannelsHolder,
-        broadcast_recipient: Tx,
+        responder: JsonRpcResponder,
         conn_id: String,
         action: WsChannelAction,
+        api_key_slot: Option<ApiKeySubscriptionSlot>,
         ws_answer_timeout_ms: u64,
     ) {
         match action {
             WsChannelAction::Subscribe(request) => {
                 Self::subscribe_stage_1(
                     &mut ws_channels_holder,
-                    &broadcast_recipient,
+                    &responder,
                     conn_id,
                     request,
+                    api_key_slot,
                     ws_answer_timeout_ms,
                 );
             }
             WsChannelAction::Unsubscribe(request) => {
-                Self::unsubscribe(&mut ws_channels_holder, conn_id, request);
+                Self::unsubscribe(&mut ws_channels_holder, &responder, conn_id, request);
             }
         }
     }
 
-    /// Prepares response data and sends to recipient
-    fn do_response(
-        broadcast_recipient: Tx,
-        sub_id: Option<JsonRpcId>,
+    /// Prepares response data for a method request.
+    /// Used by both websocket server and http server.
+    pub fn make_method_response(
         request: WsMethodRequest,
-        pair_average_price_repositories: Option<WorkerRepositoriesByPairTuple>,
-    ) {
-        match request.clone() {
+        pair_average_price_repositories: &Option<WorkerRepositoriesByPairTuple>,
+    ) -> WsChannelResponse {
+        let method = request.get_method();
+
+        let (id, result) = match request {
             WsMethodRequest::CoinAveragePriceHistorical {
                 id,
                 coin,
@@ -217,55 +398,205 @@ impl WsServer {
                     .map(date_time_from_timestamp_sec)
                     .unwrap_or_else(Utc::now);
 
-                if let Some(repositories) = pair_average_price_repositories {
-                    if let Some(repository) = repositories.get(&pair_tuple) {
-                        match repository.read_range(from, to) {
-                            Ok(values) => {
-                                let values = values.into_iter().map(|(k, v)| (k, v)).collect();
-
-                                let response = match request {
-                                    WsMethodRequest::CoinAveragePriceHistorical { .. } => WsChannelResponse {
-                                        id,
-                                        result: WsChannelResponsePayload::CoinAveragePriceHistorical {
-                                            coin,
-                                            values: F64Snapshots::with_interval(
-                                                values, interval,
-                                            ),
-                                        },
-                                    },
-                                    WsMethodRequest::CoinAveragePriceCandlesHistorical { .. } => WsChannelResponse {
-                                        id,
-                                        result: WsChannelResponsePayload::CoinAveragePriceCandlesHistorical {
-                                            coin,
-                                            values: Candles::calculate(values, interval),
-                                        },
-                                    },
-                                };
-                                let _ = ws_send_response(&broadcast_recipient, response, None);
+                let repository = pair_average_price_repositories
+                    .as_ref()
+                    .map(|v| v.get(&pair_tuple));
+
+                let result = match repository {
+                    Some(Some(repository)) => match repository.read_range(from, to) {
+                        Ok(values) => {
+                            let values = values.into_iter().collect();
+
+                            match method {
+                                WsChannelName::CoinAveragePriceHistorical => {
+                                    WsChannelResponsePayload::CoinAveragePriceHistorical {
+                                        coin,
+                                        values: F64Snapshots::with_interval(values, interval),
+                                    }
+                                }
+                                WsChannelName::CoinAveragePriceCandlesHistorical => {
+                                    WsChannelResponsePayload::CoinAveragePriceCandlesHistorical {
+                                        coin,
+                                        values: Candles::calculate(values, interval),
+                                    }
+                                }
+                                _ => unreachable!(),
                             }
-                            Err(e) => {
-                                error!("Read range error: {}", e);
-
-                                Self::send_error(
-                                    &broadcast_recipient,
-                                    sub_id,
-                                    Some(request.get_method()),
-                                    JSONRPC_ERROR_INTERNAL_ERROR,
-                                    "Internal error. Read historical data error.".to_string(),
-                                );
+                        }
+                        Err(e) => {
+                            error!("Read range error: {}", e);
+
+                            WsChannelResponsePayload::Err {
+                                method: Some(method),
+                                code: JSONRPC_ERROR_INTERNAL_ERROR,
+                                message: "Internal error. Read historical data error.".to_string(),
                             }
                         }
-                    } else {
-                        Self::send_error(
-                            &broadcast_recipient,
-                            sub_id,
-                            Some(request.get_method()),
-                            JSONRPC_ERROR_INVALID_PARAMS,
-                            format!("Coin {} not supported.", coin),
-                        );
-                    }
-                }
+                    },
+                    Some(None) => WsChannelResponsePayload::Err {
+                        method: Some(method),
+                        code: JSONRPC_ERROR_INVALID_PARAMS,
+                        message: format!("Coin {} not supported.", coin),
+                    },
+                    None => WsChannelResponsePayload::Err {
+                        method: Some(method),
+                        code: JSONRPC_ERROR_INTERNAL_ERROR,
+                        message: "Historical data storage is turned off.".to_string(),
+                    },
+                };
+
+                (id, result)
+            }
+        };
+
+        WsChannelResponse { id, result }
+    }
+
+    /// Prepares response data and sends to recipient
+    fn do_response(
+        responder: JsonRpcResponder,
+        request: WsMethodRequest,
+        pair_average_price_repositories: Option<WorkerRepositoriesByPairTuple>,
+    ) {
+        let response = Self::make_method_response(request, &pair_average_price_repositories);
+
+        responder.send_result(response.result);
+    }
+
+    /// Changes encoding of the connection's messages (including the response to this request)
+    fn configure(responder: &JsonRpcResponder, request: WsConfigureRequest) {
+        let broadcast_recipient = responder.get_broadcast_recipient();
+
+        if request.encoding != WsEncoding::Json && !broadcast_recipient.is_binary_allowed() {
+            responder.send_error(
+                Some(WsChannelName::Configure),
+                JSONRPC_ERROR_INVALID_PARAMS,
+                "Encoding is not supported by the transport.".to_string(),
+            );
+            return;
+        }
+
+        broadcast_recipient.set_encoding(request.encoding);
+
+        responder.send_result(WsChannelResponsePayload::SuccSub {
+            method: WsChannelName::Configure,
+            message: "Successfully configured.".to_string(),
+        });
+    }
+
+    /// Lists coins, exchanges, methods or intervals, which are available to the client,
+    /// or describes the whole API (OpenRPC document)
+    fn discover(
+        responder: &JsonRpcResponder,
+        ws_channels_holder: &WsChannelsHolder,
+        request: WsDiscoveryRequest,
+    ) {
+        let result = match request {
+            WsDiscoveryRequest::Coins => WsChannelResponsePayload::Coins {
+                coins: ws_channels_holder.get_coins(),
+            },
+            WsDiscoveryRequest::Exchanges => WsChannelResponsePayload::Exchanges {
+                exchanges: ws_channels_holder.get_exchanges(),
+            },
+            WsDiscoveryRequest::Methods => WsChannelResponsePayload::Methods {
+                methods: list_methods(),
+            },
+            WsDiscoveryRequest::Intervals => WsChannelResponsePayload::Intervals {
+                intervals: list_intervals(),
+            },
+            WsDiscoveryRequest::Schema => WsChannelResponsePayload::Schema(make_openrpc_document()),
+        };
+
+        responder.send_result(result);
+    }
+
+    /// Authenticates the connection. Is processed synchronously, so subsequent requests are authorized.
+    fn authenticate(responder: &JsonRpcResponder, auth: &WsAuth, request: WsAuthRequest) {
+        if auth.authenticate(&request.token) {
+            responder.send_result(WsChannelResponsePayload::SuccSub {
+                method: WsChannelName::Auth,
+                message: "Successfully authenticated.".to_string(),
+            });
+        } else {
+            responder.send_error(
+                Some(WsChannelName::Auth),
+                JSONRPC_ERROR_UNAUTHORIZED,
+                "Unauthorized. Invalid token.".to_string(),
+            );
+        }
+    }
+
+    /// Checks whether coin and exchange of the alert exist
+    fn check_alert_rule(
+        ws_channels_holder: &WsChannelsHolder,
+        rule: &AlertRule,
+    ) -> Result<(), String> {
+        let pair = (rule.coin.to_string(), "USD".to_string());
+
+        let key = ("worker".to_string(), MarketValue::PairAveragePrice, pair);
+        if !ws_channels_holder.contains_key(&key) {
+            return Err(format!("Coin {} not supported.", rule.coin));
+        }
+
+        if let AlertCondition::Divergence {
+            exchange: Some(exchange),
+            ..
+        } = &rule.condition
+        {
+            let (_, _, pair) = key;
+            let key = (exchange.to_string(), MarketValue::PairExchangePrice, pair);
+            if !ws_channels_holder.contains_key(&key) {
+                return Err(format!("Exchange {} not supported.", exchange));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds, removes or lists alerts.
+    /// `owner` - API key or connection id (alert can be removed only by its owner).
+    fn process_alert_request(
+        responder: &JsonRpcResponder,
+        ws_channels_holder: &WsChannelsHolder,
+        alerts: &Option<AlertRegistry>,
+        owner: &str,
+        request: WsAlertRequest,
+    ) {
+        let method = request.get_method();
+
+        let alerts = match alerts {
+            Some(alerts) => alerts,
+            None => {
+                responder.send_error(
+                    Some(method),
+                    JSONRPC_ERROR_METHOD_NOT_FOUND,
+                    "Alerts are turned off.".to_string(),
+                );
+                return;
             }
+        };
+
+        let result = match request {
+            WsAlertRequest::Add(rule) => Self::check_alert_rule(ws_channels_holder, &rule)
+                .map_err(|message| (JSONRPC_ERROR_INVALID_PARAMS, message))
+                .and_then(|_| alerts.add(rule, owner))
+                .map(|alert| WsChannelResponsePayload::AlertAdded { alert }),
+            WsAlertRequest::Remove(request) => {
+                alerts
+                    .remove(&request.id, owner)
+                    .map(|_| WsChannelResponsePayload::SuccSub {
+                        method,
+                        message: "Successfully removed.".to_string(),
+                    })
+            }
+            WsAlertRequest::List => Ok(WsChannelResponsePayload::Alerts {
+                alerts: alerts.list(),
+            }),
+        };
+
+        match result {
+            Ok(result) => responder.send_result(result),
+            Err((code, message)) => responder.send_error(Some(method), code, message),
         }
     }
 
@@ -274,18 +605,20 @@ impl WsServer {
     /// -- if request is `Ok` then:
     /// -- -- if request is `request`, call `Self::do_response`
     /// -- -- if request is `channel`, call `Self::process_channel_action_request`
-    /// -- else - send error response (call `Self::send_error`)
+    /// -- -- if request is `configure`, call `Self::configure`
+    /// -- -- if request is discovery (`list_*`), call `Self::discover`
+    /// -- -- if request is alert (`*_alert*`), call `Self::process_alert_request`
+    /// -- else - send error response
+    /// `api_key_slot` - reserved slot of API key for a subscription request.
     fn process_ws_channel_request(
-        ws_channels_holder: WsChannelsHolder,
-        client_addr: &SocketAddr,
-        broadcast_recipient: Tx,
-        conn_id: String,
-        sub_id: Option<JsonRpcId>,
+        context: &WsConnectionContext,
+        responder: JsonRpcResponder,
         method: WsChannelName,
         request: Result<WsRequest, String>,
-        ws_answer_timeout_ms: u64,
-        pair_average_price_repositories: Option<WorkerRepositoriesByPairTuple>,
+        api_key_slot: Option<ApiKeySubscriptionSlot>,
     ) {
+        let client_addr = &context.client_addr;
+
         match request {
             Ok(request) => match request {
                 WsRequest::Channel(request) => {
@@ -295,88 +628,169 @@ impl WsServer {
                     );
 
                     Self::process_channel_action_request(
-                        ws_channels_holder,
-                        broadcast_recipient,
-                        conn_id,
+                        context.ws_channels_holder.clone(),
+                        responder,
+                        context.conn_id.clone(),
                         request,
-                        ws_answer_timeout_ms,
+                        api_key_slot,
+                        context.ws_answer_timeout_ms,
                     );
                 }
                 WsRequest::Method(request) => {
                     info!("Client with addr: {} requested: {:?}", client_addr, request);
 
                     Self::do_response(
-                        broadcast_recipient,
-                        sub_id,
+                        responder,
                         request,
-                        pair_average_price_repositories,
+                        context.pair_average_price_repositories.clone(),
                     );
                 }
+                WsRequest::Configure(request) => {
+                    info!(
+                        "Client with addr: {} configured connection: {:?}",
+                        client_addr, request
+                    );
+
+                    Self::configure(&responder, request);
+                }
+                WsRequest::Discovery(request) => {
+                    info!("Client with addr: {} requested: {:?}", client_addr, request);
+
+                    Self::discover(&responder, &context.ws_channels_holder, request);
+                }
+                WsRequest::Alert(request) => {
+                    info!("Client with addr: {} requested: {:?}", client_addr, request);
+
+                    // Alerts of authenticated clients are owned by API key, so they outlive the connection
+                    let owner = context
+                        .auth
+                        .get_key()
+                        .unwrap_or_else(|| context.conn_id.clone());
+
+                    Self::process_alert_request(
+                        &responder,
+                        &context.ws_channels_holder,
+                        &context.alerts,
+                        &owner,
+                        request,
+                    );
+                }
+                WsRequest::Auth(..) => unreachable!(),
             },
             Err(e) => {
-                Self::send_error(
-                    &broadcast_recipient,
-                    sub_id,
-                    Some(method),
-                    JSONRPC_ERROR_INVALID_REQUEST,
-                    e,
-                );
+                let code = if responder.is_legacy() {
+                    JSONRPC_ERROR_INVALID_REQUEST
+                } else {
+                    JSONRPC_ERROR_INVALID_PARAMS
+                };
+
+                responder.send_error(Some(method), code, e);
             }
         }
     }
 
-    /// Function parses request, then calls `Self::process_ws_channel_request` in a separate thread
+    /// Function parses message (one request or a batch of requests). For every request function checks limits
+    /// and authorizes request, then calls `Self::process_ws_channel_request` in a worker pool thread.
+    /// `context` is borrowed mutably, so connection's future is `Send` (repositories aren't `Sync`).
     fn process_jsonrpc_request(
+        context: &mut WsConnectionContext,
+        limits: &WsConnectionLimits,
         request: String,
-        ws_channels_holder: &WsChannelsHolder,
-        peer_map: &PeerMap,
-        client_addr: SocketAddr,
-        conn_id: &str,
-        ws_answer_timeout_ms: u64,
-        pair_average_price_repositories: &mut Option<WorkerRepositoriesByPairTuple>,
-        _graceful_shutdown: &Arc<Mutex<bool>>,
     ) {
-        let peers = peer_map.lock().unwrap();
+        let broadcast_recipient = context
+            .peer_map
+            .lock()
+            .unwrap()
+            .get(&context.conn_id)
+            .cloned();
 
-        if let Some(broadcast_recipient) = peers.get(&client_addr).cloned() {
-            match Self::parse_jsonrpc_request(&request) {
-                Ok(request) => {
-                    let sub_id = request.id.clone();
-                    let method = request.method;
-                    let request = Self::parse_ws_request(request);
+        if let Some(broadcast_recipient) = broadcast_recipient {
+            for (responder, request) in Self::parse_jsonrpc_message(&request, &broadcast_recipient)
+            {
+                if let Err((code, message)) = limits.check_request() {
+                    let method = request.as_ref().ok().map(|v| v.method);
+                    responder.send_error(method, code, message);
+                    continue;
+                }
 
-                    let conn_id_2 = conn_id.to_string();
-                    let ws_channels_holder_2 = ws_channels_holder.clone();
-                    let pair_average_price_repository_2 = pair_average_price_repositories.clone();
+                let request = match request {
+                    Ok(request) => request,
+                    Err((code, message)) => {
+                        responder.send_error(None, code, message);
+                        continue;
+                    }
+                };
 
-                    let thread_name = format!(
-                        "fn: process_jsonrpc_request, addr: {}, request: {:?}",
-                        client_addr, request
+                let method = request.method;
+                let (request, subscription_slots) = match Self::parse_ws_request(request) {
+                    Ok(WsRequest::Auth(request)) => {
+                        Self::authenticate(&responder, &context.auth, request);
+                        continue;
+                    }
+                    Ok(request) => {
+                        let subscriptions_count = || {
+                            context
+                                .ws_channels_holder
+                                .count_subscriptions(&context.conn_id)
+                        };
+
+                        // Subscription's slots are reserved before the subscription is added,
+                        // so concurrent subscriptions can't exceed the limits (of server and API key)
+                        let checked =
+                            context
+                                .auth
+                                .authorize(&request)
+                                .and_then(|_| match &request {
+                                    WsRequest::Channel(WsChannelAction::Subscribe(..)) => {
+                                        let api_key_slot = context.auth.reserve_subscription()?;
+                                        let subscription_slot =
+                                            limits.reserve_subscription(subscriptions_count)?;
+
+                                        Ok(Some((subscription_slot, api_key_slot)))
+                                    }
+                                    _ => Ok(None),
+                                });
+                        match checked {
+                            Ok(subscription_slots) => (Ok(request), subscription_slots),
+                            Err((code, message)) => {
+                                responder.send_error(Some(method), code, message);
+                                continue;
+                            }
+                        }
+                    }
+                    Err(e) => (Err(e), None),
+                };
+
+                let context_2 = context.clone();
+                let responder_2 = responder.clone();
+
+                let is_queued = context.worker_pool.try_execute(move || {
+                    let (subscription_slot, api_key_slot) = match subscription_slots {
+                        Some((subscription_slot, api_key_slot)) => {
+                            (Some(subscription_slot), api_key_slot)
+                        }
+                        None => (None, None),
+                    };
+
+                    // API key's slot is kept by the subscription, while it exists
+                    Self::process_ws_channel_request(
+                        &context_2,
+                        responder_2,
+                        method,
+                        request,
+                        api_key_slot,
                     );
-                    thread::Builder::new()
-                        .name(thread_name)
-                        .spawn(move || {
-                            Self::process_ws_channel_request(
-                                ws_channels_holder_2,
-                                &client_addr,
-                                broadcast_recipient,
-                                conn_id_2,
-                                sub_id,
-                                method,
-                                request,
-                                ws_answer_timeout_ms,
-                                pair_average_price_repository_2,
-                            )
-                        })
-                        .unwrap();
-                }
-                Err(e) => {
-                    Self::send_error(
-                        &broadcast_recipient,
-                        None,
-                        None,
-                        JSONRPC_ERROR_INVALID_REQUEST,
-                        e.to_string(),
+
+                    // Subscription is added (or failed), so connection's slot is released
+                    drop(subscription_slot);
+                });
+                if !is_queued {
+                    METRICS.inc(WS_SERVER_REJECTED, &[("reason", "workers_queue")]);
+
+                    responder.send_error(
+                        Some(method),
+                        JSONRPC_ERROR_LIMIT_EXCEEDED,
+                        "Limit exceeded. Server is busy, try again later.".to_string(),
                     );
                 }
             }
@@ -385,53 +799,94 @@ impl WsServer {
         }
     }
 
-    /// Function handles one connection - function is executing until client is disconnected.
-    /// Function listens for requests and process them (calls `Self::process_jsonrpc_request`)
-    async fn handle_connection(
-        ws_channels_holder: WsChannelsHolder,
-        peer_map: PeerMap,
-        raw_stream: TcpStream,
-        client_addr: SocketAddr,
-        conn_id: String,
-        ws_answer_timeout_ms: u64,
-        mut pair_average_price_repositories: Option<WorkerRepositoriesByPairTuple>,
-        graceful_shutdown: Arc<Mutex<bool>>,
+    /// Function serves established connection (of any transport): processes requests from `incoming`
+    /// (calls `Self::process_jsonrpc_request`) and sends messages of the outbound queue (`outgoing`
+    /// is a future, which writes them). Function is executing until client is disconnected.
+    async fn serve_connection<I, E, O>(
+        mut context: WsConnectionContext,
+        limits: WsConnectionLimits,
+        tx: Tx,
+        incoming: I,
+        outgoing: O,
+    ) where
+        I: futures::Stream<Item = Result<String, E>>,
+        O: Future,
+    {
+        // Insert the write part of this peer to the peer map.
+        // Outbound queue is bounded, so a slow client can't make memory grow without limit.
+        context
+            .peer_map
+            .lock()
+            .unwrap()
+            .insert(context.conn_id.clone(), tx);
+        METRICS.inc(WS_SERVER_CONNECTIONS, &[]);
+
+        {
+            // TODO: Replace for_each with a simple loop (this is needed for graceful_shutdown)
+            let broadcast_incoming = incoming.try_for_each(|request| {
+                Self::process_jsonrpc_request(&mut context, &limits, request);
+                future::ok(())
+            });
+
+            pin_mut!(broadcast_incoming, outgoing);
+            future::select(broadcast_incoming, outgoing).await;
+        }
+
+        // The client is already disconnected on this line
+        context.peer_map.lock().unwrap().remove(&context.conn_id);
+        // Subscriptions are removed right away (not on the next message), so they aren't
+        // counted against API key's limit
+        context
+            .ws_channels_holder
+            .remove_connection(&context.conn_id);
+        // Alerts of not authenticated client are owned by the connection, so they can't be
+        // removed by anyone else
+        if let Some(alerts) = &context.alerts {
+            alerts.remove_owner(&context.conn_id);
+        }
+        METRICS.dec(WS_SERVER_CONNECTIONS, &[]);
+    }
+
+    /// Function handles one websocket connection - function is executing until client is disconnected.
+    async fn handle_connection<S: AsyncRead + AsyncWrite + Unpin>(
+        context: WsConnectionContext,
+        raw_stream: DeflateStream<S>,
+        limits: Result<WsConnectionLimits, StatusCode>,
+        outbound_queue: (OutboundQueueSender, OutboundQueueReceiver),
     ) {
-        match async_tungstenite::accept_async(raw_stream).await {
-            Ok(ws_stream) => {
-                info!(
-                    "WebSocket connection established, client addr: {}.",
-                    client_addr
-                );
+        let (tx, rx) = outbound_queue;
 
-                // Insert the write part of this peer to the peer map.
-                let (tx, rx) = unbounded();
-                peer_map.lock().unwrap().insert(client_addr, tx);
+        let compression_accepted = Arc::new(Mutex::new(false));
+        let handshake = WsHandshake {
+            broadcast_recipient: tx.clone(),
+            auth: context.auth.clone(),
+            rejection: limits.as_ref().err().copied(),
+            compression: raw_stream.is_compression_allowed(),
+            compression_accepted: Arc::clone(&compression_accepted),
+        };
 
-                let (outgoing, incoming) = ws_stream.split();
+        match async_tungstenite::accept_hdr_async(raw_stream, handshake).await {
+            Ok(mut ws_stream) => {
+                // Connection is rejected during the handshake if connection quota is exceeded
+                let limits = limits.unwrap();
 
-                // TODO: Replace for_each with a simple loop (this is needed for graceful_shutdown)
-                let broadcast_incoming = incoming.try_for_each(|request| {
-                    Self::process_jsonrpc_request(
-                        request.to_string(),
-                        &ws_channels_holder,
-                        &peer_map,
-                        client_addr,
-                        &conn_id,
-                        ws_answer_timeout_ms,
-                        &mut pair_average_price_repositories,
-                        &graceful_shutdown,
-                    );
-                    future::ok(())
-                });
+                let compression_accepted = *compression_accepted.lock().unwrap();
+                if compression_accepted {
+                    ws_stream.get_mut().enable();
+                }
 
-                let receive_from_others = rx.map(Ok).forward(outgoing);
+                info!(
+                    "WebSocket connection established, client addr: {}, encoding: {}, compression: {}.",
+                    context.client_addr,
+                    tx.get_encoding().to_string(),
+                    compression_accepted,
+                );
 
-                pin_mut!(broadcast_incoming, receive_from_others);
-                future::select(broadcast_incoming, receive_from_others).await;
+                let (outgoing, incoming) = ws_stream.split();
+                let incoming = incoming.map_ok(|request| request.to_string());
+                let outgoing = rx.map(Ok).forward(outgoing);
 
-                // The client is already disconnected on this line
-                peer_map.lock().unwrap().remove(&client_addr);
+                Self::serve_connection(context, limits, tx, incoming, outgoing).await;
             }
             Err(e) => {
                 error!(
@@ -442,35 +897,221 @@ impl WsServer {
         }
     }
 
+    /// Function handles one newline-delimited JSON connection (one request/response per line).
+    /// Function is executing until client is disconnected.
+    async fn handle_ndjson_connection<S: AsyncRead + AsyncWrite + Unpin>(
+        context: WsConnectionContext,
+        raw_stream: S,
+        limits: Result<WsConnectionLimits, StatusCode>,
+        outbound_queue: (OutboundQueueSender, OutboundQueueReceiver),
+    ) {
+        let (tx, rx) = outbound_queue;
+        tx.disallow_binary();
+
+        let (reader, writer) = raw_stream.split();
+
+        let limits = match limits {
+            Ok(limits) => limits,
+            Err(_) => {
+                JsonRpcResponder::new(tx.clone(), None, false, None).send_error(
+                    None,
+                    JSONRPC_ERROR_LIMIT_EXCEEDED,
+                    "Limit exceeded. Too many connections.".to_string(),
+                );
+                write_ndjson(writer, rx.take(1)).await;
+
+                return;
+            }
+        };
+
+        info!(
+            "Newline-delimited JSON connection established, client addr: {}.",
+            context.client_addr
+        );
+
+        // Connection is closed on a line, longer than the limit
+        let incoming = read_ndjson(reader, MAX_NDJSON_LINE_LEN)
+            .try_filter(|request| future::ready(!request.trim().is_empty()));
+        let outgoing = write_ndjson(writer, rx);
+
+        Self::serve_connection(context, limits, tx, incoming, outgoing).await;
+    }
+
+    /// Function periodically sends pending (conflated) responses,
+    /// so subscribers always get the latest value. Function ends on graceful shutdown.
+    async fn flush_pending(
+        ws_channels_holder: WsChannelsHolder,
+        period_ms: u64,
+        graceful_shutdown: Arc<Mutex<bool>>,
+    ) {
+        while !*graceful_shutdown.lock().unwrap() {
+            task::sleep(time::Duration::from_millis(period_ms)).await;
+
+            ws_channels_holder.flush_pending();
+        }
+    }
+
+    /// Function spawns a task, which handles one connection.
+    /// `stream` is established inside the task (e.g. TLS handshake), so it doesn't block the listener.
+    fn spawn_connection<S, F>(
+        &self,
+        state: &WsServerState,
+        stream: F,
+        client_addr: String,
+        ip: IpAddr,
+        ndjson: bool,
+    ) where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        F: Future<Output = io::Result<S>> + Send + 'static,
+    {
+        let connection_limits = state.limits.acquire_connection(ip);
+        let outbound_queue =
+            outbound_queue(self.ws_outbound_queue_size, self.ws_outbound_queue_policy);
+        outbound_queue
+            .0
+            .set_legacy_responses(self.ws_legacy_responses);
+        let ws_compression = self.ws_compression;
+        let context = WsConnectionContext {
+            ws_channels_holder: self.ws_channels_holder.clone(),
+            peer_map: state.peer_map.clone(),
+            client_addr,
+            conn_id: Uuid::new_v4().to_string(),
+            auth: WsAuth::new(self.ws_api_keys.clone()),
+            worker_pool: state.worker_pool.clone(),
+            ws_answer_timeout_ms: self.ws_answer_timeout_ms,
+            pair_average_price_repositories: self.pair_average_price_repositories.clone(),
+            alerts: self.alerts.clone(),
+        };
+
+        let _ = task::spawn(async move {
+            match stream.await {
+                Ok(stream) if ndjson => {
+                    Self::handle_ndjson_connection(
+                        context,
+                        stream,
+                        connection_limits,
+                        outbound_queue,
+                    )
+                    .await
+                }
+                Ok(stream) => {
+                    Self::handle_connection(
+                        context,
+                        DeflateStream::new(stream, ws_compression),
+                        connection_limits,
+                        outbound_queue,
+                    )
+                    .await
+                }
+                Err(e) => {
+                    error!(
+                        "Error during the TLS handshake occurred. Client addr: {}, error: {:?}",
+                        context.client_addr, e
+                    );
+                }
+            }
+        });
+    }
+
     /// Function listens and establishes connections. Function never ends.
     async fn run(self) -> Result<(), io::Error> {
-        let state = PeerMap::new(Mutex::new(HashMap::new()));
+        // Create the event loop and listeners we'll accept connections on.
+        let tcp_listener = if self.ws_tcp {
+            let try_socket = TcpListener::bind(&self.ws_addr).await;
+            let listener = try_socket.expect("Failed to bind");
+            info!("Websocket server started on: {}", self.ws_addr);
 
-        // Create the event loop and TCP listener we'll accept connections on.
-        let try_socket = TcpListener::bind(&self.ws_addr).await;
-        let listener = try_socket.expect("Failed to bind");
-        info!("Websocket server started on: {}", self.ws_addr);
+            Some(listener)
+        } else {
+            None
+        };
+        let unix_listener = match &self.ws_unix_socket {
+            Some(config) => {
+                let try_socket = bind_unix_socket(&config.path).await;
+                let listener = try_socket.expect("Failed to bind Unix domain socket");
+                info!(
+                    "Websocket server started on Unix domain socket: {}, protocol: {}",
+                    config.path,
+                    config.protocol.to_string()
+                );
 
-        // Let's spawn the handling of each connection in a separate task.
-        while let Ok((stream, client_addr)) = listener.accept().await {
-            if *self.graceful_shutdown.lock().unwrap() {
-                break;
+                Some((listener, config))
             }
+            None => None,
+        };
+        *self.ws_listener_bound.lock().unwrap() = true;
+
+        let state = WsServerState {
+            peer_map: PeerMap::new(Mutex::new(HashMap::new())),
+            limits: self.ws_limits.clone(),
+            worker_pool: WorkerPool::new(
+                "ws_server_worker",
+                self.ws_workers,
+                self.ws_workers_queue_size,
+            ),
+        };
 
-            let conn_id = Uuid::new_v4().to_string();
-
-            let _ = task::spawn(Self::handle_connection(
-                self.ws_channels_holder.clone(),
-                state.clone(),
-                stream,
-                client_addr,
-                conn_id,
-                self.ws_answer_timeout_ms,
-                self.pair_average_price_repositories.clone(),
-                Arc::clone(&self.graceful_shutdown),
-            ));
+        let ws_tls = self.ws_tls.clone().map(|config| {
+            WsTls::new(config).unwrap_or_else(|e| {
+                panic!("Got wrong config value. service_config: ws_tls_*. {}", e)
+            })
+        });
+        if let Some(ws_tls) = &ws_tls {
+            ws_tls.start_reload_listener(1000, Arc::clone(&self.graceful_shutdown));
         }
 
+        task::spawn(Self::flush_pending(
+            self.ws_channels_holder.clone(),
+            self.ws_answer_timeout_ms,
+            Arc::clone(&self.graceful_shutdown),
+        ));
+
+        // Let's spawn the handling of each connection in a separate task.
+        let accept_tcp = async {
+            if let Some(listener) = &tcp_listener {
+                while let Ok((stream, client_addr)) = listener.accept().await {
+                    if *self.graceful_shutdown.lock().unwrap() {
+                        break;
+                    }
+
+                    let ws_tls = ws_tls.clone();
+                    let stream = async move {
+                        match ws_tls {
+                            Some(ws_tls) => ws_tls.accept(stream).await,
+                            None => Ok(Stream::Plain(stream)),
+                        }
+                    };
+
+                    self.spawn_connection(
+                        &state,
+                        stream,
+                        client_addr.to_string(),
+                        client_addr.ip(),
+                        false,
+                    );
+                }
+            }
+        };
+        let accept_unix = async {
+            if let Some((listener, config)) = &unix_listener {
+                while let Ok((stream, _)) = listener.accept().await {
+                    if *self.graceful_shutdown.lock().unwrap() {
+                        break;
+                    }
+
+                    // Unix domain socket clients are local, so they share limits of localhost
+                    self.spawn_connection(
+                        &state,
+                        future::ok(stream),
+                        format!("unix:{}", config.path),
+                        IpAddr::V4(Ipv4Addr::LOCALHOST),
+                        config.protocol == UnixSocketProtocol::Ndjson,
+                    );
+                }
+            }
+        };
+        future::join(accept_tcp, accept_unix).await;
+
         Ok(())
     }
 }
@@ -0,0 +1,305 @@
+use async_std::net::TcpStream;
+use async_tls::server::TlsStream;
+use async_tls::TlsAcceptor;
+use async_tungstenite::stream::Stream;
+use rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+use rustls::{NoClientAuth, ServerConfig};
+use signal_hook::consts::signal::SIGHUP;
+use signal_hook::iterator::Signals;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use std::{thread, time};
+
+/// Plain TCP or TLS stream of a websocket connection
+pub type WsServerStream = Stream<TcpStream, TlsStream<TcpStream>>;
+
+#[derive(Debug, Clone)]
+pub struct WsTlsConfig {
+    /// Path to certificate chain (PEM)
+    pub cert_path: String,
+    /// Path to private key (PEM, PKCS8 or RSA)
+    pub key_path: String,
+}
+
+/// TLS termination of the websocket server.
+/// Certificate is reloaded on SIGHUP or when certificate/key file is changed.
+#[derive(Clone)]
+pub struct WsTls {
+    config: WsTlsConfig,
+    acceptor: Arc<Mutex<TlsAcceptor>>,
+}
+
+impl WsTls {
+    pub fn new(config: WsTlsConfig) -> Result<Self, String> {
+        let acceptor = Self::make_acceptor(&config)?;
+
+        Ok(Self {
+            config,
+            acceptor: Arc::new(Mutex::new(acceptor)),
+        })
+    }
+
+    fn open(path: &str) -> Result<BufReader<File>, String> {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| format!("Can't open file {}. Error: {}", path, e))
+    }
+
+    fn make_acceptor(config: &WsTlsConfig) -> Result<TlsAcceptor, String> {
+        let certs = certs(&mut Self::open(&config.cert_path)?)
+            .map_err(|_| format!("Can't parse certificate {}.", config.cert_path))?;
+        if certs.is_empty() {
+            return Err(format!("No certificates found in {}.", config.cert_path));
+        }
+
+        let mut keys = pkcs8_private_keys(&mut Self::open(&config.key_path)?)
+            .map_err(|_| format!("Can't parse private key {}.", config.key_path))?;
+        if keys.is_empty() {
+            keys = rsa_private_keys(&mut Self::open(&config.key_path)?)
+                .map_err(|_| format!("Can't parse private key {}.", config.key_path))?;
+        }
+        let key = keys
+            .into_iter()
+            .next()
+            .ok_or(format!("No private keys found in {}.", config.key_path))?;
+
+        let mut server_config = ServerConfig::new(NoClientAuth::new());
+        server_config
+            .set_single_cert(certs, key)
+            .map_err(|e| format!("Wrong certificate or private key. Error: {}", e))?;
+
+        Ok(TlsAcceptor::from(Arc::new(server_config)))
+    }
+
+    /// Previous certificate is kept if the new one can't be loaded
+    pub fn reload(&self) -> Result<(), String> {
+        let acceptor = Self::make_acceptor(&self.config)?;
+        *self.acceptor.lock().unwrap() = acceptor;
+
+        Ok(())
+    }
+
+    pub async fn accept(&self, stream: TcpStream) -> io::Result<WsServerStream> {
+        let acceptor = self.acceptor.lock().unwrap().clone();
+
+        acceptor.accept(stream).await.map(Stream::Tls)
+    }
+
+    fn get_modified(&self) -> Option<(SystemTime, SystemTime)> {
+        let cert_modified = std::fs::metadata(&self.config.cert_path)
+            .and_then(|v| v.modified())
+            .ok()?;
+        let key_modified = std::fs::metadata(&self.config.key_path)
+            .and_then(|v| v.modified())
+            .ok()?;
+
+        Some((cert_modified, key_modified))
+    }
+
+    /// Function checks for SIGHUP and file changes every `period_ms`. Function ends on graceful shutdown.
+    fn reload_on_change(
+        self,
+        mut signals: Signals,
+        period_ms: u64,
+        graceful_shutdown: Arc<Mutex<bool>>,
+    ) {
+        let mut modified = self.get_modified();
+
+        while !*graceful_shutdown.lock().unwrap() {
+            thread::sleep(time::Duration::from_millis(period_ms));
+
+            let sighup = signals.pending().next().is_some();
+            let new_modified = self.get_modified();
+            let files_changed = new_modified.is_some() && new_modified != modified;
+
+            if sighup || files_changed {
+                modified = new_modified;
+
+                match self.reload() {
+                    Ok(()) => info!("TLS certificate reloaded."),
+                    Err(e) => error!("TLS certificate reload error: {}", e),
+                }
+            }
+        }
+    }
+
+    pub fn start_reload_listener(&self, period_ms: u64, graceful_shutdown: Arc<Mutex<bool>>) {
+        let ws_tls = self.clone();
+        // Is registered before return, so SIGHUP doesn't terminate the process
+        let signals = Signals::new([SIGHUP]).unwrap();
+
+        let thread_name = "fn: reload_on_change".to_string();
+        // Do not join
+        let _ = thread::Builder::new()
+            .name(thread_name)
+            .spawn(move || ws_tls.reload_on_change(signals, period_ms, graceful_shutdown))
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::worker::network_helpers::ws_server::ws_tls::{WsTls, WsTlsConfig};
+    use async_std::net::{TcpListener, TcpStream};
+    use async_std::task;
+    use async_tls::TlsConnector;
+    use async_tungstenite::tungstenite::protocol::Message;
+    use futures::{SinkExt, StreamExt};
+    use rustls::{Certificate, ClientConfig};
+    use std::fs::File;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant, SystemTime};
+
+    /// Generates self-signed certificate, writes it into temp files and returns certificate (DER)
+    fn make_cert(name: &str) -> (WsTlsConfig, Certificate) {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let path = |v: &str| -> PathBuf {
+            std::env::temp_dir().join(format!("index_daemon_test_{}_{}.pem", name, v))
+        };
+
+        std::fs::write(path("cert"), cert.serialize_pem().unwrap()).unwrap();
+        std::fs::write(path("key"), cert.serialize_private_key_pem()).unwrap();
+
+        let config = WsTlsConfig {
+            cert_path: path("cert").to_str().unwrap().to_string(),
+            key_path: path("key").to_str().unwrap().to_string(),
+        };
+
+        (config, Certificate(cert.serialize_der().unwrap()))
+    }
+
+    fn remove_cert(config: &WsTlsConfig) {
+        let _ = std::fs::remove_file(&config.cert_path);
+        let _ = std::fs::remove_file(&config.key_path);
+    }
+
+    /// Connects to websocket server via TLS, trusting only `cert`. Returns echoed message.
+    async fn connect(addr: &str, cert: &Certificate) -> Option<String> {
+        let mut client_config = ClientConfig::new();
+        client_config.root_store.add(cert).unwrap();
+        let connector = TlsConnector::from(Arc::new(client_config));
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let stream = connector.connect("localhost", stream).await.ok()?;
+        let (mut ws_stream, _) = async_tungstenite::client_async(format!("wss://{}", addr), stream)
+            .await
+            .ok()?;
+
+        ws_stream
+            .send(Message::Text("test".to_string()))
+            .await
+            .ok()?;
+        match ws_stream.next().await {
+            Some(Ok(Message::Text(text))) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Accepts TLS websocket connections and echoes messages
+    async fn echo_server(listener: TcpListener, ws_tls: WsTls) {
+        while let Ok((stream, _)) = listener.accept().await {
+            let ws_tls = ws_tls.clone();
+
+            task::spawn(async move {
+                let stream = ws_tls.accept(stream).await.ok()?;
+                let mut ws_stream = async_tungstenite::accept_async(stream).await.ok()?;
+
+                while let Some(Ok(message)) = ws_stream.next().await {
+                    ws_stream.send(message).await.ok()?;
+                }
+
+                Some(())
+            });
+        }
+    }
+
+    #[test]
+    fn test_tls() {
+        let (config, cert_1) = make_cert("tls_1");
+        let (config_2, cert_2) = make_cert("tls_2");
+
+        task::block_on(async {
+            let ws_tls = WsTls::new(config.clone()).unwrap();
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap().to_string();
+            task::spawn(echo_server(listener, ws_tls.clone()));
+
+            assert_eq!(connect(&addr, &cert_1).await, Some("test".to_string()));
+            assert_eq!(connect(&addr, &cert_2).await, None);
+
+            // Certificate is changed
+            std::fs::copy(&config_2.cert_path, &config.cert_path).unwrap();
+            std::fs::copy(&config_2.key_path, &config.key_path).unwrap();
+            ws_tls.reload().unwrap();
+
+            assert_eq!(connect(&addr, &cert_1).await, None);
+            assert_eq!(connect(&addr, &cert_2).await, Some("test".to_string()));
+
+            // Wrong certificate: previous one is kept
+            std::fs::write(&config.key_path, "wrong key").unwrap();
+            assert!(ws_tls.reload().is_err());
+
+            assert_eq!(connect(&addr, &cert_2).await, Some("test".to_string()));
+        });
+
+        remove_cert(&config);
+        remove_cert(&config_2);
+    }
+
+    #[test]
+    fn test_reload_on_file_change() {
+        let (config, cert_1) = make_cert("reload_1");
+        let (config_2, cert_2) = make_cert("reload_2");
+        let graceful_shutdown = Arc::new(Mutex::new(false));
+
+        task::block_on(async {
+            let ws_tls = WsTls::new(config.clone()).unwrap();
+            ws_tls.start_reload_listener(50, Arc::clone(&graceful_shutdown));
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap().to_string();
+            task::spawn(echo_server(listener, ws_tls));
+
+            assert_eq!(connect(&addr, &cert_1).await, Some("test".to_string()));
+
+            // Modification time is moved forward, since its resolution may be coarse
+            let modified = SystemTime::now() + Duration::from_secs(10);
+            for (from, to) in [
+                (&config_2.cert_path, &config.cert_path),
+                (&config_2.key_path, &config.key_path),
+            ] {
+                std::fs::copy(from, to).unwrap();
+                File::options()
+                    .write(true)
+                    .open(to)
+                    .unwrap()
+                    .set_modified(modified)
+                    .unwrap();
+            }
+
+            // New certificate is served after the next check of the files
+            let started = Instant::now();
+            while connect(&addr, &cert_2).await.is_none() {
+                assert!(started.elapsed() < Duration::from_secs(10));
+                task::sleep(Duration::from_millis(50)).await;
+            }
+            assert_eq!(connect(&addr, &cert_1).await, None);
+        });
+
+        *graceful_shutdown.lock().unwrap() = true;
+        remove_cert(&config);
+        remove_cert(&config_2);
+    }
+
+    #[test]
+    fn test_wrong_config() {
+        let config = WsTlsConfig {
+            cert_path: "not_existing_cert.pem".to_string(),
+            key_path: "not_existing_key.pem".to_string(),
+        };
+
+        assert!(WsTls::new(config).is_err());
+    }
+}
@@ -2,17 +2,19 @@ use crate::config_scheme::config_scheme::ConfigScheme;
 use crate::config_scheme::market_config::MarketConfig;
 use crate::config_scheme::repositories_prepared::RepositoriesPrepared;
 use crate::config_scheme::service_config::ServiceConfig;
-use crate::repository::repositories::{
-    MarketRepositoriesByMarketName, WorkerRepositoriesByPairTuple,
-};
+use crate::repository::repositories::MarketRepositoriesByMarketName;
 use crate::worker::market_helpers::exchange_pair::ExchangePair;
-use crate::worker::market_helpers::market::{market_factory, Market};
+use crate::worker::market_helpers::market::{market_factory, MarketsHashMap};
 use crate::worker::market_helpers::market_channels::MarketChannels;
 use crate::worker::market_helpers::market_spine::MarketSpine;
 use crate::worker::market_helpers::pair_average_price::PairAveragePriceType;
+use crate::worker::network_helpers::grpc_server::grpc_server::GrpcServer;
+use crate::worker::network_helpers::http_server::http_server::HttpServer;
+use crate::worker::network_helpers::output_sink::output_sink::OutputSinks;
 use crate::worker::network_helpers::ws_server::ws_channels_holder::{
     WsChannelsHolder, WsChannelsHolderHashMap,
 };
+use crate::worker::network_helpers::ws_server::ws_limits::WsLimits;
 use crate::worker::network_helpers::ws_server::ws_server::WsServer;
 use std::collections::HashMap;
 use std::sync::mpsc::Sender;
@@ -22,7 +24,7 @@ use std::thread::{self, JoinHandle};
 pub struct Worker {
     tx: Sender<JoinHandle<()>>,
     graceful_shutdown: Arc<Mutex<bool>>,
-    markets: HashMap<String, Arc<Mutex<dyn Market + Send>>>,
+    markets: MarketsHashMap,
 }
 
 impl Worker {
@@ -47,6 +49,7 @@ impl Worker {
         repositories: Option<MarketRepositoriesByMarketName>,
         pair_average_price: PairAveragePriceType,
         ws_channels_holder: &WsChannelsHolderHashMap,
+        output_sinks: &OutputSinks,
     ) {
         let mut repositories = repositories.unwrap_or_default();
 
@@ -59,6 +62,7 @@ impl Worker {
                 market_name.to_string(),
                 channels.clone(),
                 Arc::clone(&self.graceful_shutdown),
+                output_sinks.clone(),
             );
             let market = market_factory(
                 market_spine,
@@ -71,31 +75,34 @@ impl Worker {
         }
     }
 
-    fn start_ws(
-        &self,
-        ws: bool,
-        ws_addr: String,
-        ws_answer_timeout_ms: u64,
-        pair_average_price_repositories: Option<WorkerRepositoriesByPairTuple>,
-        ws_channels_holder: WsChannelsHolderHashMap,
-        graceful_shutdown: Arc<Mutex<bool>>,
-    ) {
+    fn start_ws(&self, ws: bool, ws_server: WsServer) {
         if ws {
-            let ws_channels_holder = WsChannelsHolder::new(ws_channels_holder);
-
             let thread_name = "fn: start_ws".to_string();
             let thread = thread::Builder::new()
                 .name(thread_name)
-                .spawn(move || {
-                    let ws_server = WsServer {
-                        ws_channels_holder,
-                        ws_addr,
-                        ws_answer_timeout_ms,
-                        pair_average_price_repositories,
-                        graceful_shutdown,
-                    };
-                    ws_server.start();
-                })
+                .spawn(move || ws_server.start())
+                .unwrap();
+            self.tx.send(thread).unwrap();
+        }
+    }
+
+    fn start_http(&self, http: bool, http_server: HttpServer) {
+        if http {
+            let thread_name = "fn: start_http".to_string();
+            let thread = thread::Builder::new()
+                .name(thread_name)
+                .spawn(move || http_server.start())
+                .unwrap();
+            self.tx.send(thread).unwrap();
+        }
+    }
+
+    fn start_grpc(&self, grpc: bool, grpc_server: GrpcServer) {
+        if grpc {
+            let thread_name = "fn: start_grpc".to_string();
+            let thread = thread::Builder::new()
+                .name(thread_name)
+                .spawn(move || grpc_server.start())
                 .unwrap();
             self.tx.send(thread).unwrap();
         }
@@ -107,6 +114,8 @@ impl Worker {
             market_repositories,
             ws_channels_holder,
             pair_average_price,
+            output_sinks,
+            alerts,
         } = RepositoriesPrepared::make(&config);
 
         let ConfigScheme {
@@ -120,30 +129,116 @@ impl Worker {
         let ServiceConfig {
             rest_timeout_sec,
             ws,
+            ws_tcp,
             ws_addr,
+            ws_unix_socket,
             ws_answer_timeout_ms,
-            storage: _,
+            ws_outbound_queue_size,
+            ws_outbound_queue_policy,
+            ws_compression,
+            ws_api_keys,
+            ws_tls,
+            ws_limits,
+            ws_workers,
+            ws_workers_queue_size,
+            ws_legacy_responses,
+            http,
+            http_addr,
+            metrics,
+            ready_min_exchanges,
+            grpc,
+            grpc_addr,
+            storage,
             historical_storage_frequency_ms: _,
+            output_sinks: _,
+            output_sink_queue_size: _,
+            kafka_sink: _,
+            file_sink: _,
+            alerts: _,
         } = service;
 
         let markets = markets.iter().map(|v| v.as_ref()).collect();
 
+        let ws_listener_bound = Arc::new(Mutex::new(false));
+        let ws_limits = WsLimits::new(ws_limits);
+
         self.configure(
             markets,
-            exchange_pairs,
+            exchange_pairs.clone(),
             channels,
             rest_timeout_sec,
             market_repositories,
-            pair_average_price,
+            pair_average_price.clone(),
             &ws_channels_holder,
+            &output_sinks,
         );
+        let ws_channels_holder = WsChannelsHolder::new(
+            ws_channels_holder,
+            self.markets.clone(),
+            pair_average_price.clone(),
+        );
+
         self.start_ws(
             ws,
-            ws_addr,
-            ws_answer_timeout_ms,
-            pair_average_price_repository,
-            ws_channels_holder,
-            self.graceful_shutdown.clone(),
+            WsServer {
+                ws_channels_holder: ws_channels_holder.clone(),
+                ws_tcp,
+                ws_addr,
+                ws_unix_socket,
+                ws_answer_timeout_ms,
+                ws_outbound_queue_size,
+                ws_outbound_queue_policy,
+                ws_compression,
+                ws_api_keys: ws_api_keys.clone(),
+                ws_tls,
+                ws_limits: ws_limits.clone(),
+                ws_workers,
+                ws_workers_queue_size,
+                ws_legacy_responses,
+                pair_average_price_repositories: pair_average_price_repository.clone(),
+                alerts,
+                ws_listener_bound: Arc::clone(&ws_listener_bound),
+                graceful_shutdown: self.graceful_shutdown.clone(),
+            },
+        );
+        self.start_grpc(
+            grpc,
+            GrpcServer {
+                grpc_addr,
+                ws,
+                markets: self.markets.clone(),
+                pair_average_price: pair_average_price.clone(),
+                pair_average_price_repositories: Mutex::new(pair_average_price_repository.clone()),
+                ws_channels_holder: ws_channels_holder.clone(),
+                ws_api_keys: ws_api_keys.clone(),
+                ws_limits: ws_limits.clone(),
+                ws_answer_timeout_ms,
+                ws_outbound_queue_size,
+                ws_outbound_queue_policy,
+                graceful_shutdown: self.graceful_shutdown.clone(),
+            },
+        );
+        self.start_http(
+            http,
+            HttpServer {
+                http_addr,
+                metrics,
+                ws,
+                ws_listener_bound,
+                storage,
+                markets: self.markets.clone(),
+                exchange_pairs,
+                ready_min_exchanges,
+                pair_average_price,
+                pair_average_price_repositories: pair_average_price_repository,
+                ws_channels_holder,
+                ws_api_keys,
+                ws_limits,
+                ws_answer_timeout_ms,
+                ws_outbound_queue_size,
+                ws_outbound_queue_policy,
+                graceful_shutdown: self.graceful_shutdown.clone(),
+            },
         );
 
         for market in self.markets.values().cloned() {
@@ -222,6 +317,8 @@ pub mod test {
             market_repositories,
             ws_channels_holder,
             pair_average_price,
+            output_sinks,
+            alerts: _,
         } = RepositoriesPrepared::make(&config);
 
         worker.configure(
@@ -232,6 +329,7 @@ pub mod test {
             market_repositories,
             pair_average_price,
             &ws_channels_holder,
+            &output_sinks,
         );
 
         assert_eq!(markets.len(), worker.markets.len());